@@ -0,0 +1,94 @@
+/*
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/.
+ */
+
+//! Collection time of the process collector, run with `just bench process`.
+//! Spawns idle children so there's a realistic process count even on a quiet machine.
+
+use std::time::{Duration, Instant};
+
+use monitord::collector::Collector;
+use monitord::collector::process;
+use monitord::metrics;
+
+const CHILDREN: usize = 300;
+const ITERATIONS: u32 = 50;
+
+fn bench_config(user_refresh_interval: u32) -> metrics::Config {
+    metrics::Config {
+        process: Some(process::Config {
+            identity: true,
+            status: true,
+            start_time: true,
+            cpu_usage: true,
+            memory_usage: true,
+            user_refresh_interval,
+            ..Default::default()
+        }),
+        ..Default::default()
+    }
+}
+
+/// Average time of a collect.
+fn time(mut collect: impl FnMut() -> anyhow::Result<()>) -> anyhow::Result<Duration> {
+    let mut total = Duration::ZERO;
+    for _ in 0..ITERATIONS {
+        let start = Instant::now();
+        collect()?;
+        total += start.elapsed();
+    }
+    Ok(total / ITERATIONS)
+}
+
+/// Idle processes, killed when dropped so that a failing run doesn't leave them behind.
+struct Children(Vec<std::process::Child>);
+
+impl Children {
+    fn spawn(count: usize) -> anyhow::Result<Self> {
+        let mut children = Self(Vec::with_capacity(count));
+        for _ in 0..count {
+            children
+                .0
+                .push(std::process::Command::new("sleep").arg("600").spawn()?);
+        }
+        Ok(children)
+    }
+}
+
+impl Drop for Children {
+    fn drop(&mut self) {
+        for child in &mut self.0 {
+            let _ = child.kill();
+            let _ = child.wait();
+        }
+    }
+}
+
+fn main() -> anyhow::Result<()> {
+    let _children = Children::spawn(CHILDREN)?;
+
+    let config = bench_config(0);
+    let processes = process::Collector::new().collect(&config)?.processes.len();
+    println!("{processes} processes, average of {ITERATIONS} collects");
+
+    // A new collector reads the command line of every process, a warm one only reads it for
+    // processes that appeared or exec'd since the previous collect
+    let cold = time(|| process::Collector::new().collect(&config).map(drop))?;
+    let mut collector = process::Collector::new();
+    collector.collect(&config)?;
+    let warm = time(|| collector.collect(&config).map(drop))?;
+    println!("executables: {cold:.2?} uncached, {warm:.2?} cached");
+
+    // Reloading /etc/passwd every collect against the default of every 60 collects
+    let every = bench_config(1);
+    let mut collector = process::Collector::new();
+    collector.collect(&every)?;
+    let reloaded = time(|| collector.collect(&every).map(drop))?;
+    let mut collector = process::Collector::new();
+    collector.collect(&config)?;
+    let cached = time(|| collector.collect(&config).map(drop))?;
+    println!("users: {reloaded:.2?} reloaded every collect, {cached:.2?} cached");
+    Ok(())
+}
@@ -4,7 +4,15 @@
  * file, You can obtain one at https://mozilla.org/MPL/2.0/.
  */
 
+#[cfg(feature = "collector")]
+#[path = "src/collector/gpu/ids.rs"]
+mod gpu_ids;
+
 fn main() -> Result<(), Box<dyn std::error::Error>> {
+    // The gpu.ids rules below replace cargo's default of rerunning on any change in the package
+    println!("cargo:rerun-if-changed=build.rs");
+    println!("cargo:rerun-if-changed=proto");
+
     #[cfg(feature = "metrics")]
     tonic_prost_build::configure()
         .build_server(true)
@@ -40,5 +48,31 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
         .build_client(true)
         .compile_protos(&["proto/control/v1/control.proto"], &["proto/"])?;
 
+    #[cfg(feature = "collector")]
+    generate_gpu_ids()?;
+
+    Ok(())
+}
+
+/// Embeds the GPUs of the build host's pci.ids (or `MONITORD_PCI_IDS`), falling back to the
+/// checked-in subset when the host has no hwdata.
+#[cfg(feature = "collector")]
+fn generate_gpu_ids() -> Result<(), Box<dyn std::error::Error>> {
+    println!("cargo:rerun-if-env-changed=MONITORD_PCI_IDS");
+    println!("cargo:rerun-if-changed=src/collector/gpu/gpu.ids");
+    println!("cargo:rerun-if-changed=src/collector/gpu/ids.rs");
+    let out = std::path::Path::new(&std::env::var("OUT_DIR")?).join("gpu.ids");
+    let sources = std::env::var("MONITORD_PCI_IDS").into_iter().chain([
+        "/usr/share/hwdata/pci.ids".to_string(),
+        "/usr/share/misc/pci.ids".to_string(),
+    ]);
+    for source in sources {
+        if let Ok(pci_ids) = std::fs::read_to_string(&source) {
+            println!("cargo:rerun-if-changed={source}");
+            std::fs::write(out, gpu_ids::display_subset(&pci_ids))?;
+            return Ok(());
+        }
+    }
+    std::fs::copy("src/collector/gpu/gpu.ids", out)?;
     Ok(())
 }
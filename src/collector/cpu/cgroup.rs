@@ -0,0 +1,167 @@
+/*
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/.
+ */
+//! CPU quota tracking for the cgroup the daemon runs in, so utilization can be reported
+//! relative to a container's CPU limit instead of the host's CPUs.
+
+use std::path::{Path, PathBuf};
+
+use crate::collector::helpers::*;
+
+/// Tracker for the CPU usage of the daemon's own cgroup.
+pub struct Tracker {
+    /// `None` when the cgroup has no CPU quota (e.g. bare-metal hosts)
+    cgroup: Option<Cgroup>,
+    sampler: Sampler<UsageUs>,
+}
+
+/// A sample of the cgroup's CPU usage against its quota.
+#[derive(Debug, Clone, Copy)]
+pub struct Sample {
+    /// The CPU quota as a number of CPUs (e.g. 1.5)
+    pub limit_cpus: f32,
+    /// The usage of the quota as a percentage (0.0 to 100.0), `None` on the first sample
+    pub utilization: Option<f32>,
+}
+
+struct Cgroup {
+    limit_cpus: f32,
+    usage: UsageSource,
+}
+
+enum UsageSource {
+    /// cgroup v2 `cpu.stat`, `usage_usec` in microseconds
+    V2(PathBuf),
+    /// cgroup v1 `cpuacct.usage` in nanoseconds
+    V1(PathBuf),
+}
+
+/// Cumulative CPU time used by the cgroup in microseconds.
+struct UsageUs(u64);
+
+impl sampler::Differential for UsageUs {
+    type Delta = u64;
+
+    fn delta(&self, other: &Self) -> Self::Delta {
+        self.0.saturating_sub(other.0)
+    }
+}
+
+impl Tracker {
+    /// Creates a new `Tracker`, detecting the quota of the current cgroup.
+    pub fn new() -> Self {
+        let cgroup = std::fs::read_to_string("/proc/self/cgroup")
+            .ok()
+            .and_then(|contents| Cgroup::detect(&contents));
+        if let Some(cgroup) = &cgroup {
+            tracing::info!("running in a cgroup limited to {} cpus", cgroup.limit_cpus);
+        }
+        Self {
+            cgroup,
+            sampler: Sampler::new(),
+        }
+    }
+
+    /// Reads the cgroup's CPU usage, returning `None` if the cgroup has no CPU quota.
+    pub fn sample(&mut self) -> Option<Sample> {
+        let cgroup = self.cgroup.as_ref()?;
+        let utilization = cgroup
+            .read_usage_us()
+            .and_then(|usage| self.sampler.push(UsageUs(usage)))
+            .map(|delta| {
+                let available_us = delta.interval.as_micros() as f64 * cgroup.limit_cpus as f64;
+                (delta.change as f64 / available_us * 100.0) as f32
+            });
+        Some(Sample {
+            limit_cpus: cgroup.limit_cpus,
+            utilization,
+        })
+    }
+}
+
+impl Cgroup {
+    /// Detects the CPU quota from the contents of `/proc/self/cgroup`, preferring cgroup v2.
+    fn detect(proc_cgroup: &str) -> Option<Self> {
+        let mut v1_cpu = None;
+        let mut v1_cpuacct = None;
+        for line in proc_cgroup.lines() {
+            let mut fields = line.splitn(3, ':');
+            let (Some(_), Some(controllers), Some(path)) =
+                (fields.next(), fields.next(), fields.next())
+            else {
+                continue;
+            };
+            if controllers.is_empty() {
+                if let Some(cgroup) = Self::detect_v2(path) {
+                    return Some(cgroup);
+                }
+                continue;
+            }
+            let controllers = controllers.split(',').collect::<Vec<_>>();
+            if controllers.contains(&"cpu") {
+                v1_cpu = cgroup_dir(&controllers.join(","), path);
+            }
+            if controllers.contains(&"cpuacct") {
+                v1_cpuacct = cgroup_dir(&controllers.join(","), path);
+            }
+        }
+        Self::detect_v1(&v1_cpu?, &v1_cpuacct?)
+    }
+
+    fn detect_v2(path: &str) -> Option<Self> {
+        let dir = cgroup_dir("", path)?;
+        // cpu.max holds "<quota> <period>" or "max <period>" when unlimited
+        let cpu_max = std::fs::read_to_string(dir.join("cpu.max")).ok()?;
+        let (quota, period) = cpu_max.trim().split_once(' ')?;
+        let limit_cpus = quota.parse::<f32>().ok()? / period.parse::<f32>().ok()?;
+        Some(Self {
+            limit_cpus,
+            usage: UsageSource::V2(dir.join("cpu.stat")),
+        })
+    }
+
+    fn detect_v1(cpu_dir: &Path, cpuacct_dir: &Path) -> Option<Self> {
+        let read = |file: &str| {
+            std::fs::read_to_string(cpu_dir.join(file))
+                .ok()
+                .and_then(|value| value.trim().parse::<i64>().ok())
+        };
+        // A quota of -1 means the cgroup is unlimited
+        let quota = read("cpu.cfs_quota_us").filter(|&quota| quota > 0)?;
+        let period = read("cpu.cfs_period_us").filter(|&period| period > 0)?;
+        Some(Self {
+            limit_cpus: quota as f32 / period as f32,
+            usage: UsageSource::V1(cpuacct_dir.join("cpuacct.usage")),
+        })
+    }
+
+    fn read_usage_us(&self) -> Option<u64> {
+        match &self.usage {
+            UsageSource::V2(stat) => std::fs::read_to_string(stat)
+                .ok()?
+                .lines()
+                .find_map(|line| line.strip_prefix("usage_usec "))
+                .and_then(|usage| usage.trim().parse().ok()),
+            UsageSource::V1(usage) => std::fs::read_to_string(usage)
+                .ok()
+                .and_then(|usage| usage.trim().parse::<u64>().ok())
+                .map(|usage_ns| usage_ns / 1000),
+        }
+    }
+}
+
+/// Resolves the directory of a cgroup under `/sys/fs/cgroup`. Inside a cgroup namespace the
+/// reported path may not exist in the mounted hierarchy, in which case the mount root is the cgroup.
+fn cgroup_dir(hierarchy: &str, path: &str) -> Option<PathBuf> {
+    let root = Path::new("/sys/fs/cgroup").join(hierarchy);
+    let dir = root.join(path.trim_start_matches('/'));
+    if dir.is_dir() {
+        Some(dir)
+    } else if root.is_dir() {
+        Some(root)
+    } else {
+        None
+    }
+}
@@ -0,0 +1,166 @@
+/*
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/.
+ */
+//! CPU idle state (C-state) residency tracking via the cpuidle sysfs counters.
+
+use std::collections::BTreeMap;
+use std::time::Duration;
+
+use rustix::fd::AsFd;
+use rustix::fs::{Mode, OFlags};
+
+use crate::collector::helpers::*;
+
+/// Tracker for the per-core C-state residency, diffing the cumulative time spent in each state.
+#[derive(Default)]
+pub struct Tracker {
+    samplers: BTreeMap<(u32, u32, u32), Sampler<IdleTimes>>,
+}
+
+/// A single sample of C-state residency per core, cores without cpuidle are missing.
+#[derive(Debug, Clone, Default)]
+pub struct Sample {
+    pub core: BTreeMap<(u32, u32, u32), Vec<Residency>>,
+}
+
+/// The share of the sample interval a core spent in a given idle state.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Residency {
+    pub name: String,
+    /// Percentage of the interval spent in this state (0.0 to 100.0)
+    pub percent_time: f32,
+}
+
+/// The cumulative time in microseconds spent in each idle state, by state name.
+struct IdleTimes(Vec<(String, u64)>);
+
+impl sampler::Differential for IdleTimes {
+    type Delta = Vec<(String, u64)>;
+
+    fn delta(&self, other: &Self) -> Self::Delta {
+        self.0
+            .iter()
+            .map(|(name, time)| {
+                let last = other
+                    .0
+                    .iter()
+                    .find(|(last_name, _)| last_name == name)
+                    .map(|&(_, last)| last)
+                    .unwrap_or(*time);
+                (name.clone(), time.saturating_sub(last))
+            })
+            .collect()
+    }
+}
+
+impl Tracker {
+    /// Creates a new `Tracker`
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Reads the C-state residency of every core in the topology, the first sample is empty.
+    pub fn read(&mut self, topology: &super::topology::Topology) -> Sample {
+        let mut sample = Sample::default();
+        for (&package_id, package) in topology.packages.iter() {
+            for (&cluster_id, cluster) in package.clusters.iter() {
+                for (&core_id, core) in cluster.cores.iter() {
+                    let Some(&cpu_idx) = core.threads.keys().next() else {
+                        continue;
+                    };
+                    let Some(idle_times) = read_idle_times(cpu_idx) else {
+                        continue;
+                    };
+                    let key = (package_id, cluster_id, core_id);
+                    if let Some(delta) = self.samplers.entry(key).or_default().push(idle_times) {
+                        sample
+                            .core
+                            .insert(key, residency(&delta.change, delta.interval));
+                    }
+                }
+            }
+        }
+        sample
+    }
+}
+
+/// Converts the idle time deltas of each state into a percentage of the sample interval.
+fn residency(deltas: &[(String, u64)], interval: Duration) -> Vec<Residency> {
+    let interval_us = interval.as_micros() as f64;
+    deltas
+        .iter()
+        .map(|(name, time_us)| Residency {
+            name: name.clone(),
+            percent_time: if interval_us > 0.0 {
+                (*time_us as f64 / interval_us * 100.0).min(100.0) as f32
+            } else {
+                0.0
+            },
+        })
+        .collect()
+}
+
+fn read_idle_times(cpu_idx: u32) -> Option<IdleTimes> {
+    let cpuidle = rustix::fs::open(
+        format!("/sys/devices/system/cpu/cpu{cpu_idx}/cpuidle"),
+        OFlags::RDONLY | OFlags::CLOEXEC | OFlags::DIRECTORY,
+        Mode::empty(),
+    )
+    .ok()?;
+    let mut states = BTreeMap::new();
+    for entry in rustix::fs::Dir::read_from(cpuidle.as_fd()).ok()?.flatten() {
+        let file_name = entry.file_name().to_string_lossy();
+        let Some(index) = file_name
+            .strip_prefix("state")
+            .and_then(|index| index.parse::<u32>().ok())
+        else {
+            continue;
+        };
+        let name = sysfs::readat_string(cpuidle.as_fd(), &format!("{file_name}/name"));
+        let time = sysfs::readat_u64(cpuidle.as_fd(), &format!("{file_name}/time"));
+        if let (Some(name), Some(time)) = (name, time) {
+            states.insert(index, (name, time));
+        }
+    }
+    Some(IdleTimes(states.into_values().collect()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn residency_delta() {
+        use sampler::Differential;
+        let last = IdleTimes(vec![
+            ("POLL".to_string(), 1_000),
+            ("C1".to_string(), 50_000),
+            ("C6".to_string(), 900_000),
+        ]);
+        let cur = IdleTimes(vec![
+            ("POLL".to_string(), 1_000),
+            ("C1".to_string(), 150_000),
+            ("C6".to_string(), 1_600_000),
+        ]);
+        let result = residency(&cur.delta(&last), Duration::from_secs(1));
+        assert_eq!(
+            result,
+            vec![
+                Residency {
+                    name: "POLL".to_string(),
+                    percent_time: 0.0
+                },
+                Residency {
+                    name: "C1".to_string(),
+                    percent_time: 10.0
+                },
+                Residency {
+                    name: "C6".to_string(),
+                    percent_time: 70.0
+                },
+            ]
+        );
+    }
+}
@@ -0,0 +1,113 @@
+/*
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/.
+ */
+//! CPU frequency residency tracking via the cpufreq `stats/time_in_state` counters.
+
+use std::collections::BTreeMap;
+
+use crate::collector::helpers::*;
+
+/// Tracker for the per-core frequency residency, diffing the cumulative time spent in each P-state.
+#[derive(Default)]
+pub struct Tracker {
+    samplers: BTreeMap<(u32, u32, u32), Sampler<TimeInState>>,
+}
+
+/// A single sample of frequency residency per core, cores without cpufreq stats are missing.
+#[derive(Debug, Clone, Default)]
+pub struct Sample {
+    pub core: BTreeMap<(u32, u32, u32), Vec<Residency>>,
+}
+
+/// The share of the sample interval a core spent at a given frequency.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Residency {
+    pub freq_mhz: u32,
+    /// Percentage of the interval spent at this frequency (0.0 to 100.0)
+    pub percent_time: f32,
+}
+
+/// The cumulative time in 10ms units spent at each frequency in kHz.
+struct TimeInState(Vec<(u32, u64)>);
+
+impl sampler::Differential for TimeInState {
+    type Delta = Vec<Residency>;
+
+    fn delta(&self, other: &Self) -> Self::Delta {
+        let deltas = self
+            .0
+            .iter()
+            .map(|&(freq_khz, time)| {
+                let last = other
+                    .0
+                    .iter()
+                    .find(|&&(last_khz, _)| last_khz == freq_khz)
+                    .map(|&(_, last)| last)
+                    .unwrap_or(time);
+                (freq_khz, time.saturating_sub(last))
+            })
+            .collect::<Vec<_>>();
+        let total = deltas.iter().map(|&(_, time)| time).sum::<u64>();
+        deltas
+            .into_iter()
+            .map(|(freq_khz, time)| Residency {
+                freq_mhz: freq_khz / 1000,
+                percent_time: match total {
+                    0 => 0.0,
+                    total => time as f32 / total as f32 * 100.0,
+                },
+            })
+            .collect()
+    }
+}
+
+impl Tracker {
+    /// Creates a new `Tracker`
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Reads the frequency residency of every core in the topology, the first sample is empty.
+    pub fn read(&mut self, topology: &super::topology::Topology) -> Sample {
+        let mut sample = Sample::default();
+        for (&package_id, package) in topology.packages.iter() {
+            for (&cluster_id, cluster) in package.clusters.iter() {
+                for (&core_id, core) in cluster.cores.iter() {
+                    let Some(&cpu_idx) = core.threads.keys().next() else {
+                        continue;
+                    };
+                    let Some(time_in_state) = read_time_in_state(cpu_idx) else {
+                        continue;
+                    };
+                    let key = (package_id, cluster_id, core_id);
+                    if let Some(delta) = self.samplers.entry(key).or_default().push(time_in_state) {
+                        sample.core.insert(key, delta.change);
+                    }
+                }
+            }
+        }
+        sample
+    }
+}
+
+fn read_time_in_state(cpu_idx: u32) -> Option<TimeInState> {
+    let contents = sysfs::read_string_path(format!(
+        "/sys/devices/system/cpu/cpu{cpu_idx}/cpufreq/stats/time_in_state"
+    ))?;
+    Some(parse_time_in_state(&contents))
+}
+
+/// Parses the "<freq_khz> <time>" lines of a `time_in_state` file.
+fn parse_time_in_state(contents: &str) -> TimeInState {
+    TimeInState(
+        contents
+            .lines()
+            .filter_map(|line| {
+                let (freq, time) = line.split_once(' ')?;
+                Some((freq.trim().parse().ok()?, time.trim().parse().ok()?))
+            })
+            .collect(),
+    )
+}
@@ -13,10 +13,20 @@
 //! ```no_run
 //!
 //! ```
+mod arm;
+mod cgroup;
+mod cstates;
+mod freq_stats;
 mod sensors;
+mod throttle;
 mod topology;
 mod utilization;
 
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+
+use rustix::fd::AsFd;
+use rustix::fs::{Mode, OFlags};
+
 #[doc(inline)]
 pub use crate::metrics::cpu::*;
 
@@ -26,6 +36,12 @@ pub struct Collector {
     topology: Discovery<topology::Topology>,
     utilization: utilization::Tracker,
     sensors: sensors::Tracker,
+    throttle: throttle::Tracker,
+    cgroup: cgroup::Tracker,
+    freq_stats: freq_stats::Tracker,
+    cstates: cstates::Tracker,
+    /// Vulnerability mitigation status, read once since it cannot change at runtime
+    vulnerabilities: HashMap<String, String>,
 }
 
 impl Default for Collector {
@@ -57,15 +73,30 @@ impl Collector {
             topology: Discovery::default(),
             utilization: utilization::Tracker::new(),
             sensors: sensors::Tracker::new(),
+            throttle: throttle::Tracker::new(),
+            cgroup: cgroup::Tracker::new(),
+            freq_stats: freq_stats::Tracker::new(),
+            cstates: cstates::Tracker::new(),
+            vulnerabilities: read_vulnerabilities(),
         }
     }
 
+    /// Drops the cached topology, identification and frequency handles so they are re-detected on
+    /// the next collection, e.g. after CPU hotplug. The differential trackers keep their baselines
+    /// so the next collection still reports utilization and power.
+    pub fn refresh_static(&mut self) {
+        tracing::debug!("refreshing static cpu data");
+        self.topology = Discovery::default();
+        self.utilization.reopen_freq();
+    }
+
     fn collect_cpus(&mut self, config: Option<&Config>) -> anyhow::Result<Snapshot> {
         let Some(config) = config else {
             anyhow::bail!("cpu collector did not receive a config");
         };
 
-        let topo = if config.topology {
+        // Folding siblings needs the topology even if the physical layout isn't reported
+        let topo = if config.topology || config.fold_smt_siblings {
             Some(
                 self.topology
                     .require(|| topology::Topology::discover(Some(config)))?,
@@ -73,12 +104,139 @@ impl Collector {
         } else {
             None
         };
+        let physical = topo.filter(|_| config.topology);
 
         let utilization = self.utilization.sample()?;
-        let sensors = topo.and_then(|topo| self.sensors.read(topo).ok());
+        let sensors = physical.and_then(|topo| self.sensors.read(topo).ok());
+        let throttle = physical.map(|topo| self.throttle.read(topo));
+        let freq_stats = physical
+            .filter(|_| config.collect_freq_stats)
+            .map(|topo| self.freq_stats.read(topo));
+        let cstates = physical
+            .filter(|_| config.collect_cstates)
+            .map(|topo| self.cstates.read(topo));
+        let pressure = pressure::read("cpu");
+
+        let mut snapshot = assemble(
+            physical,
+            &utilization.logical,
+            sensors.as_ref(),
+            throttle.as_ref(),
+            freq_stats.as_ref(),
+            cstates.as_ref(),
+        );
+        if let Some(topo) = topo {
+            attach_siblings(&mut snapshot.logical, topo);
+        }
+        if config.fold_smt_siblings {
+            snapshot.logical = fold_siblings(std::mem::take(&mut snapshot.logical));
+        }
+        snapshot.times = utilization.total.map(Times::from);
+        if let Some(rates) = utilization.rates {
+            snapshot.context_switches_per_sec = Some(rates.context_switches_per_sec);
+            snapshot.interrupts_per_sec = Some(rates.interrupts_per_sec);
+            snapshot.softirqs_per_sec = Some(rates.softirqs_per_sec);
+        }
+        if let Some(pressure) = pressure {
+            snapshot.pressure_some = pressure.some.map(Pressure::from);
+            snapshot.pressure_full = pressure.full.map(Pressure::from);
+        }
+        if let Some(cgroup) = self.cgroup.sample() {
+            snapshot.effective_cpu_limit = Some(cgroup.limit_cpus);
+            snapshot.cgroup_utilization = cgroup.utilization;
+        }
+        snapshot.turbo_enabled = topology::read_turbo_enabled();
+        snapshot.vulnerabilities = self.vulnerabilities.clone();
+        Ok(snapshot)
+    }
+}
+
+/// Reads the mitigation status of every entry in `/sys/devices/system/cpu/vulnerabilities`.
+/// Kernels or architectures without the directory produce an empty map.
+fn read_vulnerabilities() -> HashMap<String, String> {
+    let mut vulnerabilities = HashMap::new();
+    let Ok(dir) = rustix::fs::open(
+        "/sys/devices/system/cpu/vulnerabilities",
+        OFlags::RDONLY | OFlags::CLOEXEC | OFlags::DIRECTORY,
+        Mode::empty(),
+    ) else {
+        return vulnerabilities;
+    };
+    let Ok(entries) = rustix::fs::Dir::read_from(dir.as_fd()) else {
+        return vulnerabilities;
+    };
+    for entry in entries.flatten() {
+        let name = entry.file_name().to_string_lossy();
+        if name.starts_with('.') {
+            continue;
+        }
+        if let Some(status) = sysfs::readat_string(dir.as_fd(), &name) {
+            vulnerabilities.insert(name.into_owned(), status);
+        }
+    }
+    vulnerabilities
+}
+
+/// Fills in the socket, physical core and SMT siblings of each logical CPU.
+fn attach_siblings(logical: &mut [Logical], topo: &topology::Topology) {
+    for cpu in logical.iter_mut() {
+        let Some(((package_id, _, core_id), core)) = topo.locate(cpu.os_cpu_id) else {
+            continue;
+        };
+        cpu.socket_id = Some(package_id);
+        cpu.physical_core_id = Some(core_id);
+        cpu.sibling_ids = core.threads.keys().copied().collect();
+    }
+}
 
-        Ok(assemble(topo, &utilization, sensors.as_ref()))
+/// Folds SMT siblings into one entry per physical core, averaging their utilization.
+/// The folded entry takes the ID of the first sibling, CPUs without a known core are kept as is.
+fn fold_siblings(logical: Vec<Logical>) -> Vec<Logical> {
+    let mut folded: BTreeMap<u32, (Logical, u32)> = BTreeMap::new();
+    for cpu in logical {
+        let key = cpu.sibling_ids.first().copied().unwrap_or(cpu.os_cpu_id);
+        let Some((entry, count)) = folded.get_mut(&key) else {
+            folded.insert(
+                key,
+                (
+                    Logical {
+                        os_cpu_id: key,
+                        ..cpu
+                    },
+                    1,
+                ),
+            );
+            continue;
+        };
+        entry.utilization += cpu.utilization;
+        entry.cur_freq_mhz += cpu.cur_freq_mhz;
+        if let (Some(sum), Some(times)) = (entry.times.as_mut(), cpu.times) {
+            sum.user += times.user;
+            sum.system += times.system;
+            sum.iowait += times.iowait;
+            sum.irq += times.irq;
+            sum.softirq += times.softirq;
+            sum.steal += times.steal;
+        }
+        *count += 1;
     }
+    folded
+        .into_values()
+        .map(|(mut cpu, count)| {
+            let n = count as f32;
+            cpu.utilization /= n;
+            cpu.cur_freq_mhz /= count;
+            if let Some(times) = cpu.times.as_mut() {
+                times.user /= n;
+                times.system /= n;
+                times.iowait /= n;
+                times.irq /= n;
+                times.softirq /= n;
+                times.steal /= n;
+            }
+            cpu
+        })
+        .collect()
 }
 
 /// Assembles a [`Snapshot`] from the given topology, utilization, and sensor data.
@@ -86,6 +244,9 @@ fn assemble(
     topo: Option<&topology::Topology>,
     utilization: &[utilization::Utilization],
     sensors: Option<&sensors::Sample>,
+    throttle: Option<&throttle::Sample>,
+    freq_stats: Option<&freq_stats::Sample>,
+    cstates: Option<&cstates::Sample>,
 ) -> Snapshot {
     let mut snapshot = Snapshot {
         logical: utilization
@@ -95,15 +256,43 @@ fn assemble(
                 os_cpu_id: os_cpu_id as u32,
                 utilization: util.usage,
                 cur_freq_mhz: util.cur_freq_mhz,
+                times: Some(util.times.into()),
+                socket_id: None,
+                physical_core_id: None,
+                sibling_ids: Vec::new(),
             })
             .collect::<Vec<_>>(),
         packages: Vec::new(),
+        numa_nodes: Vec::new(),
+        pressure_some: None,
+        pressure_full: None,
+        times: None,
+        total_power_w: None,
+        vulnerabilities: Default::default(),
+        turbo_enabled: None,
+        context_switches_per_sec: None,
+        interrupts_per_sec: None,
+        softirqs_per_sec: None,
+        effective_cpu_limit: None,
+        cgroup_utilization: None,
+        socket_count: None,
     };
     // Assemble the physical part
     let Some(topo) = topo else {
         return snapshot;
     };
+    snapshot.socket_count = Some(topo.packages.len() as u32);
+    snapshot.numa_nodes = topo
+        .numa_nodes
+        .iter()
+        .map(|(&node_id, node)| NumaNode {
+            node_id,
+            os_cpu_ids: node.cpus.clone(),
+            memory_total_bytes: node.memory_total_bytes,
+        })
+        .collect();
     for (&package_id, package) in topo.packages.iter() {
+        let mut governors = BTreeSet::new();
         let mut clusters = Vec::new();
         for (&cluster_id, cluster) in package.clusters.iter() {
             let mut cores = Vec::new();
@@ -125,10 +314,48 @@ fn assemble(
                         associativity: cache.associativity,
                     });
                 }
+                let cpufreq = core.cpufreq.as_deref().map(AsFd::as_fd);
+                let cpufreq_governor = cpufreq.and_then(topology::read_governor);
+                governors.extend(cpufreq_governor.clone());
                 cores.push(Core {
                     core_id,
+                    cpufreq_governor,
+                    energy_performance_preference: cpufreq
+                        .and_then(topology::read_energy_performance_preference),
+                    core_type: core.core_type.into(),
+                    numa_node: core.numa_node,
+                    throttle: throttle
+                        .and_then(|throttle| throttle.core.get(&(package_id, cluster_id, core_id)))
+                        .map(|&throttle| throttle.into()),
                     min_freq_mhz: core.min_freq_mhz,
                     max_freq_mhz: core.max_freq_mhz,
+                    scaling_max_freq_mhz: cpufreq
+                        .map(topology::read_scaling_max_freq_mhz)
+                        .unwrap_or(0),
+                    freq_residency: freq_stats
+                        .and_then(|stats| stats.core.get(&(package_id, cluster_id, core_id)))
+                        .map(|residency| {
+                            residency
+                                .iter()
+                                .map(|r| FreqResidency {
+                                    freq_mhz: r.freq_mhz,
+                                    percent_time: r.percent_time,
+                                })
+                                .collect()
+                        })
+                        .unwrap_or_default(),
+                    cstate_residency: cstates
+                        .and_then(|cstates| cstates.core.get(&(package_id, cluster_id, core_id)))
+                        .map(|residency| {
+                            residency
+                                .iter()
+                                .map(|r| CStateResidency {
+                                    name: r.name.clone(),
+                                    percent_time: r.percent_time,
+                                })
+                                .collect()
+                        })
+                        .unwrap_or_default(),
                     core_temperature_c: sensors
                         .and_then(|sensors| sensors.core_temp((package_id, cluster_id, core_id))),
                     threads,
@@ -156,15 +383,77 @@ fn assemble(
         snapshot.packages.push(Package {
             package_id,
             hwid: package.hwid.clone(),
-            drivers: package.drivers.clone(),
+            drivers: package.drivers.clone().map(|mut drivers| {
+                // The governor can be changed at runtime and per core, unlike the rest of the driver info
+                let mut governors = governors.into_iter();
+                match (governors.next(), governors.next()) {
+                    (Some(governor), None) => drivers.cpufreq_governor = governor,
+                    (Some(_), Some(_)) => drivers.cpufreq_governor = "mixed".to_string(),
+                    (None, _) => {}
+                }
+                drivers
+            }),
             package_temperature_c: sensors.and_then(|sensors| sensors.package_temp(package_id)),
             package_power_w: sensors.and_then(|sensors| sensors.package_power(package_id)),
+            temperature_sensors: sensors
+                .map(|sensors| {
+                    sensors
+                        .named_temps(package_id)
+                        .iter()
+                        .map(|(label, temperature_c)| TemperatureSensor {
+                            label: label.clone(),
+                            temperature_c: *temperature_c,
+                        })
+                        .collect()
+                })
+                .unwrap_or_default(),
+            throttle: throttle
+                .and_then(|throttle| throttle.package.get(&package_id))
+                .map(|&throttle| throttle.into()),
             clusters,
         });
     }
+    snapshot.total_power_w = snapshot
+        .packages
+        .iter()
+        .filter_map(|package| package.package_power_w)
+        .reduce(|total, watts| total + watts);
     snapshot
 }
 
+impl From<utilization::Times> for Times {
+    fn from(times: utilization::Times) -> Self {
+        Self {
+            user: times.user,
+            system: times.system,
+            iowait: times.iowait,
+            irq: times.irq,
+            softirq: times.softirq,
+            steal: times.steal,
+        }
+    }
+}
+
+impl From<throttle::Throttle> for Throttle {
+    fn from(throttle: throttle::Throttle) -> Self {
+        Self {
+            count: throttle.count,
+            currently_throttled: throttle.active,
+        }
+    }
+}
+
+impl From<pressure::Record> for Pressure {
+    fn from(record: pressure::Record) -> Self {
+        Self {
+            avg10: record.avg10,
+            avg60: record.avg60,
+            avg300: record.avg300,
+            total_us: record.total_us,
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -179,6 +468,10 @@ mod tests {
             topology: true,
             hwid: true,
             drivers: true,
+            fold_smt_siblings: false,
+            collect_freq_stats: true,
+            flags: true,
+            collect_cstates: true,
         });
 
         let _ = collector.collect(&config)?;
@@ -188,4 +481,77 @@ mod tests {
         println!("{:#?}", snapshot);
         Ok(())
     }
+
+    #[test]
+    fn refresh_keeps_baselines() -> anyhow::Result<()> {
+        let mut collector = super::Collector::new();
+        let config = crate::metrics::Config {
+            cpu: Some(Config {
+                topology: true,
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+
+        let _ = collector.collect(&config)?;
+        std::thread::sleep(std::time::Duration::from_millis(100));
+        collector.refresh_static();
+        let snapshot = collector.collect(&config)?;
+        assert!(!snapshot.logical.is_empty());
+        assert!(snapshot.times.is_some());
+        Ok(())
+    }
+
+    #[test]
+    fn fold_smt_siblings() {
+        // 2 sockets x 4 cores x 2 threads, with the second thread of each core numbered after all first threads
+        let mut topo = topology::Topology::default();
+        for package_id in 0..2 {
+            for core_id in 0..4 {
+                let cpu = package_id * 4 + core_id;
+                topo.insert_thread((package_id, 0, core_id), cpu);
+                topo.insert_thread((package_id, 0, core_id), cpu + 8);
+            }
+        }
+        let mut logical = (0..16)
+            .map(|os_cpu_id| Logical {
+                os_cpu_id,
+                utilization: os_cpu_id as f32,
+                cur_freq_mhz: 1000,
+                ..Default::default()
+            })
+            .collect::<Vec<_>>();
+
+        attach_siblings(&mut logical, &topo);
+        assert_eq!(logical[13].socket_id, Some(1));
+        assert_eq!(logical[13].physical_core_id, Some(1));
+        assert_eq!(logical[13].sibling_ids, vec![5, 13]);
+
+        let folded = fold_siblings(logical);
+        assert_eq!(folded.len(), 8);
+        for (core, cpu) in folded.iter().enumerate() {
+            assert_eq!(cpu.os_cpu_id, core as u32);
+            assert_eq!(cpu.sibling_ids, vec![core as u32, core as u32 + 8]);
+            assert_eq!(cpu.utilization, core as f32 + 4.0);
+            assert_eq!(cpu.cur_freq_mhz, 1000);
+        }
+    }
+
+    #[test]
+    fn pressure() {
+        let parsed = pressure::parse(
+            "some avg10=4.71 avg60=3.10 avg300=1.73 total=38532329\n\
+             full avg10=0.00 avg60=0.00 avg300=0.00 total=0\n",
+        );
+        let some = parsed.some.map(Pressure::from).expect("some line parsed");
+        assert_eq!(some.avg10, 4.71);
+        assert_eq!(some.avg60, 3.10);
+        assert_eq!(some.avg300, 1.73);
+        assert_eq!(some.total_us, 38532329);
+        assert_eq!(parsed.full.map(|full| full.total_us), Some(0));
+
+        // Kernels before 5.13 only report the "some" line for CPU pressure
+        let parsed = pressure::parse("some avg10=0.50 avg60=0.25 avg300=0.10 total=1234\n");
+        assert!(parsed.some.is_some() && parsed.full.is_none());
+    }
 }
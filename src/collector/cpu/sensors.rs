@@ -9,7 +9,7 @@ use std::collections::BTreeMap;
 
 use rustix::{
     fd::{AsFd, BorrowedFd, OwnedFd},
-    fs::{Mode, OFlags},
+    fs::{Mode, OFlags, SeekFrom},
 };
 
 use crate::collector::helpers::*;
@@ -18,7 +18,7 @@ use crate::collector::helpers::*;
 #[derive(Debug)]
 pub struct Tracker {
     sources: Discovery<Sources>,
-    last_energy: BTreeMap<u32, Sampler<u64>>, // for RAPL diff
+    last_energy: BTreeMap<u32, Sampler<Energy>>, // for energy counter diff
 }
 
 /// A single sample of CPU sensor data.
@@ -34,6 +34,8 @@ pub struct Temperatures {
     pub package: BTreeMap<u32, Option<f32>>,
     pub cluster: BTreeMap<(u32, u32), Option<f32>>,
     pub core: BTreeMap<(u32, u32, u32), Option<f32>>,
+    /// Every labelled sensor of the package's hwmon device (e.g. Tctl, Tdie, Tccd1)
+    pub named: BTreeMap<u32, Vec<(String, f32)>>,
 }
 
 /// A sample of CPU power sensor data.
@@ -87,16 +89,40 @@ impl Sample {
         self.temperatures.core.get(&core_id).copied().flatten()
     }
 
+    pub fn named_temps(&self, package_id: u32) -> &[(String, f32)] {
+        self.temperatures
+            .named
+            .get(&package_id)
+            .map(Vec::as_slice)
+            .unwrap_or_default()
+    }
+
     pub fn package_power(&self, package_id: u32) -> Option<f32> {
         self.power.package.get(&package_id).copied().flatten()
     }
 }
 
-impl sampler::Differential for u64 {
-    type Delta = u64;
+/// A reading of a cumulative energy counter in microjoules.
+#[derive(Debug, Clone, Copy)]
+struct Energy {
+    energy_uj: u64,
+    /// The value at which the counter wraps back to zero (`max_energy_range_uj` for RAPL)
+    max_range_uj: u64,
+}
+
+impl sampler::Differential for Energy {
+    /// `None` when the counter went backwards without a known range, i.e. it was reset
+    type Delta = Option<u64>;
 
     fn delta(&self, other: &Self) -> Self::Delta {
-        self.wrapping_sub(*other)
+        if self.energy_uj >= other.energy_uj {
+            Some(self.energy_uj - other.energy_uj)
+        } else if self.max_range_uj > other.energy_uj {
+            // The counter wrapped around since the last sample
+            Some(self.max_range_uj - other.energy_uj + self.energy_uj)
+        } else {
+            None
+        }
     }
 }
 
@@ -109,23 +135,48 @@ struct Sources {
 #[derive(Debug)]
 enum ThermalSource {
     /// Intel coretemp via platform hwmon
-    Coretemp { hwmon: OwnedFd },
+    Coretemp { hwmon: OwnedFd, channels: Channels },
     /// AMD k10temp via PCI hwmon
-    K10temp { hwmon: OwnedFd },
+    K10temp { hwmon: OwnedFd, channels: Channels },
     /// AMD zenpower (third-party, mutually exclusive with k10temp)
-    Zenpower { hwmon: OwnedFd },
+    Zenpower { hwmon: OwnedFd, channels: Channels },
     /// VIA/Centaur via platform hwmon
-    ViaCputemp { hwmon: OwnedFd },
+    ViaCputemp { hwmon: OwnedFd, channels: Channels },
     /// ARM or generic thermal zone fallback
     ThermalZone { zone: OwnedFd },
     /// No supported source found
     None,
 }
 
+/// The hwmon temperature inputs mapped to the topology level they measure.
+/// Built once from the `temp*_label` files so collection only reads `temp*_input`.
+#[derive(Debug, Default)]
+struct Channels {
+    package: Option<String>,
+    clusters: BTreeMap<u32, String>,
+    cores: BTreeMap<u32, String>,
+    /// Every labelled input as (label, input) ordered by channel index, reported as named sensors
+    labelled: Vec<(String, String)>,
+}
+
+/// The topology level a single hwmon temperature label refers to.
+#[derive(Debug, PartialEq, Eq)]
+enum Channel {
+    /// Whole package, preferred over [`Channel::PackageControl`] when both exist
+    Package,
+    /// AMD Tctl, which may carry a fan control offset
+    PackageControl,
+    Cluster(u32),
+    Core(u32),
+}
+
 #[derive(Debug)]
 enum PowerSource {
-    /// Intel RAPL energy counters (requires two-sample diffing)
-    Rapl { energy_path: OwnedFd },
+    /// Cumulative energy counters from RAPL powercap or amd_energy (requires two-sample diffing)
+    Energy {
+        energy_path: OwnedFd,
+        max_range_uj: u64,
+    },
     /// AMD power via same hwmon as thermal (instantaneous reading)
     Hwmon { path: OwnedFd },
     /// No supported source found
@@ -156,10 +207,13 @@ impl Sources {
             package: BTreeMap::new(),
             cluster: BTreeMap::new(),
             core: BTreeMap::new(),
+            named: BTreeMap::new(),
         };
 
         for (&package_id, source) in self.thermal.iter() {
-            temps.package.insert(package_id, read_package_temp(source));
+            let package_temp = read_package_temp(source);
+            temps.package.insert(package_id, package_temp);
+            temps.named.insert(package_id, read_named_temps(source));
 
             if let Some(package) = topology.packages.get(&package_id) {
                 for (&cluster_id, cluster) in package.clusters.iter() {
@@ -171,7 +225,7 @@ impl Sources {
                     for (&core_id, _) in cluster.cores.iter() {
                         temps.core.insert(
                             (package_id, cluster_id, core_id),
-                            read_core_temp(source, core_id),
+                            read_core_temp(source, core_id, package_temp),
                         );
                     }
                 }
@@ -180,14 +234,17 @@ impl Sources {
         temps
     }
 
-    fn read_power(&mut self, last_energy: &mut BTreeMap<u32, Sampler<u64>>) -> Power {
+    fn read_power(&mut self, last_energy: &mut BTreeMap<u32, Sampler<Energy>>) -> Power {
         let mut package = BTreeMap::new();
         for (&package_id, source) in self.power.iter() {
             let watts = match source {
-                PowerSource::Rapl { energy_path } => {
-                    read_rapl_energy(package_id, energy_path.as_fd(), last_energy)
-                }
-                PowerSource::Hwmon { path } => sysfs::read_hwmon_power(path.as_fd()),
+                PowerSource::Energy {
+                    energy_path,
+                    max_range_uj,
+                } => read_energy(package_id, energy_path.as_fd(), *max_range_uj, last_energy),
+                PowerSource::Hwmon { path } => rustix::fs::seek(path, SeekFrom::Start(0))
+                    .ok()
+                    .and_then(|_| sysfs::read_hwmon_power(path.as_fd())),
                 PowerSource::None => None,
             };
             package.insert(package_id, watts);
@@ -209,16 +266,34 @@ fn detect_thermal(package_id: u32, vendor: &str) -> ThermalSource {
 
 fn detect_coretemp(package_id: u32) -> ThermalSource {
     sysfs::first_hwmon_subdir_path(format!("/sys/devices/platform/coretemp.{package_id}/hwmon"))
-        .map(|hwmon| ThermalSource::Coretemp { hwmon })
-        .unwrap_or_else(|| detect_thermal_zone())
+        .or_else(|| {
+            sysfs::find_class_hwmon("coretemp")
+                .into_iter()
+                .nth(package_id as usize)
+        })
+        .map(|hwmon| ThermalSource::Coretemp {
+            channels: Channels::discover(hwmon.as_fd()),
+            hwmon,
+        })
+        .unwrap_or_else(detect_thermal_zone)
 }
 
 fn detect_amd_thermal() -> ThermalSource {
-    if let Some(hwmon) = sysfs::find_pci_driver_hwmon("zenpower") {
-        return ThermalSource::Zenpower { hwmon };
+    if let Some(hwmon) = sysfs::find_pci_driver_hwmon("zenpower")
+        .or_else(|| sysfs::find_class_hwmon("zenpower").into_iter().next())
+    {
+        return ThermalSource::Zenpower {
+            channels: Channels::discover(hwmon.as_fd()),
+            hwmon,
+        };
     }
-    if let Some(hwmon) = sysfs::find_pci_driver_hwmon("k10temp") {
-        return ThermalSource::K10temp { hwmon };
+    if let Some(hwmon) = sysfs::find_pci_driver_hwmon("k10temp")
+        .or_else(|| sysfs::find_class_hwmon("k10temp").into_iter().next())
+    {
+        return ThermalSource::K10temp {
+            channels: Channels::discover(hwmon.as_fd()),
+            hwmon,
+        };
     }
     detect_thermal_zone()
 }
@@ -233,7 +308,10 @@ fn detect_via_thermal(package_id: u32) -> ThermalSource {
         return detect_thermal_zone();
     };
     match sysfs::first_hwmon_subdir(platform.as_fd()) {
-        Some(hwmon) => ThermalSource::ViaCputemp { hwmon },
+        Some(hwmon) => ThermalSource::ViaCputemp {
+            channels: Channels::discover(hwmon.as_fd()),
+            hwmon,
+        },
         None => detect_thermal_zone(),
     }
 }
@@ -293,23 +371,60 @@ fn detect_thermal_zone() -> ThermalSource {
 fn detect_power(package_id: u32, vendor: &str) -> PowerSource {
     match vendor {
         "GenuineIntel" => detect_rapl(package_id),
-        "AuthenticAMD" => detect_amd_power(),
+        // Zen CPUs also expose package energy through the RAPL powercap interface
+        "AuthenticAMD" => match detect_rapl(package_id) {
+            PowerSource::None => detect_amd_power(package_id),
+            source => source,
+        },
         _ => PowerSource::None,
     }
 }
 
 fn detect_rapl(package_id: u32) -> PowerSource {
-    let Ok(energy_path) = rustix::fs::open(
-        format!("/sys/class/powercap/intel-rapl:{package_id}/energy_uj"),
+    let Ok(zone) = rustix::fs::open(
+        format!("/sys/class/powercap/intel-rapl:{package_id}"),
+        OFlags::RDONLY | OFlags::CLOEXEC | OFlags::DIRECTORY,
+        Mode::empty(),
+    ) else {
+        return PowerSource::None;
+    };
+    // energy_uj is only readable by root on most distributions
+    let Ok(energy_path) = rustix::fs::openat(
+        zone.as_fd(),
+        "energy_uj",
         OFlags::RDONLY | OFlags::CLOEXEC,
         Mode::empty(),
     ) else {
         return PowerSource::None;
     };
-    PowerSource::Rapl { energy_path }
+    let max_range_uj = sysfs::readat_u64(zone.as_fd(), "max_energy_range_uj").unwrap_or(0);
+    PowerSource::Energy {
+        energy_path,
+        max_range_uj,
+    }
 }
 
-fn detect_amd_power() -> PowerSource {
+fn detect_amd_power(package_id: u32) -> PowerSource {
+    // The amd_energy driver exposes per-socket counters labelled "Esocket<N>"
+    for hwmon in sysfs::find_class_hwmon("amd_energy") {
+        let Some(channel) = (1..=256).find(|n| {
+            sysfs::readat_string(hwmon.as_fd(), &format!("energy{n}_label"))
+                .is_some_and(|label| label == format!("Esocket{package_id}"))
+        }) else {
+            continue;
+        };
+        if let Ok(energy_path) = rustix::fs::openat(
+            hwmon.as_fd(),
+            format!("energy{channel}_input"),
+            OFlags::RDONLY | OFlags::CLOEXEC,
+            Mode::empty(),
+        ) {
+            return PowerSource::Energy {
+                energy_path,
+                max_range_uj: 0,
+            };
+        }
+    }
     // AMD exposes power through the same hwmon as thermal ON SOME SYSTEMS
     if let Some(hwmon) = sysfs::find_pci_driver_hwmon("zenpower") {
         if let Ok(path) = rustix::fs::openat(
@@ -336,19 +451,86 @@ fn detect_amd_power() -> PowerSource {
 
 // === Temperature reading ===
 
-fn read_package_temp(source: &ThermalSource) -> Option<f32> {
-    match source {
-        ThermalSource::Coretemp { hwmon } => {
-            // temp1_input is typically the package temperature
-            sysfs::readat_hwmon_temp(hwmon.as_fd(), "temp1_input")
+impl Channels {
+    /// Maps every labelled temperature input of a hwmon directory to the topology level it measures.
+    fn discover(hwmon: BorrowedFd) -> Self {
+        let mut channels = Self::default();
+        let Ok(entries) = rustix::fs::Dir::read_from(hwmon) else {
+            return channels;
+        };
+        let mut package_control = None;
+        let mut labelled = BTreeMap::new();
+        for entry in entries.flatten() {
+            let name = entry.file_name().to_string_lossy().into_owned();
+            let Some(index) = name
+                .strip_prefix("temp")
+                .and_then(|n| n.strip_suffix("_label"))
+            else {
+                continue;
+            };
+            let Some(label) = sysfs::readat_string(hwmon, &name) else {
+                continue;
+            };
+            let input = format!("temp{index}_input");
+            if let Ok(index) = index.parse::<u32>() {
+                labelled.insert(index, (label.clone(), input.clone()));
+            }
+            match classify_label(&label) {
+                Some(Channel::Package) => channels.package = Some(input),
+                Some(Channel::PackageControl) => package_control = Some(input),
+                Some(Channel::Cluster(cluster_id)) => {
+                    channels.clusters.insert(cluster_id, input);
+                }
+                Some(Channel::Core(core_id)) => {
+                    channels.cores.insert(core_id, input);
+                }
+                None => {}
+            }
         }
-        ThermalSource::K10temp { hwmon } | ThermalSource::Zenpower { hwmon } => {
-            // Tctl or Tdie — temp1 is usually Tctl
-            sysfs::readat_hwmon_temp(hwmon.as_fd(), "temp1_input")
+        channels.labelled = labelled.into_values().collect();
+        if channels.package.is_none() {
+            channels.package = package_control;
         }
-        ThermalSource::ViaCputemp { hwmon } => {
-            sysfs::readat_hwmon_temp(hwmon.as_fd(), "temp1_input")
+        // Unlabelled drivers (e.g. via_cputemp) only expose a single package reading
+        if channels.package.is_none()
+            && rustix::fs::statat(hwmon, "temp1_input", rustix::fs::AtFlags::empty()).is_ok()
+        {
+            channels.package = Some("temp1_input".to_string());
         }
+        channels
+    }
+}
+
+/// Classifies a hwmon `temp*_label` value from coretemp, k10temp or zenpower.
+fn classify_label(label: &str) -> Option<Channel> {
+    let label = label.trim();
+    if label.starts_with("Package id") || label == "Tdie" {
+        Some(Channel::Package)
+    } else if label == "Tctl" {
+        Some(Channel::PackageControl)
+    } else if let Some(core) = label.strip_prefix("Core ") {
+        core.trim().parse().ok().map(Channel::Core)
+    } else if let Some(ccd) = label.strip_prefix("Tccd") {
+        // CCDs are labelled starting at 1
+        ccd.trim()
+            .parse::<u32>()
+            .ok()
+            .and_then(|ccd| ccd.checked_sub(1))
+            .map(Channel::Cluster)
+    } else {
+        None
+    }
+}
+
+fn read_package_temp(source: &ThermalSource) -> Option<f32> {
+    match source {
+        ThermalSource::Coretemp { hwmon, channels }
+        | ThermalSource::K10temp { hwmon, channels }
+        | ThermalSource::Zenpower { hwmon, channels }
+        | ThermalSource::ViaCputemp { hwmon, channels } => channels
+            .package
+            .as_deref()
+            .and_then(|input| sysfs::readat_hwmon_temp(hwmon.as_fd(), input)),
         ThermalSource::ThermalZone { zone } => sysfs::readat_hwmon_temp(zone.as_fd(), "temp"),
         ThermalSource::None => None,
     }
@@ -356,36 +538,136 @@ fn read_package_temp(source: &ThermalSource) -> Option<f32> {
 
 fn read_cluster_temp(source: &ThermalSource, cluster_id: u32) -> Option<f32> {
     match source {
-        ThermalSource::K10temp { hwmon } | ThermalSource::Zenpower { hwmon } => {
-            // CCD temperatures: temp3_input, temp4_input, etc.
-            // CCD n maps to temp(n+3)_input on most AMD chips
-            sysfs::readat_hwmon_temp(hwmon.as_fd(), &format!("temp{}_input", cluster_id + 3))
-        }
+        ThermalSource::K10temp { hwmon, channels }
+        | ThermalSource::Zenpower { hwmon, channels } => channels
+            .clusters
+            .get(&cluster_id)
+            .and_then(|input| sysfs::readat_hwmon_temp(hwmon.as_fd(), input)),
         _ => None, // Most other sources don't expose per-cluster temps
     }
 }
 
-fn read_core_temp(source: &ThermalSource, core_id: u32) -> Option<f32> {
+fn read_named_temps(source: &ThermalSource) -> Vec<(String, f32)> {
     match source {
-        ThermalSource::Coretemp { hwmon } => {
-            // Core temps start at temp2_input (temp1 is package)
-            sysfs::readat_hwmon_temp(hwmon.as_fd(), &format!("temp{}_input", core_id + 2))
-        }
-        _ => None, // AMD k10temp/zenpower don't expose per-core temps
+        ThermalSource::Coretemp { hwmon, channels }
+        | ThermalSource::K10temp { hwmon, channels }
+        | ThermalSource::Zenpower { hwmon, channels }
+        | ThermalSource::ViaCputemp { hwmon, channels } => channels
+            .labelled
+            .iter()
+            .filter_map(|(label, input)| {
+                sysfs::readat_hwmon_temp(hwmon.as_fd(), input).map(|temp| (label.clone(), temp))
+            })
+            .collect(),
+        _ => Vec::new(),
     }
 }
 
+/// Reads the temperature of a core, falling back to the package temperature when the
+/// source has no per-core sensor for it.
+fn read_core_temp(source: &ThermalSource, core_id: u32, package_temp: Option<f32>) -> Option<f32> {
+    let core_temp = match source {
+        ThermalSource::Coretemp { hwmon, channels }
+        | ThermalSource::K10temp { hwmon, channels }
+        | ThermalSource::Zenpower { hwmon, channels }
+        | ThermalSource::ViaCputemp { hwmon, channels } => channels
+            .cores
+            .get(&core_id)
+            .and_then(|input| sysfs::readat_hwmon_temp(hwmon.as_fd(), input)),
+        _ => None,
+    };
+    core_temp.or(package_temp)
+}
+
 // === Power Reading ===
 
-fn read_rapl_energy(
+fn read_energy(
     package_id: u32,
     energy_path: BorrowedFd,
-    energy: &mut BTreeMap<u32, Sampler<u64>>,
+    max_range_uj: u64,
+    energy: &mut BTreeMap<u32, Sampler<Energy>>,
 ) -> Option<f32> {
-    let energy_uj = sysfs::read_u64(energy_path.as_fd()).unwrap_or_default();
+    rustix::fs::seek(energy_path, SeekFrom::Start(0)).ok()?;
+    let energy_uj = sysfs::read_u64(energy_path)?;
     let delta = energy
         .entry(package_id)
         .or_insert_with(Sampler::new)
-        .push(energy_uj);
-    delta.map(|d| (d.change as f64 / (d.interval.as_secs_f64() * 1_000_000.0)) as f32)
+        .push(Energy {
+            energy_uj,
+            max_range_uj,
+        });
+    delta.and_then(|d| {
+        d.change
+            .map(|change| (change as f64 / (d.interval.as_secs_f64() * 1_000_000.0)) as f32)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::collector::helpers::test::FakeSysfs;
+
+    #[test]
+    fn hwmon_labels() {
+        assert_eq!(classify_label("Package id 0"), Some(Channel::Package));
+        assert_eq!(classify_label("Core 12"), Some(Channel::Core(12)));
+        assert_eq!(classify_label("Tdie"), Some(Channel::Package));
+        assert_eq!(classify_label("Tctl"), Some(Channel::PackageControl));
+        assert_eq!(classify_label("Tccd1"), Some(Channel::Cluster(0)));
+        assert_eq!(classify_label("Tccd8"), Some(Channel::Cluster(7)));
+        assert_eq!(classify_label("Tccd0"), None);
+        assert_eq!(classify_label("edge"), None);
+    }
+
+    #[test]
+    fn k10temp_channels() {
+        let dir = FakeSysfs::new("k10temp");
+        for (file, contents) in [
+            ("name", "k10temp"),
+            ("temp1_label", "Tctl"),
+            ("temp1_input", "65250"),
+            ("temp3_label", "Tccd1"),
+            ("temp3_input", "55000"),
+            ("temp4_label", "Tccd2"),
+            ("temp4_input", "57125"),
+        ] {
+            dir.write(file, format!("{contents}\n"));
+        }
+
+        let hwmon = dir.open_dir("");
+        let source = ThermalSource::K10temp {
+            channels: Channels::discover(hwmon.as_fd()),
+            hwmon,
+        };
+
+        assert_eq!(read_package_temp(&source), Some(65.25));
+        assert_eq!(read_cluster_temp(&source, 1), Some(57.125));
+        assert_eq!(
+            read_named_temps(&source),
+            vec![
+                ("Tctl".to_string(), 65.25),
+                ("Tccd1".to_string(), 55.0),
+                ("Tccd2".to_string(), 57.125),
+            ]
+        );
+    }
+
+    #[test]
+    fn energy_wraparound() {
+        use sampler::Differential;
+        let energy = |energy_uj| Energy {
+            energy_uj,
+            max_range_uj: 262_143_328_850,
+        };
+        assert_eq!(energy(1_500).delta(&energy(1_000)), Some(500));
+        assert_eq!(energy(100).delta(&energy(262_143_328_800)), Some(150));
+        // A reset past the range, and a reset of a counter without a range (e.g. amd_energy)
+        assert_eq!(energy(100).delta(&energy(262_143_328_900)), None);
+        let unranged = |energy_uj| Energy {
+            energy_uj,
+            max_range_uj: 0,
+        };
+        assert_eq!(unranged(1_500).delta(&unranged(1_000)), Some(500));
+        assert_eq!(unranged(100).delta(&unranged(5_000_000)), None);
+    }
 }
@@ -0,0 +1,82 @@
+/*
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/.
+ */
+//! CPU thermal throttling tracking via the x86 `thermal_throttle` sysfs counters.
+
+use std::collections::BTreeMap;
+
+use crate::collector::helpers::sysfs;
+
+/// Tracker for the thermal throttle counters, keeps the previous counts to detect active throttling.
+#[derive(Debug, Default)]
+pub struct Tracker {
+    last_core: BTreeMap<(u32, u32, u32), u64>,
+    last_package: BTreeMap<u32, u64>,
+}
+
+/// A single sample of the throttle counters, missing entries mean the kernel doesn't expose them.
+#[derive(Debug, Clone, Default)]
+pub struct Sample {
+    pub package: BTreeMap<u32, Throttle>,
+    pub core: BTreeMap<(u32, u32, u32), Throttle>,
+}
+
+/// The throttle state of a core or package.
+#[derive(Debug, Clone, Copy)]
+pub struct Throttle {
+    /// Cumulative number of throttle events since boot
+    pub count: u64,
+    /// Whether the count increased since the previous sample
+    pub active: bool,
+}
+
+impl Tracker {
+    /// Creates a new `Tracker`
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Reads the throttle counters for every core and package in the topology.
+    pub fn read(&mut self, topology: &super::topology::Topology) -> Sample {
+        let mut sample = Sample::default();
+        for (&package_id, package) in topology.packages.iter() {
+            if let Some(count) = read_count(package.first_cpu, "package_throttle_count") {
+                let last = self.last_package.insert(package_id, count);
+                sample
+                    .package
+                    .insert(package_id, Throttle::new(count, last));
+            }
+            for (&cluster_id, cluster) in package.clusters.iter() {
+                for (&core_id, core) in cluster.cores.iter() {
+                    let Some(&cpu_idx) = core.threads.keys().next() else {
+                        continue;
+                    };
+                    let Some(count) = read_count(cpu_idx, "core_throttle_count") else {
+                        continue;
+                    };
+                    let key = (package_id, cluster_id, core_id);
+                    let last = self.last_core.insert(key, count);
+                    sample.core.insert(key, Throttle::new(count, last));
+                }
+            }
+        }
+        sample
+    }
+}
+
+impl Throttle {
+    fn new(count: u64, last: Option<u64>) -> Self {
+        Self {
+            count,
+            active: last.is_some_and(|last| count > last),
+        }
+    }
+}
+
+fn read_count(cpu_idx: u32, counter: &str) -> Option<u64> {
+    sysfs::read_u64_path(format!(
+        "/sys/devices/system/cpu/cpu{cpu_idx}/thermal_throttle/{counter}"
+    ))
+}
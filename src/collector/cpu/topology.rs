@@ -5,9 +5,10 @@
  */
 //! CPU topology discovery and cache
 use std::collections::BTreeMap;
+use std::sync::Arc;
 
 use procfs::Current;
-use rustix::fd::{AsFd, BorrowedFd};
+use rustix::fd::{AsFd, BorrowedFd, OwnedFd};
 use rustix::fs::{Mode, OFlags};
 
 use crate::collector::helpers::sysfs;
@@ -18,6 +19,7 @@ use crate::collector::helpers::sysfs;
 #[derive(Debug, Clone)]
 pub struct Topology {
     pub packages: BTreeMap<u32, Package>,
+    pub numa_nodes: BTreeMap<u32, NumaNode>,
     /// A lookup table for CPU indices to their package, cluster, and core.
     lookup: BTreeMap<u32, (u32, u32, u32)>,
 }
@@ -26,6 +28,7 @@ impl Default for Topology {
     fn default() -> Self {
         Self {
             packages: BTreeMap::new(),
+            numa_nodes: BTreeMap::new(),
             lookup: BTreeMap::new(),
         }
     }
@@ -34,6 +37,8 @@ impl Default for Topology {
 /// Represents the cached topology of a CPU package.
 #[derive(Default, Debug, Clone)]
 pub struct Package {
+    /// The first logical CPU discovered in this package, used for package-wide sysfs reads
+    pub first_cpu: u32,
     pub hwid: Option<super::Hwid>,
     pub drivers: Option<super::Drivers>,
     pub clusters: BTreeMap<u32, Cluster>,
@@ -49,12 +54,133 @@ pub struct Cluster {
 /// Represents the cached topology of a CPU core.
 #[derive(Default, Debug, Clone)]
 pub struct Core {
+    pub core_type: CoreType,
+    pub numa_node: u32,
     pub min_freq_mhz: u32,
     pub max_freq_mhz: u32,
+    /// The cpufreq directory of the core's first logical CPU, `None` when cpufreq is unavailable
+    pub cpufreq: Option<Arc<OwnedFd>>,
     pub threads: BTreeMap<u32, Thread>,
     pub private_caches: Vec<Cache>,
 }
 
+/// Represents the cached information about a NUMA node.
+#[derive(Default, Debug, Clone)]
+pub struct NumaNode {
+    pub cpus: Vec<u32>,
+    pub memory_total_bytes: u64,
+}
+
+impl NumaNode {
+    /// Reads all NUMA nodes from sysfs, returning an empty map if the kernel doesn't expose any.
+    fn discover() -> BTreeMap<u32, Self> {
+        let mut nodes = BTreeMap::new();
+        let Ok(node_dir) = rustix::fs::open(
+            "/sys/devices/system/node",
+            OFlags::RDONLY | OFlags::CLOEXEC | OFlags::DIRECTORY,
+            Mode::empty(),
+        ) else {
+            return nodes;
+        };
+        let Ok(entries) = rustix::fs::Dir::read_from(node_dir.as_fd()) else {
+            return nodes;
+        };
+
+        for entry in entries.flatten() {
+            let name = entry.file_name().to_string_lossy();
+            let Some(node_id) = name
+                .strip_prefix("node")
+                .and_then(|id| id.parse::<u32>().ok())
+            else {
+                continue;
+            };
+            let cpus = sysfs::readat_string(node_dir.as_fd(), &format!("{name}/cpulist"))
+                .and_then(|list| sysfs::parse_cpu_list(&list))
+                .unwrap_or_default();
+            let memory_total_bytes =
+                sysfs::readat_string(node_dir.as_fd(), &format!("{name}/meminfo"))
+                    .and_then(|meminfo| parse_node_mem_total(&meminfo))
+                    .unwrap_or(0);
+            nodes.insert(
+                node_id,
+                Self {
+                    cpus,
+                    memory_total_bytes,
+                },
+            );
+        }
+        nodes
+    }
+}
+
+/// Parses the `MemTotal` line of a node's meminfo (e.g. "Node 0 MemTotal:  6158152 kB") into bytes.
+fn parse_node_mem_total(meminfo: &str) -> Option<u64> {
+    meminfo.lines().find_map(|line| {
+        let (_, value) = line.split_once("MemTotal:")?;
+        let kb = value
+            .trim()
+            .trim_end_matches("kB")
+            .trim()
+            .parse::<u64>()
+            .ok()?;
+        Some(kb * 1024)
+    })
+}
+
+/// The type of a physical core on hybrid CPUs.
+#[derive(Default, PartialEq, Eq, Debug, Clone, Copy)]
+pub enum CoreType {
+    /// The CPU is not hybrid or the core type could not be determined.
+    #[default]
+    Unknown,
+    /// A performance core (Intel P-core).
+    Performance,
+    /// An efficiency core (Intel E-core).
+    Efficiency,
+}
+
+impl From<CoreType> for i32 {
+    fn from(value: CoreType) -> Self {
+        match value {
+            CoreType::Unknown => 0,
+            CoreType::Performance => 1,
+            CoreType::Efficiency => 2,
+        }
+    }
+}
+
+/// The logical CPUs belonging to each core type, as exposed by the hybrid PMUs in sysfs.
+#[derive(Default, Debug)]
+struct HybridCpus {
+    performance: Vec<u32>,
+    efficiency: Vec<u32>,
+}
+
+impl HybridCpus {
+    /// Reads the `cpu_core` and `cpu_atom` PMU CPU lists, these only exist on hybrid Intel CPUs.
+    fn discover() -> Self {
+        let read = |pmu: &str| {
+            sysfs::read_string_path(format!("/sys/devices/{pmu}/cpus"))
+                .and_then(|list| sysfs::parse_cpu_list(&list))
+                .unwrap_or_default()
+        };
+        Self {
+            performance: read("cpu_core"),
+            efficiency: read("cpu_atom"),
+        }
+    }
+
+    fn core_type(&self, cpu_idx: u32) -> CoreType {
+        if self.performance.contains(&cpu_idx) {
+            CoreType::Performance
+        } else if self.efficiency.contains(&cpu_idx) {
+            CoreType::Efficiency
+        } else {
+            CoreType::Unknown
+        }
+    }
+}
+
 /// Represents the cached topology of a CPU thread.
 #[derive(Default, Debug, Clone)]
 pub struct Thread {
@@ -100,10 +226,28 @@ impl Topology {
     /// Discovers the topology of the CPUs in the system.
     pub fn discover(config: Option<&super::Config>) -> anyhow::Result<Self> {
         let cpuinfo = procfs::CpuInfo::current()?;
-        let mut topo = Self::default();
+        let hybrid = HybridCpus::discover();
+        let mut topo = Self {
+            numa_nodes: NumaNode::discover(),
+            ..Self::default()
+        };
+
+        // Kernels without NUMA support don't expose any nodes, treat the whole system as node 0
+        if topo.numa_nodes.is_empty() {
+            let memory_total_bytes = procfs::Meminfo::current()
+                .map(|meminfo| meminfo.mem_total)
+                .unwrap_or(0);
+            topo.numa_nodes.insert(
+                0,
+                NumaNode {
+                    cpus: (0..cpuinfo.num_cores() as u32).collect(),
+                    memory_total_bytes,
+                },
+            );
+        }
 
         for cpu_idx in 0..cpuinfo.num_cores() {
-            topo.insert_cpu(config.clone(), &cpuinfo, cpu_idx as u32);
+            topo.insert_cpu(config.clone(), &cpuinfo, &hybrid, cpu_idx as u32);
         }
 
         // Second pass: attach caches (thread counts need to be calculated first)
@@ -119,40 +263,80 @@ impl Topology {
         &mut self,
         config: Option<&super::Config>,
         cpuinfo: &procfs::CpuInfo,
+        hybrid: &HybridCpus,
         cpu_idx: u32,
     ) {
-        let package_id = cpuinfo.physical_id(cpu_idx as usize).unwrap_or(0);
+        // Arm kernels don't report the physical and core IDs in /proc/cpuinfo, so fall back to sysfs
+        let package_id = cpuinfo
+            .physical_id(cpu_idx as usize)
+            .or_else(|| read_topology_id(cpu_idx, "physical_package_id"))
+            .unwrap_or(0);
         let cluster_id = read_cluster_id(cpu_idx);
         let core_id = cpuinfo
             .get_field(cpu_idx as usize, "core id")
             .and_then(|s| s.parse().ok())
+            .or_else(|| read_topology_id(cpu_idx, "core_id"))
             .unwrap_or(0);
         let os_cpu_id = cpuinfo
             .get_field(cpu_idx as usize, "processor")
             .and_then(|s| s.parse().ok())
             .unwrap_or(cpu_idx);
 
-        let pkg = self
-            .packages
+        let numa_node = self
+            .numa_nodes
+            .iter()
+            .find(|(_, node)| node.cpus.contains(&os_cpu_id))
+            .map(|(&node_id, _)| node_id)
+            .unwrap_or(0);
+
+        self.packages
             .entry(package_id)
-            .or_insert_with(|| Package::from_cpuinfo(config, cpuinfo, cpu_idx));
+            .or_insert_with(|| Package::from_cpuinfo(config, cpuinfo, cpu_idx))
+            .clusters
+            .entry(cluster_id)
+            .or_default()
+            .cores
+            .entry(core_id)
+            .or_insert_with(|| Core::from_sysfs(cpu_idx, hybrid.core_type(cpu_idx), numa_node));
 
-        let cluster = pkg.clusters.entry(cluster_id).or_default();
+        self.insert_thread((package_id, cluster_id, core_id), os_cpu_id);
+    }
 
-        let core = cluster
+    /// Inserts a logical CPU as the next thread of the given (package, cluster, core).
+    pub(super) fn insert_thread(&mut self, location: (u32, u32, u32), os_cpu_id: u32) {
+        let (package_id, cluster_id, core_id) = location;
+        let core = self
+            .packages
+            .entry(package_id)
+            .or_default()
+            .clusters
+            .entry(cluster_id)
+            .or_default()
             .cores
             .entry(core_id)
-            .or_insert_with(|| Core::from_sysfs(cpu_idx));
+            .or_default();
 
         let thread_index = core.threads.len() as u32;
-        self.lookup
-            .insert(os_cpu_id, (package_id, cluster_id, core_id));
+        self.lookup.insert(os_cpu_id, location);
         core.threads.entry(os_cpu_id).or_insert(Thread {
             os_cpu_id,
             thread_index,
         });
     }
 
+    /// Looks up the (package, cluster, core) and core of a logical CPU.
+    pub fn locate(&self, os_cpu_id: u32) -> Option<((u32, u32, u32), &Core)> {
+        let &(package_id, cluster_id, core_id) = self.lookup.get(&os_cpu_id)?;
+        let core = self
+            .packages
+            .get(&package_id)?
+            .clusters
+            .get(&cluster_id)?
+            .cores
+            .get(&core_id)?;
+        Some(((package_id, cluster_id, core_id), core))
+    }
+
     /// Reads and attaches cache information to the topology.
     fn attach_caches(&mut self, cpu_idx: u32) {
         let Some((package_id, cluster_id, core_id)) = self.lookup.get(&cpu_idx) else {
@@ -254,33 +438,7 @@ impl Package {
         let cpu_idx = cpu_idx as usize;
         let hwid = config.and_then(|c| {
             if c.hwid {
-                let vendor_id = cpuinfo
-                    .vendor_id(cpu_idx)
-                    .map(|v| v.to_string())
-                    .unwrap_or_default();
-                let model_name = cpuinfo
-                    .model_name(cpu_idx)
-                    .map(|v| v.to_string())
-                    .unwrap_or_default();
-                let family = cpuinfo
-                    .get_field(cpu_idx, "cpu family")
-                    .and_then(|v| v.parse::<u32>().ok())
-                    .unwrap_or(0);
-                let model = cpuinfo
-                    .get_field(cpu_idx, "model")
-                    .and_then(|v| v.parse::<u32>().ok())
-                    .unwrap_or(0);
-                let stepping = cpuinfo
-                    .get_field(cpu_idx, "stepping")
-                    .and_then(|v| v.parse::<u32>().ok())
-                    .unwrap_or(0);
-                Some(super::Hwid {
-                    vendor_id,
-                    model_name,
-                    family,
-                    model,
-                    stepping,
-                })
+                Some(read_hwid(cpuinfo, cpu_idx, c.flags))
             } else {
                 None
             }
@@ -291,7 +449,11 @@ impl Package {
                 let microcode_version = cpuinfo
                     .get_field(cpu_idx, "microcode")
                     .map(|v| v.to_string())
-                    .unwrap_or_default();
+                    .or_else(|| {
+                        sysfs::read_string_path(format!(
+                            "/sys/devices/system/cpu/cpu{cpu_idx}/microcode/version"
+                        ))
+                    });
                 let (cpufreq_driver, cpufreq_governor, cpufreq_mode) =
                     get_cpufreq_info(cpu_idx as u32);
 
@@ -307,6 +469,7 @@ impl Package {
         });
 
         Self {
+            first_cpu: cpu_idx as u32,
             hwid,
             drivers,
             clusters: BTreeMap::new(),
@@ -316,27 +479,24 @@ impl Package {
 
 impl Core {
     /// Creates a [`Core`] from the sysfs information for a given CPU index.
-    fn from_sysfs(cpu_idx: u32) -> Self {
-        let min_freq_mhz = rustix::fs::open(
-            format!("/sys/devices/system/cpu/cpu{cpu_idx}/cpufreq/cpuinfo_min_freq"),
-            OFlags::RDONLY | OFlags::CLOEXEC,
-            Mode::empty(),
-        )
-        .ok()
-        .and_then(|fd| sysfs::read_u32(fd.as_fd()))
-        .unwrap_or(0);
+    fn from_sysfs(cpu_idx: u32, core_type: CoreType, numa_node: u32) -> Self {
+        Self::from_cpufreq(open_cpufreq(cpu_idx), core_type, numa_node)
+    }
 
-        let max_freq_mhz = rustix::fs::open(
-            format!("/sys/devices/system/cpu/cpu{cpu_idx}/cpufreq/cpuinfo_max_freq"),
-            OFlags::RDONLY | OFlags::CLOEXEC,
-            Mode::empty(),
-        )
-        .ok()
-        .and_then(|fd| sysfs::read_u32(fd.as_fd()))
-        .unwrap_or(0);
+    /// Creates a [`Core`] from its cpufreq directory, `None` when cpufreq is unavailable.
+    fn from_cpufreq(cpufreq: Option<OwnedFd>, core_type: CoreType, numa_node: u32) -> Self {
+        let freq_mhz = |file| {
+            cpufreq
+                .as_ref()
+                .map(|fd| read_freq_mhz(fd.as_fd(), file))
+                .unwrap_or(0)
+        };
         Self {
-            min_freq_mhz,
-            max_freq_mhz,
+            core_type,
+            numa_node,
+            min_freq_mhz: freq_mhz("cpuinfo_min_freq"),
+            max_freq_mhz: freq_mhz("cpuinfo_max_freq"),
+            cpufreq: cpufreq.map(Arc::new),
             threads: BTreeMap::new(),
             private_caches: Vec::new(),
         }
@@ -374,6 +534,72 @@ impl From<&str> for CacheType {
     }
 }
 
+/// Reads the hardware identification of a CPU, decoding the Arm implementer and part fields
+/// when the x86 vendor and model fields are not present.
+fn read_hwid(cpuinfo: &procfs::CpuInfo, cpu_idx: usize, include_flags: bool) -> super::Hwid {
+    let field_u32 = |field: &str| {
+        cpuinfo
+            .get_field(cpu_idx, field)
+            .and_then(|v| v.parse::<u32>().ok())
+            .unwrap_or(0)
+    };
+    // The full flag list is ~100 entries, so it is only read when requested
+    let flags = cpuinfo
+        .get_field(cpu_idx, "flags")
+        .or_else(|| cpuinfo.get_field(cpu_idx, "Features"))
+        .filter(|_| include_flags)
+        .map(|flags| flags.split_whitespace().map(str::to_string).collect())
+        .unwrap_or_default();
+
+    if let Some(vendor_id) = cpuinfo.vendor_id(cpu_idx) {
+        return super::Hwid {
+            vendor_id: vendor_id.to_string(),
+            model_name: cpuinfo
+                .model_name(cpu_idx)
+                .map(|v| v.to_string())
+                .unwrap_or_default(),
+            family: field_u32("cpu family"),
+            model: field_u32("model"),
+            stepping: field_u32("stepping"),
+            flags,
+        };
+    }
+
+    let implementer = cpuinfo
+        .get_field(cpu_idx, "CPU implementer")
+        .and_then(super::arm::parse_hex)
+        .unwrap_or(0);
+    let part = cpuinfo
+        .get_field(cpu_idx, "CPU part")
+        .and_then(super::arm::parse_hex)
+        .unwrap_or(0);
+    let vendor_id = super::arm::implementer_name(implementer)
+        .map(str::to_string)
+        .unwrap_or_else(|| format!("{implementer:#04x}"));
+    let model_name = cpuinfo
+        .model_name(cpu_idx)
+        .filter(|name| !name.is_empty())
+        .map(|name| name.to_string())
+        .unwrap_or_else(|| match super::arm::part_name(implementer, part) {
+            Some(part_name) => format!("{vendor_id} {part_name}"),
+            None => format!("{vendor_id} {part:#05x}"),
+        });
+    super::Hwid {
+        vendor_id,
+        model_name,
+        family: field_u32("CPU architecture"),
+        model: part,
+        stepping: field_u32("CPU revision"),
+        flags,
+    }
+}
+
+fn read_topology_id(cpu_idx: u32, id: &str) -> Option<u32> {
+    sysfs::read_u32_path(format!(
+        "/sys/devices/system/cpu/cpu{cpu_idx}/topology/{id}"
+    ))
+}
+
 fn read_cluster_id(cpu_idx: u32) -> u32 {
     sysfs::read_u32_path(format!(
         "/sys/devices/system/cpu/cpu{cpu_idx}/topology/die_id"
@@ -381,6 +607,45 @@ fn read_cluster_id(cpu_idx: u32) -> u32 {
     .unwrap_or(0)
 }
 
+/// Reads the energy performance preference from a cpufreq directory, only exposed by
+/// the intel_pstate and amd-pstate drivers in active mode.
+pub fn read_energy_performance_preference(cpufreq: BorrowedFd) -> Option<String> {
+    sysfs::readat_string(cpufreq, "energy_performance_preference")
+}
+
+/// Reads the maximum frequency currently allowed by the cpufreq policy of a cpufreq directory.
+pub fn read_scaling_max_freq_mhz(cpufreq: BorrowedFd) -> u32 {
+    read_freq_mhz(cpufreq, "scaling_max_freq")
+}
+
+fn open_cpufreq(cpu_idx: u32) -> Option<OwnedFd> {
+    rustix::fs::open(
+        format!("/sys/devices/system/cpu/cpu{cpu_idx}/cpufreq"),
+        OFlags::RDONLY | OFlags::CLOEXEC | OFlags::DIRECTORY,
+        Mode::empty(),
+    )
+    .ok()
+}
+
+/// Reads a cpufreq frequency file, which is in kHz, as MHz (0 when it is missing).
+fn read_freq_mhz(cpufreq: BorrowedFd, file: &str) -> u32 {
+    sysfs::readat_u32(cpufreq, file).unwrap_or(0) / 1000
+}
+
+/// Reads whether turbo is enabled, from `intel_pstate/no_turbo` or the generic cpufreq `boost` switch.
+pub fn read_turbo_enabled() -> Option<bool> {
+    sysfs::read_u32_path("/sys/devices/system/cpu/intel_pstate/no_turbo")
+        .map(|no_turbo| no_turbo == 0)
+        .or_else(|| {
+            sysfs::read_u32_path("/sys/devices/system/cpu/cpufreq/boost").map(|boost| boost != 0)
+        })
+}
+
+/// Reads the cpufreq governor currently applied to a cpufreq directory.
+pub fn read_governor(cpufreq: BorrowedFd) -> Option<String> {
+    sysfs::readat_string(cpufreq, "scaling_governor")
+}
+
 fn get_cpufreq_info(cpu_idx: u32) -> (String, String, Option<String>) {
     let Some(cpufreq) = rustix::fs::open(
         format!("/sys/devices/system/cpu/cpu{cpu_idx}/cpufreq"),
@@ -396,3 +661,33 @@ fn get_cpufreq_info(cpu_idx: u32) -> (String, String, Option<String>) {
         .or_else(|| sysfs::read_string_path("/sys/devices/system/cpu/amd_pstate/status"));
     (driver, governor, mode)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::collector::helpers::test::FakeSysfs;
+
+    #[test]
+    fn frequency_units() {
+        let cpufreq = FakeSysfs::new("cpufreq");
+        cpufreq.write("cpuinfo_min_freq", "800000\n");
+        cpufreq.write("cpuinfo_max_freq", "4700000\n");
+        cpufreq.write("scaling_max_freq", "3000000\n");
+        cpufreq.write("scaling_governor", "powersave\n");
+
+        let core = Core::from_cpufreq(Some(cpufreq.open_dir("")), CoreType::default(), 0);
+        assert_eq!((core.min_freq_mhz, core.max_freq_mhz), (800, 4700));
+        let fd = core.cpufreq.as_deref().expect("cpufreq is cached").as_fd();
+        // The policy cap is comparable with the hardware limits
+        let scaling_max_freq_mhz = read_scaling_max_freq_mhz(fd);
+        assert_eq!(scaling_max_freq_mhz, 3000);
+        assert!(scaling_max_freq_mhz < core.max_freq_mhz);
+        // Policy files are read through the cached directory on every collection
+        cpufreq.write("scaling_governor", "performance\n");
+        assert_eq!(read_governor(fd).as_deref(), Some("performance"));
+        assert_eq!(read_energy_performance_preference(fd), None);
+
+        let core = Core::from_cpufreq(None, CoreType::default(), 0);
+        assert_eq!((core.min_freq_mhz, core.max_freq_mhz), (0, 0));
+    }
+}
@@ -6,74 +6,202 @@
 
 //! CPU utilization tracking.
 
-use procfs::CurrentSI;
-use rustix::fd::AsFd;
+use std::collections::BTreeMap;
+
+use procfs::FromBufReadSI;
+use rustix::fd::{AsFd, OwnedFd};
 use rustix::fs::{Mode, OFlags};
 
 use crate::collector::helpers::*;
 
 pub struct Tracker {
-    sampler: Sampler<procfs::KernelStats>,
+    sampler: Sampler<Stat>,
+    /// Cached `scaling_cur_freq` handles per logical CPU, `None` when cpufreq is unavailable
+    cur_freq: BTreeMap<usize, Option<OwnedFd>>,
 }
 
 impl Tracker {
     pub fn new() -> Self {
         Self {
             sampler: Sampler::new(),
+            cur_freq: BTreeMap::new(),
         }
     }
 
-    pub fn sample(&mut self) -> anyhow::Result<Vec<Utilization>> {
-        match procfs::KernelStats::current() {
-            Ok(stat) => match self.sampler.push(stat) {
-                Some(delta) => Ok(delta.change),
-                None => Ok(Vec::new()),
-            },
+    pub fn sample(&mut self) -> anyhow::Result<Sample> {
+        let delta = match Stat::current() {
+            Ok(stat) => self.sampler.push(stat),
             Err(e) => {
                 tracing::warn!("failed to read /proc/stat: {}", e);
-                Ok(Vec::new())
+                None
             }
+        };
+        let Some(delta) = delta else {
+            return Ok(Sample::default());
+        };
+        let breakdown = delta.change;
+        let per_sec = |count: u64| match delta.interval.as_secs_f64() {
+            0.0 => 0.0,
+            secs => (count as f64 / secs) as f32,
+        };
+        Ok(Sample {
+            total: Some(breakdown.total),
+            rates: Some(Rates {
+                context_switches_per_sec: per_sec(breakdown.context_switches),
+                interrupts_per_sec: per_sec(breakdown.interrupts),
+                softirqs_per_sec: per_sec(breakdown.softirqs),
+            }),
+            logical: breakdown
+                .per_cpu
+                .into_iter()
+                .enumerate()
+                .map(|(cpu_idx, times)| Utilization {
+                    usage: times.usage,
+                    cur_freq_mhz: self.cur_freq_mhz(cpu_idx),
+                    times,
+                })
+                .collect(),
+        })
+    }
+
+    /// Closes the cached frequency handles so they're reopened for the CPUs that are now online,
+    /// keeping the /proc/stat baseline.
+    pub fn reopen_freq(&mut self) {
+        self.cur_freq.clear();
+    }
+
+    /// Reads the current frequency of a logical CPU, opening its sysfs file only on first use.
+    fn cur_freq_mhz(&mut self, cpu_idx: usize) -> u32 {
+        let Some(fd) = self
+            .cur_freq
+            .entry(cpu_idx)
+            .or_insert_with(|| open_cur_freq(cpu_idx))
+        else {
+            return 0;
+        };
+        if rustix::fs::seek(fd.as_fd(), rustix::fs::SeekFrom::Start(0)).is_err() {
+            return 0;
         }
+        sysfs::read_u32(fd.as_fd()).unwrap_or(0) / 1000
+    }
+}
+
+/// A single read of `/proc/stat`, including the interrupt totals that `procfs` doesn't parse.
+struct Stat {
+    kernel: procfs::KernelStats,
+    interrupts: u64,
+    softirqs: u64,
+}
+
+impl Stat {
+    fn current() -> anyhow::Result<Self> {
+        let contents = std::fs::read_to_string("/proc/stat")?;
+        let kernel =
+            procfs::KernelStats::from_buf_read(contents.as_bytes(), procfs::current_system_info())?;
+        // The first value of the "intr" and "softirq" lines is the total across all sources
+        let total = |prefix: &str| {
+            contents
+                .lines()
+                .find_map(|line| line.strip_prefix(prefix))
+                .and_then(|values| values.split_whitespace().next())
+                .and_then(|total| total.parse().ok())
+                .unwrap_or(0)
+        };
+        Ok(Self {
+            interrupts: total("intr "),
+            softirqs: total("softirq "),
+            kernel,
+        })
     }
 }
 
-impl sampler::Differential for procfs::KernelStats {
-    type Delta = Vec<Utilization>;
+impl sampler::Differential for Stat {
+    type Delta = Breakdown;
 
     fn delta(&self, other: &Self) -> Self::Delta {
-        let mut per_core = Vec::with_capacity(self.cpu_time.len());
-        for i in 0..other.cpu_time.len() {
-            let usage = diff_stats(i, other, self);
-            let cur_freq_mhz = get_cur_freq_mhz(i);
-            per_core.push(Utilization {
-                usage,
-                cur_freq_mhz,
-            })
+        let (cur, last) = (&self.kernel, &other.kernel);
+        Breakdown {
+            total: Times::between(&last.total, &cur.total),
+            per_cpu: (0..last.cpu_time.len())
+                .map(|i| match (last.cpu_time.get(i), cur.cpu_time.get(i)) {
+                    (Some(last), Some(cur)) => Times::between(last, cur),
+                    _ => Times::default(),
+                })
+                .collect(),
+            context_switches: cur.ctxt.saturating_sub(last.ctxt),
+            interrupts: self.interrupts.saturating_sub(other.interrupts),
+            softirqs: self.softirqs.saturating_sub(other.softirqs),
         }
-        per_core
     }
 }
 
+/// The CPU time breakdown and event counts between two `/proc/stat` samples.
+pub struct Breakdown {
+    pub total: Times,
+    pub per_cpu: Vec<Times>,
+    pub context_switches: u64,
+    pub interrupts: u64,
+    pub softirqs: u64,
+}
+
+/// The result of a utilization sample, empty on the first sample since there is nothing to diff against.
+#[derive(Default, Debug, Clone)]
+pub struct Sample {
+    /// The aggregate breakdown across all logical CPUs
+    pub total: Option<Times>,
+    pub rates: Option<Rates>,
+    pub logical: Vec<Utilization>,
+}
+
+/// System-wide event rates over the sample interval.
+#[derive(Default, Debug, Clone, Copy)]
+pub struct Rates {
+    pub context_switches_per_sec: f32,
+    pub interrupts_per_sec: f32,
+    pub softirqs_per_sec: f32,
+}
+
 #[derive(Debug, Clone)]
 pub struct Utilization {
     pub usage: f32,
     pub cur_freq_mhz: u32,
+    pub times: Times,
 }
 
-fn diff_stats(
-    cpu_idx: usize,
-    last_stat: &procfs::KernelStats,
-    cur_stat: &procfs::KernelStats,
-) -> f32 {
-    let Some(cur) = cur_stat.cpu_time.get(cpu_idx) else {
-        return 0.0;
-    };
-    let Some(last) = last_stat.cpu_time.get(cpu_idx) else {
-        return 0.0;
-    };
-    let (active_cur, total_cur) = cpu_times(cur);
-    let (active_last, total_last) = cpu_times(last);
-    ((active_cur - active_last) as f32 / (total_cur - total_last) as f32) * 100.0
+/// Percentages of CPU time spent in each state over the sample interval (0.0 to 100.0).
+#[derive(Default, Debug, Clone, Copy)]
+pub struct Times {
+    pub usage: f32,
+    /// User time, including niced processes
+    pub user: f32,
+    pub system: f32,
+    pub iowait: f32,
+    pub irq: f32,
+    pub softirq: f32,
+    pub steal: f32,
+}
+
+impl Times {
+    /// Computes the time breakdown between two `procfs::CpuTime` samples.
+    fn between(last: &procfs::CpuTime, cur: &procfs::CpuTime) -> Self {
+        let (active_cur, total_cur) = cpu_times(cur);
+        let (active_last, total_last) = cpu_times(last);
+        let total = total_cur.saturating_sub(total_last);
+        if total == 0 {
+            return Self::default();
+        }
+        let percent =
+            |cur: u64, last: u64| (cur.saturating_sub(last) as f32 / total as f32) * 100.0;
+        Self {
+            usage: percent(active_cur, active_last),
+            user: percent(cur.user + cur.nice, last.user + last.nice),
+            system: percent(cur.system, last.system),
+            iowait: percent(cur.iowait.unwrap_or(0), last.iowait.unwrap_or(0)),
+            irq: percent(cur.irq.unwrap_or(0), last.irq.unwrap_or(0)),
+            softirq: percent(cur.softirq.unwrap_or(0), last.softirq.unwrap_or(0)),
+            steal: percent(cur.steal.unwrap_or(0), last.steal.unwrap_or(0)),
+        }
+    }
 }
 
 /// Returns the active and total CPU times for a given `procfs::CpuTime` respectively.
@@ -87,14 +215,11 @@ fn cpu_times(time: &procfs::CpuTime) -> (u64, u64) {
     (active, active + time.idle + time.iowait.unwrap_or(0))
 }
 
-fn get_cur_freq_mhz(cpu_idx: usize) -> u32 {
+fn open_cur_freq(cpu_idx: usize) -> Option<OwnedFd> {
     rustix::fs::open(
         format!("/sys/devices/system/cpu/cpu{cpu_idx}/cpufreq/scaling_cur_freq"),
         OFlags::RDONLY | OFlags::CLOEXEC,
         Mode::empty(),
     )
     .ok()
-    .and_then(|fd| sysfs::read_u32(fd.as_fd()))
-    .unwrap_or(0)
-        / 1000
 }
@@ -24,6 +24,10 @@ pub struct Card {
     power_counters: Option<gpu_metrics::PowerCounters>,
     memory_total: Discovery<u64>,
     system_total: Discovery<u64>,
+    ecc: Sampler<super::EccCounts>,
+    memory_bus: Discovery<MemoryBus>,
+    architecture: String,
+    gfx_target: String,
 }
 
 impl Card {
@@ -62,6 +66,7 @@ impl Card {
             rustix::fs::OFlags::RDONLY | rustix::fs::OFlags::CLOEXEC,
             rustix::fs::Mode::empty(),
         )?;
+        let (architecture, gfx_target) = architecture(fd.as_fd(), &render_node);
         Ok(Self {
             card_fd: fd,
             primary_node,
@@ -72,6 +77,10 @@ impl Card {
             power_counters: None,
             memory_total: Discovery::default(),
             system_total: Discovery::default(),
+            ecc: Sampler::new(),
+            memory_bus: Discovery::default(),
+            architecture,
+            gfx_target,
         })
     }
 
@@ -108,7 +117,18 @@ impl Card {
 
 impl super::Card for Card {
     fn identify(&self) -> (String, String, Option<String>, Option<String>) {
-        (String::new(), String::new(), None, None)
+        (
+            sysfs::readat_string(self.card_fd.as_fd(), "device/vendor")
+                .and_then(|v| v.strip_prefix("0x").map(|v| v.to_string()))
+                .unwrap_or_default(),
+            sysfs::readat_string(self.card_fd.as_fd(), "device/device")
+                .and_then(|d| d.strip_prefix("0x").map(|d| d.to_string()))
+                .unwrap_or_default(),
+            sysfs::readat_string(self.card_fd.as_fd(), "device/subsystem_vendor")
+                .and_then(|sv| sv.strip_prefix("0x").map(|sv| sv.to_string())),
+            sysfs::readat_string(self.card_fd.as_fd(), "device/subsystem_device")
+                .and_then(|sd| sd.strip_prefix("0x").map(|sd| sd.to_string())),
+        )
     }
 
     fn collect(&mut self, config: &super::Config) -> anyhow::Result<super::Gpu> {
@@ -131,6 +151,8 @@ impl super::Card for Card {
             opengl: None,
             vulkan: None,
         });
+        gpu.architecture = self.architecture.clone();
+        gpu.compute_capability = self.gfx_target.clone();
         gpu.primary_node = self.primary_node.to_string_lossy().to_string();
         gpu.render_node = self.render_node.to_string_lossy().to_string();
         gpu.pci_id = rustix::fs::readlinkat(self.card_fd.as_fd(), "device", [])
@@ -145,11 +167,35 @@ impl super::Card for Card {
             .engines
             .then(|| gpu_metrics.engines())
             .unwrap_or_default();
+        // Older gpu_metrics revisions have no memory controller activity, which sysfs exposes on dGPUs
+        if config.engines
+            && super::memory_controller_utilization(&gpu.engines).is_none()
+            && let Some(busy) = sysfs::readat_u64(self.card_fd.as_fd(), "device/mem_busy_percent")
+        {
+            gpu.engines.push(Engine {
+                identifier: Some(EngineIdentifier {
+                    r#type: EngineType::MemoryController as i32,
+                    index: 0,
+                    clock: Some(ClockIdentifier {
+                        domain: ClockDomain::Memory as i32,
+                        index: 0,
+                    }),
+                }),
+                utilization: busy,
+            });
+        }
         gpu.clocks = config
             .clocks
             .then(|| gpu_metrics.clocks())
             .unwrap_or_default();
         gpu.memory = config.memory.then(|| self.memory()).unwrap_or_default();
+        if config.memory {
+            // Static, so it's only queried once
+            gpu.memory_bus = self
+                .memory_bus
+                .probe(|| memory_bus(self.card_fd.as_fd(), &self.render_node))
+                .cloned();
+        }
         gpu.power = config
             .power
             .then(|| {
@@ -165,10 +211,36 @@ impl super::Card for Card {
             .thermals
             .then(|| gpu_metrics.thermals())
             .unwrap_or_default();
+        if config.power {
+            gpu.throttle_reasons = gpu_metrics.throttle_reasons();
+            gpu.is_throttled = !gpu.throttle_reasons.is_empty();
+        }
+
+        gpu.pcie = config
+            .pcie
+            .then(|| super::sysfs_pcie(self.card_fd.as_fd()))
+            .flatten();
+        gpu.fans = config
+            .fans
+            .then(|| sysfs::first_hwmon_subdir_at(self.card_fd.as_fd(), "device/hwmon"))
+            .flatten()
+            .map(|hwmon| super::hwmon_fans(hwmon.as_fd()))
+            .unwrap_or_default();
+
+        gpu.dpm = config.clocks.then(|| dpm(self.card_fd.as_fd())).flatten();
+        if config.ecc {
+            gpu.ecc = ras_ecc(self.card_fd.as_fd());
+            if let Some(ecc) = gpu.ecc.as_mut() {
+                super::sample_ecc(&mut self.ecc, ecc);
+            }
+        }
 
         populate_max_clocks(self.card_fd.as_fd(), gpu.clocks.as_mut());
         populate_max_power(self.card_fd.as_fd(), gpu.power.as_mut());
         populate_max_thermal(self.card_fd.as_fd(), gpu.thermals.as_mut());
+        if config.thermals {
+            merge_hwmon_thermals(self.card_fd.as_fd(), &mut gpu.thermals);
+        }
 
         Ok(gpu)
     }
@@ -204,7 +276,7 @@ impl super::Card for Card {
                                 }),
                                 "dma" => engine_utilization.push(Engine {
                                     identifier: Some(EngineIdentifier {
-                                        r#type: EngineType::MemoryController as i32,
+                                        r#type: EngineType::Copy as i32,
                                         index: 0,
                                         clock: None,
                                     }),
@@ -255,16 +327,31 @@ impl super::Card for Card {
                                 }
                             }
                         }
-                        output.processes.push(Process {
+                        let mut gpu_process = Process {
                             pid: *pid,
                             engine_utilization,
                             vram_usage: gpu_usage.vram_usage,
                             gtt_usage: gpu_usage.system_usage,
-                        })
+                            ..Default::default()
+                        };
+                        super::fill_owner(&mut gpu_process, process);
+                        output.processes.push(gpu_process);
                     }
                 }
             }
         }
+
+        // gpu_metrics only has the graphics and a combined multimedia activity, so compute, copy
+        // and the individual video engines are the sum of the per-process fdinfo utilization instead
+        for engine in super::process_engines(&output.processes) {
+            let reported = output.engines.iter().any(|reported| {
+                reported.identifier.map(|identifier| identifier.r#type)
+                    == engine.identifier.map(|identifier| identifier.r#type)
+            });
+            if !reported {
+                output.engines.push(engine);
+            }
+        }
         Ok(())
     }
 
@@ -307,6 +394,244 @@ fn get_brand_name(fd: BorrowedFd) -> anyhow::Result<String> {
     ))
 }
 
+/// Reads the VRAM details from the device info query, erroring on APUs that have no dedicated VRAM.
+fn memory_bus(card: BorrowedFd, render_node: &std::path::Path) -> anyhow::Result<MemoryBus> {
+    let render_node = rustix::fs::open(
+        render_node,
+        rustix::fs::OFlags::RDWR | rustix::fs::OFlags::CLOEXEC,
+        rustix::fs::Mode::empty(),
+    )?;
+    let info = drm_amdgpu::device_info(render_node.as_fd())?;
+    if info.ids_flags & drm_amdgpu::AMDGPU_IDS_FLAGS_FUSION != 0 {
+        anyhow::bail!("APUs share system memory");
+    }
+    // reported in kHz
+    let max_clock_mhz = (info.max_memory_clock / 1000) as u32;
+    Ok(MemoryBus {
+        vendor: sysfs::readat_string(card, "device/mem_info_vram_vendor").unwrap_or_default(),
+        r#type: vram_type_name(info.vram_type).to_string(),
+        width_bits: info.vram_bit_width,
+        max_clock_mhz,
+        bandwidth_bytes_per_second: super::memory_bandwidth(
+            info.vram_bit_width,
+            max_clock_mhz,
+            memory_ops_per_clock(info.vram_type),
+        ),
+    })
+}
+
+/// Finds the architecture and gfx target of a card. The graphics IP version from the IP discovery
+/// table is preferred, GPUs older than Navi don't have one and fall back to the ASIC family.
+fn architecture(card: BorrowedFd, render_node: &std::path::Path) -> (String, String) {
+    let version = |name| sysfs::readat_u32(card, &format!("device/ip_discovery/die/0/GC/0/{name}"));
+    if let (Some(major), Some(minor), Some(revision)) =
+        (version("major"), version("minor"), version("revision"))
+    {
+        let gfx_target = if major >= 10 {
+            format!("gfx{major}{minor}{revision:x}")
+        } else {
+            String::new()
+        };
+        return (
+            gc_architecture(major, minor, revision).to_string(),
+            gfx_target,
+        );
+    }
+    let info = rustix::fs::open(
+        render_node,
+        rustix::fs::OFlags::RDWR | rustix::fs::OFlags::CLOEXEC,
+        rustix::fs::Mode::empty(),
+    )
+    .and_then(|render_node| drm_amdgpu::device_info(render_node.as_fd()));
+    match info {
+        Ok(info) => (
+            family_architecture(info.family, info.external_rev).to_string(),
+            String::new(),
+        ),
+        Err(err) => {
+            tracing::debug!("could not query amdgpu device info: {}", err);
+            (String::new(), String::new())
+        }
+    }
+}
+
+/// Names the architecture of a graphics (GC) IP version.
+fn gc_architecture(major: u32, minor: u32, revision: u32) -> &'static str {
+    match (major, minor, revision) {
+        (9, 4, 1) => "CDNA",
+        (9, 4, 2) => "CDNA 2",
+        (9, 4, 3..) => "CDNA 3",
+        (9, 5, _) => "CDNA 4",
+        (9, ..) => "GCN 5",
+        (10, 1, _) => "RDNA",
+        (10, 3, _) => "RDNA 2",
+        (11, 0, _) => "RDNA 3",
+        (11, 5, _) => "RDNA 3.5",
+        (12, ..) => "RDNA 4",
+        _ => "",
+    }
+}
+
+/// Names the architecture of an AMDGPU_FAMILY_* value, the external revision separates Polaris from
+/// the older chips of the VI family.
+fn family_architecture(family: u32, external_rev: u32) -> &'static str {
+    match family {
+        110 => "GCN 1",
+        120 | 125 => "GCN 2",
+        130 if external_rev >= 0x50 => "GCN 4",
+        130 | 135 => "GCN 3",
+        141 | 142 => "GCN 5",
+        143 => "RDNA",
+        144 | 146 | 149 | 151 => "RDNA 2",
+        145 | 148 => "RDNA 3",
+        150 => "RDNA 3.5",
+        152 => "RDNA 4",
+        _ => "",
+    }
+}
+
+/// Names an AMDGPU_VRAM_TYPE_* value.
+fn vram_type_name(vram_type: u32) -> &'static str {
+    match vram_type {
+        1 => "GDDR1",
+        2 => "DDR2",
+        3 => "GDDR3",
+        4 => "GDDR4",
+        5 => "GDDR5",
+        6 => "HBM",
+        7 => "DDR3",
+        8 => "DDR4",
+        9 => "GDDR6",
+        10 => "DDR5",
+        11 => "LPDDR4",
+        12 => "LPDDR5",
+        13 => "HBM3E",
+        _ => "",
+    }
+}
+
+/// Transfers per memory clock of an AMDGPU_VRAM_TYPE_* (the MemoryOpsPerClockTable used by Mesa and PAL),
+/// 0 for unknown types so no bandwidth is reported.
+fn memory_ops_per_clock(vram_type: u32) -> u32 {
+    match vram_type {
+        1..=4 | 6..=8 | 11 | 13 => 2,
+        5 | 10 | 12 => 4,
+        9 => 16,
+        _ => 0,
+    }
+}
+
+/// Sums the RAS error counters of every IP block, `None` on GPUs without RAS support.
+fn ras_ecc(fd: BorrowedFd) -> Option<Ecc> {
+    let ras = rustix::fs::openat(
+        fd,
+        "device/ras",
+        rustix::fs::OFlags::DIRECTORY | rustix::fs::OFlags::RDONLY | rustix::fs::OFlags::CLOEXEC,
+        rustix::fs::Mode::empty(),
+    )
+    .ok()?;
+    // "feature mask: 0x..." of the blocks with RAS enabled
+    let enabled = sysfs::readat_string(ras.as_fd(), "features")
+        .and_then(|features| {
+            let (_, mask) = features.split_once("0x")?;
+            u64::from_str_radix(mask.trim(), 16).ok()
+        })
+        .is_some_and(|mask| mask != 0);
+    let mut ecc = Ecc {
+        enabled,
+        ..Default::default()
+    };
+    for entry in rustix::fs::Dir::read_from(&ras).ok()?.flatten() {
+        let name = entry.file_name().to_string_lossy();
+        // one file per IP block, e.g. umc_err_count for the memory controller
+        if !name.ends_with("_err_count") {
+            continue;
+        }
+        let Some(counts) = sysfs::readat_string(ras.as_fd(), name.as_ref()) else {
+            continue;
+        };
+        let (corrected, uncorrected) = parse_ras_count(&counts);
+        ecc.corrected += corrected;
+        ecc.uncorrected += uncorrected;
+    }
+    Some(ecc)
+}
+
+/// Parses a RAS error count file ("ue: 0\nce: 1"), returning the corrected and uncorrected counts.
+fn parse_ras_count(s: &str) -> (u64, u64) {
+    let mut counts = (0, 0);
+    for line in s.lines() {
+        let Some((kind, count)) = line.split_once(':') else {
+            continue;
+        };
+        let count = count.trim().parse().unwrap_or(0);
+        match kind.trim() {
+            "ce" => counts.0 = count,
+            "ue" => counts.1 = count,
+            _ => {}
+        }
+    }
+    counts
+}
+
+/// Reads the DPM state tables and power management settings, `None` when power management is unavailable.
+fn dpm(fd: BorrowedFd) -> Option<Dpm> {
+    let sclk = sysfs::readat_string(fd, "device/pp_dpm_sclk").and_then(|s| parse_dpm_table(&s));
+    let mclk = sysfs::readat_string(fd, "device/pp_dpm_mclk").and_then(|s| parse_dpm_table(&s));
+    let performance_level =
+        sysfs::readat_string(fd, "device/power_dpm_force_performance_level").unwrap_or_default();
+    let power_profile = sysfs::readat_string(fd, "device/pp_power_profile_mode")
+        .and_then(|s| parse_power_profile(&s))
+        .unwrap_or_default();
+    if sclk.is_none() && mclk.is_none() && performance_level.is_empty() {
+        return None;
+    }
+    Some(Dpm {
+        sclk,
+        mclk,
+        performance_level,
+        power_profile,
+    })
+}
+
+/// Parses a pp_dpm_* file, where each line is a state (e.g. "1: 2000Mhz *") and the active one is marked with '*'.
+fn parse_dpm_table(s: &str) -> Option<DpmTable> {
+    let mut table = DpmTable::default();
+    for line in s.lines() {
+        let Some((index, state)) = line.split_once(':') else {
+            continue;
+        };
+        // Deep sleep states are listed as "S: 19Mhz" on some SMUs and aren't part of the table
+        let Ok(index) = index.trim().parse::<u32>() else {
+            continue;
+        };
+        let Some(mhz) = state
+            .split_whitespace()
+            .next()
+            .and_then(|f| f.strip_suffix("Mhz").or_else(|| f.strip_suffix("MHz")))
+            .and_then(|f| f.parse::<u32>().ok())
+        else {
+            continue;
+        };
+        if state.contains('*') {
+            table.current_state = Some(index);
+        }
+        table.states_mhz.push(mhz);
+    }
+    (!table.states_mhz.is_empty()).then_some(table)
+}
+
+/// Finds the active profile in pp_power_profile_mode, whose layout differs per SMU generation
+/// but always starts each profile with its index and name and marks the active one with '*'.
+fn parse_power_profile(s: &str) -> Option<String> {
+    s.lines().find_map(|line| {
+        let (header, _) = line.split_once('*')?;
+        let mut tokens = header.split_whitespace();
+        tokens.next()?.parse::<u32>().ok()?;
+        Some(tokens.next()?.trim_end_matches(':').to_string())
+    })
+}
+
 fn populate_max_clocks(fd: BorrowedFd, clocks: &mut [Clock]) {
     for clock in clocks.iter_mut() {
         let Some(identifier) = clock.identifier.as_ref() else {
@@ -413,12 +738,42 @@ fn populate_max_power(fd: BorrowedFd, power: Option<&mut Power>) {
     let Some(power) = power else {
         return;
     };
-    let Some(power1_cap) = sysfs::first_hwmon_subdir_at(fd, "device/hwmon")
-        .and_then(|hwmon| sysfs::readat_u32(hwmon.as_fd(), "power1_cap"))
-    else {
+    let Some(hwmon) = sysfs::first_hwmon_subdir_at(fd, "device/hwmon") else {
+        return;
+    };
+    // hwmon power caps are in microwatts
+    let read_mw = |name: &str| sysfs::readat_u64(hwmon.as_fd(), name).map(|uw| (uw / 1000) as u32);
+    let Some(power1_cap) = read_mw("power1_cap") else {
         return;
     };
     power.max_power_mw = power1_cap;
+    power.limits = Some(PowerLimits {
+        min_mw: read_mw("power1_cap_min"),
+        max_mw: read_mw("power1_cap_max"),
+        default_mw: read_mw("power1_cap_default"),
+    });
+}
+
+/// Labels the gpu_metrics temperatures and adds any hwmon sensors that gpu_metrics doesn't report.
+fn merge_hwmon_thermals(fd: BorrowedFd, thermals: &mut Vec<Thermal>) {
+    let hwmon_thermals = sysfs::first_hwmon_subdir_at(fd, "device/hwmon")
+        .map(|hwmon| super::hwmon_thermals(hwmon.as_fd()))
+        .unwrap_or_default();
+    for thermal in thermals.iter_mut() {
+        thermal.label = hwmon_thermals
+            .iter()
+            .find(|sensor| sensor.location == thermal.location)
+            .map(|sensor| sensor.label.clone())
+            .unwrap_or_else(|| super::location_label(thermal.location()).to_string());
+    }
+    for sensor in hwmon_thermals {
+        if thermals
+            .iter()
+            .all(|thermal| thermal.location != sensor.location)
+        {
+            thermals.push(sensor);
+        }
+    }
 }
 
 fn populate_max_thermal(fd: BorrowedFd, thermals: &mut [Thermal]) {
@@ -437,3 +792,214 @@ fn populate_max_thermal(fd: BorrowedFd, thermals: &mut [Thermal]) {
         thermal.max_celsius = temp / 1000;
     }
 }
+
+mod drm_amdgpu {
+    use crate::_ioc;
+    use crate::collector::helpers::ioctl::*;
+    use rustix::fd::BorrowedFd;
+
+    /// The ioctl directory (drm)
+    const DRM_IOCTL_BASE: u32 = 'd' as u32;
+
+    /// The offset at which driver-specific drm commands begin
+    const DRM_COMMAND_BASE: u32 = 0x40;
+
+    /// The offset of the info query command
+    const DRM_AMDGPU_INFO: u32 = 0x05;
+
+    /// The info query command for drm ioctl (write-only, the result is copied to the return pointer)
+    const DRM_IOCTL_AMDGPU_INFO: u32 = _ioc!(
+        _IOC_WRITE,
+        DRM_IOCTL_BASE,
+        DRM_COMMAND_BASE + DRM_AMDGPU_INFO,
+        size_of::<Info>() as u32
+    );
+
+    /// Queries the static device information
+    const AMDGPU_INFO_DEV_INFO: u32 = 0x16;
+
+    /// Set in [`DeviceInfo::ids_flags`] for APUs
+    pub const AMDGPU_IDS_FLAGS_FUSION: u64 = 0x1;
+
+    /// struct drm_amdgpu_info (linux/include/uapi/drm/amdgpu_drm.h)
+    /// The query specific parameters are a union of at most 16 bytes, none are needed for the device info
+    #[repr(C)]
+    struct Info {
+        return_pointer: u64,
+        return_size: u32,
+        query: u32,
+        _params: [u32; 4],
+    }
+
+    unsafe impl rustix::ioctl::Ioctl for Info {
+        type Output = ();
+        const IS_MUTATING: bool = false;
+
+        fn opcode(&self) -> rustix::ioctl::Opcode {
+            DRM_IOCTL_AMDGPU_INFO
+        }
+
+        fn as_ptr(&mut self) -> *mut std::ffi::c_void {
+            self as *mut _ as *mut std::ffi::c_void
+        }
+
+        unsafe fn output_from_ptr(
+            _: rustix::ioctl::IoctlOutput,
+            _: *mut std::ffi::c_void,
+        ) -> rustix::io::Result<Self::Output> {
+            Ok(())
+        }
+    }
+
+    /// The leading fields of struct drm_amdgpu_info_device (linux/include/uapi/drm/amdgpu_drm.h),
+    /// the kernel only copies as much as was asked for so the remaining fields can be left out
+    #[repr(C)]
+    #[derive(Default)]
+    pub struct DeviceInfo {
+        pub device_id: u32,
+        pub chip_rev: u32,
+        pub external_rev: u32,
+        pub pci_rev: u32,
+        pub family: u32,
+        pub num_shader_engines: u32,
+        pub num_shader_arrays_per_engine: u32,
+        pub gpu_counter_freq: u32,
+        pub max_engine_clock: u64,
+        pub max_memory_clock: u64,
+        pub cu_active_number: u32,
+        pub cu_ao_mask: u32,
+        pub cu_bitmap: [[u32; 4]; 4],
+        pub enabled_rb_pipes_mask: u32,
+        pub num_rb_pipes: u32,
+        pub num_hw_gfx_contexts: u32,
+        pub pcie_gen: u32,
+        pub ids_flags: u64,
+        pub virtual_address_offset: u64,
+        pub virtual_address_max: u64,
+        pub virtual_address_alignment: u32,
+        pub pte_fragment_size: u32,
+        pub gart_page_size: u32,
+        pub ce_ram_size: u32,
+        pub vram_type: u32,
+        pub vram_bit_width: u32,
+    }
+
+    /// Queries the device information through a render node of the card
+    pub fn device_info(render_node: BorrowedFd) -> rustix::io::Result<DeviceInfo> {
+        let mut info = DeviceInfo::default();
+        let query = Info {
+            return_pointer: &mut info as *mut DeviceInfo as u64,
+            return_size: size_of::<DeviceInfo>() as u32,
+            query: AMDGPU_INFO_DEV_INFO,
+            _params: [0; 4],
+        };
+        unsafe { rustix::ioctl::ioctl(render_node, query) }?;
+        Ok(info)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::collector::helpers::test::FakeSysfs;
+
+    #[test]
+    fn architectures() {
+        assert_eq!(gc_architecture(10, 3, 0), "RDNA 2"); // Navi 21
+        assert_eq!(gc_architecture(11, 0, 0), "RDNA 3"); // Navi 31
+        assert_eq!(gc_architecture(9, 4, 2), "CDNA 2"); // MI250X
+        assert_eq!(gc_architecture(9, 0, 1), "GCN 5"); // Vega 10
+        assert_eq!(family_architecture(130, 0x3c), "GCN 3"); // Fiji
+        assert_eq!(family_architecture(130, 0x50), "GCN 4"); // Polaris 10
+        assert_eq!(family_architecture(143, 0), "RDNA");
+        assert_eq!(family_architecture(0, 0), "");
+    }
+
+    #[test]
+    fn dpm_tables() {
+        // Navi 21
+        let sclk = parse_dpm_table("0: 500Mhz \n1: 2105Mhz *\n2: 2660Mhz \n").unwrap();
+        assert_eq!(sclk.states_mhz, vec![500, 2105, 2660]);
+        assert_eq!(sclk.current_state, Some(1));
+        let mclk = parse_dpm_table("0: 96Mhz *\n1: 456Mhz \n2: 673Mhz \n3: 1000Mhz \n").unwrap();
+        assert_eq!(mclk.states_mhz, vec![96, 456, 673, 1000]);
+        assert_eq!(mclk.current_state, Some(0));
+
+        // Vega 10
+        let sclk = parse_dpm_table(
+            "0: 852Mhz \n1: 991Mhz \n2: 1084Mhz \n3: 1138Mhz \n4: 1200Mhz \n5: 1401Mhz \n6: 1536Mhz \n7: 1630Mhz *\n",
+        )
+        .unwrap();
+        assert_eq!(sclk.states_mhz.len(), 8);
+        assert_eq!(sclk.current_state, Some(7));
+        assert_eq!(sclk.states_mhz.last(), Some(&1630));
+
+        // APUs list a deep sleep state that isn't indexed
+        let sclk = parse_dpm_table("S: 19Mhz *\n0: 400Mhz \n1: 2200Mhz \n").unwrap();
+        assert_eq!(sclk.states_mhz, vec![400, 2200]);
+        assert_eq!(sclk.current_state, None);
+
+        assert!(parse_dpm_table("").is_none());
+    }
+
+    #[test]
+    fn ras_counters() {
+        let root = FakeSysfs::new("ras");
+        root.write("device/ras/features", "feature mask: 0x3fff\n");
+        root.write("device/ras/umc_err_count", "ue: 1\nce: 4\n");
+        root.write("device/ras/gfx_err_count", "ue: 0\nce: 2\n");
+        root.write("device/ras/auto_reboot", "0\n");
+
+        let card = root.open_dir("");
+        let ecc = ras_ecc(card.as_fd()).unwrap();
+        assert!(ecc.enabled);
+        assert_eq!(ecc.corrected, 6);
+        assert_eq!(ecc.uncorrected, 1);
+        assert_eq!(ecc.corrected_since_last_sample, None);
+    }
+
+    #[test]
+    fn vram_types() {
+        assert_eq!(std::mem::offset_of!(drm_amdgpu::DeviceInfo, vram_type), 176);
+        assert_eq!(vram_type_name(9), "GDDR6");
+        assert_eq!(vram_type_name(6), "HBM");
+        assert_eq!(vram_type_name(0), "");
+        // RX 6800 XT: 256-bit GDDR6 with a 1000 MHz memory clock
+        assert_eq!(
+            super::super::memory_bandwidth(256, 1000, memory_ops_per_clock(9)),
+            512_000_000_000
+        );
+        assert_eq!(memory_ops_per_clock(0), 0);
+    }
+
+    #[test]
+    fn power_profile() {
+        // Navi 21
+        let navi = "PROFILE_INDEX(NAME) CLOCK_TYPE(NAME) FPS MinActiveFreqType MinActiveFreq BoosterFreqType BoosterFreq PD_Data_limit_c PD_Data_error_coeff PD_Data_error_rate_coeff
+ 0 BOOTUP_DEFAULT*:
+                        0(       GFXCLK)       0       5       1       0       4     800 4587520  -65536       0
+                        1(       SOCCLK)       0       5       1       0       1       0 3276800   -6553   -65536
+ 1 3D_FULL_SCREEN :
+                        0(       GFXCLK)       0       5       1       0       4     650  655360   -6553       0
+";
+        assert_eq!(parse_power_profile(navi).as_deref(), Some("BOOTUP_DEFAULT"));
+
+        // Vega 10
+        let vega = "NUM        MODE_NAME BUSY_SET_POINT FPS USE_RLC_BUSY MIN_ACTIVE_LEVEL
+  0 BOOTUP_DEFAULT :             70  60          0              0
+  1 3D_FULL_SCREEN*:             70  60          1              3
+  2   POWER_SAVING :             90  60          0              0
+";
+        assert_eq!(parse_power_profile(vega).as_deref(), Some("3D_FULL_SCREEN"));
+
+        // Polaris puts the marker after a space
+        let polaris = "NUM        MODE_NAME     SCLK_UP_HYST   SCLK_DOWN_HYST SCLK_ACTIVE_LEVEL
+  0   BOOTUP_DEFAULT:        -        -          -
+  1 3D_FULL_SCREEN *:        0      100         30
+";
+        assert_eq!(
+            parse_power_profile(polaris).as_deref(),
+            Some("3D_FULL_SCREEN")
+        );
+    }
+}
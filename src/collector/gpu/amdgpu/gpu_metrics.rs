@@ -157,6 +157,75 @@ impl GpuMetrics {
             GpuMetrics::F3C0(metrics) => metrics.thermals(),
         }
     }
+
+    /// Decodes the active throttlers, empty when unconstrained or not reported by this table version.
+    pub fn throttle_reasons(&self) -> Vec<String> {
+        let status = match self {
+            GpuMetrics::F1C0(metrics) => metrics.indep_throttle_status(),
+            GpuMetrics::F1C1(metrics) => metrics.indep_throttle_status(),
+            GpuMetrics::F1C2(metrics) => metrics.indep_throttle_status(),
+            GpuMetrics::F1C3(metrics) => metrics.indep_throttle_status(),
+            GpuMetrics::F1C4(metrics) => metrics.indep_throttle_status(),
+            GpuMetrics::F1C5(metrics) => metrics.indep_throttle_status(),
+            GpuMetrics::F1C6(metrics) => metrics.indep_throttle_status(),
+            GpuMetrics::F1C7(metrics) => metrics.indep_throttle_status(),
+            GpuMetrics::F1C8(metrics) => metrics.indep_throttle_status(),
+            GpuMetrics::F1C9(metrics) => metrics.indep_throttle_status(),
+            GpuMetrics::F2C0(metrics) => metrics.indep_throttle_status(),
+            GpuMetrics::F2C1(metrics) => metrics.indep_throttle_status(),
+            GpuMetrics::F2C2(metrics) => metrics.indep_throttle_status(),
+            GpuMetrics::F2C3(metrics) => metrics.indep_throttle_status(),
+            GpuMetrics::F2C4(metrics) => metrics.indep_throttle_status(),
+            GpuMetrics::F3C0(metrics) => metrics.indep_throttle_status(),
+        };
+        status.map(decode_throttlers).unwrap_or_default()
+    }
+}
+
+/// SMU_THROTTLER_*_BIT names (drivers/gpu/drm/amd/pm/swsmu/inc/amdgpu_smu.h)
+const THROTTLERS: &[(u32, &str)] = &[
+    (0, "ppt0"),
+    (1, "ppt1"),
+    (2, "ppt2"),
+    (3, "ppt3"),
+    (4, "spl"),
+    (5, "fppt"),
+    (6, "sppt"),
+    (7, "sppt_apu"),
+    (16, "tdc_gfx"),
+    (17, "tdc_soc"),
+    (18, "tdc_mem"),
+    (19, "tdc_vdd"),
+    (20, "tdc_cvip"),
+    (21, "edc_cpu"),
+    (22, "edc_gfx"),
+    (23, "apcc"),
+    (32, "temp_gpu"),
+    (33, "temp_core"),
+    (34, "temp_mem"),
+    (35, "temp_edge"),
+    (36, "temp_hotspot"),
+    (37, "temp_soc"),
+    (38, "temp_vr_gfx"),
+    (39, "temp_vr_soc"),
+    (40, "temp_vr_mem0"),
+    (41, "temp_vr_mem1"),
+    (42, "temp_liquid0"),
+    (43, "temp_liquid1"),
+    (44, "vrhot0"),
+    (45, "vrhot1"),
+    (56, "prochot_cpu"),
+    (57, "prochot_gfx"),
+    (58, "ppm"),
+    (59, "fit"),
+];
+
+fn decode_throttlers(status: u64) -> Vec<String> {
+    THROTTLERS
+        .iter()
+        .filter(|(bit, _)| status & (1 << bit) != 0)
+        .map(|(_, name)| name.to_string())
+        .collect()
 }
 
 fn from_bytes<T>(bytes: &[u8]) -> Option<T> {
@@ -173,6 +242,10 @@ trait AmdgpuMetrics {
     fn clocks(&self) -> Vec<Clock>;
     fn power(&self, last: Option<&PowerCounters>) -> Option<(Power, PowerCounters)>;
     fn thermals(&self) -> Vec<Thermal>;
+    /// The SMU throttler bitmask, only present in the newer table revisions
+    fn indep_throttle_status(&self) -> Option<u64> {
+        None
+    }
 }
 
 /// Contains the struct definitions from the amdgpu driver.
@@ -382,6 +455,7 @@ mod amdgpu {
                             max_power_mw: 0, // needs to be populated from hwmon (if it exists)
                             is_power_throttled: false, // unrealistic due to the value reported being different depending on gpu
                             is_thermal_throttled: false, // same as above
+                            limits: None,
                         },
                         PowerCounters {
                             prochot_residency_acc: 0,
@@ -404,6 +478,7 @@ mod amdgpu {
                         location: 1,
                         current_celsius: self.temperature_edge as u32,
                         max_celsius: 0, // needs to be populated from hwmon (if it exists)
+                        label: String::new(), // labelled from hwmon later
                     });
                 }
                 if self.temperature_hotspot != 0xFFFF {
@@ -411,6 +486,7 @@ mod amdgpu {
                         location: ThermalLocation::Hotspot as i32,
                         current_celsius: self.temperature_hotspot as u32,
                         max_celsius: 0, // needs to be populated from hwmon (if it exists)
+                        label: String::new(), // labelled from hwmon later
                     });
                 }
                 if self.temperature_mem != 0xFFFF {
@@ -418,6 +494,7 @@ mod amdgpu {
                         location: ThermalLocation::Memory as i32,
                         current_celsius: self.temperature_mem as u32,
                         max_celsius: 0, // needs to be populated from hwmon (if it exists)
+                        label: String::new(), // labelled from hwmon later
                     });
                 }
                 if self.temperature_vrgfx != 0xFFFF {
@@ -425,6 +502,7 @@ mod amdgpu {
                         location: ThermalLocation::Vrgfx as i32,
                         current_celsius: self.temperature_vrgfx as u32,
                         max_celsius: 0, // needs to be populated from hwmon (if it exists)
+                        label: String::new(), // labelled from hwmon later
                     });
                 }
                 if self.temperature_vrsoc != 0xFFFF {
@@ -432,6 +510,7 @@ mod amdgpu {
                         location: ThermalLocation::Vrsoc as i32,
                         current_celsius: self.temperature_vrsoc as u32,
                         max_celsius: 0, // needs to be populated from hwmon (if it exists)
+                        label: String::new(), // labelled from hwmon later
                     });
                 }
                 if self.temperature_vrmem != 0xFFFF {
@@ -439,6 +518,7 @@ mod amdgpu {
                         location: ThermalLocation::Vrmem as i32,
                         current_celsius: self.temperature_vrmem as u32,
                         max_celsius: 0, // needs to be populated from hwmon (if it exists)
+                        label: String::new(), // labelled from hwmon later
                     });
                 }
 
@@ -657,6 +737,7 @@ mod amdgpu {
                             max_power_mw: 0, // needs to be populated from hwmon (if it exists)
                             is_power_throttled: false, // read v1_0's comment
                             is_thermal_throttled: false,
+                            limits: None,
                         },
                         PowerCounters {
                             prochot_residency_acc: 0,
@@ -679,6 +760,7 @@ mod amdgpu {
                         location: ThermalLocation::Edge as i32,
                         current_celsius: self.temperature_edge as u32,
                         max_celsius: 0,
+                        label: String::new(), // labelled from hwmon later
                     });
                 }
                 if self.temperature_hotspot != 0xFFFF {
@@ -686,6 +768,7 @@ mod amdgpu {
                         location: ThermalLocation::Hotspot as i32,
                         current_celsius: self.temperature_hotspot as u32,
                         max_celsius: 0,
+                        label: String::new(), // labelled from hwmon later
                     });
                 }
                 if self.temperature_mem != 0xFFFF {
@@ -693,6 +776,7 @@ mod amdgpu {
                         location: ThermalLocation::Memory as i32,
                         current_celsius: self.temperature_mem as u32,
                         max_celsius: 0,
+                        label: String::new(), // labelled from hwmon later
                     });
                 }
                 if self.temperature_vrgfx != 0xFFFF {
@@ -700,6 +784,7 @@ mod amdgpu {
                         location: ThermalLocation::Vrgfx as i32,
                         current_celsius: self.temperature_vrgfx as u32,
                         max_celsius: 0,
+                        label: String::new(), // labelled from hwmon later
                     });
                 }
                 if self.temperature_vrmem != 0xFFFF {
@@ -707,6 +792,7 @@ mod amdgpu {
                         location: ThermalLocation::Vrmem as i32,
                         current_celsius: self.temperature_vrmem as u32,
                         max_celsius: 0,
+                        label: String::new(), // labelled from hwmon later
                     });
                 }
                 if self.temperature_vrsoc != 0xFFFF {
@@ -714,6 +800,7 @@ mod amdgpu {
                         location: ThermalLocation::Vrsoc as i32,
                         current_celsius: self.temperature_vrsoc as u32,
                         max_celsius: 0,
+                        label: String::new(), // labelled from hwmon later
                     });
                 }
 
@@ -927,6 +1014,7 @@ mod amdgpu {
                             max_power_mw: 0, // needs to be populated from hwmon (if it exists)
                             is_power_throttled: false, // read v1_0's comment
                             is_thermal_throttled: false,
+                            limits: None,
                         },
                         PowerCounters {
                             prochot_residency_acc: 0,
@@ -949,6 +1037,7 @@ mod amdgpu {
                         location: ThermalLocation::Edge as i32,
                         current_celsius: self.temperature_edge as u32,
                         max_celsius: 0,
+                        label: String::new(), // labelled from hwmon later
                     });
                 }
                 if self.temperature_hotspot != 0xFFFF {
@@ -956,6 +1045,7 @@ mod amdgpu {
                         location: ThermalLocation::Hotspot as i32,
                         current_celsius: self.temperature_hotspot as u32,
                         max_celsius: 0,
+                        label: String::new(), // labelled from hwmon later
                     });
                 }
                 if self.temperature_mem != 0xFFFF {
@@ -963,6 +1053,7 @@ mod amdgpu {
                         location: ThermalLocation::Memory as i32,
                         current_celsius: self.temperature_mem as u32,
                         max_celsius: 0,
+                        label: String::new(), // labelled from hwmon later
                     });
                 }
                 if self.temperature_vrgfx != 0xFFFF {
@@ -970,6 +1061,7 @@ mod amdgpu {
                         location: ThermalLocation::Vrgfx as i32,
                         current_celsius: self.temperature_vrgfx as u32,
                         max_celsius: 0,
+                        label: String::new(), // labelled from hwmon later
                     });
                 }
                 if self.temperature_vrsoc != 0xFFFF {
@@ -977,6 +1069,7 @@ mod amdgpu {
                         location: ThermalLocation::Vrsoc as i32,
                         current_celsius: self.temperature_vrsoc as u32,
                         max_celsius: 0,
+                        label: String::new(), // labelled from hwmon later
                     });
                 }
                 if self.temperature_vrmem != 0xFFFF {
@@ -984,6 +1077,7 @@ mod amdgpu {
                         location: ThermalLocation::Vrmem as i32,
                         current_celsius: self.temperature_vrmem as u32,
                         max_celsius: 0,
+                        label: String::new(), // labelled from hwmon later
                     });
                 }
 
@@ -1068,6 +1162,10 @@ mod amdgpu {
         }
 
         impl AmdgpuMetrics for C3 {
+            fn indep_throttle_status(&self) -> Option<u64> {
+                Some(self.indep_throttle_status)
+            }
+
             fn from_bytes(bytes: &[u8]) -> anyhow::Result<GpuMetrics> {
                 tracing::trace!("reading format 1 content 3");
                 let bytes = from_bytes::<Self>(bytes);
@@ -1218,6 +1316,7 @@ mod amdgpu {
                             is_thermal_throttled: (self.indep_throttle_status
                                 & INDEP_THERMAL_THROTTLE_MASK)
                                 != 0,
+                            limits: None,
                         },
                         PowerCounters {
                             prochot_residency_acc: 0,
@@ -1240,6 +1339,7 @@ mod amdgpu {
                         location: ThermalLocation::Edge as i32,
                         current_celsius: self.temperature_edge as u32,
                         max_celsius: 0,
+                        label: String::new(), // labelled from hwmon later
                     });
                 }
                 if self.temperature_hotspot != 0xFFFF {
@@ -1247,6 +1347,7 @@ mod amdgpu {
                         location: ThermalLocation::Hotspot as i32,
                         current_celsius: self.temperature_hotspot as u32,
                         max_celsius: 0,
+                        label: String::new(), // labelled from hwmon later
                     });
                 }
                 if self.temperature_mem != 0xFFFF {
@@ -1254,6 +1355,7 @@ mod amdgpu {
                         location: ThermalLocation::Memory as i32,
                         current_celsius: self.temperature_mem as u32,
                         max_celsius: 0,
+                        label: String::new(), // labelled from hwmon later
                     });
                 }
                 if self.temperature_vrgfx != 0xFFFF {
@@ -1261,6 +1363,7 @@ mod amdgpu {
                         location: ThermalLocation::Vrgfx as i32,
                         current_celsius: self.temperature_vrgfx as u32,
                         max_celsius: 0,
+                        label: String::new(), // labelled from hwmon later
                     });
                 }
                 if self.temperature_vrmem != 0xFFFF {
@@ -1268,6 +1371,7 @@ mod amdgpu {
                         location: ThermalLocation::Vrmem as i32,
                         current_celsius: self.temperature_vrmem as u32,
                         max_celsius: 0,
+                        label: String::new(), // labelled from hwmon later
                     });
                 }
                 if self.temperature_vrsoc != 0xFFFF {
@@ -1275,6 +1379,7 @@ mod amdgpu {
                         location: ThermalLocation::Vrsoc as i32,
                         current_celsius: self.temperature_vrsoc as u32,
                         max_celsius: 0,
+                        label: String::new(), // labelled from hwmon later
                     });
                 }
 
@@ -1494,6 +1599,7 @@ mod amdgpu {
                             max_power_mw: 0, // needs to be populated from hwmon (if it exists)
                             is_power_throttled: false, // read v1_0's comment
                             is_thermal_throttled: false,
+                            limits: None,
                         },
                         PowerCounters {
                             prochot_residency_acc: 0,
@@ -1516,6 +1622,7 @@ mod amdgpu {
                         location: ThermalLocation::Hotspot as i32,
                         current_celsius: self.temperature_hotspot as u32,
                         max_celsius: 0, // needs to be populated from hwmon (if it exists)
+                        label: String::new(), // labelled from hwmon later
                     });
                 }
                 if self.temperature_mem != 0xFFFF {
@@ -1523,6 +1630,7 @@ mod amdgpu {
                         location: ThermalLocation::Memory as i32,
                         current_celsius: self.temperature_mem as u32,
                         max_celsius: 0, // needs to be populated from hwmon (if it exists)
+                        label: String::new(), // labelled from hwmon later
                     });
                 }
                 if self.temperature_vrsoc != 0xFFFF {
@@ -1530,6 +1638,7 @@ mod amdgpu {
                         location: ThermalLocation::Vrsoc as i32,
                         current_celsius: self.temperature_vrsoc as u32,
                         max_celsius: 0, // needs to be populated from hwmon (if it exists)
+                        label: String::new(), // labelled from hwmon later
                     });
                 }
 
@@ -1769,6 +1878,7 @@ mod amdgpu {
                             max_power_mw: 0, // needs to be populated from hwmon (if it exists)
                             is_power_throttled: false, // read v1_0's comment
                             is_thermal_throttled: false,
+                            limits: None,
                         },
                         PowerCounters {
                             prochot_residency_acc: 0,
@@ -1791,6 +1901,7 @@ mod amdgpu {
                         location: ThermalLocation::Hotspot as i32,
                         current_celsius: self.temperature_hotspot as u32,
                         max_celsius: 0, // needs to be populated from hwmon (if it exists)
+                        label: String::new(), // labelled from hwmon later
                     });
                 }
                 if self.temperature_mem != 0xFFFF {
@@ -1798,6 +1909,7 @@ mod amdgpu {
                         location: ThermalLocation::Memory as i32,
                         current_celsius: self.temperature_mem as u32,
                         max_celsius: 0, // needs to be populated from hwmon (if it exists)
+                        label: String::new(), // labelled from hwmon later
                     });
                 }
                 if self.temperature_vrsoc != 0xFFFF {
@@ -1805,6 +1917,7 @@ mod amdgpu {
                         location: ThermalLocation::Vrsoc as i32,
                         current_celsius: self.temperature_vrsoc as u32,
                         max_celsius: 0, // needs to be populated from hwmon (if it exists)
+                        label: String::new(), // labelled from hwmon later
                     });
                 }
 
@@ -2087,6 +2200,7 @@ mod amdgpu {
                                     || last.hbm_thm_residency_acc < self.hbm_thm_residency_acc
                                     || last.prochot_residency_acc < self.prochot_residency_acc
                             }),
+                            limits: None,
                         },
                         PowerCounters {
                             prochot_residency_acc: self.prochot_residency_acc,
@@ -2108,21 +2222,24 @@ mod amdgpu {
                     thermals.push(Thermal {
                         location: ThermalLocation::Hotspot as i32,
                         current_celsius: self.temperature_hotspot as u32,
-                        max_celsius: 0, // populated later
+                        max_celsius: 0,       // populated later
+                        label: String::new(), // labelled from hwmon later
                     });
                 }
                 if self.temperature_mem != 0xFFFF {
                     thermals.push(Thermal {
                         location: ThermalLocation::Memory as i32,
                         current_celsius: self.temperature_mem as u32,
-                        max_celsius: 0, // populated later
+                        max_celsius: 0,       // populated later
+                        label: String::new(), // labelled from hwmon later
                     });
                 }
                 if self.temperature_vrsoc != 0xFFFF {
                     thermals.push(Thermal {
                         location: ThermalLocation::Vrsoc as i32,
                         current_celsius: self.temperature_vrsoc as u32,
-                        max_celsius: 0, // populated later
+                        max_celsius: 0,       // populated later
+                        label: String::new(), // labelled from hwmon later
                     });
                 }
 
@@ -2408,6 +2525,7 @@ mod amdgpu {
                                     || last.hbm_thm_residency_acc < self.hbm_thm_residency_acc
                                     || last.prochot_residency_acc < self.prochot_residency_acc
                             }),
+                            limits: None,
                         },
                         PowerCounters {
                             prochot_residency_acc: self.prochot_residency_acc,
@@ -2429,21 +2547,24 @@ mod amdgpu {
                     thermals.push(Thermal {
                         location: ThermalLocation::Hotspot as i32,
                         current_celsius: self.temperature_hotspot as u32,
-                        max_celsius: 0, // populated later
+                        max_celsius: 0,       // populated later
+                        label: String::new(), // labelled from hwmon later
                     });
                 }
                 if self.temperature_mem != 0xFFFF {
                     thermals.push(Thermal {
                         location: ThermalLocation::Memory as i32,
                         current_celsius: self.temperature_mem as u32,
-                        max_celsius: 0, // populated later
+                        max_celsius: 0,       // populated later
+                        label: String::new(), // labelled from hwmon later
                     });
                 }
                 if self.temperature_vrsoc != 0xFFFF {
                     thermals.push(Thermal {
                         location: ThermalLocation::Vrsoc as i32,
                         current_celsius: self.temperature_vrsoc as u32,
-                        max_celsius: 0, // populated later
+                        max_celsius: 0,       // populated later
+                        label: String::new(), // labelled from hwmon later
                     });
                 }
 
@@ -2733,6 +2854,7 @@ mod amdgpu {
                                     || last.hbm_thm_residency_acc < self.hbm_thm_residency_acc
                                     || last.prochot_residency_acc < self.prochot_residency_acc
                             }),
+                            limits: None,
                         },
                         PowerCounters {
                             prochot_residency_acc: self.prochot_residency_acc,
@@ -2754,21 +2876,24 @@ mod amdgpu {
                     thermals.push(Thermal {
                         location: ThermalLocation::Hotspot as i32,
                         current_celsius: self.temperature_hotspot as u32,
-                        max_celsius: 0, // populated later
+                        max_celsius: 0,       // populated later
+                        label: String::new(), // labelled from hwmon later
                     });
                 }
                 if self.temperature_mem != 0xFFFF {
                     thermals.push(Thermal {
                         location: ThermalLocation::Memory as i32,
                         current_celsius: self.temperature_mem as u32,
-                        max_celsius: 0, // populated later
+                        max_celsius: 0,       // populated later
+                        label: String::new(), // labelled from hwmon later
                     });
                 }
                 if self.temperature_vrsoc != 0xFFFF {
                     thermals.push(Thermal {
                         location: ThermalLocation::Vrsoc as i32,
                         current_celsius: self.temperature_vrsoc as u32,
-                        max_celsius: 0, // populated later
+                        max_celsius: 0,       // populated later
+                        label: String::new(), // labelled from hwmon later
                     });
                 }
 
@@ -3335,6 +3460,7 @@ mod amdgpu {
                                     < counters.socket_thm_residency_acc
                                 || last.vr_thm_residency_acc < counters.vr_thm_residency_acc
                                 || last.hbm_thm_residency_acc < counters.hbm_thm_residency_acc,
+                            limits: None,
                         },
                         counters,
                     ))
@@ -3354,7 +3480,8 @@ mod amdgpu {
                                     thermals.push(Thermal {
                                         location: ThermalLocation::Hotspot as i32,
                                         current_celsius: *value as u32,
-                                        max_celsius: 0, // populate later
+                                        max_celsius: 0,       // populate later
+                                        label: String::new(), // labelled from hwmon later
                                     });
                                 }
                             }
@@ -3365,7 +3492,8 @@ mod amdgpu {
                                     thermals.push(Thermal {
                                         location: ThermalLocation::Memory as i32,
                                         current_celsius: *value as u32,
-                                        max_celsius: 0, // populate later
+                                        max_celsius: 0,       // populate later
+                                        label: String::new(), // labelled from hwmon later
                                     });
                                 }
                             }
@@ -3376,7 +3504,8 @@ mod amdgpu {
                                     thermals.push(Thermal {
                                         location: ThermalLocation::Vrsoc as i32,
                                         current_celsius: *value as u32,
-                                        max_celsius: 0, // populate later
+                                        max_celsius: 0,       // populate later
+                                        label: String::new(), // labelled from hwmon later
                                     });
                                 }
                             }
@@ -3554,6 +3683,7 @@ mod amdgpu {
                             max_power_mw: 0,
                             is_power_throttled: false, // read v1_0's comment
                             is_thermal_throttled: false,
+                            limits: None,
                         },
                         PowerCounters {
                             prochot_residency_acc: 0,
@@ -3576,6 +3706,7 @@ mod amdgpu {
                         location: ThermalLocation::Hotspot as i32,
                         current_celsius: self.temperature_gfx as u32,
                         max_celsius: 0,
+                        label: String::new(), // labelled from hwmon later
                     });
                 }
 
@@ -3744,6 +3875,7 @@ mod amdgpu {
                             max_power_mw: 0,
                             is_power_throttled: false, // read v1_0's comment
                             is_thermal_throttled: false,
+                            limits: None,
                         },
                         PowerCounters {
                             prochot_residency_acc: 0,
@@ -3766,6 +3898,7 @@ mod amdgpu {
                         location: ThermalLocation::Hotspot as i32,
                         current_celsius: self.temperature_gfx as u32,
                         max_celsius: 0,
+                        label: String::new(), // labelled from hwmon later
                     });
                 }
 
@@ -3831,6 +3964,10 @@ mod amdgpu {
         }
 
         impl AmdgpuMetrics for C2 {
+            fn indep_throttle_status(&self) -> Option<u64> {
+                Some(self.indep_throttle_status)
+            }
+
             fn from_bytes(bytes: &[u8]) -> anyhow::Result<GpuMetrics> {
                 tracing::trace!("reading format 2 content 2");
                 let bytes = from_bytes::<Self>(bytes);
@@ -3941,6 +4078,7 @@ mod amdgpu {
                             is_thermal_throttled: self.indep_throttle_status
                                 & INDEP_THERMAL_THROTTLE_MASK
                                 != 0,
+                            limits: None,
                         },
                         PowerCounters {
                             prochot_residency_acc: 0,
@@ -3963,6 +4101,7 @@ mod amdgpu {
                         location: ThermalLocation::Hotspot as i32,
                         current_celsius: self.temperature_gfx as u32,
                         max_celsius: 0,
+                        label: String::new(), // labelled from hwmon later
                     });
                 }
 
@@ -4034,6 +4173,10 @@ mod amdgpu {
         }
 
         impl AmdgpuMetrics for C3 {
+            fn indep_throttle_status(&self) -> Option<u64> {
+                Some(self.indep_throttle_status)
+            }
+
             fn from_bytes(bytes: &[u8]) -> anyhow::Result<GpuMetrics> {
                 tracing::trace!("reading format 2 content 3");
                 let bytes = from_bytes::<Self>(bytes);
@@ -4142,8 +4285,9 @@ mod amdgpu {
                                 & INDEP_POWER_THROTTLE_MASK
                                 != 0,
                             is_thermal_throttled: self.indep_throttle_status
-                                & INDEP_POWER_THROTTLE_MASK
+                                & INDEP_THERMAL_THROTTLE_MASK
                                 != 0,
+                            limits: None,
                         },
                         PowerCounters {
                             prochot_residency_acc: 0,
@@ -4166,6 +4310,7 @@ mod amdgpu {
                         location: ThermalLocation::Hotspot as i32,
                         current_celsius: self.average_temperature_gfx as u32,
                         max_celsius: 0,
+                        label: String::new(), // labelled from hwmon later
                     });
                 }
 
@@ -4247,6 +4392,10 @@ mod amdgpu {
         }
 
         impl AmdgpuMetrics for C4 {
+            fn indep_throttle_status(&self) -> Option<u64> {
+                Some(self.indep_throttle_status)
+            }
+
             fn from_bytes(bytes: &[u8]) -> anyhow::Result<GpuMetrics> {
                 tracing::trace!("reading format 2 content 4");
                 let bytes = from_bytes::<Self>(bytes);
@@ -4355,8 +4504,9 @@ mod amdgpu {
                                 & INDEP_POWER_THROTTLE_MASK
                                 != 0,
                             is_thermal_throttled: self.indep_throttle_status
-                                & INDEP_POWER_THROTTLE_MASK
+                                & INDEP_THERMAL_THROTTLE_MASK
                                 != 0,
+                            limits: None,
                         },
                         PowerCounters {
                             prochot_residency_acc: 0,
@@ -4379,6 +4529,7 @@ mod amdgpu {
                         location: ThermalLocation::Hotspot as i32,
                         current_celsius: self.average_temperature_gfx as u32,
                         max_celsius: 0,
+                        label: String::new(), // labelled from hwmon later
                     });
                 }
 
@@ -4590,6 +4741,7 @@ mod amdgpu {
                             } else {
                                 false
                             },
+                            limits: None,
                         },
                         // i know the names don't match up but shhh
                         PowerCounters {
@@ -4613,6 +4765,7 @@ mod amdgpu {
                         location: ThermalLocation::Hotspot as i32,
                         current_celsius: self.temperature_gfx as u32,
                         max_celsius: 0,
+                        label: String::new(), // labelled from hwmon later
                     });
                 }
 
@@ -4621,3 +4774,20 @@ mod amdgpu {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn throttlers() {
+        assert!(decode_throttlers(0).is_empty());
+        assert_eq!(decode_throttlers(1 << 4), vec!["spl"]);
+        assert_eq!(
+            decode_throttlers((1 << 0) | (1 << 36) | (1 << 57)),
+            vec!["ppt0", "temp_hotspot", "prochot_gfx"]
+        );
+        // Reserved bits are ignored
+        assert!(decode_throttlers(1 << 10).is_empty());
+    }
+}
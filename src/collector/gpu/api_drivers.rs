@@ -6,7 +6,7 @@
 //! Reader for OpenGL and Vulkan driver information
 
 use crate::metrics::gpu::ApiDriver;
-use std::{collections::HashMap, ffi::c_void, path::PathBuf};
+use std::{collections::HashMap, ffi::c_void, path::PathBuf, time::Duration};
 
 /// Holds the OpenGL and Vulkan driver information for a GPU. Mappings are as follows:
 /// GL: render node -> [`ApiDriver`]
@@ -17,6 +17,27 @@ pub struct DriverInfo {
     pub vk_drivers: HashMap<String, ApiDriver>,
 }
 
+/// Enumerates the drivers on a separate thread, so a hung OpenGL or Vulkan driver can't stall collection.
+/// The thread is left behind when it doesn't finish in time, since there's no way to cancel it.
+pub fn get_drivers_timeout(timeout: Duration) -> anyhow::Result<DriverInfo> {
+    with_timeout(timeout, get_drivers)
+}
+
+fn with_timeout<T: Send + 'static>(
+    timeout: Duration,
+    f: impl FnOnce() -> T + Send + 'static,
+) -> anyhow::Result<T> {
+    let (tx, rx) = std::sync::mpsc::sync_channel(1);
+    std::thread::Builder::new()
+        .name("api-drivers".to_string())
+        .spawn(move || {
+            // the receiver is gone if this took too long
+            let _ = tx.send(f());
+        })?;
+    rx.recv_timeout(timeout)
+        .map_err(|_| anyhow::anyhow!("driver enumeration did not finish within {timeout:?}"))
+}
+
 pub fn get_drivers() -> DriverInfo {
     let gl_drivers = opengl::init()
         .inspect_err(|e| tracing::error!("failed to get OpenGL drivers: {e}"))
@@ -378,3 +399,19 @@ mod vulkan {
         Ok(drivers)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn timeout() {
+        assert_eq!(with_timeout(Duration::from_secs(5), || 42).ok(), Some(42));
+        assert!(
+            with_timeout(Duration::from_millis(10), || {
+                std::thread::sleep(Duration::from_secs(1))
+            })
+            .is_err()
+        );
+    }
+}
@@ -0,0 +1,139 @@
+/*
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/.
+ */
+
+//! Fallback for cards whose driver has no dedicated collector (e.g. virtio-gpu, mgag200, ast).
+//! Only reports what the generic PCI and hwmon sysfs attributes expose.
+
+use rustix::fd::{AsFd, OwnedFd};
+use std::path::PathBuf;
+
+use crate::{collector::helpers::sysfs, metrics::gpu::*};
+
+pub struct Card {
+    card_fd: OwnedFd,
+    hwmon: Option<OwnedFd>,
+    driver: String,
+    primary_node: PathBuf,
+    render_node: PathBuf,
+    pci_id: String,
+}
+
+impl Card {
+    pub fn new(fd: OwnedFd, driver: &str) -> anyhow::Result<Self> {
+        // Framebuffer drivers like simpledrm sit on a platform device and aren't a GPU of their own
+        if sysfs::readat_string(fd.as_fd(), "device/vendor").is_none() {
+            anyhow::bail!("{} card has no PCI device", driver);
+        }
+        let pci_id = PathBuf::from(
+            rustix::fs::readlinkat(&fd, "device", Vec::new())?
+                .to_string_lossy()
+                .to_string(),
+        )
+        .file_name()
+        .ok_or_else(|| anyhow::anyhow!("could not read GPU PCI address"))?
+        .to_string_lossy()
+        .to_string();
+        let drm_root = rustix::fs::openat(
+            &fd,
+            "device/drm",
+            rustix::fs::OFlags::DIRECTORY
+                | rustix::fs::OFlags::RDONLY
+                | rustix::fs::OFlags::CLOEXEC,
+            rustix::fs::Mode::empty(),
+        )?;
+        let mut primary_node = PathBuf::new();
+        let mut render_node = PathBuf::new();
+        for entry in rustix::fs::Dir::read_from(&drm_root)? {
+            let entry = entry?;
+            let name = entry.file_name().to_string_lossy().to_string();
+            if name.starts_with("card") {
+                primary_node = PathBuf::from(format!("/dev/dri/{}", name));
+            } else if name.starts_with("renderD") {
+                render_node = PathBuf::from(format!("/dev/dri/{}", name));
+            }
+        }
+        Ok(Self {
+            hwmon: sysfs::first_hwmon_subdir_at(fd.as_fd(), "device/hwmon"),
+            card_fd: fd,
+            driver: driver.to_string(),
+            primary_node,
+            render_node,
+            pci_id,
+        })
+    }
+
+    fn power(&self) -> Option<Power> {
+        let hwmon = self.hwmon.as_ref()?.as_fd();
+        let read_mw = |name: &str| sysfs::readat_u64(hwmon, name).map(|uw| (uw / 1000) as u32);
+        Some(Power {
+            current_power_mw: read_mw("power1_average").or_else(|| read_mw("power1_input"))?,
+            max_power_mw: read_mw("power1_cap").unwrap_or(0),
+            is_power_throttled: false,
+            is_thermal_throttled: false,
+            limits: None,
+        })
+    }
+}
+
+impl super::Card for Card {
+    fn identify(&self) -> (String, String, Option<String>, Option<String>) {
+        (
+            sysfs::readat_string(self.card_fd.as_fd(), "device/vendor")
+                .and_then(|v| v.strip_prefix("0x").map(|v| v.to_string()))
+                .unwrap_or_default(),
+            sysfs::readat_string(self.card_fd.as_fd(), "device/device")
+                .and_then(|d| d.strip_prefix("0x").map(|d| d.to_string()))
+                .unwrap_or_default(),
+            sysfs::readat_string(self.card_fd.as_fd(), "device/subsystem_vendor")
+                .and_then(|sv| sv.strip_prefix("0x").map(|sv| sv.to_string())),
+            sysfs::readat_string(self.card_fd.as_fd(), "device/subsystem_device")
+                .and_then(|sd| sd.strip_prefix("0x").map(|sd| sd.to_string())),
+        )
+    }
+
+    fn collect(&mut self, config: &super::Config) -> anyhow::Result<Gpu> {
+        let hwmon = self.hwmon.as_ref().map(|hwmon| hwmon.as_fd());
+        Ok(Gpu {
+            primary_node: self.primary_node.to_string_lossy().to_string(),
+            render_node: self.render_node.to_string_lossy().to_string(),
+            pci_id: self.pci_id.clone(),
+            drivers: config.drivers.then(|| Drivers {
+                kernel: Some(KernelDriver {
+                    name: self.driver.clone(),
+                    version: None,
+                }),
+                opengl: None,
+                vulkan: None,
+            }),
+            power: config.power.then(|| self.power()).flatten(),
+            thermals: hwmon
+                .filter(|_| config.thermals)
+                .map(super::hwmon_thermals)
+                .unwrap_or_default(),
+            fans: hwmon
+                .filter(|_| config.fans)
+                .map(super::hwmon_fans)
+                .unwrap_or_default(),
+            pcie: config
+                .pcie
+                .then(|| super::sysfs_pcie(self.card_fd.as_fd()))
+                .flatten(),
+            ..Default::default()
+        })
+    }
+
+    fn resolve(
+        &mut self,
+        _input: &super::process::Snapshot,
+        _output: &mut Gpu,
+    ) -> anyhow::Result<()> {
+        Ok(())
+    }
+
+    fn pci_id(&self) -> String {
+        self.pci_id.clone()
+    }
+}
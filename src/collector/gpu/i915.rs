@@ -6,16 +6,20 @@
 
 use crate::collector::helpers::sysfs;
 use crate::metrics::gpu::*;
-use std::{collections::HashMap, path::PathBuf};
+use std::path::PathBuf;
 
 use rustix::fd::{AsFd, OwnedFd};
 
+/// I915_MAX_GT
+const MAX_GT: u32 = 4;
+
 pub struct Card {
     card_fd: OwnedFd,
     primary_node: PathBuf,
     render_node: PathBuf,
     render_node_fd: OwnedFd,
     pci_id: String,
+    hwmon: Option<super::intel_hwmon::Hwmon>,
 }
 
 impl Card {
@@ -56,7 +60,9 @@ impl Card {
                 )?);
             }
         }
+        let hwmon = super::intel_hwmon::Hwmon::open(fd.as_fd());
         Ok(Self {
+            hwmon,
             card_fd: fd,
             primary_node,
             render_node: render_node_path,
@@ -66,6 +72,32 @@ impl Card {
     }
 
     fn clocks(&self) -> Vec<Clock> {
+        // Multi-GT parts (e.g. Meteor Lake's media GT) expose each GT under gt/gtN, older kernels
+        // only have the card level files which describe gt0
+        let mut clocks: Vec<Clock> = (0..MAX_GT)
+            .filter_map(|gt| {
+                let current_frequency_mhz = sysfs::readat_u32(
+                    self.card_fd.as_fd(),
+                    &format!("gt/gt{gt}/rps_cur_freq_mhz"),
+                )?;
+                let max_frequency_mhz = sysfs::readat_u32(
+                    self.card_fd.as_fd(),
+                    &format!("gt/gt{gt}/rps_max_freq_mhz"),
+                )?;
+                Some(Clock {
+                    identifier: Some(ClockIdentifier {
+                        domain: ClockDomain::Gt as i32,
+                        index: gt,
+                    }),
+                    current_frequency_mhz,
+                    max_frequency_mhz,
+                })
+            })
+            .collect();
+        if !clocks.is_empty() {
+            return clocks;
+        }
+
         let Some(current_frequency_mhz) =
             sysfs::readat_u32(self.card_fd.as_fd(), "gt_cur_freq_mhz")
         else {
@@ -76,14 +108,39 @@ impl Card {
         else {
             return Vec::new();
         };
-        vec![Clock {
+        clocks.push(Clock {
             identifier: Some(ClockIdentifier {
                 domain: ClockDomain::Gt as i32,
                 index: 0,
             }),
             current_frequency_mhz,
             max_frequency_mhz,
-        }]
+        });
+        clocks
+    }
+
+    /// Reads the active limiters of the primary GT from its throttle_reason_* files.
+    fn throttle_reasons(&self) -> Vec<String> {
+        const REASONS: &[&str] = &[
+            "pl1",
+            "pl2",
+            "pl4",
+            "thermal",
+            "prochot",
+            "ratl",
+            "vr_thermalert",
+            "vr_tdc",
+        ];
+        REASONS
+            .iter()
+            .filter(|reason| {
+                sysfs::readat_u32(
+                    self.card_fd.as_fd(),
+                    &format!("gt/gt0/throttle_reason_{reason}"),
+                ) == Some(1)
+            })
+            .map(|reason| reason.to_string())
+            .collect()
     }
 
     #[allow(unused_assignments)]
@@ -145,20 +202,15 @@ impl Card {
             .collect()
     }
 
-    fn power(&self) -> Option<Power> {
-        let Some(_hwmon_fd) = sysfs::first_hwmon_subdir_at(self.card_fd.as_fd(), "device/hwmon")
-        else {
-            return None;
-        };
-        None // I don't have an Arc GPU to figure out where the power file is located
+    fn power(&mut self) -> Option<Power> {
+        self.hwmon.as_mut()?.power()
     }
 
     fn thermals(&self) -> Vec<Thermal> {
-        let Some(_hwmon_fd) = sysfs::first_hwmon_subdir_at(self.card_fd.as_fd(), "device/hwmon")
-        else {
-            return Vec::new();
-        };
-        Vec::new() // Same as above
+        self.hwmon
+            .as_ref()
+            .map(|hwmon| hwmon.thermals())
+            .unwrap_or_default()
     }
 }
 
@@ -195,6 +247,7 @@ impl super::Card for Card {
                     .map(|n| n.to_string_lossy().to_string())
             })
             .unwrap_or_default();
+        gpu.architecture = super::sysfs_intel_architecture(self.card_fd.as_fd());
         gpu.drivers = config.drivers.then(|| Drivers {
             kernel: Some(KernelDriver {
                 name: "i915".to_string(),
@@ -207,7 +260,20 @@ impl super::Card for Card {
         gpu.clocks = config.clocks.then(|| self.clocks()).unwrap_or_default();
         gpu.memory = config.memory.then(|| self.memory()).unwrap_or_default();
         gpu.power = config.power.then(|| self.power()).unwrap_or_default();
+        if config.power {
+            gpu.throttle_reasons = self.throttle_reasons();
+            gpu.is_throttled = !gpu.throttle_reasons.is_empty();
+        }
         gpu.thermals = config.thermals.then(|| self.thermals()).unwrap_or_default();
+        gpu.pcie = config
+            .pcie
+            .then(|| super::sysfs_pcie(self.card_fd.as_fd()))
+            .flatten();
+        gpu.fans = config
+            .fans
+            .then(|| self.hwmon.as_ref().map(|hwmon| hwmon.fans()))
+            .flatten()
+            .unwrap_or_default();
 
         Ok(gpu)
     }
@@ -270,34 +336,21 @@ impl super::Card for Card {
                                 }
                             }
                         }
-                        output.processes.push(Process {
+                        let mut gpu_process = Process {
                             pid: *pid,
                             engine_utilization,
                             vram_usage: gpu_usage.vram_usage,
                             gtt_usage: gpu_usage.system_usage,
-                        })
+                            ..Default::default()
+                        };
+                        super::fill_owner(&mut gpu_process, process);
+                        output.processes.push(gpu_process);
                     }
                 }
             }
         }
-        let mut engine_utilizations: HashMap<EngineIdentifier, u64> = HashMap::new();
-        // After the fact, we can use the processes to figure out engine utilization
-        for process in &output.processes {
-            for engine_utilization in &process.engine_utilization {
-                let engine = engine_utilizations
-                    .entry(engine_utilization.identifier.unwrap_or_default())
-                    .or_default();
-
-                *engine += engine_utilization.utilization;
-            }
-        }
-        output.engines = engine_utilizations
-            .into_iter()
-            .map(|(identifier, utiilization)| Engine {
-                identifier: Some(identifier),
-                utilization: utiilization,
-            })
-            .collect::<Vec<_>>();
+        // The driver has no device-wide busyness, so the engines are the sum of the processes' fdinfo
+        output.engines = super::process_engines(&output.processes);
         Ok(())
     }
 
@@ -0,0 +1,185 @@
+/*
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/.
+ */
+//! Extracts the display controllers of the AMD, Intel and NVIDIA entries from pci.ids, the
+//! subset embedded for systems that ship without hwdata. Also compiled into build.rs, so it only
+//! uses std.
+
+/// The vendors whose display controllers are kept.
+pub const VENDORS: &[&str] = &["1002", "8086", "10de"];
+
+/// Device names of other functions sharing a vendor with its GPUs (audio, chipsets, bridges).
+const NOT_DISPLAY: &[&str] = &[
+    "Audio",
+    "Azalia",
+    "USB",
+    "SMBus",
+    "SATA",
+    "IDE",
+    "LPC",
+    "PCI",
+    "Bridge",
+    "bridge",
+    "Root Port",
+    "Root Complex",
+    "IOMMU",
+    "Ethernet",
+    "Memory Controller",
+    "Crypto",
+    "Serial",
+];
+
+/// Intel makes far more than GPUs, so its devices are only kept when named as graphics.
+const INTEL_DISPLAY: &[&str] = &["Graphics", "Arc", "Iris", "GT1", "GT2", "GT3", "DG1", "DG2"];
+
+/// Header of the generated file, in pci.ids comment syntax.
+const HEADER: &str = "\
+#
+#\tDisplay controllers of the AMD, Intel and NVIDIA entries in the PCI ID
+#\tdatabase, embedded for systems that ship without hwdata. Generated from
+#\tpci.ids by src/collector/gpu/ids.rs, regenerate with `just gpu-ids`.
+#
+#\tSource: https://pci-ids.ucw.cz/ (pci.ids, BSD-3-Clause or GPL-2.0-or-later)
+#
+";
+
+/// Returns the pci.ids entries of the display controllers of [`VENDORS`], keeping the
+/// subsystems of every kept device.
+pub fn display_subset(pci_ids: &str) -> String {
+    let mut subset = String::from(HEADER);
+    let mut vendor = None;
+    let mut keep_device = false;
+    for line in pci_ids.lines() {
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        // The device class section follows every vendor
+        if line.starts_with("C ") {
+            break;
+        }
+        match line.chars().take_while(|c| *c == '\t').count() {
+            0 => {
+                vendor = VENDORS.iter().find(|id| line.starts_with(*id)).copied();
+                keep_device = false;
+                if vendor.is_some() {
+                    subset.push_str(line);
+                    subset.push('\n');
+                }
+            }
+            1 => {
+                keep_device = vendor.is_some_and(|vendor| {
+                    let name = line
+                        .trim_start()
+                        .split_once(' ')
+                        .map_or("", |(_, name)| name);
+                    is_display(vendor, name)
+                });
+                if keep_device {
+                    subset.push_str(line);
+                    subset.push('\n');
+                }
+            }
+            _ if keep_device => {
+                subset.push_str(line);
+                subset.push('\n');
+            }
+            _ => {}
+        }
+    }
+    subset
+}
+
+fn is_display(vendor: &str, name: &str) -> bool {
+    if NOT_DISPLAY.iter().any(|keyword| name.contains(keyword)) {
+        return false;
+    }
+    vendor != "8086" || INTEL_DISPLAY.iter().any(|keyword| name.contains(keyword))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::collector::helpers::PciIds;
+
+    const PCI_IDS: &str = "\
+# pci.ids excerpt
+1002  Advanced Micro Devices, Inc. [AMD/ATI]
+\t7590  Navi 44 [Radeon RX 9060 XT]
+\t\t1da2 e490  Pulse Radeon RX 9060 XT
+\tab40  Navi 31 HDMI/DP Audio
+1022  Advanced Micro Devices, Inc. [AMD]
+\t14e8  Phoenix Dummy Host Bridge
+1af4  Red Hat, Inc.
+\t1050  Virtio 1.0 GPU
+8086  Intel Corporation
+\te211  Battlemage G21 [Arc Pro B60]
+\ta0e8  Tiger Lake-LP Serial IO I2C Controller #0
+\t7d67  Meteor Lake-S [Intel Graphics]
+10de  NVIDIA Corporation
+\t2d04  GB206 [GeForce RTX 5060 Ti]
+\t22eb  GB203 High Definition Audio Controller
+\t0aa2  MCP79 SMBus
+C 03  Display controller
+\t00  VGA compatible controller
+";
+
+    #[test]
+    fn display_subset_lookup() -> anyhow::Result<()> {
+        let pci_ids = PciIds::parse(&display_subset(PCI_IDS))?;
+        assert_eq!(
+            pci_ids.lookup("1002", "7590", None, None),
+            Some("Navi 44 [Radeon RX 9060 XT]")
+        );
+        assert_eq!(
+            pci_ids.lookup("1002", "7590", Some("1da2"), Some("e490")),
+            Some("Pulse Radeon RX 9060 XT")
+        );
+        assert_eq!(
+            pci_ids.lookup("8086", "e211", None, None),
+            Some("Battlemage G21 [Arc Pro B60]")
+        );
+        assert_eq!(
+            pci_ids.lookup("8086", "7d67", None, None),
+            Some("Meteor Lake-S [Intel Graphics]")
+        );
+        assert_eq!(
+            pci_ids.lookup("10de", "2d04", None, None),
+            Some("GB206 [GeForce RTX 5060 Ti]")
+        );
+        // Other functions of the vendors fall back to the vendor name
+        for (vendor, device, name) in [
+            ("1002", "ab40", "Advanced Micro Devices, Inc. [AMD/ATI]"),
+            ("8086", "a0e8", "Intel Corporation"),
+            ("10de", "22eb", "NVIDIA Corporation"),
+            ("10de", "0aa2", "NVIDIA Corporation"),
+        ] {
+            assert_eq!(pci_ids.lookup(vendor, device, None, None), Some(name));
+        }
+        // Other vendors are left out
+        assert_eq!(pci_ids.vendor("1022"), None);
+        assert_eq!(pci_ids.lookup("1af4", "1050", None, None), None);
+        Ok(())
+    }
+
+    #[test]
+    fn checked_in_subset() {
+        // The fallback is the generator's own output, so regenerating it doesn't change it
+        let checked_in = include_str!("gpu.ids");
+        assert_eq!(display_subset(checked_in), checked_in);
+    }
+
+    /// Rewrites the checked-in fallback from a pci.ids file, see `just gpu-ids`.
+    #[test]
+    #[ignore]
+    fn regenerate_gpu_ids() -> anyhow::Result<()> {
+        let source = std::env::var("MONITORD_PCI_IDS")?;
+        let subset = display_subset(&std::fs::read_to_string(source)?);
+        std::fs::write(
+            concat!(env!("CARGO_MANIFEST_DIR"), "/src/collector/gpu/gpu.ids"),
+            subset,
+        )?;
+        Ok(())
+    }
+}
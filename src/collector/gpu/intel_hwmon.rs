@@ -0,0 +1,77 @@
+/*
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/.
+ */
+
+//! Power and temperature readings from the hwmon interface shared by the i915 and xe drivers.
+//! Only discrete GPUs register a hwmon device, integrated GPUs are accounted for in the CPU package.
+
+use rustix::fd::{AsFd, BorrowedFd, OwnedFd};
+
+use crate::collector::helpers::*;
+use crate::metrics::gpu::*;
+
+pub struct Hwmon {
+    fd: OwnedFd,
+    energy: Sampler<Energy>,
+}
+
+impl Hwmon {
+    /// Opens the hwmon device of a card, returning `None` for GPUs without one.
+    pub fn open(card_fd: BorrowedFd) -> Option<Self> {
+        Some(Self {
+            fd: sysfs::first_hwmon_subdir_at(card_fd, "device/hwmon")?,
+            energy: Sampler::new(),
+        })
+    }
+
+    /// Derives the card power draw from the energy counter, `None` until two samples have been taken.
+    pub fn power(&mut self) -> Option<Power> {
+        // energy1 covers the whole card on both drivers (xe adds energy2 for the package only)
+        let energy_uj = sysfs::readat_u64(self.fd.as_fd(), "energy1_input")?;
+        let delta = self.energy.push(Energy(energy_uj))?;
+        let secs = delta.interval.as_secs_f64();
+        if secs == 0.0 {
+            return None;
+        }
+        // µJ/s is µW
+        let current_power_mw = (delta.change as f64 / secs / 1000.0) as u32;
+        let read_mw =
+            |name: &str| sysfs::readat_u64(self.fd.as_fd(), name).map(|uw| (uw / 1000) as u32);
+        // power1_max is the sustained (PL1) limit and power1_rated_max the card's TDP
+        let max_power_mw = read_mw("power1_max").unwrap_or(0);
+        let limits = read_mw("power1_rated_max").map(|rated_mw| PowerLimits {
+            min_mw: None,
+            max_mw: None,
+            default_mw: Some(rated_mw),
+        });
+        Some(Power {
+            current_power_mw,
+            max_power_mw,
+            is_power_throttled: false,
+            is_thermal_throttled: false,
+            limits,
+        })
+    }
+
+    /// Reads every temperature channel, i915 only exposes them on recent kernels.
+    pub fn thermals(&self) -> Vec<Thermal> {
+        super::hwmon_thermals(self.fd.as_fd())
+    }
+
+    pub fn fans(&self) -> Vec<Fan> {
+        super::hwmon_fans(self.fd.as_fd())
+    }
+}
+
+/// Cumulative card energy in microjoules.
+struct Energy(u64);
+
+impl sampler::Differential for Energy {
+    type Delta = u64;
+
+    fn delta(&self, other: &Self) -> Self::Delta {
+        self.0.saturating_sub(other.0)
+    }
+}
@@ -12,22 +12,28 @@
 //!
 //! ```
 mod amdgpu;
+mod generic;
 mod i915;
+mod intel_hwmon;
 mod nouveau;
 mod nvidia;
 mod xe;
 
 mod api_drivers;
+// Compiled into build.rs to generate the embedded pci.ids subset, only its tests run here
+#[cfg(test)]
+mod ids;
 
 use std::collections::HashMap;
 use std::collections::HashSet;
 
 use std::path::PathBuf;
 use std::sync::Arc;
+use std::time::{Duration, Instant};
 
 use crate::collector::helpers::*;
 use crate::metrics::process;
-use rustix::fd::{AsFd, OwnedFd};
+use rustix::fd::{AsFd, BorrowedFd, OwnedFd};
 
 #[doc(inline)]
 pub use crate::metrics::gpu::*;
@@ -40,8 +46,20 @@ pub struct Collector {
     cards: HashMap<CardFileId, Box<dyn Card + Send>>,
     nvml: Discovery<Arc<nvml_wrapper::Nvml>>,
     drivers: Discovery<api_drivers::DriverInfo>,
+    drm_path: PathBuf,
+    // NVML fails to initialize until the nvidia module is loaded, e.g. when an eGPU is attached later
+    nvml_retry_at: Option<Instant>,
 }
 
+/// Display controllers from pci.ids, for systems that don't ship hwdata (generated by build.rs)
+const BUILTIN_PCI_IDS: &str = include_str!(concat!(env!("OUT_DIR"), "/gpu.ids"));
+
+/// How long to wait for the graphics API drivers to be enumerated before reporting them as unknown
+const API_DRIVERS_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// How long to wait before retrying NVML initialization after it failed
+const NVML_RETRY_INTERVAL: Duration = Duration::from_secs(30);
+
 impl Default for Collector {
     fn default() -> Self {
         Self::new()
@@ -56,6 +74,32 @@ impl Collector {
             cards: HashMap::default(),
             nvml: Discovery::default(),
             drivers: Discovery::default(),
+            drm_path: PathBuf::from("/sys/class/drm"),
+            nvml_retry_at: None,
+        }
+    }
+
+    /// Creates a collector that enumerates cards from a mock DRM class directory.
+    #[cfg(test)]
+    fn with_drm_path(drm_path: impl Into<PathBuf>) -> Self {
+        Self {
+            drm_path: drm_path.into(),
+            ..Self::new()
+        }
+    }
+
+    /// Allows NVML initialization to be retried once the retry interval has passed since it failed.
+    fn retry_nvml(&mut self) {
+        if !matches!(self.nvml, Discovery::Unavailable) {
+            return;
+        }
+        match self.nvml_retry_at {
+            Some(retry_at) if Instant::now() >= retry_at => {
+                self.nvml = Discovery::Pending;
+                self.nvml_retry_at = None;
+            }
+            Some(_) => {}
+            None => self.nvml_retry_at = Some(Instant::now() + NVML_RETRY_INTERVAL),
         }
     }
 }
@@ -69,26 +113,34 @@ impl super::Collector for Collector {
 
     fn collect(&mut self, config: &crate::metrics::Config) -> anyhow::Result<Self::Output> {
         tracing::trace!("collecting GPU metrics");
-        let Some(api_drivers) = self.drivers.probe(|| Ok(api_drivers::get_drivers())) else {
-            anyhow::bail!("failed to collect graphics API drivers");
-        };
+        self.retry_nvml();
 
         let Some(config) = &config.gpu else {
             anyhow::bail!("GPU Collector did not receive a config");
         };
 
-        let drm_root = self.drm_root.require(|| {
+        let drm_root = match self.drm_root.require(|| {
             rustix::fs::open(
-                "/sys/class/drm",
+                &self.drm_path,
                 rustix::fs::OFlags::RDONLY
                     | rustix::fs::OFlags::DIRECTORY
                     | rustix::fs::OFlags::CLOEXEC,
                 rustix::fs::Mode::empty(),
             )
             .map_err(|e| anyhow::anyhow!(e))
-        })?;
+        }) {
+            Ok(drm_root) => drm_root,
+            // Without the DRM class (e.g. in a container or without any GPU driver loaded) there are no GPUs,
+            // which is a valid result and not a collection failure
+            Err(e) if e.downcast_ref::<rustix::io::Errno>() == Some(&rustix::io::Errno::NOENT) => {
+                tracing::debug!("{} does not exist, no GPUs", self.drm_path.display());
+                return Ok(Snapshot::default());
+            }
+            Err(e) => return Err(e),
+        };
 
         let mut seen: HashSet<CardFileId> = HashSet::with_capacity(self.cards.len());
+        let mut topology_changed = false;
         let mut gpus = Vec::new();
 
         let dir = rustix::fs::Dir::read_from(drm_root)?;
@@ -129,7 +181,7 @@ impl super::Collector for Collector {
                     drop(card);
                 }
                 None => {
-                    let device = match new_card(card, &mut self.nvml) {
+                    let device = match new_card(card, &mut self.nvml, config.collect_other) {
                         Ok(device) => device,
                         Err(e) => {
                             tracing::warn!("failed to create card tracker: {}", e);
@@ -137,6 +189,7 @@ impl super::Collector for Collector {
                         }
                     };
                     self.cards.insert(id, device);
+                    topology_changed = true;
                 }
             }
 
@@ -146,23 +199,67 @@ impl super::Collector for Collector {
                 Ok(snap) => snap,
                 Err(e) => {
                     tracing::warn!("failed to collect GPU snapshot: {}", e);
+                    // The device was unbound or removed behind a cached handle, so drop the card
+                    // and set it up again if it comes back
+                    if is_device_gone(&e) {
+                        self.cards.remove(&id);
+                        seen.remove(&id);
+                    }
                     continue;
                 }
             };
             // GPU name fallback
-            if snap.brand_name.is_empty() {
-                snap.brand_name = sysfs::read_string_path("/usr/share/hwdata/pci.ids")
+            let pci_ids = self.pci_ids.probe(|| {
+                let system = sysfs::read_string_path("/usr/share/hwdata/pci.ids")
                     .or_else(|| sysfs::read_string_path("/usr/share/misc/pci.ids"))
-                    .and_then(|pci_ids| self.pci_ids.probe(|| PciIds::parse(&pci_ids)))
-                    .and_then(|pci_ids| {
-                        let (vendor, device, subvendor, subdevice) = gpu.identify();
-                        pci_ids.lookup(&vendor, &device, subvendor.as_deref(), subdevice.as_deref())
-                    })
+                    .unwrap_or_default();
+                // Later entries win, so the system database overrides the built-in one
+                PciIds::parse(&format!("{BUILTIN_PCI_IDS}\n{system}"))
+            });
+            if let Some(pci_ids) = pci_ids {
+                let (vendor, device, subvendor, subdevice) = gpu.identify();
+                if snap.brand_name.is_empty() {
+                    snap.brand_name = pci_ids
+                        .lookup(&vendor, &device, subvendor.as_deref(), subdevice.as_deref())
+                        .map(String::from)
+                        .unwrap_or_default();
+                }
+                snap.vendor = pci_ids
+                    .vendor(&vendor)
                     .map(String::from)
                     .unwrap_or_default();
             }
-            // Driver association
-            if let Some(drivers) = snap.drivers.as_mut() {
+            // Only NVML provides a UUID, the PCI address is stable for everything else
+            if snap.uuid.is_empty() {
+                snap.uuid = format!("pci-{}", snap.pci_id);
+            }
+            let mut partitions = gpu.partitions(config);
+            for gpu in std::iter::once(&mut snap).chain(partitions.iter_mut()) {
+                gpu.memory_controller_utilization_percent =
+                    memory_controller_utilization(&gpu.engines);
+                gpu.vram_allocation_percent = vram_allocation(&gpu.memory);
+            }
+            gpus.push(snap);
+            gpus.extend(partitions);
+        }
+
+        let cached = self.cards.len();
+        self.cards.retain(|id, _| seen.contains(id));
+        topology_changed |= self.cards.len() != cached;
+
+        // Enumerating the graphics API drivers initializes every driver, so it's only redone when cards come or go
+        if topology_changed {
+            self.drivers = Discovery::Pending;
+        }
+        if config.drivers
+            && let Some(api_drivers) = self
+                .drivers
+                .probe(|| api_drivers::get_drivers_timeout(API_DRIVERS_TIMEOUT))
+        {
+            for snap in gpus.iter_mut() {
+                let Some(drivers) = snap.drivers.as_mut() else {
+                    continue;
+                };
                 if let Some(opengl) = api_drivers.gl_drivers.get(
                     &PathBuf::from(&snap.render_node)
                         .file_name()
@@ -180,11 +277,8 @@ impl super::Collector for Collector {
                     drivers.vulkan = Some(vulkan.clone());
                 }
             }
-
-            gpus.push(snap);
         }
 
-        self.cards.retain(|id, _| seen.contains(id));
         Ok(Snapshot { gpus })
     }
 }
@@ -195,6 +289,10 @@ impl super::Resolver for Collector {
     fn resolve(&mut self, input: &Self::Input, output: &mut Self::Output) -> anyhow::Result<()> {
         let mut gpus = Vec::new();
         for gpu in output.gpus.iter_mut() {
+            // Partitions share the PCI address of their physical GPU, which already gets the process usage
+            if !gpu.parent_uuid.is_empty() {
+                continue;
+            }
             let (_, card) = self
                 .cards
                 .iter_mut()
@@ -215,6 +313,10 @@ trait Card {
     fn pci_id(&self) -> String;
     // Resolves a snapshot based on the staging
     fn resolve(&mut self, input: &process::Snapshot, output: &mut Gpu) -> anyhow::Result<()>;
+    // Collects the partitions the card is split into (e.g. NVIDIA MIG instances), reported as separate GPUs
+    fn partitions(&mut self, _config: &Config) -> Vec<Gpu> {
+        Vec::new()
+    }
 }
 
 #[derive(PartialEq, Eq, Hash, Copy, Clone, Debug)]
@@ -223,9 +325,284 @@ struct CardFileId {
     ino: u64,
 }
 
+/// Whether a collection error means the device behind a card disappeared.
+fn is_device_gone(error: &anyhow::Error) -> bool {
+    error.chain().any(|cause| {
+        if let Some(errno) = cause.downcast_ref::<rustix::io::Errno>() {
+            return matches!(*errno, rustix::io::Errno::NOENT | rustix::io::Errno::NODEV);
+        }
+        if let Some(nvml) = cause.downcast_ref::<nvml_wrapper::error::NvmlError>() {
+            return matches!(
+                nvml,
+                nvml_wrapper::error::NvmlError::GpuLost | nvml_wrapper::error::NvmlError::NotFound
+            );
+        }
+        false
+    })
+}
+
+/// Reads the fans of a GPU hwmon device, deriving the speed percentage from the PWM duty cycle
+/// or, without PWM control, from the tachometer against the fan's maximum speed.
+fn hwmon_fans(hwmon: BorrowedFd) -> Vec<Fan> {
+    let Ok(dir) = rustix::fs::Dir::read_from(hwmon) else {
+        return Vec::new();
+    };
+    let mut channels: Vec<u32> = dir
+        .flatten()
+        .filter_map(|entry| {
+            let name = entry.file_name().to_string_lossy().into_owned();
+            let channel = match name.strip_prefix("pwm") {
+                Some(channel) => channel,
+                None => name.strip_prefix("fan")?.strip_suffix("_input")?,
+            };
+            channel.parse().ok()
+        })
+        .collect();
+    channels.sort_unstable();
+    channels.dedup();
+    channels
+        .into_iter()
+        .map(|channel| {
+            let rpm = sysfs::readat_u32(hwmon, &format!("fan{channel}_input"));
+            let pwm_percent = sysfs::readat_u32(hwmon, &format!("pwm{channel}"))
+                .map(|pwm| pwm.min(255) as f32 / 255.0 * 100.0);
+            let percent = pwm_percent.or_else(|| {
+                let max = sysfs::readat_u32(hwmon, &format!("fan{channel}_max"))
+                    .filter(|&max| max > 0)?;
+                Some((rpm? as f32 / max as f32 * 100.0).min(100.0))
+            });
+            Fan {
+                index: channel.saturating_sub(1),
+                rpm,
+                percent,
+            }
+        })
+        .collect()
+}
+
+/// Reads every temperature channel of a GPU hwmon device, locating each by its label.
+fn hwmon_thermals(hwmon: BorrowedFd) -> Vec<Thermal> {
+    let Ok(dir) = rustix::fs::Dir::read_from(hwmon) else {
+        return Vec::new();
+    };
+    let mut channels: Vec<u32> = dir
+        .flatten()
+        .filter_map(|entry| {
+            entry
+                .file_name()
+                .to_string_lossy()
+                .strip_prefix("temp")?
+                .strip_suffix("_input")?
+                .parse()
+                .ok()
+        })
+        .collect();
+    channels.sort_unstable();
+    channels
+        .into_iter()
+        .filter_map(|channel| {
+            let current = sysfs::readat_hwmon_temp(hwmon, &format!("temp{channel}_input"))?;
+            let max = sysfs::readat_hwmon_temp(hwmon, &format!("temp{channel}_crit"))
+                .or_else(|| sysfs::readat_hwmon_temp(hwmon, &format!("temp{channel}_max")))
+                .unwrap_or(0.0);
+            let label =
+                sysfs::readat_string(hwmon, &format!("temp{channel}_label")).unwrap_or_default();
+            Some(Thermal {
+                location: thermal_location(&label) as i32,
+                current_celsius: current as u32,
+                max_celsius: max as u32,
+                label,
+            })
+        })
+        .collect()
+}
+
+/// Maps a hwmon temperature label to a location, unlabelled channels are the GPU die.
+fn thermal_location(label: &str) -> ThermalLocation {
+    match label {
+        // amdgpu
+        "junction" => ThermalLocation::Hotspot,
+        "mem" => ThermalLocation::Memory,
+        // xe
+        "vram" | "mctrl" => ThermalLocation::Memory,
+        _ => ThermalLocation::Edge,
+    }
+}
+
+/// The label drivers use for a location, for sensors not read from hwmon.
+fn location_label(location: ThermalLocation) -> &'static str {
+    match location {
+        ThermalLocation::Unspecified => "",
+        ThermalLocation::Edge => "edge",
+        ThermalLocation::Hotspot => "junction",
+        ThermalLocation::Memory => "mem",
+        ThermalLocation::Vrgfx => "vrgfx",
+        ThermalLocation::Vrsoc => "vrsoc",
+        ThermalLocation::Vrmem => "vrmem",
+    }
+}
+
+/// The share of time the memory controller was busy, `None` when the driver doesn't report it.
+fn memory_controller_utilization(engines: &[Engine]) -> Option<f32> {
+    engines
+        .iter()
+        .find(|engine| {
+            engine
+                .identifier
+                .is_some_and(|identifier| identifier.r#type() == EngineType::MemoryController)
+        })
+        .map(|engine| engine.utilization as f32)
+}
+
+/// The allocated share of VRAM, `None` without dedicated VRAM or when its size is unknown.
+fn vram_allocation(memory: &[Memory]) -> Option<f32> {
+    memory
+        .iter()
+        .find(|memory| memory.r#type() == MemoryType::Vram && memory.total_memory > 0)
+        .map(|memory| memory.used_memory as f32 / memory.total_memory as f32 * 100.0)
+}
+
+/// Names the graphics architecture of an Intel GPU from its PCI device ID, empty for unknown IDs.
+fn intel_architecture(device_id: u16) -> &'static str {
+    match device_id >> 8 {
+        // Apollo Lake shares its prefix with Cannon Lake
+        0x5a if matches!(device_id, 0x5a84 | 0x5a85) => "Gen9",
+        0x5a => "Gen10",
+        0x19 | 0x59 | 0x3e | 0x9b | 0x87 | 0x31 => "Gen9",
+        0x8a | 0x4e | 0x45 => "Gen11",
+        0x9a | 0x4c | 0x46 | 0xa7 | 0x49 => "Xe-LP",
+        0x56 | 0x4f => "Xe-HPG",
+        0x7d => "Xe-LPG",
+        0x0b => "Xe-HPC",
+        0x64 | 0xe2 => "Xe2",
+        0xb0 => "Xe3",
+        _ => "",
+    }
+}
+
+/// Reads the architecture of an Intel card from its PCI device ID.
+fn sysfs_intel_architecture(card: BorrowedFd) -> String {
+    sysfs::readat_string(card, "device/device")
+        .and_then(|device| u16::from_str_radix(device.trim_start_matches("0x"), 16).ok())
+        .map(|device| intel_architecture(device).to_string())
+        .unwrap_or_default()
+}
+
+/// Theoretical peak bandwidth of a memory bus, from the number of transfers each lane makes per memory clock.
+fn memory_bandwidth(width_bits: u32, clock_mhz: u32, transfers_per_clock: u32) -> u64 {
+    width_bits as u64 / 8 * clock_mhz as u64 * 1_000_000 * transfers_per_clock as u64
+}
+
+/// Sums the per-process engine utilization of a card into device-wide engines, ordered by type and
+/// index. Each engine is capped at 100% since the per-process figures are sampled at slightly different times.
+fn process_engines(processes: &[Process]) -> Vec<Engine> {
+    let mut engines: HashMap<EngineIdentifier, u64> = HashMap::new();
+    for engine in processes
+        .iter()
+        .flat_map(|process| &process.engine_utilization)
+    {
+        if let Some(identifier) = engine.identifier {
+            *engines.entry(identifier).or_default() += engine.utilization;
+        }
+    }
+    let mut engines: Vec<_> = engines
+        .into_iter()
+        .map(|(identifier, utilization)| Engine {
+            identifier: Some(identifier),
+            utilization: utilization.min(100),
+        })
+        .collect();
+    engines.sort_by_key(|engine| {
+        engine
+            .identifier
+            .map(|identifier| (identifier.r#type, identifier.index))
+    });
+    engines
+}
+
+/// Corrected and uncorrected memory errors since the driver loaded.
+struct EccCounts(u64, u64);
+
+impl sampler::Differential for EccCounts {
+    type Delta = (u64, u64);
+
+    fn delta(&self, previous: &Self) -> Self::Delta {
+        (
+            self.0.saturating_sub(previous.0),
+            self.1.saturating_sub(previous.1),
+        )
+    }
+}
+
+/// Fills in the errors since the previous sample of a card.
+fn sample_ecc(sampler: &mut Sampler<EccCounts>, ecc: &mut Ecc) {
+    if let Some(delta) = sampler.push(EccCounts(ecc.corrected, ecc.uncorrected)) {
+        ecc.corrected_since_last_sample = Some(delta.change.0);
+        ecc.uncorrected_since_last_sample = Some(delta.change.1);
+    }
+}
+
+/// Reads the PCIe link state of a card from the PCI device's sysfs attributes.
+fn sysfs_pcie(card: BorrowedFd) -> Option<Pcie> {
+    let link_gen = |attribute: &str| {
+        parse_link_gen(&sysfs::readat_string(card, attribute)?).filter(|&g| g > 0)
+    };
+    Some(Pcie {
+        current_gen: link_gen("device/current_link_speed")?,
+        current_width: sysfs::readat_u32(card, "device/current_link_width")?,
+        max_gen: link_gen("device/max_link_speed")?,
+        max_width: sysfs::readat_u32(card, "device/max_link_width")?,
+        rx_kbps: None,
+        tx_kbps: None,
+    })
+}
+
+/// Converts a sysfs link speed (e.g. "16.0 GT/s PCIe") to its PCIe generation.
+fn parse_link_gen(speed: &str) -> Option<u32> {
+    let transfer_rate: f32 = speed.split_whitespace().next()?.parse().ok()?;
+    Some(match transfer_rate {
+        rate if rate >= 64.0 => 6,
+        rate if rate >= 32.0 => 5,
+        rate if rate >= 16.0 => 4,
+        rate if rate >= 8.0 => 3,
+        rate if rate >= 5.0 => 2,
+        rate if rate >= 2.5 => 1,
+        // "Unknown" or 0 for links that are down
+        _ => 0,
+    })
+}
+
+/// Fills the name, owner and start time of a GPU process from the process collector's snapshot.
+fn fill_owner(gpu_process: &mut Process, process: &process::Process) {
+    if let Some(identity) = process.identity.as_ref() {
+        gpu_process.name = identity.name.clone();
+        gpu_process.uid = identity.uid;
+    }
+    gpu_process.start_time = process.start_time;
+}
+
+/// Reads the name (as the process collector reports it), owner and start time of a process, `None`
+/// once it has exited.
+fn process_owner(pid: u32) -> Option<(String, u32, u64)> {
+    let process = procfs::process::Process::new(pid as i32).ok()?;
+    let uid = process.uid().ok()?;
+    let (name, start_time) = match process.stat() {
+        Ok(stat) => (stat.comm, stat.starttime),
+        // Fall back to the executable name from the command line
+        Err(_) => process
+            .cmdline()
+            .ok()?
+            .first()
+            .and_then(|arg0| std::path::Path::new(arg0).file_name())
+            .map(|name| (name.to_string_lossy().into_owned(), 0))?,
+    };
+    Some((name, uid, start_time))
+}
+
 fn new_card<'a>(
     fd: OwnedFd,
     nvml: &mut Discovery<Arc<nvml_wrapper::Nvml>>,
+    collect_other: bool,
 ) -> anyhow::Result<Box<dyn Card + Send + 'a>> {
     let driver = rustix::fs::readlinkat(fd.as_fd(), "device/driver", Vec::new())?
         .to_string_lossy()
@@ -251,6 +628,9 @@ fn new_card<'a>(
                 "amdgpu" => Box::new(amdgpu::Card::new(fd)?) as Box<dyn Card + Send>,
                 "i915" => Box::new(i915::Card::new(fd)?) as Box<dyn Card + Send>,
                 "xe" => Box::new(xe::Card::new(fd)?) as Box<dyn Card + Send>,
+                _ if collect_other => {
+                    Box::new(generic::Card::new(fd, &name)?) as Box<dyn Card + Send>
+                }
                 _ => anyhow::bail!("unsupported driver: {}", name),
             }
         }
@@ -265,6 +645,7 @@ fn new_card<'a>(
 mod tests {
     use super::*;
     use crate::collector::Collector;
+    use crate::collector::helpers::test::FakeSysfs;
 
     #[test]
     fn gpu() -> Result<(), Box<dyn std::error::Error>> {
@@ -279,6 +660,10 @@ mod tests {
             power: true,
             thermals: true,
             processes: true,
+            fans: true,
+            pcie: true,
+            collect_other: true,
+            ecc: true,
         });
         let _ = collector.collect(&config)?;
         std::thread::sleep(std::time::Duration::from_secs(1));
@@ -287,4 +672,291 @@ mod tests {
         println!("{:#?}", snapshot);
         Ok(())
     }
+
+    #[test]
+    fn card_removal() -> anyhow::Result<()> {
+        let root = FakeSysfs::new("drm");
+        let add_card = |card: &str, pci_address: &str| {
+            root.dir(format!("devices/{pci_address}/drm/{card}"));
+            root.symlink(
+                "../../../bus/pci/drivers/nouveau",
+                format!("devices/{pci_address}/driver"),
+            );
+            root.symlink(
+                format!("../../devices/{pci_address}"),
+                format!("drm/{card}/device"),
+            );
+        };
+        add_card("card0", "0000:01:00.0");
+        add_card("card1", "0000:02:00.0");
+
+        let mut collector = super::Collector::with_drm_path(root.path("drm"));
+        let config = crate::metrics::Config {
+            gpu: Some(Config::default()),
+            ..Default::default()
+        };
+        let before = collector.collect(&config);
+        root.remove("drm/card1");
+        let after = collector.collect(&config);
+        let cached = collector.cards.len();
+
+        let pci_ids = |snapshot: Snapshot| -> Vec<String> {
+            let mut ids: Vec<_> = snapshot.gpus.into_iter().map(|gpu| gpu.pci_id).collect();
+            ids.sort();
+            ids
+        };
+        assert_eq!(pci_ids(before?), vec!["0000:01:00.0", "0000:02:00.0"]);
+        assert_eq!(pci_ids(after?), vec!["0000:01:00.0"]);
+        assert_eq!(cached, 1);
+        Ok(())
+    }
+
+    #[test]
+    fn no_drm_class() -> anyhow::Result<()> {
+        let root = FakeSysfs::new("no-drm");
+        let mut collector = super::Collector::with_drm_path(root.path("drm"));
+        let config = crate::metrics::Config {
+            gpu: Some(Config::default()),
+            ..Default::default()
+        };
+        assert!(collector.collect(&config)?.gpus.is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn other_drivers() -> anyhow::Result<()> {
+        let root = FakeSysfs::new("drm-other");
+        root.dir("devices/0000:03:00.0/drm/card0");
+        root.write("devices/0000:03:00.0/vendor", "0x1a03\n");
+        root.write("devices/0000:03:00.0/device", "0x2000\n");
+        root.write("devices/0000:03:00.0/hwmon/hwmon0/temp1_input", "45000\n");
+        root.symlink(
+            "../../../bus/pci/drivers/ast",
+            "devices/0000:03:00.0/driver",
+        );
+        root.symlink("../../devices/0000:03:00.0", "drm/card0/device");
+
+        let collect = |collect_other: bool| {
+            let config = crate::metrics::Config {
+                gpu: Some(Config {
+                    drivers: true,
+                    thermals: true,
+                    collect_other,
+                    ..Default::default()
+                }),
+                ..Default::default()
+            };
+            super::Collector::with_drm_path(root.path("drm")).collect(&config)
+        };
+        let skipped = collect(false);
+        let reported = collect(true);
+
+        assert!(skipped?.gpus.is_empty());
+        let gpus = reported?.gpus;
+        assert_eq!(gpus.len(), 1);
+        assert_eq!(gpus[0].pci_id, "0000:03:00.0");
+        assert_eq!(
+            gpus[0]
+                .drivers
+                .as_ref()
+                .and_then(|drivers| drivers.kernel.as_ref())
+                .map(|kernel| kernel.name.as_str()),
+            Some("ast")
+        );
+        assert_eq!(gpus[0].thermals.len(), 1);
+        assert_eq!(gpus[0].thermals[0].current_celsius, 45);
+        Ok(())
+    }
+
+    #[test]
+    fn builtin_pci_ids() -> anyhow::Result<()> {
+        // The checked-in fallback, the build host's pci.ids is embedded instead when it has one
+        let pci_ids = PciIds::parse(include_str!("gpu.ids"))?;
+        assert_eq!(
+            pci_ids.lookup("1002", "73bf", None, None),
+            Some("Navi 21 [Radeon RX 6800/6800 XT / 6900 XT]")
+        );
+        assert_eq!(
+            pci_ids.lookup("1002", "73bf", Some("1002"), Some("0e3a")),
+            Some("Radeon RX 6900 XT")
+        );
+        assert_eq!(
+            pci_ids.lookup("8086", "56a0", None, None),
+            Some("DG2 [Arc A770]")
+        );
+        assert_eq!(
+            pci_ids.lookup("10de", "2684", None, None),
+            Some("AD102 [GeForce RTX 4090]")
+        );
+        // Unknown devices fall back to the vendor name
+        assert_eq!(
+            pci_ids.lookup("1002", "ffff", None, None),
+            Some("Advanced Micro Devices, Inc. [AMD/ATI]")
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn intel_architectures() {
+        assert_eq!(intel_architecture(0x3e92), "Gen9"); // UHD 630
+        assert_eq!(intel_architecture(0x5a85), "Gen9"); // Apollo Lake HD 500
+        assert_eq!(intel_architecture(0x5a52), "Gen10"); // Cannon Lake
+        assert_eq!(intel_architecture(0x9a49), "Xe-LP"); // Tiger Lake
+        assert_eq!(intel_architecture(0x56a0), "Xe-HPG"); // Arc A770
+        assert_eq!(intel_architecture(0x7d55), "Xe-LPG"); // Meteor Lake
+        assert_eq!(intel_architecture(0xe20b), "Xe2"); // Arc B580
+        assert_eq!(intel_architecture(0x1234), "");
+    }
+
+    #[test]
+    fn bandwidth() {
+        // RTX 4090: 384-bit GDDR6X, NVML reports half the 21 Gbps data rate
+        assert_eq!(memory_bandwidth(384, 10501, 2), 1_008_096_000_000);
+        // RX 6800 XT: 256-bit GDDR6 at 16 Gbps
+        assert_eq!(memory_bandwidth(256, 1000, 16), 512_000_000_000);
+    }
+
+    #[test]
+    fn engines_from_processes() {
+        let engine = |r#type: EngineType, index: u32, utilization: u64| Engine {
+            identifier: Some(EngineIdentifier {
+                r#type: r#type as i32,
+                index,
+                clock: None,
+            }),
+            utilization,
+        };
+        let processes = [
+            Process {
+                pid: 1,
+                engine_utilization: vec![
+                    engine(EngineType::Compute, 0, 70),
+                    engine(EngineType::EngineType3d, 0, 10),
+                ],
+                ..Default::default()
+            },
+            Process {
+                pid: 2,
+                engine_utilization: vec![
+                    engine(EngineType::Compute, 0, 40),
+                    engine(EngineType::Copy, 0, 5),
+                ],
+                ..Default::default()
+            },
+        ];
+        assert_eq!(
+            process_engines(&processes),
+            vec![
+                engine(EngineType::EngineType3d, 0, 10),
+                engine(EngineType::Compute, 0, 100),
+                engine(EngineType::Copy, 0, 5),
+            ]
+        );
+    }
+
+    #[test]
+    fn owner() {
+        let mut gpu_process = Process {
+            pid: 1600,
+            ..Default::default()
+        };
+        fill_owner(
+            &mut gpu_process,
+            &process::Process {
+                identity: Some(process::Identity {
+                    name: "cargo".to_string(),
+                    uid: 1000,
+                    ..Default::default()
+                }),
+                start_time: 4242,
+                ..Default::default()
+            },
+        );
+        assert_eq!(
+            (
+                gpu_process.name.as_str(),
+                gpu_process.uid,
+                gpu_process.start_time
+            ),
+            ("cargo", 1000, 4242)
+        );
+
+        // Identity not collected
+        let mut gpu_process = Process::default();
+        fill_owner(
+            &mut gpu_process,
+            &process::Process {
+                start_time: 4242,
+                ..Default::default()
+            },
+        );
+        assert_eq!(
+            (
+                gpu_process.name.as_str(),
+                gpu_process.uid,
+                gpu_process.start_time
+            ),
+            ("", 0, 4242)
+        );
+    }
+
+    #[test]
+    fn link_gen() {
+        assert_eq!(parse_link_gen("2.5 GT/s PCIe"), Some(1));
+        assert_eq!(parse_link_gen("8.0 GT/s PCIe"), Some(3));
+        assert_eq!(parse_link_gen("16.0 GT/s PCIe"), Some(4));
+        assert_eq!(parse_link_gen("32.0 GT/s PCIe"), Some(5));
+        assert_eq!(parse_link_gen("Unknown"), None);
+    }
+
+    #[test]
+    fn thermal_labels() {
+        assert_eq!(thermal_location("edge"), ThermalLocation::Edge);
+        assert_eq!(thermal_location("junction"), ThermalLocation::Hotspot);
+        assert_eq!(thermal_location("mem"), ThermalLocation::Memory);
+        assert_eq!(thermal_location("vram"), ThermalLocation::Memory);
+        assert_eq!(thermal_location(""), ThermalLocation::Edge);
+        assert_eq!(location_label(ThermalLocation::Hotspot), "junction");
+    }
+
+    #[test]
+    fn memory_utilization() {
+        let engine = |r#type: EngineType, utilization: u64| Engine {
+            identifier: Some(EngineIdentifier {
+                r#type: r#type as i32,
+                index: 0,
+                clock: None,
+            }),
+            utilization,
+        };
+        let memory = |r#type: MemoryType, total_memory: u64, used_memory: u64| Memory {
+            r#type: r#type as i32,
+            total_memory,
+            used_memory,
+        };
+        assert_eq!(
+            memory_controller_utilization(&[
+                engine(EngineType::EngineType3d, 90),
+                engine(EngineType::MemoryController, 35),
+            ]),
+            Some(35.0)
+        );
+        assert_eq!(
+            memory_controller_utilization(&[engine(EngineType::EngineType3d, 90)]),
+            None
+        );
+        // Allocation is independent of how busy the memory controller is
+        assert_eq!(
+            vram_allocation(&[
+                memory(MemoryType::System, 4096, 4096),
+                memory(MemoryType::Vram, 8192, 2048),
+            ]),
+            Some(25.0)
+        );
+        assert_eq!(vram_allocation(&[memory(MemoryType::Vram, 0, 2048)]), None);
+        assert_eq!(
+            vram_allocation(&[memory(MemoryType::System, 4096, 1024)]),
+            None
+        );
+    }
 }
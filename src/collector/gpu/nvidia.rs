@@ -4,8 +4,22 @@
  * file, You can obtain one at https://mozilla.org/MPL/2.0/.
  */
 
-use crate::{collector::helpers::sysfs, metrics::gpu::*};
-use std::{path::PathBuf, sync::Arc};
+use crate::{
+    collector::helpers::{Discovery, Sampler, sysfs},
+    metrics::gpu::*,
+};
+use nvml_wrapper::bitmasks::device::ThrottleReasons;
+use nvml_wrapper::enum_wrappers::device::{
+    EccCounter, MemoryError, PcieUtilCounter, TemperatureSensor, TemperatureThreshold,
+};
+use nvml_wrapper::enums::device::{DeviceArchitecture, UsedGpuMemory};
+use nvml_wrapper::error::NvmlError;
+use nvml_wrapper::struct_wrappers::device::ProcessUtilizationSample;
+use std::{
+    collections::{BTreeMap, HashMap},
+    path::PathBuf,
+    sync::Arc,
+};
 
 use rustix::fd::{AsFd, OwnedFd};
 
@@ -15,6 +29,10 @@ pub struct Card {
     pci: String,
     primary_node: PathBuf,
     render_node: PathBuf,
+    /// Timestamp of the newest process utilization sample, so each collection only gets new samples
+    last_seen_timestamp: Option<u64>,
+    ecc: Sampler<super::EccCounts>,
+    memory_bus: Discovery<MemoryBus>,
 }
 
 impl Card {
@@ -53,6 +71,9 @@ impl Card {
             pci,
             primary_node,
             render_node,
+            last_seen_timestamp: None,
+            ecc: Sampler::new(),
+            memory_bus: Discovery::default(),
         })
     }
 
@@ -212,14 +233,28 @@ impl Card {
     fn power<'a>(&self, device: &nvml_wrapper::Device<'a>) -> Option<Power> {
         Some(Power {
             current_power_mw: device.power_usage().ok()?,
-            max_power_mw: device.power_management_limit().ok()?,
+            max_power_mw: device
+                .enforced_power_limit()
+                .or_else(|_| device.power_management_limit())
+                .ok()?,
             is_power_throttled: device.current_throttle_reasons().is_ok_and(|reasons| {
-                reasons
-                    .contains(nvml_wrapper::bitmasks::device::ThrottleReasons::SW_THERMAL_SLOWDOWN)
+                reasons.intersects(
+                    ThrottleReasons::SW_POWER_CAP | ThrottleReasons::HW_POWER_BRAKE_SLOWDOWN,
+                )
             }),
             is_thermal_throttled: device.current_throttle_reasons().is_ok_and(|reasons| {
-                reasons.contains(nvml_wrapper::bitmasks::device::ThrottleReasons::SW_POWER_CAP)
+                reasons.intersects(
+                    ThrottleReasons::SW_THERMAL_SLOWDOWN | ThrottleReasons::HW_THERMAL_SLOWDOWN,
+                )
             }),
+            limits: device
+                .power_management_limit_constraints()
+                .ok()
+                .map(|constraints| PowerLimits {
+                    min_mw: Some(constraints.min_limit),
+                    max_mw: Some(constraints.max_limit),
+                    default_mw: device.power_management_limit_default().ok(),
+                }),
         })
     }
 
@@ -239,76 +274,188 @@ impl Card {
 
         for i in 0..thermal_settings.count {
             let thermal = &thermal_settings.sensor[i as usize];
+            let (location, label) = match thermal.target {
+                nvml_wrapper_sys::bindings::nvmlThermalTarget_t_NVML_THERMAL_TARGET_GPU => {
+                    (ThermalLocation::Edge, "gpu")
+                }
+                nvml_wrapper_sys::bindings::nvmlThermalTarget_t_NVML_THERMAL_TARGET_MEMORY => {
+                    (ThermalLocation::Memory, "memory")
+                }
+                nvml_wrapper_sys::bindings::nvmlThermalTarget_t_NVML_THERMAL_TARGET_POWER_SUPPLY => {
+                    (ThermalLocation::Vrsoc, "power_supply")
+                }
+                _ => continue,
+            };
             thermals.push(Thermal {
-                location: match thermal.target {
-                    nvml_wrapper_sys::bindings::nvmlThermalTarget_t_NVML_THERMAL_TARGET_GPU => {
-                        ThermalLocation::Edge as i32
-                    }
-                    nvml_wrapper_sys::bindings::nvmlThermalTarget_t_NVML_THERMAL_TARGET_MEMORY => {
-                        ThermalLocation::Memory as i32
-                    }
-                    nvml_wrapper_sys::bindings::nvmlThermalTarget_t_NVML_THERMAL_TARGET_POWER_SUPPLY => {
-                        ThermalLocation::Vrsoc as i32
-                    }
-                    _ => continue,
-                },
+                location: location as i32,
                 current_celsius: thermal.currentTemp as u32,
                 max_celsius: thermal.defaultMaxTemp as u32,
+                label: label.to_string(),
             })
         }
 
+        // Older drivers and some consumer boards don't support the thermal settings query
+        if thermals.is_empty()
+            && let Ok(current_celsius) = device.temperature(TemperatureSensor::Gpu)
+        {
+            thermals.push(Thermal {
+                location: ThermalLocation::Edge as i32,
+                current_celsius,
+                max_celsius: device
+                    .temperature_threshold(TemperatureThreshold::Slowdown)
+                    .unwrap_or_default(),
+                label: "gpu".to_string(),
+            });
+        }
+
         thermals
     }
 
-    fn processes<'a>(&self, device: &nvml_wrapper::Device<'a>) -> Vec<Process> {
-        let mut processes = Vec::new();
-        let utilization_stats = match device.process_utilization_stats(None) {
-            Ok(stats) => stats,
-            Err(_) => return Vec::new(),
+    fn fans<'a>(&self, device: &nvml_wrapper::Device<'a>) -> Vec<Fan> {
+        let Ok(count) = device.num_fans() else {
+            return Vec::new();
         };
-        for process in utilization_stats.iter() {
-            processes.push(Process {
-                pid: process.pid,
-                engine_utilization: vec![
-                    Engine {
-                        identifier: Some(EngineIdentifier {
-                            r#type: EngineType::EngineType3d as i32,
-                            index: 0,
-                            clock: Some(ClockIdentifier {
-                                domain: ClockDomain::Graphics as i32,
-                                index: 0,
-                            }),
-                        }),
-                        utilization: process.sm_util as u64,
-                    },
-                    Engine {
-                        identifier: Some(EngineIdentifier {
-                            r#type: EngineType::VideoEncode as i32,
-                            index: 0,
-                            clock: Some(ClockIdentifier {
-                                domain: ClockDomain::VideoUnified as i32,
-                                index: 0,
-                            }),
-                        }),
-                        utilization: process.enc_util as u64,
-                    },
-                    Engine {
-                        identifier: Some(EngineIdentifier {
-                            r#type: EngineType::VideoDecode as i32,
+        (0..count)
+            .filter_map(|index| {
+                let percent = device.fan_speed(index).ok()?;
+                Some(Fan {
+                    index,
+                    rpm: device.fan_speed_rpm(index).ok(),
+                    percent: Some(percent as f32),
+                })
+            })
+            .collect()
+    }
+
+    fn memory_bus<'a>(device: &nvml_wrapper::Device<'a>) -> Option<MemoryBus> {
+        let width_bits = device.memory_bus_width().ok()?;
+        let max_clock_mhz = device
+            .max_clock_info(nvml_wrapper::enum_wrappers::device::Clock::Memory)
+            .unwrap_or(0);
+        Some(MemoryBus {
+            width_bits,
+            max_clock_mhz,
+            // The memory clock is half the effective data rate on both GDDR and HBM
+            bandwidth_bytes_per_second: super::memory_bandwidth(width_bits, max_clock_mhz, 2),
+            ..Default::default()
+        })
+    }
+
+    fn ecc<'a>(&mut self, device: &nvml_wrapper::Device<'a>) -> Option<Ecc> {
+        // NotSupported on consumer GPUs
+        let mode = device.is_ecc_enabled().ok()?;
+        let count = |error, counter| device.total_ecc_errors(error, counter).ok();
+        let mut ecc = Ecc {
+            enabled: mode.currently_enabled,
+            corrected: count(MemoryError::Corrected, EccCounter::Volatile).unwrap_or(0),
+            uncorrected: count(MemoryError::Uncorrected, EccCounter::Volatile).unwrap_or(0),
+            corrected_aggregate: count(MemoryError::Corrected, EccCounter::Aggregate),
+            uncorrected_aggregate: count(MemoryError::Uncorrected, EccCounter::Aggregate),
+            ..Default::default()
+        };
+        super::sample_ecc(&mut self.ecc, &mut ecc);
+        Some(ecc)
+    }
+
+    fn encoder_sessions<'a>(&self, device: &nvml_wrapper::Device<'a>) -> Option<EncoderSessions> {
+        // NotSupported on GPUs without NVENC (e.g. the A100 and H100)
+        let stats = device.encoder_stats().ok()?;
+        Some(EncoderSessions {
+            session_count: stats.session_count,
+            average_fps: stats.average_fps,
+            average_latency_us: stats.average_latency,
+        })
+    }
+
+    fn pcie<'a>(&self, device: &nvml_wrapper::Device<'a>) -> Option<Pcie> {
+        Some(Pcie {
+            current_gen: device.current_pcie_link_gen().ok()?,
+            current_width: device.current_pcie_link_width().ok()?,
+            max_gen: device.max_pcie_link_gen().ok()?,
+            max_width: device.max_pcie_link_width().ok()?,
+            rx_kbps: device.pcie_throughput(PcieUtilCounter::Receive).ok(),
+            tx_kbps: device.pcie_throughput(PcieUtilCounter::Send).ok(),
+        })
+    }
+
+    fn processes<'a>(&mut self, device: &nvml_wrapper::Device<'a>) -> Vec<Process> {
+        // Processes idle over the sample window have no utilization samples, so the running
+        // process lists decide which processes are reported and how much memory they hold
+        let mut vram_usage: BTreeMap<u32, u64> = BTreeMap::new();
+        let graphics = device.running_graphics_processes().unwrap_or_default();
+        let compute = device.running_compute_processes().unwrap_or_default();
+        for process in graphics.into_iter().chain(compute) {
+            let used = match process.used_gpu_memory {
+                UsedGpuMemory::Used(bytes) => bytes,
+                UsedGpuMemory::Unavailable => 0,
+            };
+            // A process with both graphics and compute contexts is listed twice with the same memory
+            let entry = vram_usage.entry(process.pid).or_default();
+            *entry = (*entry).max(used);
+        }
+
+        // Only request samples since the previous collection, keeping the newest one per process
+        let mut samples: HashMap<u32, ProcessUtilizationSample> = HashMap::new();
+        match device.process_utilization_stats(self.last_seen_timestamp) {
+            Ok(stats) => {
+                for sample in stats {
+                    self.last_seen_timestamp = self.last_seen_timestamp.max(Some(sample.timestamp));
+                    match samples.get(&sample.pid) {
+                        Some(newest) if newest.timestamp >= sample.timestamp => {}
+                        _ => {
+                            samples.insert(sample.pid, sample);
+                        }
+                    }
+                }
+            }
+            // NVML reports NotFound when no process was active since the last timestamp
+            Err(NvmlError::NotFound) => {}
+            Err(err) => tracing::debug!("could not get process utilization samples: {}", err),
+        }
+        for &pid in samples.keys() {
+            vram_usage.entry(pid).or_default();
+        }
+
+        // NVML can list the same process several times, so only look each one up once per collection
+        let mut owners: HashMap<u32, Option<(String, u32, u64)>> = HashMap::new();
+        vram_usage
+            .into_iter()
+            .map(|(pid, vram_usage)| {
+                let (name, uid, start_time) = owners
+                    .entry(pid)
+                    .or_insert_with(|| super::process_owner(pid))
+                    .clone()
+                    .unwrap_or_default();
+                let sample = samples.get(&pid);
+                let engine = |r#type: EngineType, utilization: Option<u32>| Engine {
+                    identifier: Some(EngineIdentifier {
+                        r#type: r#type as i32,
+                        index: 0,
+                        clock: Some(ClockIdentifier {
+                            domain: match r#type {
+                                EngineType::EngineType3d => ClockDomain::Graphics as i32,
+                                _ => ClockDomain::VideoUnified as i32,
+                            },
                             index: 0,
-                            clock: Some(ClockIdentifier {
-                                domain: ClockDomain::VideoUnified as i32,
-                                index: 0,
-                            }),
                         }),
-                        utilization: process.dec_util as u64,
-                    },
-                ],
-                vram_usage: process.mem_util as u64,
-                gtt_usage: 0,
-            });
-        }
-        processes
+                    }),
+                    utilization: utilization.unwrap_or(0) as u64,
+                };
+                Process {
+                    pid,
+                    engine_utilization: vec![
+                        engine(EngineType::EngineType3d, sample.map(|s| s.sm_util)),
+                        engine(EngineType::VideoEncode, sample.map(|s| s.enc_util)),
+                        engine(EngineType::VideoDecode, sample.map(|s| s.dec_util)),
+                    ],
+                    vram_usage,
+                    gtt_usage: 0,
+                    name,
+                    uid,
+                    start_time,
+                }
+            })
+            .collect()
     }
 }
 
@@ -334,11 +481,23 @@ impl super::Card for Card {
 
     fn collect(&mut self, config: &Config) -> anyhow::Result<super::Gpu> {
         let mut gpu = Gpu::default();
-        let device = self.nvml.device_by_pci_bus_id(self.pci.clone())?;
+        let nvml = self.nvml.clone();
+        let device = nvml.device_by_pci_bus_id(self.pci.clone())?;
         gpu.brand_name = device.name().unwrap_or_default();
+        gpu.uuid = device.uuid().unwrap_or_default();
         gpu.primary_node = self.primary_node.to_string_lossy().to_string();
         gpu.render_node = self.render_node.to_string_lossy().to_string();
         gpu.pci_id = self.pci.clone();
+        gpu.architecture = device
+            .architecture()
+            .ok()
+            .filter(|architecture| *architecture != DeviceArchitecture::Unknown)
+            .map(|architecture| architecture.to_string())
+            .unwrap_or_default();
+        gpu.compute_capability = device
+            .cuda_compute_capability()
+            .map(|capability| format!("{}.{}", capability.major, capability.minor))
+            .unwrap_or_default();
         gpu.drivers = config.drivers.then(|| Drivers {
             kernel: Some(KernelDriver {
                 name: "nvidia".to_string(),
@@ -367,6 +526,31 @@ impl super::Card for Card {
             .thermals
             .then(|| self.thermal(&device))
             .unwrap_or_default();
+        if config.power
+            && let Ok(reasons) = device.current_throttle_reasons()
+        {
+            gpu.throttle_reasons = throttle_reason_names(reasons);
+            gpu.is_throttled = !gpu.throttle_reasons.is_empty();
+        }
+        gpu.pcie = config.pcie.then(|| self.pcie(&device)).flatten();
+        gpu.fans = if config.fans {
+            self.fans(&device)
+        } else {
+            Vec::new()
+        };
+        gpu.ecc = config.ecc.then(|| self.ecc(&device)).flatten();
+        gpu.encoder_sessions = config
+            .engines
+            .then(|| self.encoder_sessions(&device))
+            .flatten();
+        if config.memory {
+            // Static, so it's only queried once
+            let memory_bus = self.memory_bus.probe(|| {
+                Self::memory_bus(&device)
+                    .ok_or_else(|| anyhow::anyhow!("memory bus width not supported"))
+            });
+            gpu.memory_bus = memory_bus.cloned();
+        }
         gpu.processes = config
             .processes
             .then(|| self.processes(&device))
@@ -383,7 +567,95 @@ impl super::Card for Card {
         Ok(())
     }
 
+    fn partitions(&mut self, config: &Config) -> Vec<Gpu> {
+        let nvml = self.nvml.clone();
+        let Ok(device) = nvml.device_by_pci_bus_id(self.pci.clone()) else {
+            return Vec::new();
+        };
+        // Unsupported on anything but data center GPUs, which is the same as MIG being disabled
+        if !device
+            .mig_mode()
+            .is_ok_and(|mode| mode.current == nvml_wrapper_sys::bindings::NVML_DEVICE_MIG_ENABLE)
+        {
+            return Vec::new();
+        }
+        let parent_uuid = device.uuid().unwrap_or_default();
+        // The count is the maximum number of instances, unused slots aren't found
+        let count = device.mig_device_count().unwrap_or(0);
+        (0..count)
+            .filter_map(|index| device.mig_device_by_index(index).ok())
+            .map(|instance| Gpu {
+                brand_name: instance.name().unwrap_or_default(),
+                uuid: instance.uuid().unwrap_or_default(),
+                parent_uuid: parent_uuid.clone(),
+                primary_node: self.primary_node.to_string_lossy().to_string(),
+                render_node: self.render_node.to_string_lossy().to_string(),
+                pci_id: self.pci.clone(),
+                // NVML only reports utilization of MIG instances on some driver versions
+                engines: if config.engines && instance.utilization_rates().is_ok() {
+                    self.engines(&instance)
+                } else {
+                    Vec::new()
+                },
+                memory: if config.memory {
+                    self.memory(&instance)
+                } else {
+                    Vec::new()
+                },
+                ..Default::default()
+            })
+            .collect()
+    }
+
     fn pci_id(&self) -> String {
         self.pci.clone()
     }
 }
+
+/// Names the bits of a throttle reason bitmask. An idle GPU isn't constrained, so
+/// `GPU_IDLE` is left out.
+fn throttle_reason_names(reasons: ThrottleReasons) -> Vec<String> {
+    const NAMES: &[(ThrottleReasons, &str)] = &[
+        (
+            ThrottleReasons::APPLICATIONS_CLOCKS_SETTING,
+            "applications_clocks_setting",
+        ),
+        (ThrottleReasons::SW_POWER_CAP, "sw_power_cap"),
+        (ThrottleReasons::HW_SLOWDOWN, "hw_slowdown"),
+        (ThrottleReasons::SYNC_BOOST, "sync_boost"),
+        (ThrottleReasons::SW_THERMAL_SLOWDOWN, "sw_thermal_slowdown"),
+        (ThrottleReasons::HW_THERMAL_SLOWDOWN, "hw_thermal_slowdown"),
+        (
+            ThrottleReasons::HW_POWER_BRAKE_SLOWDOWN,
+            "hw_power_brake_slowdown",
+        ),
+        (
+            ThrottleReasons::DISPLAY_CLOCK_SETTING,
+            "display_clock_setting",
+        ),
+    ];
+    NAMES
+        .iter()
+        .filter(|(reason, _)| reasons.contains(*reason))
+        .map(|(_, name)| name.to_string())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn throttle_reasons() {
+        assert!(throttle_reason_names(ThrottleReasons::NONE).is_empty());
+        assert!(throttle_reason_names(ThrottleReasons::GPU_IDLE).is_empty());
+        assert_eq!(
+            throttle_reason_names(
+                ThrottleReasons::GPU_IDLE
+                    | ThrottleReasons::SW_POWER_CAP
+                    | ThrottleReasons::HW_THERMAL_SLOWDOWN
+            ),
+            vec!["sw_power_cap", "hw_thermal_slowdown"]
+        );
+    }
+}
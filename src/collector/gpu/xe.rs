@@ -6,7 +6,6 @@
 
 use crate::collector::helpers::sysfs;
 use crate::metrics::gpu::*;
-use std::collections::HashMap;
 use std::path::PathBuf;
 
 use rustix::fd::{AsFd, OwnedFd};
@@ -18,6 +17,7 @@ pub struct Card {
     render_node: PathBuf,
     render_node_fd: OwnedFd,
     pci_id: String,
+    hwmon: Option<super::intel_hwmon::Hwmon>,
 }
 
 impl Card {
@@ -58,7 +58,9 @@ impl Card {
                 )?);
             }
         }
+        let hwmon = super::intel_hwmon::Hwmon::open(fd.as_fd());
         Ok(Self {
+            hwmon,
             card_fd: fd,
             primary_node,
             render_node: render_node_path,
@@ -200,20 +202,15 @@ impl Card {
             .collect()
     }
 
-    fn power(&self) -> Option<Power> {
-        let Some(_hwmon_fd) = sysfs::first_hwmon_subdir_at(self.card_fd.as_fd(), "device/hwmon")
-        else {
-            return None;
-        };
-        None // I don't have an Arc GPU to figure out where the power file is located
+    fn power(&mut self) -> Option<Power> {
+        self.hwmon.as_mut()?.power()
     }
 
     fn thermals(&self) -> Vec<Thermal> {
-        let Some(_hwmon_fd) = sysfs::first_hwmon_subdir_at(self.card_fd.as_fd(), "device/hwmon")
-        else {
-            return Vec::new();
-        };
-        Vec::new() // Same as above
+        self.hwmon
+            .as_ref()
+            .map(|hwmon| hwmon.thermals())
+            .unwrap_or_default()
     }
 }
 
@@ -249,6 +246,7 @@ impl super::Card for Card {
                     .map(|n| n.to_string_lossy().to_string())
             })
             .unwrap_or_default();
+        gpu.architecture = super::sysfs_intel_architecture(self.card_fd.as_fd());
         gpu.drivers = config.drivers.then(|| Drivers {
             kernel: Some(KernelDriver {
                 name: "xe".to_string(),
@@ -261,6 +259,15 @@ impl super::Card for Card {
         gpu.memory = config.memory.then(|| self.memory()).unwrap_or_default();
         gpu.power = config.power.then(|| self.power()).unwrap_or_default();
         gpu.thermals = config.thermals.then(|| self.thermals()).unwrap_or_default();
+        gpu.pcie = config
+            .pcie
+            .then(|| super::sysfs_pcie(self.card_fd.as_fd()))
+            .flatten();
+        gpu.fans = config
+            .fans
+            .then(|| self.hwmon.as_ref().map(|hwmon| hwmon.fans()))
+            .flatten()
+            .unwrap_or_default();
         Ok(gpu)
     }
     fn resolve(
@@ -329,34 +336,21 @@ impl super::Card for Card {
                                 }
                             }
                         }
-                        output.processes.push(Process {
+                        let mut gpu_process = Process {
                             pid: *pid,
                             engine_utilization,
                             vram_usage: gpu_usage.vram_usage,
                             gtt_usage: gpu_usage.system_usage,
-                        })
+                            ..Default::default()
+                        };
+                        super::fill_owner(&mut gpu_process, process);
+                        output.processes.push(gpu_process);
                     }
                 }
             }
         }
-        let mut engine_utilizations: HashMap<EngineIdentifier, u64> = HashMap::new();
-        // After the fact, we can use the processes to figure out engine utilization
-        for process in &output.processes {
-            for engine_utilization in &process.engine_utilization {
-                let engine = engine_utilizations
-                    .entry(engine_utilization.identifier.unwrap_or_default())
-                    .or_default();
-
-                *engine += engine_utilization.utilization;
-            }
-        }
-        output.engines = engine_utilizations
-            .into_iter()
-            .map(|(identifier, utiilization)| Engine {
-                identifier: Some(identifier),
-                utilization: utiilization,
-            })
-            .collect::<Vec<_>>();
+        // The driver has no device-wide busyness, so the engines are the sum of the processes' fdinfo
+        output.engines = super::process_engines(&output.processes);
         Ok(())
     }
 
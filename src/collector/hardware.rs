@@ -0,0 +1,108 @@
+/*
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/.
+ */
+//! Reads the identification of the machine from DMI (`/sys/class/dmi/id`), falling back to the
+//! device tree model on systems without SMBIOS such as Arm boards and some VMs.
+
+use std::path::Path;
+
+use crate::collector::helpers::sysfs::read_string_path;
+use crate::metrics::HardwareInfo;
+
+/// Values firmware vendors leave in DMI fields they didn't fill in.
+const PLACEHOLDERS: &[&str] = &[
+    "To Be Filled By O.E.M.",
+    "To be filled by O.E.M.",
+    "Default string",
+    "System Product Name",
+    "System Version",
+    "Not Applicable",
+    "Not Specified",
+    "None",
+];
+
+/// Reads the hardware identification of the machine, `None` when neither DMI nor a device tree
+/// model is present.
+pub fn read() -> Option<HardwareInfo> {
+    read_from(
+        Path::new("/sys/class/dmi/id"),
+        Path::new("/proc/device-tree/model"),
+    )
+}
+
+fn read_from(dmi: &Path, device_tree_model: &Path) -> Option<HardwareInfo> {
+    let field = |name: &str| {
+        read_string_path(dmi.join(name))
+            .filter(|value| !value.is_empty() && !PLACEHOLDERS.contains(&value.as_str()))
+    };
+    let mut info = HardwareInfo {
+        vendor: field("sys_vendor"),
+        product_name: field("product_name"),
+        product_version: field("product_version"),
+        board_name: field("board_name"),
+        bios_version: field("bios_version"),
+        bios_date: field("bios_date"),
+        model: None,
+    };
+    if info == HardwareInfo::default() {
+        // The device tree property is NUL-terminated
+        info.model = read_string_path(device_tree_model)
+            .map(|model| model.trim_end_matches('\0').to_string())
+            .filter(|model| !model.is_empty());
+    }
+    (info != HardwareInfo::default()).then_some(info)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::collector::helpers::test::FakeSysfs;
+
+    #[test]
+    fn hardware_info() {
+        let root = FakeSysfs::new("hardware");
+        let dmi = root.dir("dmi");
+        let model = root.path("model");
+        root.write("model", "Raspberry Pi 4 Model B Rev 1.4\0");
+
+        // Without DMI
+        let device_tree = read_from(&dmi, &model);
+        let neither = read_from(&dmi, &root.path("missing"));
+
+        for (name, value) in [
+            ("sys_vendor", "LENOVO\n"),
+            ("product_name", "21HM004GUS\n"),
+            ("product_version", "ThinkPad X1 Carbon Gen 11\n"),
+            ("board_name", "21HM004GUS\n"),
+            ("bios_version", "N3XET53W (1.28 )\n"),
+            ("bios_date", "01/16/2024\n"),
+        ] {
+            root.write(format!("dmi/{name}"), value);
+        }
+        let full = read_from(&dmi, &model);
+        root.write("dmi/product_version", "To Be Filled By O.E.M.\n");
+        let placeholder = read_from(&dmi, &model);
+
+        assert_eq!(
+            device_tree,
+            Some(HardwareInfo {
+                model: Some("Raspberry Pi 4 Model B Rev 1.4".to_string()),
+                ..Default::default()
+            })
+        );
+        assert_eq!(neither, None);
+        let full = full.expect("DMI is present");
+        assert_eq!(full.vendor.as_deref(), Some("LENOVO"));
+        assert_eq!(
+            full.product_version.as_deref(),
+            Some("ThinkPad X1 Carbon Gen 11")
+        );
+        assert_eq!(full.bios_version.as_deref(), Some("N3XET53W (1.28 )"));
+        assert_eq!(full.bios_date.as_deref(), Some("01/16/2024"));
+        // The device tree is only a fallback
+        assert_eq!(full.model, None);
+        assert_eq!(placeholder.and_then(|info| info.product_version), None);
+    }
+}
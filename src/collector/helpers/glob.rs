@@ -0,0 +1,57 @@
+/*
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/.
+ */
+//! Shell-style name patterns, used by the config options that select interfaces, processes and
+//! environment variables by name.
+
+/// Matches a name against a glob pattern where '*' stands for any run of characters and '?' for
+/// a single one.
+pub fn glob_matches(pattern: &str, name: &str) -> bool {
+    let (pattern, name): (Vec<char>, Vec<char>) =
+        (pattern.chars().collect(), name.chars().collect());
+    let (mut p, mut n) = (0, 0);
+    // Where the last '*' was seen and the name position it currently swallows up to
+    let mut backtrack = None;
+    while n < name.len() {
+        match pattern.get(p) {
+            Some('*') => {
+                backtrack = Some((p, n));
+                p += 1;
+            }
+            Some(&c) if c == '?' || c == name[n] => {
+                p += 1;
+                n += 1;
+            }
+            _ => match backtrack {
+                // Let the '*' swallow one more character and retry
+                Some((star, swallowed)) => {
+                    p = star + 1;
+                    n = swallowed + 1;
+                    backtrack = Some((star, swallowed + 1));
+                }
+                None => return false,
+            },
+        }
+    }
+    pattern[p..].iter().all(|&c| c == '*')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn patterns() {
+        assert!(glob_matches("veth*", "veth1a2b3c"));
+        assert!(glob_matches("veth*", "veth"));
+        assert!(!glob_matches("veth*", "eth0"));
+        assert!(glob_matches("docker0", "docker0"));
+        assert!(!glob_matches("docker0", "docker01"));
+        assert!(glob_matches("enp?s*", "enp3s0"));
+        assert!(glob_matches("*-*-*", "a-b-c"));
+        assert!(!glob_matches("*-*-*", "a-b"));
+        assert!(glob_matches("*", ""));
+    }
+}
@@ -10,9 +10,14 @@ pub(crate) mod discovery;
 pub(crate) use discovery::Discovery;
 pub(crate) mod fam;
 pub(crate) use fam::FAM;
+pub(crate) mod glob;
+pub(crate) use glob::glob_matches;
 pub(crate) mod ioctl;
 pub(crate) mod pciids;
 pub(crate) use pciids::PciIds;
+pub(crate) mod pressure;
 pub(crate) mod sampler;
 pub(crate) use sampler::Sampler;
 pub(crate) mod sysfs;
+#[cfg(test)]
+pub(crate) mod test;
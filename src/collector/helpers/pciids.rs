@@ -66,6 +66,11 @@ impl PciIds {
         Ok(Self { entries })
     }
 
+    /// Looks up the name of a vendor by its ID.
+    pub fn vendor(&self, vendor: &str) -> Option<&str> {
+        self.entries.get(vendor).map(String::as_str)
+    }
+
     pub fn lookup(
         &self,
         vendor: &str,
@@ -0,0 +1,69 @@
+/*
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/.
+ */
+//! Pressure stall information (PSI) parsing for the files in `/proc/pressure`.
+//! Unlike `procfs`, missing `full` lines (CPU pressure on kernels before 5.13) are tolerated.
+
+/// A single `some` or `full` line of a pressure file.
+#[derive(Default, Debug, Clone, Copy, PartialEq)]
+pub struct Record {
+    /// Percentage of time stalled over the last 10 seconds
+    pub avg10: f32,
+    /// Percentage of time stalled over the last 60 seconds
+    pub avg60: f32,
+    /// Percentage of time stalled over the last 300 seconds
+    pub avg300: f32,
+    /// Total stall time in microseconds
+    pub total_us: u64,
+}
+
+/// The parsed contents of a pressure file.
+#[derive(Default, Debug, Clone, Copy, PartialEq)]
+pub struct Pressure {
+    /// Share of time in which at least some tasks were stalled
+    pub some: Option<Record>,
+    /// Share of time in which all non-idle tasks were stalled
+    pub full: Option<Record>,
+}
+
+/// Reads `/proc/pressure/{resource}`, returning `None` if the kernel was built without PSI.
+pub fn read(resource: &str) -> Option<Pressure> {
+    let contents = std::fs::read_to_string(format!("/proc/pressure/{resource}")).ok()?;
+    Some(parse(&contents))
+}
+
+/// Parses the contents of a pressure file, skipping any malformed lines.
+pub fn parse(contents: &str) -> Pressure {
+    let mut pressure = Pressure::default();
+    for line in contents.lines() {
+        let Some((kind, fields)) = line.split_once(' ') else {
+            continue;
+        };
+        let Some(record) = parse_record(fields) else {
+            continue;
+        };
+        match kind {
+            "some" => pressure.some = Some(record),
+            "full" => pressure.full = Some(record),
+            _ => {}
+        }
+    }
+    pressure
+}
+
+fn parse_record(fields: &str) -> Option<Record> {
+    let mut record = Record::default();
+    for field in fields.split_whitespace() {
+        let (key, value) = field.split_once('=')?;
+        match key {
+            "avg10" => record.avg10 = value.parse().ok()?,
+            "avg60" => record.avg60 = value.parse().ok()?,
+            "avg300" => record.avg300 = value.parse().ok()?,
+            "total" => record.total_us = value.parse().ok()?,
+            _ => {}
+        }
+    }
+    Some(record)
+}
@@ -28,7 +28,11 @@ impl<T: Differential> Sampler<T> {
 
     /// Replaces the current sample with the given value and returns a delta if there was a previous sample.
     pub fn push(&mut self, value: T) -> Option<Delta<T::Delta>> {
-        let now = Instant::now();
+        self.push_at(value, Instant::now())
+    }
+
+    /// Like `push`, for a value that was read at the given time.
+    pub fn push_at(&mut self, value: T, now: Instant) -> Option<Delta<T::Delta>> {
         let delta = self.last.take().map(|last| Delta {
             change: value.delta(&last.value),
             interval: now - last.taken_at,
@@ -39,6 +43,11 @@ impl<T: Differential> Sampler<T> {
         });
         delta
     }
+
+    /// When the last sample was taken, `None` before the first one.
+    pub fn taken_at(&self) -> Option<Instant> {
+        self.last.as_ref().map(|last| last.taken_at)
+    }
 }
 
 /// The total change over the period between two samples.
@@ -84,7 +84,6 @@ pub fn readat_u64(fd: BorrowedFd, path: &str) -> Option<u64> {
     readat_string(fd, path).and_then(|s| s.parse::<u64>().ok())
 }
 
-#[allow(dead_code)]
 /// Reads a 64-bit unsigned integer from a given path.
 pub fn read_u64_path<P: rustix::path::Arg>(path: P) -> Option<u64> {
     read_string_path(path).and_then(|s| s.parse::<u64>().ok())
@@ -130,6 +129,19 @@ pub fn count_cpu_list(cpu_list: &str) -> Option<u32> {
     Some(count)
 }
 
+/// Parses a CPU list (e.g. "0-3,8,10-11") into the individual CPU indices
+pub fn parse_cpu_list(cpu_list: &str) -> Option<Vec<u32>> {
+    let mut cpus = Vec::new();
+    for range in cpu_list.trim().split(',').filter(|r| !r.is_empty()) {
+        if let Some((start, end)) = range.split_once('-') {
+            cpus.extend(start.parse::<u32>().ok()?..=end.parse::<u32>().ok()?);
+        } else {
+            cpus.push(range.parse().ok()?);
+        }
+    }
+    Some(cpus)
+}
+
 #[allow(dead_code)]
 /// Reads a temperature from a given hwmon fd, converting from millidegrees Celsius to degrees Celsius.
 pub fn read_hwmon_temp(fd: BorrowedFd) -> Option<f32> {
@@ -243,3 +255,40 @@ pub fn find_pci_driver_hwmon(driver_name: &str) -> Option<OwnedFd> {
     }
     None
 }
+
+/// Opens every /sys/class/hwmon entry whose `name` matches the given driver name, ordered by hwmon index.
+pub fn find_class_hwmon(driver_name: &str) -> Vec<OwnedFd> {
+    let Ok(class) = rustix::fs::open(
+        "/sys/class/hwmon",
+        OFlags::RDONLY | OFlags::CLOEXEC | OFlags::DIRECTORY,
+        Mode::empty(),
+    ) else {
+        return Vec::new();
+    };
+    let Ok(entries) = rustix::fs::Dir::read_from(&class) else {
+        return Vec::new();
+    };
+    let mut found = Vec::new();
+    for entry in entries.flatten() {
+        let entry_name = entry.file_name().to_string_lossy().to_string();
+        let Some(index) = entry_name
+            .strip_prefix("hwmon")
+            .and_then(|i| i.parse::<u32>().ok())
+        else {
+            continue;
+        };
+        let Ok(hwmon) = rustix::fs::openat(
+            &class,
+            entry_name,
+            OFlags::RDONLY | OFlags::DIRECTORY | OFlags::CLOEXEC,
+            Mode::empty(),
+        ) else {
+            continue;
+        };
+        if readat_string(hwmon.as_fd(), "name").is_some_and(|name| name == driver_name) {
+            found.push((index, hwmon));
+        }
+    }
+    found.sort_by_key(|(index, _)| *index);
+    found.into_iter().map(|(_, hwmon)| hwmon).collect()
+}
@@ -0,0 +1,95 @@
+/*
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/.
+ */
+//! Fixtures shared by the collector tests.
+
+use std::os::fd::OwnedFd;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use rustix::fs::{Mode, OFlags};
+
+/// A scratch directory standing in for a part of sysfs or procfs, removed when dropped so that
+/// a failing test doesn't leave it behind.
+pub struct FakeSysfs {
+    root: PathBuf,
+}
+
+impl FakeSysfs {
+    /// Creates an empty directory unique to this test run.
+    pub fn new(name: &str) -> Self {
+        static NEXT: AtomicUsize = AtomicUsize::new(0);
+        let root = std::env::temp_dir().join(format!(
+            "monitord-{name}-{}-{}",
+            std::process::id(),
+            NEXT.fetch_add(1, Ordering::Relaxed)
+        ));
+        // Left over from a run that was killed before cleaning up
+        let _ = std::fs::remove_dir_all(&root);
+        std::fs::create_dir_all(&root).expect("create fixture root");
+        Self { root }
+    }
+
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    /// The absolute path of a file or directory in the fixture.
+    pub fn path(&self, path: impl AsRef<Path>) -> PathBuf {
+        self.root.join(path)
+    }
+
+    /// Creates a directory and its parents, returning its absolute path.
+    pub fn dir(&self, path: impl AsRef<Path>) -> PathBuf {
+        let dir = self.path(path);
+        std::fs::create_dir_all(&dir).expect("create fixture directory");
+        dir
+    }
+
+    /// Writes a file, creating its parent directories.
+    pub fn write(&self, path: impl AsRef<Path>, contents: impl AsRef<[u8]>) {
+        let path = self.path(path);
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent).expect("create fixture directory");
+        }
+        std::fs::write(path, contents).expect("write fixture file");
+    }
+
+    /// Creates a symlink at `link`, relative to the fixture root, pointing to `target` as given
+    /// (relative targets resolve against the directory of the link, like sysfs links).
+    pub fn symlink(&self, target: impl AsRef<Path>, link: impl AsRef<Path>) {
+        let link = self.path(link);
+        if let Some(parent) = link.parent() {
+            std::fs::create_dir_all(parent).expect("create fixture directory");
+        }
+        std::os::unix::fs::symlink(target, link).expect("create fixture symlink");
+    }
+
+    /// Removes a file or directory from the fixture.
+    pub fn remove(&self, path: impl AsRef<Path>) {
+        let path = self.path(path);
+        if path.is_dir() {
+            std::fs::remove_dir_all(path).expect("remove fixture directory");
+        } else {
+            std::fs::remove_file(path).expect("remove fixture file");
+        }
+    }
+
+    /// Opens a directory of the fixture the way the collectors open sysfs directories.
+    pub fn open_dir(&self, path: impl AsRef<Path>) -> OwnedFd {
+        rustix::fs::open(
+            self.path(path).as_path(),
+            OFlags::RDONLY | OFlags::CLOEXEC | OFlags::DIRECTORY,
+            Mode::empty(),
+        )
+        .expect("open fixture directory")
+    }
+}
+
+impl Drop for FakeSysfs {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(&self.root);
+    }
+}
@@ -0,0 +1,200 @@
+/*
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/.
+ */
+//! ECC error counters from the EDAC subsystem in `/sys/devices/system/edac/mc`.
+
+use std::collections::BTreeMap;
+use std::path::Path;
+
+use crate::collector::helpers::*;
+use crate::metrics::memory::{Ecc, EccDimm, MemoryController};
+
+pub const EDAC_ROOT: &str = "/sys/devices/system/edac/mc";
+
+/// Tracks the error counts of every memory controller to report the errors since the last sample.
+#[derive(Default)]
+pub struct Tracker {
+    sampler: Sampler<Counts>,
+}
+
+impl Tracker {
+    /// Reads the error counters under `root`, returning `None` when no memory controller is registered.
+    pub fn sample(&mut self, root: &Path) -> Option<Ecc> {
+        let mut controllers: Vec<MemoryController> = std::fs::read_dir(root)
+            .ok()?
+            .flatten()
+            .filter(|entry| entry.file_name().to_string_lossy().starts_with("mc"))
+            .filter_map(|entry| read_controller(&entry.path()))
+            .collect();
+        if controllers.is_empty() {
+            return None;
+        }
+        controllers.sort_by(|a, b| a.name.cmp(&b.name));
+
+        let counts = Counts(
+            controllers
+                .iter()
+                .map(|mc| (mc.name.clone(), (mc.corrected, mc.uncorrected)))
+                .collect(),
+        );
+        let delta = self.sampler.push(counts).map(|delta| delta.change);
+        if let Some(delta) = &delta {
+            for mc in &mut controllers {
+                // Controllers that appeared since the last sample have nothing to diff against
+                if let Some(&(corrected, uncorrected)) = delta.get(&mc.name) {
+                    mc.corrected_since_last_sample = Some(corrected);
+                    mc.uncorrected_since_last_sample = Some(uncorrected);
+                }
+            }
+        }
+
+        let sum = |f: fn(&MemoryController) -> u64| controllers.iter().map(f).sum();
+        let sum_delta = |f: fn(&(u64, u64)) -> u64| delta.as_ref().map(|d| d.values().map(f).sum());
+        let ecc = Ecc {
+            corrected: sum(|mc| mc.corrected),
+            uncorrected: sum(|mc| mc.uncorrected),
+            corrected_since_last_sample: sum_delta(|&(corrected, _)| corrected),
+            uncorrected_since_last_sample: sum_delta(|&(_, uncorrected)| uncorrected),
+            controllers,
+        };
+        if let Some(errors) = ecc.uncorrected_since_last_sample.filter(|&e| e > 0) {
+            tracing::error!("{errors} uncorrected memory errors since the last sample");
+        } else if let Some(errors) = ecc.corrected_since_last_sample.filter(|&e| e > 0) {
+            tracing::warn!("{errors} corrected memory errors since the last sample");
+        }
+        Some(ecc)
+    }
+}
+
+/// Corrected and uncorrected error counts keyed by controller name.
+struct Counts(BTreeMap<String, (u64, u64)>);
+
+impl sampler::Differential for Counts {
+    type Delta = BTreeMap<String, (u64, u64)>;
+
+    fn delta(&self, other: &Self) -> Self::Delta {
+        self.0
+            .iter()
+            .filter_map(|(name, &(corrected, uncorrected))| {
+                let &(last_corrected, last_uncorrected) = other.0.get(name)?;
+                Some((
+                    name.clone(),
+                    (
+                        corrected.saturating_sub(last_corrected),
+                        uncorrected.saturating_sub(last_uncorrected),
+                    ),
+                ))
+            })
+            .collect()
+    }
+}
+
+fn read_controller(path: &Path) -> Option<MemoryController> {
+    Some(MemoryController {
+        name: path.file_name()?.to_string_lossy().into_owned(),
+        driver: sysfs::read_string_path(path.join("mc_name")).unwrap_or_default(),
+        corrected: sysfs::read_u64_path(path.join("ce_count"))?,
+        uncorrected: sysfs::read_u64_path(path.join("ue_count"))?,
+        corrected_since_last_sample: None,
+        uncorrected_since_last_sample: None,
+        dimms: read_dimms(path),
+    })
+}
+
+/// Reads the per-DIMM counters, falling back to the chip-select rows of drivers without DIMM entries.
+fn read_dimms(path: &Path) -> Vec<EccDimm> {
+    let dimms = read_entries(path, "dimm", |entry| {
+        Some(EccDimm {
+            label: sysfs::read_string_path(entry.join("dimm_label")).unwrap_or_default(),
+            corrected: sysfs::read_u64_path(entry.join("dimm_ce_count"))?,
+            uncorrected: sysfs::read_u64_path(entry.join("dimm_ue_count"))?,
+        })
+    });
+    if !dimms.is_empty() {
+        return dimms;
+    }
+    read_entries(path, "csrow", |entry| {
+        Some(EccDimm {
+            label: sysfs::read_string_path(entry.join("ch0_dimm_label")).unwrap_or_default(),
+            corrected: sysfs::read_u64_path(entry.join("ce_count"))?,
+            uncorrected: sysfs::read_u64_path(entry.join("ue_count"))?,
+        })
+    })
+}
+
+/// Reads every `<prefix>N` subdirectory in index order, labelling unlabelled entries by their name.
+fn read_entries<F>(path: &Path, prefix: &str, read: F) -> Vec<EccDimm>
+where
+    F: Fn(&Path) -> Option<EccDimm>,
+{
+    let Ok(entries) = std::fs::read_dir(path) else {
+        return Vec::new();
+    };
+    let mut dimms: Vec<(u32, EccDimm)> = entries
+        .flatten()
+        .filter_map(|entry| {
+            let name = entry.file_name().to_string_lossy().into_owned();
+            let index = name.strip_prefix(prefix)?.parse().ok()?;
+            let mut dimm = read(&entry.path())?;
+            if dimm.label.is_empty() {
+                dimm.label = name;
+            }
+            Some((index, dimm))
+        })
+        .collect();
+    dimms.sort_by_key(|(index, _)| *index);
+    dimms.into_iter().map(|(_, dimm)| dimm).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::collector::helpers::test::FakeSysfs;
+
+    #[test]
+    fn edac_counters() {
+        let root = FakeSysfs::new("edac");
+        let write = |path: &str, contents: &str| root.write(path, format!("{contents}\n"));
+        write("mc0/mc_name", "Skylake Socket#0 IMC#0");
+        write("mc0/ce_count", "3");
+        write("mc0/ue_count", "0");
+        write("mc0/dimm0/dimm_label", "CPU_SrcID#0_MC#0_Chan#0_DIMM#0");
+        write("mc0/dimm0/dimm_ce_count", "3");
+        write("mc0/dimm0/dimm_ue_count", "0");
+        write("mc0/dimm1/dimm_label", "");
+        write("mc0/dimm1/dimm_ce_count", "0");
+        write("mc0/dimm1/dimm_ue_count", "0");
+        write("mc1/mc_name", "amd64_edac");
+        write("mc1/ce_count", "1");
+        write("mc1/ue_count", "0");
+        write("mc1/csrow0/ce_count", "1");
+        write("mc1/csrow0/ue_count", "0");
+
+        let mut tracker = Tracker::default();
+        let first = tracker.sample(root.root());
+        write("mc0/ce_count", "5");
+        write("mc1/ue_count", "1");
+        let second = tracker.sample(root.root());
+
+        let first = first.expect("controllers should be found");
+        assert_eq!((first.corrected, first.uncorrected), (4, 0));
+        assert_eq!(first.corrected_since_last_sample, None);
+        assert_eq!(first.controllers.len(), 2);
+        let dimms = &first.controllers[0].dimms;
+        assert_eq!(dimms[0].label, "CPU_SrcID#0_MC#0_Chan#0_DIMM#0");
+        assert_eq!(dimms[0].corrected, 3);
+        assert_eq!(dimms[1].label, "dimm1");
+        assert_eq!(first.controllers[1].dimms[0].label, "csrow0");
+
+        let second = second.expect("controllers should be found");
+        assert_eq!(second.corrected_since_last_sample, Some(2));
+        assert_eq!(second.uncorrected_since_last_sample, Some(1));
+        assert_eq!(second.controllers[0].corrected_since_last_sample, Some(2));
+        assert_eq!(second.controllers[1].uncorrected_since_last_sample, Some(1));
+
+        let mut tracker = Tracker::default();
+        assert_eq!(tracker.sample(Path::new("/nonexistent/edac/mc")), None);
+    }
+}
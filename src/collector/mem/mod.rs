@@ -11,7 +11,14 @@
 //! ```no_run
 //!
 //! ```
-use std::{collections::BTreeMap, path::PathBuf};
+mod edac;
+mod smbios;
+
+use std::{
+    collections::BTreeMap,
+    path::PathBuf,
+    time::{Duration, Instant},
+};
 
 use super::helpers::*;
 use anyhow::Context;
@@ -23,6 +30,31 @@ pub use crate::metrics::memory::*;
 /// The metric collector, create an instance with `mem::Collector::new()` and collect with `collector.collect(&store)`
 pub struct Collector {
     cached_dimms: Discovery<Vec<Dimm>>,
+    stall_some: Sampler<Counter>,
+    stall_full: Sampler<Counter>,
+    oom_kills: Sampler<Counter>,
+    ecc: edac::Tracker,
+    overcommit: Option<Overcommit>,
+}
+
+/// The overcommit sysctls, which rarely change so are only re-read every `OVERCOMMIT_REFRESH`.
+struct Overcommit {
+    memory: u32,
+    ratio: u32,
+    read_at: Instant,
+}
+
+const OVERCOMMIT_REFRESH: Duration = Duration::from_secs(60);
+
+/// A cumulative kernel counter, e.g. memory stall time or OOM kills.
+struct Counter(u64);
+
+impl sampler::Differential for Counter {
+    type Delta = u64;
+
+    fn delta(&self, other: &Self) -> Self::Delta {
+        self.0.saturating_sub(other.0)
+    }
 }
 
 impl Default for Collector {
@@ -50,6 +82,11 @@ impl Collector {
         tracing::info!("creating collector");
         Self {
             cached_dimms: Discovery::default(),
+            stall_some: Sampler::new(),
+            stall_full: Sampler::new(),
+            oom_kills: Sampler::new(),
+            ecc: edac::Tracker::default(),
+            overcommit: None,
         }
     }
 
@@ -64,40 +101,334 @@ impl Collector {
             procfs::Meminfo::current().with_context(|| format!("{} on {}", file!(), line!()))?;
         tracing::trace!("read /proc/meminfo");
 
-        let capacity = meminfo.mem_total;
-        let in_use = meminfo.mem_total - meminfo.mem_free;
-        let free = meminfo.mem_free;
-        let cached = meminfo.cached;
-        let available = meminfo.mem_available.unwrap_or(0);
-        let swap_capacity = meminfo.swap_total;
-        let swap_in_use = meminfo.swap_total - meminfo.swap_free;
-        let logical = Some(Logical {
-            capacity,
-            in_use,
-            free,
-            cached,
-            available,
-            swap_capacity,
-            swap_in_use,
-        });
+        let logical = Some(logical_from_meminfo(&meminfo));
+
+        let commit = self.commit(&meminfo);
+        let huge_pages = read_huge_pages(&meminfo);
+        let compressed = read_compressed();
+        let ksm = read_ksm();
+        let pressure = pressure::read("memory").unwrap_or_default();
+        let pressure_some = pressure
+            .some
+            .map(|record| stall_pressure(record, &mut self.stall_some));
+        let pressure_full = pressure
+            .full
+            .map(|record| stall_pressure(record, &mut self.stall_full));
+
+        // The oom_kill counter was added in 4.13
+        let oom_kills_total = procfs::vmstat()
+            .ok()
+            .and_then(|vmstat| vmstat.get("oom_kill").copied())
+            .map(|kills| kills.max(0) as u64);
+        let oom_kills_since_last_sample = oom_kills_total
+            .and_then(|kills| self.oom_kills.push(Counter(kills)))
+            .map(|delta| delta.change);
+        if let Some(kills) = oom_kills_since_last_sample.filter(|&kills| kills > 0) {
+            tracing::warn!("the kernel OOM killer fired {kills} times since the last sample");
+        }
+
+        let swap_devices = std::fs::read_to_string("/proc/swaps")
+            .map(|swaps| parse_swaps(&swaps))
+            .unwrap_or_default();
+
+        let ecc = self.ecc.sample(std::path::Path::new(edac::EDAC_ROOT));
 
         let dimms = config
             .dimms
-            .then(|| self.cached_dimms.probe(collect_dimms).cloned())
+            .then(|| {
+                self.cached_dimms
+                    .probe(|| collect_dimms(config.dmi_table_fallback))
+                    .cloned()
+            })
             .flatten()
             .unwrap_or_default();
 
-        Ok(Snapshot { logical, dimms })
+        Ok(Snapshot {
+            logical,
+            dimms,
+            huge_pages,
+            compressed,
+            pressure_some,
+            pressure_full,
+            oom_kills_total,
+            oom_kills_since_last_sample,
+            swap_devices,
+            ecc,
+            commit,
+            ksm,
+        })
+    }
+
+    /// Builds the commit charge, re-reading the overcommit sysctls once they are stale.
+    fn commit(&mut self, meminfo: &procfs::Meminfo) -> Option<Commit> {
+        let limit = meminfo.commit_limit?;
+        if self
+            .overcommit
+            .as_ref()
+            .is_none_or(|overcommit| overcommit.read_at.elapsed() >= OVERCOMMIT_REFRESH)
+        {
+            self.overcommit = Some(Overcommit {
+                memory: sysfs::read_u32_path("/proc/sys/vm/overcommit_memory").unwrap_or(0),
+                ratio: sysfs::read_u32_path("/proc/sys/vm/overcommit_ratio").unwrap_or(0),
+                read_at: Instant::now(),
+            });
+        }
+        let overcommit = self.overcommit.as_ref()?;
+        Some(Commit {
+            limit,
+            committed: meminfo.committed_as,
+            load_percent: load_percent(meminfo.committed_as, limit),
+            overcommit_memory: overcommit.memory,
+            overcommit_ratio: overcommit.ratio,
+        })
+    }
+}
+
+/// Builds the runtime memory info from a parsed /proc/meminfo.
+fn logical_from_meminfo(meminfo: &procfs::Meminfo) -> Logical {
+    let capacity = meminfo.mem_total;
+    let available = meminfo.mem_available.unwrap_or(0);
+    let swap_capacity = meminfo.swap_total;
+    let swap_in_use = meminfo.swap_total - meminfo.swap_free;
+    Logical {
+        capacity,
+        in_use: meminfo.mem_total - meminfo.mem_free,
+        free: meminfo.mem_free,
+        cached: meminfo.cached,
+        available,
+        swap_capacity,
+        swap_in_use,
+        // Reclaimable cache is available, so RAM load is based on available rather than free memory
+        load_percent: load_percent(capacity.saturating_sub(available), capacity),
+        swap_load_percent: load_percent(swap_in_use, swap_capacity),
+        dirty: Some(meminfo.dirty),
+        writeback: Some(meminfo.writeback),
+        mapped: Some(meminfo.mapped),
+        slab: Some(meminfo.slab),
+        slab_reclaimable: meminfo.s_reclaimable,
+        slab_unreclaimable: meminfo.s_unreclaim,
+        kernel_stack: meminfo.kernel_stack,
+        page_tables: meminfo.page_tables,
+    }
+}
+
+/// Returns `used` as a percentage (0.0 to 100.0) of `total`, or 0 when there is no capacity.
+fn load_percent(used: u64, total: u64) -> f32 {
+    if total == 0 {
+        0.0
+    } else {
+        (used as f64 / total as f64 * 100.0) as f32
+    }
+}
+
+/// Converts a pressure record, turning its stall total into a rate against the previous sample.
+fn stall_pressure(record: pressure::Record, sampler: &mut Sampler<Counter>) -> Pressure {
+    let stall_us_per_sec = sampler.push(Counter(record.total_us)).and_then(|delta| {
+        let secs = delta.interval.as_secs_f64();
+        (secs > 0.0).then(|| (delta.change as f64 / secs) as f32)
+    });
+    Pressure {
+        avg10: record.avg10,
+        avg60: record.avg60,
+        avg300: record.avg300,
+        total_us: record.total_us,
+        stall_us_per_sec,
+    }
+}
+
+/// Reads the huge page state from the already parsed meminfo and the per-size pools in sysfs.
+fn read_huge_pages(meminfo: &procfs::Meminfo) -> Option<HugePages> {
+    let total = meminfo.hugepages_total?;
+    let transparent_mode = sysfs::read_string_path("/sys/kernel/mm/transparent_hugepage/enabled")
+        .and_then(|modes| parse_selected_mode(&modes))
+        .unwrap_or_default();
+    Some(HugePages {
+        total,
+        free: meminfo.hugepages_free.unwrap_or(0),
+        reserved: meminfo.hugepages_rsvd.unwrap_or(0),
+        surplus: meminfo.hugepages_surp.unwrap_or(0),
+        page_size_kb: meminfo.hugepagesize.unwrap_or(0) / 1024,
+        transparent_mode,
+        transparent_in_use: meminfo.anon_hugepages.unwrap_or(0),
+        pools: read_huge_page_pools(),
+    })
+}
+
+fn read_huge_page_pools() -> Vec<HugePagePool> {
+    let Ok(entries) = std::fs::read_dir("/sys/kernel/mm/hugepages") else {
+        return Vec::new();
+    };
+    let mut pools = entries
+        .flatten()
+        .filter_map(|entry| {
+            let name = entry.file_name();
+            let page_size_kb = name
+                .to_str()?
+                .strip_prefix("hugepages-")?
+                .strip_suffix("kB")?
+                .parse()
+                .ok()?;
+            let path = entry.path();
+            let read = |file: &str| sysfs::read_u64_path(path.join(file)).unwrap_or(0);
+            Some(HugePagePool {
+                page_size_kb,
+                total: read("nr_hugepages"),
+                free: read("free_hugepages"),
+                reserved: read("resv_hugepages"),
+                surplus: read("surplus_hugepages"),
+            })
+        })
+        .collect::<Vec<_>>();
+    pools.sort_by_key(|pool| pool.page_size_kb);
+    pools
+}
+
+/// Parses the contents of /proc/swaps, skipping the header and any malformed lines.
+fn parse_swaps(swaps: &str) -> Vec<SwapDevice> {
+    swaps
+        .lines()
+        .skip(1)
+        .filter_map(|line| {
+            let mut fields = line.split_whitespace();
+            let name = fields.next()?;
+            let kind = fields.next()?;
+            let size_kb: u64 = fields.next()?.parse().ok()?;
+            let used_kb: u64 = fields.next()?.parse().ok()?;
+            let priority = fields.next()?.parse().ok()?;
+            Some(SwapDevice {
+                // The kernel escapes whitespace in paths as octal
+                name: name.replace("\\040", " ").replace("\\011", "\t"),
+                r#type: kind.to_string(),
+                size_bytes: size_kb * 1024,
+                used_bytes: used_kb * 1024,
+                priority,
+            })
+        })
+        .collect()
+}
+
+/// Reads the zram devices and zswap cache, returning `None` when neither is in use.
+fn read_compressed() -> Option<CompressedMemory> {
+    let zram = read_zram_devices();
+    let zswap = read_zswap();
+    if zram.is_empty() && zswap.is_none() {
+        return None;
+    }
+
+    let (mut orig, mut compr) = zram.iter().fold((0, 0), |(orig, compr), dev| {
+        (orig + dev.orig_data_size, compr + dev.compr_data_size)
+    });
+    if let Some(Zswap {
+        pool_total_size: Some(pool),
+        stored_bytes: Some(stored),
+        ..
+    }) = &zswap
+    {
+        orig += stored;
+        compr += pool;
+    }
+    Some(CompressedMemory {
+        zram,
+        zswap,
+        compression_ratio: compression_ratio(orig, compr),
+    })
+}
+
+fn read_zram_devices() -> Vec<ZramDevice> {
+    let Ok(entries) = std::fs::read_dir("/sys/block") else {
+        return Vec::new();
+    };
+    let mut devices = entries
+        .flatten()
+        .filter_map(|entry| {
+            let name = entry.file_name().to_str()?.to_string();
+            if !name.starts_with("zram") {
+                return None;
+            }
+            let mm_stat = sysfs::read_string_path(entry.path().join("mm_stat"))?;
+            let (orig_data_size, compr_data_size, mem_used_total) = parse_mm_stat(&mm_stat)?;
+            Some(ZramDevice {
+                name,
+                orig_data_size,
+                compr_data_size,
+                mem_used_total,
+                compression_ratio: compression_ratio(orig_data_size, compr_data_size),
+            })
+        })
+        .collect::<Vec<_>>();
+    devices.sort_by(|a, b| a.name.cmp(&b.name));
+    devices
+}
+
+/// Parses the orig_data_size, compr_data_size and mem_used_total columns of a zram `mm_stat` file.
+fn parse_mm_stat(mm_stat: &str) -> Option<(u64, u64, u64)> {
+    let mut columns = mm_stat.split_whitespace().map(|c| c.parse::<u64>().ok());
+    Some((columns.next()??, columns.next()??, columns.next()??))
+}
+
+fn read_zswap() -> Option<Zswap> {
+    let parameters = std::path::Path::new("/sys/module/zswap/parameters");
+    let enabled = sysfs::read_string_path(parameters.join("enabled"))?;
+    if enabled != "Y" {
+        return None;
     }
+    // The pool statistics live in debugfs, which is usually only readable by root
+    let debug = std::path::Path::new("/sys/kernel/debug/zswap");
+    let page_size = procfs::page_size();
+    Some(Zswap {
+        compressor: sysfs::read_string_path(parameters.join("compressor")).unwrap_or_default(),
+        max_pool_percent: sysfs::read_u32_path(parameters.join("max_pool_percent")).unwrap_or(0),
+        pool_total_size: sysfs::read_u64_path(debug.join("pool_total_size")),
+        stored_bytes: sysfs::read_u64_path(debug.join("stored_pages"))
+            .map(|pages| pages * page_size),
+    })
 }
 
-fn collect_dimms() -> anyhow::Result<Vec<Dimm>> {
-    match collect_from_dmi() {
+/// Reads the KSM counters, returning `None` when the kernel was built without KSM.
+fn read_ksm() -> Option<Ksm> {
+    let ksm = std::path::Path::new("/sys/kernel/mm/ksm");
+    // 0 stops ksmd, 1 runs it and 2 stops it and unmerges all merged pages
+    let run = sysfs::read_u32_path(ksm.join("run"))?;
+    let read = |name: &str| sysfs::read_u64_path(ksm.join(name)).unwrap_or(0);
+    let pages_sharing = read("pages_sharing");
+    Some(Ksm {
+        enabled: run == 1,
+        pages_shared: read("pages_shared"),
+        pages_sharing,
+        pages_unshared: read("pages_unshared"),
+        full_scans: read("full_scans"),
+        memory_saved_bytes: pages_sharing * procfs::page_size(),
+    })
+}
+
+fn compression_ratio(orig: u64, compr: u64) -> f32 {
+    if compr == 0 {
+        0.0
+    } else {
+        orig as f32 / compr as f32
+    }
+}
+
+/// Returns the bracketed entry of a sysfs mode list such as "always [madvise] never".
+fn parse_selected_mode(modes: &str) -> Option<String> {
+    let (_, rest) = modes.split_once('[')?;
+    let (selected, _) = rest.split_once(']')?;
+    Some(selected.to_string())
+}
+
+fn collect_dimms(dmi_table_fallback: bool) -> anyhow::Result<Vec<Dimm>> {
+    match collect_from_dmi_entries() {
         Ok(dimms) => return Ok(dimms),
         Err(e) => tracing::warn!(
-            "dmi reading failed, falling back to udev (this is okay for testing, just means the program doesn't have root access): {e}"
+            "dmi entry reading failed, falling back (this is okay for testing, just means the program doesn't have root access): {e}"
         ),
     }
+    if dmi_table_fallback {
+        match collect_from_dmi() {
+            Ok(dimms) => return Ok(dimms),
+            Err(e) => tracing::warn!("dmi table parsing failed, falling back to udev: {e}"),
+        }
+    }
     match collect_from_udev_database() {
         Ok(dimms) => return Ok(dimms),
         Err(e) => tracing::warn!(
@@ -107,11 +438,52 @@ fn collect_dimms() -> anyhow::Result<Vec<Dimm>> {
     Ok(Vec::new())
 }
 
+fn collect_from_dmi_entries() -> anyhow::Result<Vec<Dimm>> {
+    tracing::debug!("attempting to read SMBIOS memory device entries");
+    let mut entries = std::fs::read_dir("/sys/firmware/dmi/entries")?
+        .flatten()
+        .filter(|entry| entry.file_name().to_string_lossy().starts_with("17-"))
+        .map(|entry| entry.path())
+        .collect::<Vec<_>>();
+    entries.sort();
+
+    let mut dimms = Vec::new();
+    for entry in entries {
+        let raw = std::fs::read(entry.join("raw"))?;
+        let Some(device) = smbios::MemoryDevice::parse(&raw) else {
+            tracing::warn!("malformed memory device entry at {}", entry.display());
+            continue;
+        };
+        dimms.extend(entry_dimm(device));
+    }
+    Ok(dimms)
+}
+
+/// Converts a parsed memory device entry, `None` for an empty slot.
+fn entry_dimm(device: smbios::MemoryDevice) -> Option<Dimm> {
+    // Empty slots have no size
+    let capacity = device.size?;
+    Some(Dimm {
+        locator: device.locator,
+        capacity,
+        speed_mts: device.speed_mts,
+        form_factor: formfactor_to_string(device.form_factor.into()),
+        ram_type: ramtype_to_string(device.memory_type.into()),
+        manufacturer: device.manufacturer,
+        part_number: device.part_number,
+    })
+}
+
 fn collect_from_dmi() -> anyhow::Result<Vec<Dimm>> {
     tracing::debug!("attempting to parse DMI tables");
     // read in bytes from /sys/firmware/dmi/tables/DMI
     let bytes = std::fs::read(PathBuf::from("/sys/firmware/dmi/tables/DMI"))?;
-    let entrypoint = dmidecode::EntryPoint::search(bytes.as_slice())?;
+    table_dimms(&bytes)
+}
+
+/// Reads the memory devices of a buffer holding an SMBIOS entry point and the table it points to.
+fn table_dimms(bytes: &[u8]) -> anyhow::Result<Vec<Dimm>> {
+    let entrypoint = dmidecode::EntryPoint::search(bytes)?;
 
     let memory_devices = entrypoint
         .structures(&bytes[entrypoint.smbios_address() as usize..])
@@ -122,20 +494,22 @@ fn collect_from_dmi() -> anyhow::Result<Vec<Dimm>> {
         let memory_device = memory_device?;
         match memory_device {
             dmidecode::Structure::MemoryDevice(memory_device) => {
-                if memory_device.size.is_some_and(|size| size != 0) {
+                // Empty slots have no size, and dmidecode already maps an unknown size to None
+                if let Some(capacity) = memory_device
+                    .size
+                    .and_then(|size| smbios::size_bytes(size, Some(memory_device.extended_size)))
+                {
                     dimms.push(Dimm {
                         locator: memory_device.device_locator.to_string(),
-                        capacity: memory_device
-                            .size
-                            .filter(|&size| size != 0x7FFF)
-                            .map(|size| size as u64)
-                            .unwrap_or(memory_device.extended_size as u64),
+                        capacity,
                         speed_mts: memory_device
                             .configured_memory_speed
                             .map(|speed| speed as u64)
                             .unwrap_or(memory_device.speed.unwrap_or(0) as u64),
                         form_factor: formfactor_to_string(memory_device.form_factor),
                         ram_type: ramtype_to_string(memory_device.memory_type),
+                        manufacturer: memory_device.manufacturer.to_string(),
+                        part_number: memory_device.part_number.to_string(),
                     });
                 }
             }
@@ -254,6 +628,8 @@ fn collect_from_udev_database() -> anyhow::Result<Vec<Dimm>> {
             "SPEED_MTS" => dimm.speed_mts = value.parse::<u64>().unwrap_or(0),
             "FORM_FACTOR" => dimm.form_factor = value.to_string(),
             "TYPE" => dimm.ram_type = value.to_string(),
+            "MANUFACTURER" => dimm.manufacturer = value.to_string(),
+            "PART_NUMBER" => dimm.part_number = value.to_string(),
             _ => {}
         }
     }
@@ -266,15 +642,193 @@ mod tests {
     use super::*;
     use crate::collector::Collector;
 
+    #[test]
+    fn dmi_paths() -> anyhow::Result<()> {
+        // A populated DDR4 SO-DIMM from an SMBIOS 3.2 laptop, with the size fields replaced
+        let device = |size: u16, extended_size: u32| {
+            let mut raw = vec![
+                0x11, 0x28, 0x40, 0x00, 0x3e, 0x00, 0xfe, 0xff, 0x40, 0x00, 0x40, 0x00, 0x00, 0x40,
+                0x0d, 0x00, 0x01, 0x02, 0x1a, 0x80, 0x00, 0x80, 0x0c, 0x03, 0x04, 0x05, 0x06, 0x01,
+                0x00, 0x00, 0x00, 0x00, 0xb8, 0x0b, 0xb0, 0x04, 0xb0, 0x04, 0xb0, 0x04,
+            ];
+            raw[0x0c..0x0e].copy_from_slice(&size.to_le_bytes());
+            raw[0x1c..0x20].copy_from_slice(&extended_size.to_le_bytes());
+            raw.extend_from_slice(
+                b"ChannelA-DIMM0\0BANK 0\0Samsung\x0012345678\0None\0M471A2K43DB1-CWE\0\0",
+            );
+            raw
+        };
+        // The same structure behind an SMBIOS 3 entry point, followed by the end of table
+        let table = |raw: &[u8]| {
+            let mut table = raw.to_vec();
+            table.extend_from_slice(&[0x7f, 0x04, 0xff, 0xfe, 0x00, 0x00]);
+            let mut buffer = vec![0u8; 0x18];
+            buffer[..5].copy_from_slice(b"_SM3_");
+            buffer[6] = 0x18;
+            buffer[7] = 3;
+            buffer[8] = 2;
+            buffer[10] = 1;
+            buffer[0x0c..0x10].copy_from_slice(&(table.len() as u32).to_le_bytes());
+            buffer[0x10..0x18].copy_from_slice(&0x18u64.to_le_bytes());
+            buffer[5] = buffer.iter().fold(0u8, |sum, b| sum.wrapping_sub(*b));
+            buffer.extend_from_slice(&table);
+            buffer
+        };
+
+        for (size, extended_size, capacity) in [
+            (0x4000, 0, 16u64 << 30),
+            // Sizes below 32 MiB are in KiB
+            (0x8000 | 512, 0, 512 << 10),
+            (0x7fff, 64 << 10, 64 << 30),
+        ] {
+            let raw = device(size, extended_size);
+            let entry = smbios::MemoryDevice::parse(&raw).and_then(entry_dimm);
+            assert_eq!(entry.as_ref().map(|dimm| dimm.capacity), Some(capacity));
+            assert_eq!(table_dimms(&table(&raw))?, Vec::from_iter(entry));
+        }
+        // An empty slot
+        let raw = device(0, 0);
+        assert_eq!(smbios::MemoryDevice::parse(&raw).and_then(entry_dimm), None);
+        assert!(table_dimms(&table(&raw))?.is_empty());
+        Ok(())
+    }
+
     #[tracing_test::traced_test]
     #[test]
     fn memory() -> anyhow::Result<()> {
         let mut collector = super::Collector::new();
         let mut config = crate::metrics::Config::default();
-        config.memory = Some(Config { dimms: true });
+        config.memory = Some(Config {
+            dimms: true,
+            dmi_table_fallback: true,
+        });
         let snapshot = collector.collect(&config)?;
         assert!(snapshot.logical.is_some() && !snapshot.dimms.is_empty());
         println!("{:#?}", snapshot);
         Ok(())
     }
+
+    #[test]
+    fn meminfo_breakdown() -> anyhow::Result<()> {
+        use procfs::FromBufRead;
+        let meminfo = procfs::Meminfo::from_buf_read(MEMINFO.as_bytes())?;
+        let logical = logical_from_meminfo(&meminfo);
+        assert_eq!(logical.capacity, 16_316_828 * 1024);
+        assert_eq!(logical.dirty, Some(1_204 * 1024));
+        assert_eq!(logical.writeback, Some(0));
+        assert_eq!(logical.mapped, Some(1_054_392 * 1024));
+        assert_eq!(logical.slab, Some(689_484 * 1024));
+        assert_eq!(logical.slab_reclaimable, Some(453_520 * 1024));
+        assert_eq!(logical.slab_unreclaimable, Some(235_964 * 1024));
+        assert_eq!(logical.kernel_stack, Some(21_360 * 1024));
+        assert_eq!(logical.page_tables, Some(52_116 * 1024));
+
+        let commit = super::Collector::new()
+            .commit(&meminfo)
+            .expect("CommitLimit should be present");
+        assert_eq!(commit.limit, 16_546_256 * 1024);
+        assert_eq!(commit.committed, 14_069_460 * 1024);
+        assert!((commit.load_percent - 85.03).abs() < 0.01);
+        Ok(())
+    }
+
+    // Captured from a 16 GiB desktop running 6.8
+    const MEMINFO: &str = "MemTotal:       16316828 kB
+MemFree:         6203452 kB
+MemAvailable:   11052040 kB
+Buffers:          263020 kB
+Cached:          4946964 kB
+SwapCached:            0 kB
+Active:          5513908 kB
+Inactive:        3585240 kB
+Active(anon):    3936156 kB
+Inactive(anon):        0 kB
+Active(file):    1577752 kB
+Inactive(file):  3585240 kB
+Unevictable:      134772 kB
+Mlocked:              32 kB
+SwapTotal:       8388604 kB
+SwapFree:        8388604 kB
+Zswap:                 0 kB
+Zswapped:              0 kB
+Dirty:              1204 kB
+Writeback:             0 kB
+AnonPages:       4023568 kB
+Mapped:          1054392 kB
+Shmem:            181960 kB
+KReclaimable:     453520 kB
+Slab:             689484 kB
+SReclaimable:     453520 kB
+SUnreclaim:       235964 kB
+KernelStack:       21360 kB
+PageTables:        52116 kB
+SecPageTables:         0 kB
+NFS_Unstable:          0 kB
+Bounce:                0 kB
+WritebackTmp:          0 kB
+CommitLimit:    16546256 kB
+Committed_AS:   14069460 kB
+VmallocTotal:   34359738367 kB
+VmallocUsed:       85512 kB
+VmallocChunk:          0 kB
+Percpu:            10112 kB
+HardwareCorrupted:     0 kB
+AnonHugePages:         0 kB
+ShmemHugePages:        0 kB
+ShmemPmdMapped:        0 kB
+FileHugePages:         0 kB
+FilePmdMapped:         0 kB
+HugePages_Total:       0
+HugePages_Free:        0
+HugePages_Rsvd:        0
+HugePages_Surp:        0
+Hugepagesize:       2048 kB
+Hugetlb:               0 kB
+DirectMap4k:      413428 kB
+DirectMap2M:     8931328 kB
+DirectMap1G:     8388608 kB
+";
+
+    #[test]
+    fn load() {
+        const GIB: u64 = 1024 * 1024 * 1024;
+        assert_eq!(load_percent(4 * GIB, 16 * GIB), 25.0);
+        assert_eq!(load_percent(16 * GIB, 16 * GIB), 100.0);
+        // No swap configured
+        assert_eq!(load_percent(0, 0), 0.0);
+    }
+
+    #[test]
+    fn zram_mm_stat() {
+        // Captured from a 4 GiB zram swap device under load
+        let mm_stat = "1003466752 267521574 276078592        0 287834112   123418     3452    11200     9087\n";
+        assert_eq!(
+            parse_mm_stat(mm_stat),
+            Some((1003466752, 267521574, 276078592))
+        );
+        let ratio = compression_ratio(1003466752, 267521574);
+        assert!((ratio - 3.75).abs() < 0.01);
+        assert_eq!(parse_mm_stat("4096 1024"), None);
+    }
+
+    #[test]
+    fn swaps() {
+        let swaps = "\
+Filename\t\t\t\tType\t\tSize\t\tUsed\t\tPriority
+/dev/nvme0n1p3                          partition\t16777212\t524288\t\t-2
+/dev/zram0                              partition\t8388604\t\t2097152\t\t100
+/var/swap\\040file                       file\t\t1048572\t\t0\t\t-3
+";
+        let devices = parse_swaps(swaps);
+        assert_eq!(devices.len(), 3);
+        assert_eq!(devices[0].name, "/dev/nvme0n1p3");
+        assert_eq!(devices[0].size_bytes, 16777212 * 1024);
+        assert_eq!(devices[0].used_bytes, 524288 * 1024);
+        assert_eq!(devices[0].priority, -2);
+        assert_eq!(devices[1].priority, 100);
+        assert_eq!(devices[2].name, "/var/swap file");
+        assert_eq!(devices[2].r#type, "file");
+        // No swap configured
+        assert!(parse_swaps("Filename\t\t\t\tType\t\tSize\t\tUsed\t\tPriority\n").is_empty());
+    }
 }
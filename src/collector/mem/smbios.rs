@@ -0,0 +1,132 @@
+/*
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/.
+ */
+//! Minimal parser for SMBIOS type 17 (memory device) structures as exposed individually by
+//! the kernel in `/sys/firmware/dmi/entries/17-*/raw`.
+
+/// The fields of a type 17 structure needed to describe a DIMM slot.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MemoryDevice {
+    pub locator: String,
+    /// Size in bytes, `None` when the slot is empty or the size is unknown
+    pub size: Option<u64>,
+    pub form_factor: u8,
+    pub memory_type: u8,
+    /// Speed in MT/s, preferring the configured speed over the maximum capable speed
+    pub speed_mts: u64,
+    pub manufacturer: String,
+    pub part_number: String,
+}
+
+const SIZE_UNKNOWN: u16 = 0xFFFF;
+const SIZE_EXTENDED: u16 = 0x7FFF;
+const SIZE_IN_KB: u16 = 0x8000;
+
+impl MemoryDevice {
+    /// Parses a raw type 17 structure (formatted area followed by its string set).
+    pub fn parse(raw: &[u8]) -> Option<Self> {
+        let (&ty, &length) = (raw.first()?, raw.get(1)?);
+        if ty != 17 || raw.len() < length as usize || length < 0x15 {
+            return None;
+        }
+        let (formatted, strings) = raw.split_at(length as usize);
+        let byte = |offset: usize| formatted.get(offset).copied();
+        let word = |offset: usize| {
+            formatted
+                .get(offset..offset + 2)
+                .map(|b| u16::from_le_bytes([b[0], b[1]]))
+        };
+        let dword = |offset: usize| {
+            formatted
+                .get(offset..offset + 4)
+                .map(|b| u32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+        };
+        let string = |offset: usize| {
+            byte(offset)
+                .and_then(|index| string_at(strings, index))
+                .unwrap_or_default()
+        };
+
+        let size = size_bytes(word(0x0C)?, dword(0x1C));
+        // 0 means unknown and 0xFFFF means the speed is in the extended field
+        let speed = |offset: usize, extended: usize| match word(offset) {
+            Some(0xFFFF) => dword(extended).map(|speed| speed as u64),
+            Some(0) | None => None,
+            Some(speed) => Some(speed as u64),
+        };
+
+        Some(Self {
+            locator: string(0x10),
+            size,
+            form_factor: byte(0x0E)?,
+            memory_type: byte(0x12)?,
+            speed_mts: speed(0x20, 0x58).or_else(|| speed(0x15, 0x54)).unwrap_or(0),
+            manufacturer: string(0x17),
+            part_number: string(0x1A),
+        })
+    }
+}
+
+/// Converts the size field of a type 17 structure to bytes, given the extended size field when
+/// the structure has one. `None` when the slot is empty or the size is unknown.
+pub fn size_bytes(size: u16, extended_size: Option<u32>) -> Option<u64> {
+    match size {
+        0 | SIZE_UNKNOWN => None,
+        SIZE_EXTENDED => extended_size.map(|mb| (mb & 0x7FFF_FFFF) as u64 * 1024 * 1024),
+        size if size & SIZE_IN_KB != 0 => Some((size & !SIZE_IN_KB) as u64 * 1024),
+        size => Some(size as u64 * 1024 * 1024),
+    }
+}
+
+/// Returns the 1-based string from the string set following a formatted area, 0 means no string.
+fn string_at(strings: &[u8], index: u8) -> Option<String> {
+    if index == 0 {
+        return None;
+    }
+    strings
+        .split(|&b| b == 0)
+        .nth(index as usize - 1)
+        .map(|s| String::from_utf8_lossy(s).trim().to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn memory_device() {
+        // A populated 16 GiB DDR4 SO-DIMM from an SMBIOS 3.2 laptop
+        let mut raw = vec![
+            0x11, 0x28, 0x40, 0x00, 0x3e, 0x00, 0xfe, 0xff, 0x40, 0x00, 0x40, 0x00, 0x00, 0x40,
+            0x0d, 0x00, 0x01, 0x02, 0x1a, 0x80, 0x00, 0x80, 0x0c, 0x03, 0x04, 0x05, 0x06, 0x01,
+            0x00, 0x00, 0x00, 0x00, 0xb8, 0x0b, 0xb0, 0x04, 0xb0, 0x04, 0xb0, 0x04,
+        ];
+        raw.extend_from_slice(
+            b"ChannelA-DIMM0\0BANK 0\0Samsung\x0012345678\0None\0M471A2K43DB1-CWE\0\0",
+        );
+        let device = MemoryDevice::parse(&raw).expect("valid type 17 structure");
+        assert_eq!(
+            device,
+            MemoryDevice {
+                locator: "ChannelA-DIMM0".to_string(),
+                size: Some(16 * 1024 * 1024 * 1024),
+                form_factor: 0x0d,
+                memory_type: 0x1a,
+                speed_mts: 3000,
+                manufacturer: "Samsung".to_string(),
+                part_number: "M471A2K43DB1-CWE".to_string(),
+            }
+        );
+
+        // An empty slot reports a size of 0
+        raw[0x0c] = 0;
+        raw[0x0d] = 0;
+        assert_eq!(MemoryDevice::parse(&raw).and_then(|d| d.size), None);
+
+        // Other structure types are rejected
+        raw[0] = 16;
+        assert!(MemoryDevice::parse(&raw).is_none());
+    }
+}
@@ -5,6 +5,7 @@
  */
 pub mod cpu;
 pub mod gpu;
+pub mod hardware;
 pub mod mem;
 pub mod net;
 pub mod process;
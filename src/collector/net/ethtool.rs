@@ -0,0 +1,139 @@
+/*
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/.
+ */
+//! Link settings reader using the ethtool ioctl (SIOCETHTOOL).
+//! Used where the sysfs link attributes can't be read, e.g. when /sys belongs to another network namespace.
+
+use rustix::fd::AsFd;
+
+use super::Link;
+
+/// The socket ioctl carrying ethtool commands
+const SIOCETHTOOL: u32 = 0x8946;
+
+/// Gets the link settings of an interface (Linux 4.9 and later)
+const ETHTOOL_GLINKSETTINGS: u32 = 0x4c;
+
+/// The link mode bitmap length is an s8 in the uapi struct, so it can't be any longer than this
+const MAX_LINK_MODE_WORDS: usize = 127;
+
+/// Reported as the speed while the link is down or for drivers that can't tell
+const SPEED_UNKNOWN: u32 = u32::MAX;
+
+const DUPLEX_HALF: u8 = 0x00;
+const DUPLEX_FULL: u8 = 0x01;
+
+/// struct ethtool_link_settings (linux/include/uapi/linux/ethtool.h), followed by the supported,
+/// advertised and partner link mode bitmaps
+#[repr(C)]
+struct LinkSettings {
+    cmd: u32,
+    speed: u32,
+    duplex: u8,
+    port: u8,
+    phy_address: u8,
+    autoneg: u8,
+    mdio_support: u8,
+    eth_tp_mdix: u8,
+    eth_tp_mdix_ctrl: u8,
+    link_mode_masks_nwords: i8,
+    transceiver: u8,
+    master_slave_cfg: u8,
+    master_slave_state: u8,
+    rate_matching: u8,
+    reserved: [u32; 7],
+    link_mode_masks: [u32; 3 * MAX_LINK_MODE_WORDS],
+}
+
+/// struct ifreq (linux/include/uapi/linux/if.h) with the data member of the union,
+/// padded to the size of the largest member (struct ifmap)
+#[repr(C)]
+struct IfReq {
+    name: [u8; 16],
+    data: *mut LinkSettings,
+    _pad: [u8; 24 - size_of::<usize>()],
+}
+
+unsafe impl rustix::ioctl::Ioctl for IfReq {
+    type Output = ();
+    const IS_MUTATING: bool = false;
+
+    fn opcode(&self) -> rustix::ioctl::Opcode {
+        SIOCETHTOOL as rustix::ioctl::Opcode
+    }
+
+    fn as_ptr(&mut self) -> *mut std::ffi::c_void {
+        self as *mut _ as *mut std::ffi::c_void
+    }
+
+    unsafe fn output_from_ptr(
+        _: rustix::ioctl::IoctlOutput,
+        _: *mut std::ffi::c_void,
+    ) -> rustix::io::Result<Self::Output> {
+        Ok(())
+    }
+}
+
+/// Reads the speed and duplex of an interface
+pub fn link(interface: &str) -> anyhow::Result<Link> {
+    let socket = nix::sys::socket::socket(
+        nix::sys::socket::AddressFamily::Inet,
+        nix::sys::socket::SockType::Datagram,
+        nix::sys::socket::SockFlag::SOCK_CLOEXEC,
+        None,
+    )?;
+    let mut name = [0u8; 16];
+    if interface.len() >= name.len() {
+        anyhow::bail!("interface name {} is too long", interface);
+    }
+    name[..interface.len()].copy_from_slice(interface.as_bytes());
+
+    let mut settings = LinkSettings {
+        cmd: ETHTOOL_GLINKSETTINGS,
+        speed: 0,
+        duplex: 0,
+        port: 0,
+        phy_address: 0,
+        autoneg: 0,
+        mdio_support: 0,
+        eth_tp_mdix: 0,
+        eth_tp_mdix_ctrl: 0,
+        link_mode_masks_nwords: 0,
+        transceiver: 0,
+        master_slave_cfg: 0,
+        master_slave_state: 0,
+        rate_matching: 0,
+        reserved: [0; 7],
+        link_mode_masks: [0; 3 * MAX_LINK_MODE_WORDS],
+    };
+    // The first request only negotiates the bitmap length, the kernel answers with its negation
+    for _ in 0..2 {
+        let request = IfReq {
+            name,
+            data: &mut settings,
+            _pad: [0; 24 - size_of::<usize>()],
+        };
+        unsafe { rustix::ioctl::ioctl(socket.as_fd(), request) }?;
+        if settings.link_mode_masks_nwords >= 0 {
+            break;
+        }
+        settings.link_mode_masks_nwords = -settings.link_mode_masks_nwords;
+        settings.cmd = ETHTOOL_GLINKSETTINGS;
+    }
+    if settings.link_mode_masks_nwords <= 0 {
+        anyhow::bail!("ethtool link mode handshake failed for {}", interface);
+    }
+
+    Ok(Link {
+        speed_mbps: (settings.speed != SPEED_UNKNOWN && settings.speed != 0)
+            .then_some(settings.speed),
+        duplex: match settings.duplex {
+            DUPLEX_HALF => "half",
+            DUPLEX_FULL => "full",
+            _ => "",
+        }
+        .to_string(),
+    })
+}
@@ -12,6 +12,11 @@
 //! ```no_run
 //!
 //! ```
+mod ethtool;
+mod routes;
+mod rtnetlink;
+pub(crate) mod sockets;
+#[cfg(feature = "nl80211")]
 mod wifi;
 
 use super::helpers::*;
@@ -26,9 +31,10 @@ pub use crate::metrics::network::*;
 
 /// Network collector
 pub struct Collector {
-    /// Map of network adapter names to its tx/rx counters
-    counters: std::collections::HashMap<String, Sampler<Counters>>,
+    /// Map of network adapter names to the state kept across samples
+    interfaces: std::collections::HashMap<String, Interface>,
     /// Wi-Fi reader wrapped in a `Discovery` lazy-init wrapper
+    #[cfg(feature = "nl80211")]
     wifi_reader: Discovery<wifi::WifiReader>,
 }
 
@@ -57,7 +63,8 @@ impl super::Collector for Collector {
 impl Collector {
     pub fn new() -> Self {
         Self {
-            counters: std::collections::HashMap::new(),
+            interfaces: std::collections::HashMap::new(),
+            #[cfg(feature = "nl80211")]
             wifi_reader: Discovery::default(),
         }
     }
@@ -81,25 +88,8 @@ impl Collector {
                     if interface_name == "." || interface_name == ".." {
                         continue;
                     }
-                    if interface_name == "lo"
-                        || interface_name.starts_with("veth")
-                        || interface_name.starts_with("docker")
-                        || interface_name.starts_with("br-")
-                        || interface_name.starts_with("cni")
-                        || interface_name.starts_with("flannel")
-                        || interface_name.starts_with("cali")
-                        || interface_name.starts_with("virbr")
-                        || interface_name.starts_with("vnet")
-                        || interface_name.starts_with("vmnet")
-                        || interface_name.starts_with("vboxnet")
-                        || interface_name.starts_with("tun")
-                        || interface_name.starts_with("tap")
-                        || interface_name.starts_with("wg")
-                        || interface_name.starts_with("sit")
-                        || interface_name.starts_with("ipip")
-                        || interface_name.starts_with("dummy")
-                        || interface_name.starts_with("ifb")
-                        || interface_name.starts_with("teql")
+                    if !is_selected(config, &interface_name)
+                        || config.exclude_virtual && is_virtual(net_root.as_fd(), &interface_name)
                     {
                         continue;
                     }
@@ -120,7 +110,21 @@ impl Collector {
                     ));
                 }
 
-                Ok(Snapshot { adapters })
+                // A name can come back as a different device, so forget the state of removed interfaces
+                self.interfaces.retain(|name, _| {
+                    adapters
+                        .iter()
+                        .any(|adapter| adapter.interface_name == *name)
+                });
+                Ok(Snapshot {
+                    adapters,
+                    sockets: config.sockets.then(sockets::read),
+                    default_routes: if config.routes {
+                        routes::read(config.gateway_state)
+                    } else {
+                        Vec::new()
+                    },
+                })
             }
             Err(e) => {
                 tracing::warn!("unable to read /sys/class/net: {}", e);
@@ -146,15 +150,45 @@ impl Collector {
             .unwrap_or_default();
         let adapter_type = classify_adapter(fd);
 
-        let is_up = sysfs::readat_string(fd, "operstate")
-            .map(|s| s == "up")
-            .unwrap_or(false);
-        let packet_counters = Counters::read(fd.clone());
-        let counter_delta = self
-            .counters
+        let (operstate, is_up) = link_state(fd);
+        let link = match adapter_type {
+            // Wireless rates live in the Wi-Fi info, virtual links have no meaningful speed
+            adapter::AdapterType::Wifi
+            | adapter::AdapterType::Loopback
+            | adapter::AdapterType::Virtual
+            | adapter::AdapterType::Tunnel => Link::default(),
+            _ => read_link(fd).unwrap_or_else(|| {
+                ethtool::link(name)
+                    .inspect_err(|e| tracing::debug!("no link settings for {}: {}", name, e))
+                    .unwrap_or_default()
+            }),
+        };
+        let ifindex = sysfs::readat_u32(fd, "ifindex").unwrap_or_default();
+        let interface = self
+            .interfaces
             .entry(name.to_string())
-            .or_insert_with(Sampler::new)
-            .push(packet_counters.clone());
+            .or_insert_with(|| Interface::new(ifindex, fd));
+        // Removing and re-adding an interface between two samples keeps the name but resets the counters
+        if interface.ifindex != ifindex {
+            *interface = Interface::new(ifindex, fd);
+        }
+        let packet_counters = Counters::read(fd.clone());
+        let counter_delta = interface.counters.push(packet_counters.clone());
+        let driver = interface.driver.clone();
+        let mtu = sysfs::readat_u32(fd, "mtu").unwrap_or_default();
+        let carrier_changes = sysfs::readat_u64(fd, "carrier_changes").unwrap_or_default();
+        let now = std::time::Instant::now();
+        let carrier_changes_since_last_sample = interface.observe(carrier_changes, mtu, now);
+        let last_change_seconds_ago = interface
+            .last_change
+            .map(|last_change| now.duration_since(last_change).as_secs());
+        // Every rate is taken over the window between the same two counter samples
+        let per_second = |change: fn(&CounterDelta) -> u64| {
+            counter_delta
+                .as_ref()
+                .map(|delta| rate(change(&delta.change), delta.interval))
+                .unwrap_or_default()
+        };
         let wifi = config
             .wifi_info
             .then(|| self.read_wifi(adapter_type, is_up, name))
@@ -165,8 +199,9 @@ impl Collector {
             ipv4_addresses,
             ipv6_addresses,
             adapter_type: adapter_type as i32,
-            mtu: sysfs::readat_u32(fd, "mtu").unwrap_or_default(),
+            mtu,
             is_up,
+            operstate,
             rx_bytes_total: packet_counters.rx_bytes,
             tx_bytes_total: packet_counters.tx_bytes,
             rx_packets_total: packet_counters.rx_packets,
@@ -175,36 +210,109 @@ impl Collector {
             tx_errors_total: packet_counters.tx_errors,
             rx_drops_total: packet_counters.rx_drops,
             tx_drops_total: packet_counters.tx_drops,
-            rx_bytes_per_second: counter_delta
-                .as_ref()
-                .map(|delta| (delta.change.rx_bytes as f64 / delta.interval.as_secs_f64()) as u64)
-                .unwrap_or_default(),
-            tx_bytes_per_second: counter_delta
-                .map(|delta| (delta.change.tx_bytes as f64 / delta.interval.as_secs_f64()) as u64)
-                .unwrap_or_default(),
+            rx_bytes_per_second: per_second(|change| change.rx_bytes),
+            tx_bytes_per_second: per_second(|change| change.tx_bytes),
             wifi_info: wifi,
+            link_speed_mbps: link.speed_mbps,
+            duplex: link.duplex,
+            max_bandwidth_bytes_per_second: link.speed_mbps.map(|mbps| mbps as u64 * 1_000_000 / 8),
+            driver,
+            collisions_total: packet_counters.collisions,
+            rx_missed_errors_total: packet_counters.rx_missed_errors,
+            rx_drops_per_second: per_second(|change| change.rx_drops),
+            tx_drops_per_second: per_second(|change| change.tx_drops),
+            collisions_per_second: per_second(|change| change.collisions),
+            rx_missed_errors_per_second: per_second(|change| change.rx_missed_errors),
+            parent_interface: parent_interface(fd, adapter_type).unwrap_or_default(),
+            rx_packets_per_second: per_second(|change| change.rx_packets),
+            tx_packets_per_second: per_second(|change| change.tx_packets),
+            rx_errors_per_second: per_second(|change| change.rx_errors),
+            tx_errors_per_second: per_second(|change| change.tx_errors),
+            carrier_changes_total: carrier_changes,
+            carrier_changes_since_last_sample,
+            last_change_seconds_ago,
+            aggregate_info: read_aggregate(fd, adapter_type),
         }
     }
 
+    /// Reads the Wi-Fi info of an associated wireless interface, wired interfaces are skipped before
+    /// any netlink traffic.
     fn read_wifi(
         &mut self,
         adapter_type: adapter::AdapterType,
         is_up: bool,
         name: &str,
     ) -> Option<WifiInfo> {
-        if adapter_type == adapter::AdapterType::Wifi && is_up {
-            self.wifi_reader
-                .probe_mut(wifi::WifiReader::new)
-                .and_then(|reader| match reader.read(name) {
-                    Ok(wifi_info) => Some(wifi_info),
-                    Err(e) => {
-                        tracing::warn!("failed to read wifi info for {}: {}", name, e);
-                        None
-                    }
-                })
-        } else {
-            None
+        if adapter_type != adapter::AdapterType::Wifi || !is_up {
+            return None;
         }
+        #[cfg(feature = "nl80211")]
+        if let Some(reader) = self.wifi_reader.probe_mut(wifi::WifiReader::new) {
+            match reader.read(name) {
+                Ok(wifi_info) => return Some(wifi_info),
+                Err(e) => tracing::warn!("failed to read wifi info for {}: {}", name, e),
+            }
+        }
+        // The wireless extensions only carry the signal, which is still enough for a signal meter
+        let wireless = sysfs::read_string_path("/proc/net/wireless")?;
+        Some(WifiInfo {
+            signal_strength_dbm: parse_wireless_signal(&wireless, name)?,
+            ..Default::default()
+        })
+    }
+}
+
+/// Finds the signal level of an interface in /proc/net/wireless, `None` unless it's reported in dBm.
+fn parse_wireless_signal(wireless: &str, name: &str) -> Option<i32> {
+    // Two header lines, then "<iface>: <status> <link> <level> <noise> ..." with trailing dots on updated values
+    wireless.lines().skip(2).find_map(|line| {
+        let (interface, fields) = line.split_once(':')?;
+        if interface.trim() != name {
+            return None;
+        }
+        let level = fields
+            .split_whitespace()
+            .nth(2)?
+            .trim_end_matches('.')
+            .parse::<i32>()
+            .ok()?;
+        (level < 0).then_some(level)
+    })
+}
+
+/// What the collector remembers about an interface between samples
+struct Interface {
+    /// A name that comes back with another index belongs to a different interface
+    ifindex: u32,
+    counters: Sampler<Counters>,
+    /// Doesn't change while the interface exists
+    driver: String,
+    /// Carrier changes and MTU as of the previous sample, `None` before the first one
+    previous: Option<(u64, u32)>,
+    /// When a carrier or MTU change was last seen, unknown until one happens while monitoring
+    last_change: Option<std::time::Instant>,
+}
+
+impl Interface {
+    fn new(ifindex: u32, fd: BorrowedFd) -> Self {
+        Self {
+            ifindex,
+            counters: Sampler::new(),
+            driver: read_driver(fd, ifindex),
+            previous: None,
+            last_change: None,
+        }
+    }
+
+    /// Records the link state of a sample, returning how often the carrier changed since the previous one.
+    fn observe(&mut self, carrier_changes: u64, mtu: u32, now: std::time::Instant) -> Option<u64> {
+        let previous = self.previous.replace((carrier_changes, mtu));
+        let (previous_changes, previous_mtu) = previous?;
+        let changes = carrier_changes.wrapping_sub(previous_changes);
+        if changes != 0 || mtu != previous_mtu {
+            self.last_change = Some(now);
+        }
+        Some(changes)
     }
 }
 
@@ -218,6 +326,8 @@ struct Counters {
     tx_errors: u64,
     rx_drops: u64,
     tx_drops: u64,
+    collisions: u64,
+    rx_missed_errors: u64,
 }
 
 impl Counters {
@@ -231,6 +341,9 @@ impl Counters {
             tx_errors: sysfs::readat_u64(fd.as_fd(), "statistics/tx_errors").unwrap_or_default(),
             rx_drops: sysfs::readat_u64(fd.as_fd(), "statistics/rx_dropped").unwrap_or_default(),
             tx_drops: sysfs::readat_u64(fd.as_fd(), "statistics/tx_dropped").unwrap_or_default(),
+            collisions: sysfs::readat_u64(fd.as_fd(), "statistics/collisions").unwrap_or_default(),
+            rx_missed_errors: sysfs::readat_u64(fd.as_fd(), "statistics/rx_missed_errors")
+                .unwrap_or_default(),
         }
     }
 }
@@ -242,6 +355,16 @@ impl sampler::Differential for Counters {
         CounterDelta {
             rx_bytes: self.rx_bytes.wrapping_sub(previous.rx_bytes),
             tx_bytes: self.tx_bytes.wrapping_sub(previous.tx_bytes),
+            rx_packets: self.rx_packets.wrapping_sub(previous.rx_packets),
+            tx_packets: self.tx_packets.wrapping_sub(previous.tx_packets),
+            rx_errors: self.rx_errors.wrapping_sub(previous.rx_errors),
+            tx_errors: self.tx_errors.wrapping_sub(previous.tx_errors),
+            rx_drops: self.rx_drops.wrapping_sub(previous.rx_drops),
+            tx_drops: self.tx_drops.wrapping_sub(previous.tx_drops),
+            collisions: self.collisions.wrapping_sub(previous.collisions),
+            rx_missed_errors: self
+                .rx_missed_errors
+                .wrapping_sub(previous.rx_missed_errors),
         }
     }
 }
@@ -250,27 +373,279 @@ impl sampler::Differential for Counters {
 struct CounterDelta {
     rx_bytes: u64,
     tx_bytes: u64,
+    rx_packets: u64,
+    tx_packets: u64,
+    rx_errors: u64,
+    tx_errors: u64,
+    rx_drops: u64,
+    tx_drops: u64,
+    collisions: u64,
+    rx_missed_errors: u64,
+}
+
+/// Applies the include and exclude patterns of the config to an interface name.
+fn is_selected(config: &Config, name: &str) -> bool {
+    (config.include_interfaces.is_empty()
+        || config
+            .include_interfaces
+            .iter()
+            .any(|pattern| glob_matches(pattern, name)))
+        && !config
+            .exclude_interfaces
+            .iter()
+            .any(|pattern| glob_matches(pattern, name))
+}
+
+/// Reads the operational state of an interface and whether it can pass traffic.
+/// Drivers without operstate support (some virtual and USB adapters) stay at "unknown", so those
+/// fall back to the carrier.
+fn link_state(fd: BorrowedFd) -> (String, bool) {
+    let operstate = sysfs::readat_string(fd, "operstate").unwrap_or_default();
+    let is_up = match operstate.as_str() {
+        "up" => true,
+        // Reading carrier fails with EINVAL while the interface is administratively down
+        "unknown" => sysfs::readat_u32(fd, "carrier") == Some(1),
+        _ => false,
+    };
+    (operstate, is_up)
+}
+
+/// Converts a counter change into a per-second rate over the interval it was measured in.
+fn rate(change: u64, interval: std::time::Duration) -> u64 {
+    let secs = interval.as_secs_f64();
+    if secs == 0.0 {
+        return 0;
+    }
+    (change as f64 / secs) as u64
+}
+
+/// Speed and duplex of a wired link
+#[derive(Debug, Default, PartialEq)]
+struct Link {
+    speed_mbps: Option<u32>,
+    duplex: String,
+}
+
+/// Reads the link settings from sysfs, `None` when the attributes can't be read.
+/// The kernel reports -1 (SPEED_UNKNOWN) while there is no carrier.
+fn read_link(fd: BorrowedFd) -> Option<Link> {
+    let speed = sysfs::readat_string(fd, "speed")?.parse::<i64>().ok()?;
+    let speed_mbps = u32::try_from(speed).ok().filter(|&speed| speed > 0);
+    let duplex = match sysfs::readat_string(fd, "duplex").as_deref() {
+        Some(duplex @ ("full" | "half")) if speed_mbps.is_some() => duplex.to_string(),
+        _ => String::new(),
+    };
+    Some(Link { speed_mbps, duplex })
+}
+
+/// Reads the driver bound to the interface's device. Virtual interfaces have no device, so they're
+/// named after the DEVTYPE the kernel gives them, or the kind of link they are when they have none.
+fn read_driver(fd: BorrowedFd, ifindex: u32) -> String {
+    let driver = read_device_driver(fd);
+    if !driver.is_empty() {
+        return driver;
+    }
+    rtnetlink::link_kind(ifindex)
+        .inspect_err(|e| tracing::debug!("failed to read link kind of interface {ifindex}: {e}"))
+        .ok()
+        .flatten()
+        .unwrap_or_default()
+}
+
+/// Reads the driver of an interface from sysfs alone, empty when sysfs doesn't name it.
+fn read_device_driver(fd: BorrowedFd) -> String {
+    if let Some(driver) = rustix::fs::readlinkat(fd, "device/driver", Vec::new())
+        .ok()
+        .and_then(|path| {
+            std::path::Path::new(&*path.to_string_lossy())
+                .file_name()
+                .map(|name| name.to_string_lossy().into_owned())
+        })
+    {
+        return driver;
+    }
+    if let Some(devtype) = read_devtype(fd) {
+        return devtype;
+    }
+    match sysfs::readat_u32(fd, "type") {
+        Some(ARPHRD_LOOPBACK) => "loopback".to_string(),
+        _ => String::new(),
+    }
+}
+
+/// Reads the DEVTYPE the kernel gives an interface in its uevent (e.g. "bridge", "vlan", "wireguard").
+fn read_devtype(fd: BorrowedFd) -> Option<String> {
+    sysfs::readat_string(fd, "uevent")?
+        .lines()
+        .find_map(|line| line.strip_prefix("DEVTYPE=").map(String::from))
+}
+
+/// Lists the interfaces an interface is stacked on from its lower_<name> links, the ports of a
+/// bridge or bond and the parent of a VLAN.
+fn lower_interfaces(fd: BorrowedFd) -> Vec<String> {
+    let Ok(dir) = rustix::fs::Dir::read_from(fd) else {
+        return Vec::new();
+    };
+    let mut lower: Vec<_> = dir
+        .flatten()
+        .filter_map(|entry| {
+            entry
+                .file_name()
+                .to_string_lossy()
+                .strip_prefix("lower_")
+                .map(String::from)
+        })
+        .collect();
+    lower.sort();
+    lower
+}
+
+/// Whether an interface is purely software. Bridges, bonds and VLANs count as physical when an
+/// interface beneath them is, so a host's uplink bridge is kept while container bridges aren't.
+fn is_virtual(net_root: BorrowedFd, name: &str) -> bool {
+    fn backed_by_device(net_root: BorrowedFd, name: &str, depth: u32) -> bool {
+        let Ok(interface) = rustix::fs::openat(
+            net_root,
+            name,
+            OFlags::RDONLY | OFlags::CLOEXEC | OFlags::DIRECTORY,
+            Mode::empty(),
+        ) else {
+            return false;
+        };
+        if rustix::fs::statat(interface.as_fd(), "device", AtFlags::empty()).is_ok() {
+            return true;
+        }
+        // Stacks are rarely more than a VLAN on a bond on a NIC deep
+        depth < 4
+            && lower_interfaces(interface.as_fd())
+                .iter()
+                .any(|lower| backed_by_device(net_root, lower, depth + 1))
+    }
+    !backed_by_device(net_root, name, 0)
+}
+
+/// Finds the bridge or bond an interface is enslaved to, or the interface a VLAN (or macvlan) sits on.
+fn parent_interface(fd: BorrowedFd, adapter_type: adapter::AdapterType) -> Option<String> {
+    if let Ok(master) = rustix::fs::readlinkat(fd, "master", Vec::new()) {
+        return std::path::Path::new(&*master.to_string_lossy())
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned());
+    }
+    // The lower interfaces of a bridge or bond are its members rather than its parents
+    match adapter_type {
+        adapter::AdapterType::Bridge | adapter::AdapterType::Bond => None,
+        _ => lower_interfaces(fd).into_iter().next(),
+    }
+}
+
+/// Reads the mode and members of a bond or bridge.
+fn read_aggregate(fd: BorrowedFd, adapter_type: adapter::AdapterType) -> Option<AggregateInfo> {
+    let (mode, active_member, members, stp_state) = match adapter_type {
+        adapter::AdapterType::Bond => (
+            // "<name> <number>", e.g. "active-backup 1"
+            sysfs::readat_string(fd, "bonding/mode")?
+                .split_whitespace()
+                .next()?
+                .to_string(),
+            sysfs::readat_string(fd, "bonding/active_slave").unwrap_or_default(),
+            sysfs::readat_string(fd, "bonding/slaves")
+                .unwrap_or_default()
+                .split_whitespace()
+                .map(String::from)
+                .collect::<Vec<_>>(),
+            String::new(),
+        ),
+        adapter::AdapterType::Bridge => {
+            let ports = rustix::fs::openat(
+                fd,
+                "brif",
+                OFlags::RDONLY | OFlags::CLOEXEC | OFlags::DIRECTORY,
+                Mode::empty(),
+            )
+            .ok()?;
+            let mut members: Vec<_> = rustix::fs::Dir::read_from(ports.as_fd())
+                .ok()?
+                .flatten()
+                .map(|entry| entry.file_name().to_string_lossy().into_owned())
+                .filter(|name| name != "." && name != "..")
+                .collect();
+            members.sort();
+            let stp_state = match sysfs::readat_u32(fd, "bridge/stp_state") {
+                Some(0) => "disabled",
+                Some(1) => "kernel",
+                Some(2) => "user",
+                _ => "",
+            };
+            (
+                "bridge".to_string(),
+                String::new(),
+                members,
+                stp_state.to_string(),
+            )
+        }
+        _ => return None,
+    };
+    let members = members
+        .into_iter()
+        .map(|name| {
+            // Every member is linked from its master as lower_<name>
+            let is_up = rustix::fs::openat(
+                fd,
+                format!("lower_{name}"),
+                OFlags::RDONLY | OFlags::CLOEXEC | OFlags::DIRECTORY,
+                Mode::empty(),
+            )
+            .is_ok_and(|member| link_state(member.as_fd()).1);
+            AggregateMember {
+                interface_name: name,
+                is_up,
+            }
+        })
+        .collect();
+    Some(AggregateInfo {
+        mode,
+        active_member,
+        members,
+        stp_state,
+    })
 }
 
 const ARPHRD_ETHER: u32 = 1;
 const ARPHRD_TUNNEL: u32 = 768;
+const ARPHRD_TUNNEL6: u32 = 769;
 const ARPHRD_LOOPBACK: u32 = 772;
 const ARPHRD_SIT: u32 = 776;
+const ARPHRD_IPGRE: u32 = 778;
 const ARPHRD_NONE: u32 = 65534;
 
 fn classify_adapter(fd: BorrowedFd) -> adapter::AdapterType {
-    if rustix::fs::statat(fd, "wireless", AtFlags::empty()).is_ok()
+    let r#type = sysfs::readat_u32(fd, "type");
+    if r#type == Some(ARPHRD_LOOPBACK) {
+        adapter::AdapterType::Loopback
+    } else if rustix::fs::statat(fd, "wireless", AtFlags::empty()).is_ok()
         || rustix::fs::statat(fd, "phy80211", AtFlags::empty()).is_ok()
     {
         adapter::AdapterType::Wifi
     } else if rustix::fs::statat(fd, "bridge", AtFlags::empty()).is_ok() {
         adapter::AdapterType::Bridge
+    } else if rustix::fs::statat(fd, "bonding", AtFlags::empty()).is_ok() {
+        adapter::AdapterType::Bond
+    } else if rustix::fs::statat(fd, "tun_flags", AtFlags::empty()).is_ok() {
+        // tun and tap devices, tap being ARPHRD_ETHER
+        adapter::AdapterType::Tunnel
     } else {
-        match sysfs::readat_u32(fd, "type") {
-            Some(ARPHRD_LOOPBACK) => adapter::AdapterType::Loopback,
-            Some(ARPHRD_ETHER) => adapter::AdapterType::Ethernet,
-            Some(ARPHRD_NONE) | Some(ARPHRD_TUNNEL) | Some(ARPHRD_SIT) => {
-                adapter::AdapterType::Virtual
+        match (read_devtype(fd).as_deref(), r#type) {
+            (Some("vlan"), _) => adapter::AdapterType::Vlan,
+            (Some("wireguard"), _)
+            | (_, Some(ARPHRD_NONE | ARPHRD_TUNNEL | ARPHRD_TUNNEL6 | ARPHRD_SIT | ARPHRD_IPGRE)) => {
+                adapter::AdapterType::Tunnel
+            }
+            (_, Some(ARPHRD_ETHER)) => {
+                if rustix::fs::statat(fd, "device", AtFlags::empty()).is_ok() {
+                    adapter::AdapterType::Ethernet
+                } else {
+                    adapter::AdapterType::Virtual
+                }
             }
             _ => adapter::AdapterType::Unknown,
         }
@@ -345,6 +720,7 @@ fn get_addresses() -> anyhow::Result<Vec<IfAddr>> {
 mod tests {
     use super::*;
     use crate::collector::Collector;
+    use crate::collector::helpers::test::FakeSysfs;
 
     #[test]
     fn network() -> anyhow::Result<()> {
@@ -354,6 +730,12 @@ mod tests {
         config.network = Some(Config {
             addresses: true,
             wifi_info: true,
+            sockets: true,
+            exclude_virtual: true,
+            routes: true,
+            gateway_state: true,
+            include_interfaces: Vec::new(),
+            exclude_interfaces: vec!["veth*".to_string()],
         });
         let _ = collector.collect(&config)?;
         std::thread::sleep(std::time::Duration::from_secs(1));
@@ -361,4 +743,297 @@ mod tests {
         println!("{:#?}", snapshot);
         Ok(())
     }
+
+    #[test]
+    fn operational_state() {
+        let root = FakeSysfs::new("net");
+        let interface = |name: &str, operstate: &str, carrier: Option<&str>| {
+            root.write(format!("{name}/operstate"), format!("{operstate}\n"));
+            if let Some(carrier) = carrier {
+                root.write(format!("{name}/carrier"), format!("{carrier}\n"));
+            }
+            root.open_dir(name)
+        };
+        let states = [
+            interface("eth0", "up", Some("1")),
+            interface("eth1", "down", None),
+            interface("wlan0", "dormant", Some("1")),
+            interface("usb0", "unknown", Some("1")),
+            interface("usb1", "unknown", Some("0")),
+            interface("vlan0", "lowerlayerdown", Some("0")),
+        ]
+        .map(|fd| link_state(fd.as_fd()));
+
+        assert_eq!(states[0], ("up".to_string(), true));
+        assert_eq!(states[1], ("down".to_string(), false));
+        assert_eq!(states[2], ("dormant".to_string(), false));
+        assert_eq!(states[3], ("unknown".to_string(), true));
+        assert_eq!(states[4], ("unknown".to_string(), false));
+        assert_eq!(states[5], ("lowerlayerdown".to_string(), false));
+    }
+
+    #[test]
+    fn link_settings() {
+        let root = FakeSysfs::new("link");
+        let interface = |name: &str, attributes: &[(&str, &str)]| {
+            root.dir(name);
+            for (attribute, value) in attributes {
+                root.write(format!("{name}/{attribute}"), format!("{value}\n"));
+            }
+            root.open_dir(name)
+        };
+        let links = [
+            interface("eth0", &[("speed", "2500"), ("duplex", "full")]),
+            interface("eth1", &[("speed", "-1"), ("duplex", "unknown")]),
+            interface("eth2", &[]),
+        ]
+        .map(|fd| read_link(fd.as_fd()));
+
+        assert_eq!(
+            links[0],
+            Some(Link {
+                speed_mbps: Some(2500),
+                duplex: "full".to_string(),
+            })
+        );
+        assert_eq!(links[1], Some(Link::default()));
+        assert_eq!(links[2], None);
+    }
+
+    #[test]
+    fn topology() -> anyhow::Result<()> {
+        let root = FakeSysfs::new("topology");
+        let interface = |name: &str, r#type: u32, entries: &[&str], links: &[(&str, &str)]| {
+            root.write(format!("{name}/type"), format!("{type}\n"));
+            for entry in entries {
+                root.dir(format!("{name}/{entry}"));
+            }
+            for (link, target) in links {
+                root.symlink(target, format!("{name}/{link}"));
+            }
+        };
+        interface("lo", ARPHRD_LOOPBACK, &[], &[]);
+        interface("eth0", ARPHRD_ETHER, &["device"], &[("master", "../br0")]);
+        interface(
+            "br0",
+            ARPHRD_ETHER,
+            &["bridge"],
+            &[("lower_eth0", "../eth0")],
+        );
+        interface("eth1", ARPHRD_ETHER, &["device"], &[]);
+        interface("eth1.100", ARPHRD_ETHER, &[], &[("lower_eth1", "../eth1")]);
+        root.write("eth1.100/uevent", "DEVTYPE=vlan\nINTERFACE=eth1.100\n");
+        interface("veth1", ARPHRD_ETHER, &[], &[("master", "../docker0")]);
+        interface(
+            "docker0",
+            ARPHRD_ETHER,
+            &["bridge"],
+            &[("lower_veth1", "../veth1")],
+        );
+        interface("tun0", ARPHRD_NONE, &["tun_flags"], &[]);
+
+        let net_root = root.open_dir("");
+        let describe = |name: &str| -> anyhow::Result<_> {
+            let fd = rustix::fs::openat(
+                net_root.as_fd(),
+                name,
+                OFlags::RDONLY | OFlags::CLOEXEC | OFlags::DIRECTORY,
+                Mode::empty(),
+            )?;
+            let adapter_type = classify_adapter(fd.as_fd());
+            Ok((
+                adapter_type,
+                is_virtual(net_root.as_fd(), name),
+                parent_interface(fd.as_fd(), adapter_type),
+            ))
+        };
+        let described = ["lo", "eth0", "br0", "eth1.100", "veth1", "docker0", "tun0"]
+            .map(describe)
+            .into_iter()
+            .collect::<anyhow::Result<Vec<_>>>();
+
+        use adapter::AdapterType::*;
+        assert_eq!(
+            described?,
+            vec![
+                (Loopback, true, None),
+                (Ethernet, false, Some("br0".to_string())),
+                (Bridge, false, None),
+                (Vlan, false, Some("eth1".to_string())),
+                (Virtual, true, Some("docker0".to_string())),
+                (Bridge, true, None),
+                (Tunnel, true, None),
+            ]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn aggregates() {
+        let root = FakeSysfs::new("aggregate");
+        let file = |path: &str, contents: &str| root.write(path, contents);
+        file("eth0/operstate", "up\n");
+        file("eth1/operstate", "down\n");
+        file("eth2/operstate", "up\n");
+        file("bond0/bonding/mode", "active-backup 1\n");
+        file("bond0/bonding/active_slave", "eth0\n");
+        file("bond0/bonding/slaves", "eth0 eth1\n");
+        file("br0/bridge/stp_state", "1\n");
+        root.symlink("../../eth2/brport", "br0/brif/eth2");
+        for (master, member) in [("bond0", "eth0"), ("bond0", "eth1"), ("br0", "eth2")] {
+            root.symlink(format!("../{member}"), format!("{master}/lower_{member}"));
+        }
+        let read =
+            |name: &str, adapter_type| read_aggregate(root.open_dir(name).as_fd(), adapter_type);
+        let bond = read("bond0", adapter::AdapterType::Bond);
+        let bridge = read("br0", adapter::AdapterType::Bridge);
+        let ethernet = read("eth0", adapter::AdapterType::Ethernet);
+
+        let member = |name: &str, is_up| AggregateMember {
+            interface_name: name.to_string(),
+            is_up,
+        };
+        assert_eq!(
+            bond,
+            Some(AggregateInfo {
+                mode: "active-backup".to_string(),
+                active_member: "eth0".to_string(),
+                // A degraded bond, the backup is down
+                members: vec![member("eth0", true), member("eth1", false)],
+                stp_state: String::new(),
+            })
+        );
+        assert_eq!(
+            bridge,
+            Some(AggregateInfo {
+                mode: "bridge".to_string(),
+                active_member: String::new(),
+                members: vec![member("eth2", true)],
+                stp_state: "kernel".to_string(),
+            })
+        );
+        assert_eq!(ethernet, None);
+    }
+
+    #[test]
+    fn rates() {
+        let counters = |bytes: u64, packets: u64| Counters {
+            rx_bytes: bytes,
+            tx_bytes: bytes / 2,
+            rx_packets: packets,
+            tx_packets: packets,
+            rx_errors: 0,
+            tx_errors: 0,
+            rx_drops: 0,
+            tx_drops: 0,
+            collisions: 0,
+            rx_missed_errors: 0,
+        };
+        let mut sampler = Sampler::new();
+        let start = std::time::Instant::now();
+        assert!(sampler.push_at(counters(1_000, 10), start).is_none());
+        let delta = sampler
+            .push_at(
+                counters(5_001_000, 4_010),
+                start + std::time::Duration::from_secs(2),
+            )
+            .unwrap();
+        assert_eq!(rate(delta.change.rx_bytes, delta.interval), 2_500_000);
+        assert_eq!(rate(delta.change.tx_bytes, delta.interval), 1_250_000);
+        assert_eq!(rate(delta.change.rx_packets, delta.interval), 2_000);
+        assert_eq!(rate(delta.change.rx_packets, std::time::Duration::ZERO), 0);
+    }
+
+    #[test]
+    fn interface_patterns() {
+        let config = Config {
+            include_interfaces: vec!["eth*".to_string(), "veth*".to_string()],
+            exclude_interfaces: vec!["veth*".to_string()],
+            ..Default::default()
+        };
+        assert!(is_selected(&config, "eth0"));
+        assert!(!is_selected(&config, "veth1a2b3c"));
+        assert!(!is_selected(&config, "wlan0"));
+
+        let config = Config {
+            exclude_interfaces: vec!["docker0".to_string()],
+            ..Default::default()
+        };
+        assert!(is_selected(&config, "wlan0"));
+        assert!(!is_selected(&config, "docker0"));
+    }
+
+    #[test]
+    fn transitions() {
+        let mut interface = Interface {
+            ifindex: 2,
+            counters: Sampler::new(),
+            driver: String::new(),
+            previous: None,
+            last_change: None,
+        };
+        let start = std::time::Instant::now();
+        let second = |secs: u64| start + std::time::Duration::from_secs(secs);
+        assert_eq!(interface.observe(4, 1500, start), None);
+        assert_eq!(interface.observe(4, 1500, second(1)), Some(0));
+        assert_eq!(interface.last_change, None);
+        // The link flapped twice
+        assert_eq!(interface.observe(6, 1500, second(2)), Some(2));
+        assert_eq!(interface.last_change, Some(second(2)));
+        assert_eq!(interface.observe(6, 9000, second(3)), Some(0));
+        assert_eq!(interface.last_change, Some(second(3)));
+        assert_eq!(interface.observe(6, 9000, second(4)), Some(0));
+        assert_eq!(interface.last_change, Some(second(3)));
+    }
+
+    #[test]
+    fn wireless_signal() {
+        let wireless = "Inter-| sta-|   Quality        |   Discarded packets               | Missed | WE\n \
+ face | tus | link level noise |  nwid  crypt   frag  retry   misc | beacon | 22\n \
+wlp2s0: 0000   54.  -56.  -256        0      0      0      0     35        0\n \
+ wlan1: 0000    0     0     0        0      0      0      0      0        0\n";
+        assert_eq!(parse_wireless_signal(wireless, "wlp2s0"), Some(-56));
+        assert_eq!(parse_wireless_signal(wireless, "wlan1"), None);
+        assert_eq!(parse_wireless_signal(wireless, "wlan2"), None);
+    }
+
+    #[test]
+    fn drivers() {
+        let root = FakeSysfs::new("driver");
+        let interface = |name: &str, driver: Option<&str>, uevent: &str, r#type: u32| {
+            if let Some(driver) = driver {
+                root.symlink(
+                    format!("../../../bus/pci/drivers/{driver}"),
+                    format!("{name}/device/driver"),
+                );
+            }
+            root.write(format!("{name}/uevent"), uevent);
+            root.write(format!("{name}/type"), format!("{type}\n"));
+            root.open_dir(name)
+        };
+        let drivers = [
+            interface(
+                "enp3s0",
+                Some("e1000e"),
+                "INTERFACE=enp3s0\nIFINDEX=2\n",
+                ARPHRD_ETHER,
+            ),
+            interface(
+                "br0",
+                None,
+                "DEVTYPE=bridge\nINTERFACE=br0\nIFINDEX=3\n",
+                ARPHRD_ETHER,
+            ),
+            interface("lo", None, "INTERFACE=lo\nIFINDEX=1\n", ARPHRD_LOOPBACK),
+            interface(
+                "dummy0",
+                None,
+                "INTERFACE=dummy0\nIFINDEX=4\n",
+                ARPHRD_ETHER,
+            ),
+        ]
+        .map(|fd| read_device_driver(fd.as_fd()));
+
+        assert_eq!(drivers, ["e1000e", "bridge", "loopback", ""]);
+    }
 }
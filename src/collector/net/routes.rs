@@ -0,0 +1,177 @@
+/*
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/.
+ */
+//! Default routes from the procfs routing tables, and the neighbor state of their gateways from the ARP cache.
+//! Only covers the main routing table of the network namespace monitord runs in.
+
+use std::net::{Ipv4Addr, Ipv6Addr};
+
+use super::{NeighborState, Route};
+
+/// Route is usable
+const RTF_UP: u32 = 0x0001;
+/// Destination is reached through a gateway
+const RTF_GATEWAY: u32 = 0x0002;
+
+/// ARP entry is resolved
+const ATF_COM: u32 = 0x02;
+/// ARP entry is static
+const ATF_PERM: u32 = 0x04;
+
+/// Reads the IPv4 and IPv6 default routes, ordered by metric so the preferred route comes first.
+pub fn read(neighbors: bool) -> Vec<Route> {
+    let mut routes = Vec::new();
+    if let Ok(table) = std::fs::read_to_string("/proc/net/route") {
+        routes.extend(parse_ipv4(&table));
+    }
+    if let Ok(table) = std::fs::read_to_string("/proc/net/ipv6_route") {
+        routes.extend(parse_ipv6(&table));
+    }
+    routes.sort_by_key(|route| route.metric);
+    if neighbors && let Ok(arp) = std::fs::read_to_string("/proc/net/arp") {
+        for route in routes.iter_mut() {
+            route.gateway_state =
+                neighbor_state(&arp, &route.gateway, &route.interface_name) as i32;
+        }
+    }
+    routes
+}
+
+/// Finds the default routes of /proc/net/route. Lines look like
+/// "eth0 00000000 0102A8C0 0003 0 0 100 00000000 0 0 0", the addresses being the raw network order
+/// words printed as hex.
+fn parse_ipv4(table: &str) -> Vec<Route> {
+    table
+        .lines()
+        .skip(1)
+        .filter_map(|line| {
+            let fields: Vec<_> = line.split_whitespace().collect();
+            let hex = |index: usize| u32::from_str_radix(fields.get(index)?, 16).ok();
+            let (destination, gateway, flags, metric, mask) = (
+                hex(1)?,
+                hex(2)?,
+                hex(3)?,
+                fields.get(6)?.parse().ok()?,
+                hex(7)?,
+            );
+            (destination == 0
+                && mask == 0
+                && flags & (RTF_UP | RTF_GATEWAY) == RTF_UP | RTF_GATEWAY)
+                .then(|| Route {
+                    gateway: Ipv4Addr::from(gateway.to_ne_bytes()).to_string(),
+                    interface_name: fields[0].to_string(),
+                    metric,
+                    ..Default::default()
+                })
+        })
+        .collect()
+}
+
+/// Finds the default routes of /proc/net/ipv6_route. Lines are
+/// "<destination> <prefix length> <source> <prefix length> <next hop> <metric> <refcount> <use> <flags> <interface>"
+/// with every number in hex.
+fn parse_ipv6(table: &str) -> Vec<Route> {
+    table
+        .lines()
+        .filter_map(|line| {
+            let fields: Vec<_> = line.split_whitespace().collect();
+            if fields.len() < 10 {
+                return None;
+            }
+            let next_hop = u128::from_str_radix(fields[4], 16).ok()?;
+            let metric = u32::from_str_radix(fields[5], 16).ok()?;
+            let flags = u32::from_str_radix(fields[8], 16).ok()?;
+            (fields[0].bytes().all(|digit| digit == b'0')
+                && fields[1] == "00"
+                && next_hop != 0
+                && flags & (RTF_UP | RTF_GATEWAY) == RTF_UP | RTF_GATEWAY)
+                .then(|| Route {
+                    gateway: Ipv6Addr::from(next_hop).to_string(),
+                    interface_name: fields[9].to_string(),
+                    metric,
+                    ..Default::default()
+                })
+        })
+        .collect()
+}
+
+/// Looks the gateway up in /proc/net/arp. Lines look like
+/// "192.168.1.1 0x1 0x2 aa:bb:cc:dd:ee:ff * wlan0", IPv6 neighbors aren't in procfs.
+fn neighbor_state(arp: &str, gateway: &str, interface_name: &str) -> NeighborState {
+    if gateway.contains(':') {
+        return NeighborState::Unspecified;
+    }
+    arp.lines()
+        .skip(1)
+        .find_map(|line| {
+            let fields: Vec<_> = line.split_whitespace().collect();
+            if fields.len() < 6 || fields[0] != gateway || fields[5] != interface_name {
+                return None;
+            }
+            let flags = u32::from_str_radix(fields[2].trim_start_matches("0x"), 16).ok()?;
+            Some(if flags & ATF_PERM != 0 {
+                NeighborState::Permanent
+            } else if flags & ATF_COM != 0 {
+                NeighborState::Resolved
+            } else {
+                NeighborState::Incomplete
+            })
+        })
+        .unwrap_or(NeighborState::Missing)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn default_routes() {
+        let ipv4 =
+            "Iface\tDestination\tGateway \tFlags\tRefCnt\tUse\tMetric\tMask\t\tMTU\tWindow\tIRTT
+wlan0\t00000000\t0101A8C0\t0003\t0\t0\t600\t00000000\t0\t0\t0
+wlan0\t0001A8C0\t00000000\t0001\t0\t0\t600\t00FFFFFF\t0\t0\t0
+eth0\t00000000\t0100000A\t0003\t0\t0\t100\t00000000\t0\t0\t0
+";
+        let routes = parse_ipv4(ipv4);
+        assert_eq!(routes.len(), 2);
+        assert_eq!(routes[0].gateway, "192.168.1.1");
+        assert_eq!(routes[0].interface_name, "wlan0");
+        assert_eq!(routes[0].metric, 600);
+        assert_eq!(routes[1].gateway, "10.0.0.1");
+
+        let ipv6 = "00000000000000000000000000000000 00 00000000000000000000000000000000 00 fe800000000000000000000000000001 00000400 00000001 00000000 00450003    eth0
+fe800000000000000000000000000000 40 00000000000000000000000000000000 00 00000000000000000000000000000000 00000100 00000001 00000000 00000001    eth0
+00000000000000000000000000000000 00 00000000000000000000000000000000 00 00000000000000000000000000000000 ffffffff 00000001 00000000 00200200       lo
+";
+        let routes = parse_ipv6(ipv6);
+        assert_eq!(routes.len(), 1);
+        assert_eq!(routes[0].gateway, "fe80::1");
+        assert_eq!(routes[0].metric, 1024);
+    }
+
+    #[test]
+    fn gateway_state() {
+        let arp = "IP address       HW type     Flags       HW address            Mask     Device
+192.168.1.1      0x1         0x2         aa:bb:cc:dd:ee:ff     *        wlan0
+10.0.0.1         0x1         0x0         00:00:00:00:00:00     *        eth0
+";
+        assert_eq!(
+            neighbor_state(arp, "192.168.1.1", "wlan0"),
+            NeighborState::Resolved
+        );
+        assert_eq!(
+            neighbor_state(arp, "10.0.0.1", "eth0"),
+            NeighborState::Incomplete
+        );
+        assert_eq!(
+            neighbor_state(arp, "192.168.1.1", "eth0"),
+            NeighborState::Missing
+        );
+        assert_eq!(
+            neighbor_state(arp, "fe80::1", "eth0"),
+            NeighborState::Unspecified
+        );
+    }
+}
@@ -0,0 +1,151 @@
+/*
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/.
+ */
+//! Link kind reader using rtnetlink (RTM_GETLINK).
+//! Virtual interfaces such as veth pairs or dummies have neither a device nor a DEVTYPE in sysfs,
+//! but the kernel still names the kind of link that created them.
+
+use rustix::fd::AsFd;
+
+/// Netlink message types (linux/include/uapi/linux/netlink.h, rtnetlink.h)
+const NLMSG_ERROR: u16 = 0x02;
+const RTM_NEWLINK: u16 = 16;
+const RTM_GETLINK: u16 = 18;
+
+const NLM_F_REQUEST: u16 = 0x01;
+
+/// Sizes of struct nlmsghdr and struct ifinfomsg
+const NLMSG_HDRLEN: usize = 16;
+const IFINFOMSG_LEN: usize = 16;
+
+/// Link attributes (linux/include/uapi/linux/if_link.h)
+const IFLA_LINKINFO: u16 = 18;
+const IFLA_INFO_KIND: u16 = 1;
+
+/// The nested and byte order flags share the attribute type field
+const NLA_TYPE_MASK: u16 = 0x3fff;
+
+/// Reads the kind of link an interface is (e.g. "veth", "dummy", "bond"), `None` for physical
+/// interfaces, which have no kind.
+pub fn link_kind(ifindex: u32) -> anyhow::Result<Option<String>> {
+    let socket = nix::sys::socket::socket(
+        nix::sys::socket::AddressFamily::Netlink,
+        nix::sys::socket::SockType::Raw,
+        nix::sys::socket::SockFlag::SOCK_CLOEXEC,
+        nix::sys::socket::SockProtocol::NetlinkRoute,
+    )?;
+
+    let mut request = [0u8; NLMSG_HDRLEN + IFINFOMSG_LEN];
+    request[0..4].copy_from_slice(&((NLMSG_HDRLEN + IFINFOMSG_LEN) as u32).to_ne_bytes());
+    request[4..6].copy_from_slice(&RTM_GETLINK.to_ne_bytes());
+    request[6..8].copy_from_slice(&NLM_F_REQUEST.to_ne_bytes());
+    // ifi_family is AF_UNSPEC, only the index selects the link
+    request[NLMSG_HDRLEN + 4..NLMSG_HDRLEN + 8].copy_from_slice(&ifindex.to_ne_bytes());
+    // Without a destination the message goes to the kernel
+    rustix::io::write(socket.as_fd(), &request)?;
+
+    // Large enough for the statistics and VF attributes that come along with the link info
+    let mut reply = vec![0u8; 32 * 1024];
+    let len = rustix::io::read(socket.as_fd(), &mut reply)?;
+    parse_link_kind(&reply[..len])
+}
+
+/// Finds IFLA_INFO_KIND in an RTM_NEWLINK reply.
+fn parse_link_kind(reply: &[u8]) -> anyhow::Result<Option<String>> {
+    let header = reply
+        .get(..NLMSG_HDRLEN)
+        .ok_or_else(|| anyhow::anyhow!("truncated netlink reply"))?;
+    let len = u32::from_ne_bytes(header[0..4].try_into()?) as usize;
+    let r#type = u16::from_ne_bytes(header[4..6].try_into()?);
+    let message = reply
+        .get(..len)
+        .ok_or_else(|| anyhow::anyhow!("truncated netlink reply"))?;
+    match r#type {
+        RTM_NEWLINK => {}
+        NLMSG_ERROR => {
+            let errno = message
+                .get(NLMSG_HDRLEN..NLMSG_HDRLEN + 4)
+                .and_then(|errno| errno.try_into().ok())
+                .map_or(0, i32::from_ne_bytes);
+            anyhow::bail!(std::io::Error::from_raw_os_error(-errno));
+        }
+        _ => anyhow::bail!("unexpected netlink message type {}", r#type),
+    }
+    let attributes = message
+        .get(NLMSG_HDRLEN + IFINFOMSG_LEN..)
+        .unwrap_or_default();
+    Ok(attribute(attributes, IFLA_LINKINFO)
+        .and_then(|linkinfo| attribute(linkinfo, IFLA_INFO_KIND))
+        .map(|kind| {
+            let kind = kind.split(|b| *b == 0).next().unwrap_or_default();
+            String::from_utf8_lossy(kind).into_owned()
+        }))
+}
+
+/// Returns the payload of the first attribute of a type in a run of attributes.
+fn attribute(mut attributes: &[u8], r#type: u16) -> Option<&[u8]> {
+    while attributes.len() >= 4 {
+        let len = u16::from_ne_bytes([attributes[0], attributes[1]]) as usize;
+        if len < 4 || len > attributes.len() {
+            return None;
+        }
+        if u16::from_ne_bytes([attributes[2], attributes[3]]) & NLA_TYPE_MASK == r#type {
+            return Some(&attributes[4..len]);
+        }
+        // Attributes are padded to 4 bytes
+        attributes = attributes
+            .get(len.next_multiple_of(4)..)
+            .unwrap_or_default();
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn attr(r#type: u16, payload: &[u8]) -> Vec<u8> {
+        let mut attr = Vec::new();
+        attr.extend(((4 + payload.len()) as u16).to_ne_bytes());
+        attr.extend(r#type.to_ne_bytes());
+        attr.extend(payload);
+        attr.resize(attr.len().next_multiple_of(4), 0);
+        attr
+    }
+
+    fn message(r#type: u16, payload: &[u8]) -> Vec<u8> {
+        let mut message = Vec::new();
+        message.extend(((NLMSG_HDRLEN + payload.len()) as u32).to_ne_bytes());
+        message.extend(r#type.to_ne_bytes());
+        message.extend([0; 10]);
+        message.extend(payload);
+        message
+    }
+
+    #[test]
+    fn link_kinds() -> anyhow::Result<()> {
+        // IFLA_IFNAME, then IFLA_LINKINFO (flagged nested) holding IFLA_INFO_KIND
+        let mut link = vec![0u8; IFINFOMSG_LEN];
+        link.extend(attr(3, b"veth0\0"));
+        link.extend(attr(
+            IFLA_LINKINFO | 0x8000,
+            &[attr(IFLA_INFO_KIND, b"veth\0"), attr(2, &[0; 8])].concat(),
+        ));
+        assert_eq!(
+            parse_link_kind(&message(RTM_NEWLINK, &link))?,
+            Some("veth".to_string())
+        );
+
+        // Physical interfaces have no link info
+        let mut link = vec![0u8; IFINFOMSG_LEN];
+        link.extend(attr(3, b"enp3s0\0"));
+        assert_eq!(parse_link_kind(&message(RTM_NEWLINK, &link))?, None);
+
+        // ENODEV for an interface that went away
+        let error = message(NLMSG_ERROR, &(-19i32).to_ne_bytes());
+        assert!(parse_link_kind(&error).is_err());
+        Ok(())
+    }
+}
@@ -0,0 +1,135 @@
+/*
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/.
+ */
+//! System-wide socket counts from the procfs socket tables.
+//! Only covers the network namespace monitord runs in.
+
+use std::io::BufRead;
+
+use super::SocketSummary;
+
+// TCP_* in include/net/tcp_states.h, UDP sockets reuse ESTABLISHED for connected sockets and CLOSE otherwise
+pub const TCP_ESTABLISHED: u8 = 0x01;
+pub const TCP_CLOSE: u8 = 0x07;
+pub const TCP_LISTEN: u8 = 0x0a;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Protocol {
+    Tcp,
+    Udp,
+}
+
+/// A single entry of a socket table
+#[derive(Debug, Clone, PartialEq)]
+pub struct Socket {
+    pub protocol: Protocol,
+    pub state: u8,
+    pub local_port: u16,
+    /// Inode of the socket, which the owning processes' fds link to (0 once the socket is orphaned, e.g. in TIME_WAIT)
+    pub inode: u64,
+}
+
+/// Reads every socket table, tables missing because of a disabled protocol (e.g. IPv6) are skipped.
+pub fn read_tables() -> Vec<Socket> {
+    let mut sockets = Vec::new();
+    for (table, protocol) in [
+        ("/proc/net/tcp", Protocol::Tcp),
+        ("/proc/net/tcp6", Protocol::Tcp),
+        ("/proc/net/udp", Protocol::Udp),
+        ("/proc/net/udp6", Protocol::Udp),
+    ] {
+        if let Ok(file) = std::fs::File::open(table) {
+            parse_table(std::io::BufReader::new(file), protocol, &mut sockets);
+        }
+    }
+    sockets
+}
+
+/// Counts the sockets of every table.
+pub fn read() -> SocketSummary {
+    summarize(&read_tables())
+}
+
+/// Adds the entries of a socket table. Lines look like
+/// "0: 0100007F:0277 00000000:0000 0A 00000000:00000000 00:00000000 00000000 0 0 20584 ...",
+/// with the state in hex in the fourth column and the inode in the tenth.
+fn parse_table(table: impl BufRead, protocol: Protocol, sockets: &mut Vec<Socket>) {
+    for line in table.lines().skip(1).map_while(Result::ok) {
+        let fields: Vec<_> = line.split_whitespace().collect();
+        let (Some(local), Some(state), Some(inode)) = (fields.get(1), fields.get(3), fields.get(9))
+        else {
+            continue;
+        };
+        let (Some(local_port), Ok(state), Ok(inode)) = (
+            local
+                .rsplit_once(':')
+                .and_then(|(_, port)| u16::from_str_radix(port, 16).ok()),
+            u8::from_str_radix(state, 16),
+            inode.parse::<u64>(),
+        ) else {
+            continue;
+        };
+        sockets.push(Socket {
+            protocol,
+            state,
+            local_port,
+            inode,
+        });
+    }
+}
+
+fn summarize(sockets: &[Socket]) -> SocketSummary {
+    let mut summary = SocketSummary::default();
+    for socket in sockets {
+        let count = match (socket.protocol, socket.state) {
+            (Protocol::Udp, _) => &mut summary.udp_sockets,
+            (Protocol::Tcp, TCP_ESTABLISHED) => &mut summary.tcp_established,
+            (Protocol::Tcp, 0x02) => &mut summary.tcp_syn_sent,
+            (Protocol::Tcp, 0x03) => &mut summary.tcp_syn_recv,
+            (Protocol::Tcp, 0x04) => &mut summary.tcp_fin_wait1,
+            (Protocol::Tcp, 0x05) => &mut summary.tcp_fin_wait2,
+            (Protocol::Tcp, 0x06) => &mut summary.tcp_time_wait,
+            (Protocol::Tcp, TCP_CLOSE) => &mut summary.tcp_close,
+            (Protocol::Tcp, 0x08) => &mut summary.tcp_close_wait,
+            (Protocol::Tcp, 0x09) => &mut summary.tcp_last_ack,
+            (Protocol::Tcp, TCP_LISTEN) => &mut summary.tcp_listen,
+            (Protocol::Tcp, 0x0b) => &mut summary.tcp_closing,
+            _ => continue,
+        };
+        *count += 1;
+    }
+    summary
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tcp_states() {
+        let table = "  sl  local_address rem_address   st tx_queue rx_queue tr tm->when retrnsmt   uid  timeout inode
+   0: 0100007F:0277 00000000:0000 0A 00000000:00000000 00:00000000 00000000     0        0 20584 1 0000000000000000 100 0 0 10 0
+   1: 0F02000A:0016 0202000A:C5A2 01 00000000:00000000 02:0009AB35 00000000     0        0 61428 4 0000000000000000 20 4 30 10 -1
+   2: 0F02000A:0050 0202000A:C5A4 06 00000000:00000000 03:00001716 00000000     0        0 0 3 0000000000000000
+   3: 0F02000A:0050 0202000A:C5A6 06 00000000:00000000 03:00000F25 00000000     0        0 0 3 0000000000000000
+";
+        let mut sockets = Vec::new();
+        parse_table(table.as_bytes(), Protocol::Tcp, &mut sockets);
+        assert_eq!(
+            sockets[0],
+            Socket {
+                protocol: Protocol::Tcp,
+                state: TCP_LISTEN,
+                local_port: 631,
+                inode: 20584,
+            }
+        );
+        let summary = summarize(&sockets);
+        assert_eq!(summary.tcp_listen, 1);
+        assert_eq!(summary.tcp_established, 1);
+        assert_eq!(summary.tcp_time_wait, 2);
+        assert_eq!(summary.tcp_close_wait, 0);
+    }
+}
@@ -51,8 +51,10 @@ impl WifiReader {
             ssid: interface.ssid,
             signal_strength_dbm: station.signal_strength as i32,
             frequency_mhz: interface.frequency,
-            link_speed_up_mbps: station.link_speed_up,
-            link_speed_down_mbps: station.link_speed_down,
+            // nl80211 reports bitrates in units of 100 kbit/s
+            link_speed_up_mbps: station.link_speed_up / 10,
+            link_speed_down_mbps: station.link_speed_down / 10,
+            bssid: station.bssid,
         })
     }
 
@@ -167,6 +169,16 @@ impl WifiReader {
             let msg = msg?;
             if let NlPayload::Payload(payload) = msg.nl_payload() {
                 for attr in payload.attrs().iter() {
+                    // The station of a managed interface is the access point it's associated with
+                    if *attr.nla_type().nla_type() == NL80211_ATTR_MAC {
+                        station.get_or_insert_default().bssid = attr
+                            .nla_payload()
+                            .as_ref()
+                            .iter()
+                            .map(|byte| format!("{byte:02x}"))
+                            .collect::<Vec<_>>()
+                            .join(":");
+                    }
                     // Look for the NL80211_ATTR_STA_INFO attribute, which contains station information in nested format
                     if *attr.nla_type().nla_type() == NL80211_ATTR_STA_INFO
                         && let Ok(handle) = attr.get_attr_handle::<Attr>()
@@ -205,14 +217,9 @@ impl WifiReader {
                                         }
                                     }
                                 }
-                                NL80211_STA_INFO_SIGNAL_AVG => {
-                                    station
-                                        .get_or_insert(StationInfo {
-                                            link_speed_up: 0,
-                                            link_speed_down: 0,
-                                            signal_strength: 0,
-                                        })
-                                        .signal_strength = buf[0] as i8;
+                                // The average comes after the last signal, so it wins when the driver reports both
+                                NL80211_STA_INFO_SIGNAL | NL80211_STA_INFO_SIGNAL_AVG => {
+                                    station.get_or_insert_default().signal_strength = buf[0] as i8;
                                 }
                                 _ => {}
                             }
@@ -240,6 +247,7 @@ struct StationInfo {
     link_speed_up: u32,
     link_speed_down: u32,
     signal_strength: i8,
+    bssid: String,
 }
 
 // Command and attribute identifier data types as defined in the NetLink protocol specs. Command identifiers are unsigned 8-bit integers, attribute identifiers are unsigned 16-bit integers.
@@ -253,10 +261,12 @@ const NL80211_CMD_GET_STATION: u8 = 17;
 
 const NL80211_ATTR_IFINDEX: u16 = 3;
 const NL80211_ATTR_IFTYPE: u16 = 5;
+const NL80211_ATTR_MAC: u16 = 6;
 const NL80211_ATTR_STA_INFO: u16 = 21;
 const NL80211_ATTR_WIPHY_FREQ: u16 = 38;
 const NL80211_ATTR_SSID: u16 = 52;
 
+const NL80211_STA_INFO_SIGNAL: u16 = 7;
 const NL80211_STA_INFO_TX_BITRATE: u16 = 8;
 const NL80211_STA_INFO_SIGNAL_AVG: u16 = 13;
 const NL80211_STA_INFO_RX_BITRATE: u16 = 14;
@@ -0,0 +1,175 @@
+/*
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/.
+ */
+
+//! Cgroups of processes, and the container they run in as far as the cgroup path tells.
+//! Container runtimes name the cgroups they create after the container id, with the systemd
+//! cgroup driver wrapped in a "<runtime>-<id>.scope" unit, so the id can be picked out of the path
+//! without talking to the runtime.
+
+/// Prefixes container runtimes put before the container id in systemd scope names
+const RUNTIME_PREFIXES: &[&str] = &["docker-", "cri-containerd-", "crio-", "libpod-"];
+
+/// Reads the cgroup path of a process, None when the file can't be read (the process exited).
+pub fn read(pid: i32) -> Option<String> {
+    parse(&std::fs::read_to_string(format!("/proc/{pid}/cgroup")).ok()?)
+}
+
+/// Picks the path out of /proc/<pid>/cgroup, "hierarchy-ID:controllers:path" per line. The unified
+/// hierarchy ("0::/path") is preferred, on cgroup v1 hosts the one systemd manages.
+fn parse(cgroup: &str) -> Option<String> {
+    let entries: Vec<(&str, &str, &str)> = cgroup
+        .lines()
+        .filter_map(|line| {
+            let (id, rest) = line.split_once(':')?;
+            let (controllers, path) = rest.split_once(':')?;
+            Some((id, controllers, path))
+        })
+        .collect();
+    entries
+        .iter()
+        .find(|(id, controllers, _)| *id == "0" && controllers.is_empty())
+        .or_else(|| {
+            entries
+                .iter()
+                .find(|(_, controllers, _)| *controllers == "name=systemd")
+        })
+        .or_else(|| entries.first())
+        .map(|(_, _, path)| path.to_string())
+}
+
+/// Derives what a process runs in from its cgroup path: the container id for docker, containerd,
+/// cri-o and podman, the pod UID for Kubernetes cgroups above the container level, and the systemd
+/// unit otherwise.
+pub fn container_id(path: &str) -> Option<String> {
+    let components: Vec<&str> = path.split('/').filter(|c| !c.is_empty()).collect();
+    components
+        .iter()
+        .rev()
+        .find_map(|component| {
+            let name = component.strip_suffix(".scope").unwrap_or(component);
+            let id = RUNTIME_PREFIXES
+                .iter()
+                .find_map(|prefix| name.strip_prefix(prefix))
+                .unwrap_or(name);
+            (id.len() == 64 && id.bytes().all(|c| c.is_ascii_hexdigit())).then(|| id.to_string())
+        })
+        .or_else(|| {
+            components
+                .iter()
+                .rev()
+                .find_map(|component| pod_uid(component))
+        })
+        .or_else(|| {
+            components
+                .iter()
+                .rev()
+                .find(|component| component.ends_with(".service") || component.ends_with(".scope"))
+                .map(|unit| unit.to_string())
+        })
+}
+
+/// Finds the pod UID in a Kubernetes pod cgroup, "pod<uid>" with the cgroupfs driver and
+/// "kubepods-<qos>-pod<uid with underscores>.slice" with the systemd one.
+fn pod_uid(component: &str) -> Option<String> {
+    let name = component.strip_suffix(".slice").unwrap_or(component);
+    let uid = match name.strip_prefix("pod") {
+        Some(uid) => uid,
+        None if name.starts_with("kubepods") => name.rsplit_once("-pod")?.1,
+        None => return None,
+    };
+    let uid = uid.replace('_', "-");
+    (uid.len() == 36 && uid.bytes().all(|c| c.is_ascii_hexdigit() || c == b'-')).then_some(uid)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ID: &str = "4f1d6b0e6c3a2b9f8e7d6c5b4a39281706f5e4d3c2b1a09f8e7d6c5b4a392817";
+
+    #[test]
+    fn cgroup_paths() {
+        assert_eq!(
+            parse("0::/system.slice/sshd.service\n").as_deref(),
+            Some("/system.slice/sshd.service")
+        );
+        // The unified hierarchy of a hybrid setup wins over the v1 ones, which are only used without it
+        assert_eq!(
+            parse(&format!(
+                "12:memory:/docker/{ID}\n1:name=systemd:/docker/{ID}\n0::/\n"
+            ))
+            .as_deref(),
+            Some("/")
+        );
+        assert_eq!(
+            parse(&format!(
+                "12:memory:/docker/{ID}\n1:name=systemd:/docker/{ID}\n"
+            ))
+            .as_deref(),
+            Some(format!("/docker/{ID}").as_str())
+        );
+        assert_eq!(parse(""), None);
+    }
+
+    #[test]
+    fn containers() {
+        let id = Some(ID.to_string());
+        // docker with the systemd and cgroupfs drivers
+        assert_eq!(
+            container_id(&format!("/system.slice/docker-{ID}.scope")),
+            id
+        );
+        assert_eq!(container_id(&format!("/docker/{ID}")), id);
+        // containerd and cri-o under Kubernetes
+        assert_eq!(
+            container_id(&format!(
+                "/kubepods.slice/kubepods-burstable.slice/kubepods-burstable-pod8f2a7c1e_3b4d_4e5f_9a6b_7c8d9e0f1a2b.slice/cri-containerd-{ID}.scope"
+            )),
+            id
+        );
+        assert_eq!(
+            container_id(&format!(
+                "/kubepods.slice/kubepods-besteffort.slice/kubepods-besteffort-pod8f2a7c1e_3b4d_4e5f_9a6b_7c8d9e0f1a2b.slice/crio-{ID}.scope"
+            )),
+            id
+        );
+        assert_eq!(
+            container_id(&format!(
+                "/kubepods/burstable/pod8f2a7c1e-3b4d-4e5f-9a6b-7c8d9e0f1a2b/{ID}"
+            )),
+            id
+        );
+        // rootless podman
+        assert_eq!(
+            container_id(&format!(
+                "/user.slice/user-1000.slice/user@1000.service/user.slice/libpod-{ID}.scope/container"
+            )),
+            id
+        );
+        // cri-o's conmon runs next to the container, in the pod but not in a container
+        assert_eq!(
+            container_id(&format!(
+                "/kubepods.slice/kubepods-besteffort.slice/kubepods-besteffort-pod8f2a7c1e_3b4d_4e5f_9a6b_7c8d9e0f1a2b.slice/crio-conmon-{ID}.scope"
+            ))
+            .as_deref(),
+            Some("8f2a7c1e-3b4d-4e5f-9a6b-7c8d9e0f1a2b")
+        );
+        // systemd units
+        assert_eq!(
+            container_id("/system.slice/sshd.service").as_deref(),
+            Some("sshd.service")
+        );
+        assert_eq!(
+            container_id(
+                "/user.slice/user-1000.slice/user@1000.service/app.slice/app-org.gnome.Terminal.slice/vte-spawn-1a2b.scope"
+            )
+            .as_deref(),
+            Some("vte-spawn-1a2b.scope")
+        );
+        assert_eq!(container_id("/init.scope").as_deref(), Some("init.scope"));
+        assert_eq!(container_id("/"), None);
+    }
+}
@@ -0,0 +1,118 @@
+/*
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/.
+ */
+
+//! Environment variables of processes from /proc/<pid>/environ, with the values of anything that
+//! looks like a credential redacted.
+//! The file holds the environment the process was started with, changes it made afterwards
+//! aren't visible.
+
+use std::collections::HashMap;
+
+use super::Config;
+use crate::collector::helpers::glob_matches;
+
+/// Variables whose values are always redacted, the config can add more but not remove these
+const REDACTED: &[&str] = &[
+    "*TOKEN*",
+    "*SECRET*",
+    "*PASSWORD*",
+    "*PASSWD*",
+    "*CREDENTIAL*",
+    "*API_KEY*",
+    "*ACCESS_KEY*",
+    "*PRIVATE_KEY*",
+];
+
+/// Size cap when the config leaves it at 0
+const DEFAULT_MAX_BYTES: usize = 16 * 1024;
+
+/// Whether the environment of a process is collected. An empty list selects every process.
+pub fn is_selected(config: &Config, pid: u32, name: &str) -> bool {
+    config.environment_processes.is_empty()
+        || super::is_listed(&config.environment_processes, pid, name)
+}
+
+/// Reads the environment of a process, empty when the file can't be read (another user's
+/// process when not running as root, or the process exited).
+pub fn read(config: &Config, pid: i32) -> HashMap<String, String> {
+    let Ok(environ) = std::fs::read(format!("/proc/{pid}/environ")) else {
+        return HashMap::new();
+    };
+    let max_bytes = match config.environment_max_bytes {
+        0 => DEFAULT_MAX_BYTES,
+        max_bytes => max_bytes as usize,
+    };
+    parse(&environ, max_bytes, &config.redact_environment)
+}
+
+/// Splits the NUL separated "KEY=value" entries, stopping at the first one that would take the
+/// environment over the size cap.
+fn parse(environ: &[u8], max_bytes: usize, redact: &[String]) -> HashMap<String, String> {
+    let mut environment = HashMap::new();
+    let mut size = 0;
+    for entry in environ
+        .split(|&byte| byte == 0)
+        .filter(|entry| !entry.is_empty())
+    {
+        size += entry.len();
+        if size > max_bytes {
+            break;
+        }
+        let entry = String::from_utf8_lossy(entry);
+        let Some((key, value)) = entry.split_once('=') else {
+            continue;
+        };
+        let value = if is_redacted(key, redact) {
+            "<redacted>".to_string()
+        } else {
+            value.to_string()
+        };
+        environment.insert(key.to_string(), value);
+    }
+    environment
+}
+
+/// Matches the key against the redaction patterns, ignoring case.
+fn is_redacted(key: &str, redact: &[String]) -> bool {
+    let key = key.to_ascii_uppercase();
+    REDACTED
+        .iter()
+        .copied()
+        .chain(redact.iter().map(String::as_str))
+        .any(|pattern| glob_matches(&pattern.to_ascii_uppercase(), &key))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn redaction() {
+        let environ = b"HOME=/home/user\0GITHUB_TOKEN=ghp_abc\0db_password=hunter2\0\
+            SESSION_ID=42\0EMPTY=\0LANG=C.UTF-8\0";
+        let environment = parse(environ, usize::MAX, &["SESSION_*".to_string()]);
+        assert_eq!(environment.len(), 6);
+        assert_eq!(environment["HOME"], "/home/user");
+        assert_eq!(environment["GITHUB_TOKEN"], "<redacted>");
+        assert_eq!(environment["db_password"], "<redacted>");
+        assert_eq!(environment["SESSION_ID"], "<redacted>");
+        assert_eq!(environment["EMPTY"], "");
+
+        // The cap drops the entries that don't fit
+        let environment = parse(environ, 40, &[]);
+        assert_eq!(environment.len(), 2);
+        assert!(environment.contains_key("GITHUB_TOKEN"));
+
+        let config = Config {
+            environment_processes: vec!["1234".to_string(), "postgres*".to_string()],
+            ..Default::default()
+        };
+        assert!(is_selected(&config, 1234, "bash"));
+        assert!(is_selected(&config, 99, "postgres: writer"));
+        assert!(!is_selected(&config, 99, "bash"));
+        assert!(is_selected(&Config::default(), 99, "bash"));
+    }
+}
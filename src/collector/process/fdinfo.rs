@@ -0,0 +1,365 @@
+/*
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/.
+ */
+
+//! Per-client GPU usage from the DRM fdinfo interface.
+//!
+//! Every open DRM file description exposes `drm-*` keys in `/proc/<pid>/fdinfo/<fd>`, following
+//! the kernel's drm-usage-stats format. amdgpu, i915, xe and most other DRM drivers implement it,
+//! so usage is tracked the same way for every vendor: counters are keyed by the device (`drm-pdev`)
+//! and the client on that device (`drm-client-id`), and diffed between samples.
+
+use std::collections::HashMap;
+use std::time::Instant;
+
+use rustix::fd::AsFd;
+use rustix::fs::{Mode, OFlags};
+
+use super::GpuUsage;
+use crate::collector::helpers::*;
+
+/// A DRM client is only unique within the device it was opened on.
+type ClientKey = (String, u32);
+
+/// Tracks the fdinfo counters of every DRM client between samples.
+#[derive(Default)]
+pub struct Clients {
+    previous: HashMap<ClientKey, DrmFdinfo>,
+    current: HashMap<ClientKey, DrmFdinfo>,
+}
+
+/// Usage of a single DRM client since the previous sample.
+pub struct ClientUsage {
+    /// PCI address of the device the client was opened on
+    pub pdev: String,
+    /// Processes sharing the client, e.g. after a fork or when the fd was passed over a socket
+    pub pids: Vec<u32>,
+    pub usage: GpuUsage,
+}
+
+impl Clients {
+    /// Records the fdinfo of an open file descriptor, ignoring anything that isn't a DRM client.
+    pub fn observe(&mut self, pid: u32, fd: u32) {
+        let Ok(fdinfo) = read(pid, fd) else {
+            return;
+        };
+        let (Some(pdev), Some(client_id)) = (fdinfo.pdev.clone(), fdinfo.client_id) else {
+            return;
+        };
+        let pids = &mut self.current.entry((pdev, client_id)).or_insert(fdinfo).pids;
+        if !pids.contains(&pid) {
+            pids.push(pid);
+        }
+    }
+
+    /// Diffs the clients observed since the last call against the previous sample.
+    ///
+    /// Clients seen for the first time have nothing to diff against and are skipped.
+    pub fn finish(&mut self) -> Vec<ClientUsage> {
+        let usages = self
+            .current
+            .iter()
+            .filter_map(|(key, cur)| {
+                let usage = diff(self.previous.get(key)?, cur)?;
+                Some(ClientUsage {
+                    pdev: key.0.clone(),
+                    pids: cur.pids.clone(),
+                    usage,
+                })
+            })
+            .collect();
+        self.previous = std::mem::take(&mut self.current);
+        usages
+    }
+}
+
+#[derive(Debug, Clone)]
+struct DrmFdinfo {
+    timestamp: Instant,
+    driver: Option<String>,
+    client_id: Option<u32>,
+    pdev: Option<String>,
+    // busy time of each engine class in nanoseconds
+    times: HashMap<String, u64>,
+    // number of engines of each class, the busy time is summed over all of them
+    capacity: HashMap<String, u64>,
+    cycles: HashMap<String, u64>,
+    total_cycles: HashMap<String, u64>,
+    maxfreq: HashMap<String, u64>,
+    shared_mem: HashMap<String, u64>,
+    resident_mem: HashMap<String, u64>,
+
+    pids: Vec<u32>,
+}
+
+impl Default for DrmFdinfo {
+    fn default() -> Self {
+        Self {
+            timestamp: Instant::now(),
+            driver: None,
+            client_id: None,
+            pdev: None,
+            times: HashMap::new(),
+            capacity: HashMap::new(),
+            cycles: HashMap::new(),
+            total_cycles: HashMap::new(),
+            maxfreq: HashMap::new(),
+            shared_mem: HashMap::new(),
+            resident_mem: HashMap::new(),
+            pids: Vec::new(),
+        }
+    }
+}
+
+fn read(pid: u32, fd: u32) -> anyhow::Result<DrmFdinfo> {
+    // open the fdinfo file. we can safely assume that the pid is not reused because the collect function still has an open pidfd.
+    let path = format!("/proc/{}/fdinfo/{}", pid, fd);
+    let file = rustix::fs::open(path, OFlags::RDONLY | OFlags::CLOEXEC, Mode::empty())?;
+
+    let contents = sysfs::read_string(file.as_fd())
+        .ok_or_else(|| anyhow::anyhow!("failed to read fdinfo file"))?;
+
+    let mut fdinfo = parse(&contents);
+    if fdinfo.driver.is_none() {
+        anyhow::bail!("not a DRM file descriptor");
+    }
+    fdinfo.pids.push(pid);
+    Ok(fdinfo)
+}
+
+fn parse(contents: &str) -> DrmFdinfo {
+    let mut fdinfo = DrmFdinfo::default();
+
+    for line in contents.lines() {
+        let Some((key, value)) = line.split_once(':') else {
+            continue;
+        };
+        let value = value.trim();
+        if key == "drm-driver" {
+            fdinfo.driver = Some(value.to_string());
+        } else if key == "drm-client-id" {
+            fdinfo.client_id = value.parse().ok();
+        } else if key == "drm-pdev" {
+            fdinfo.pdev = Some(value.to_string());
+        } else if let Some(engine) = key.strip_prefix("drm-engine-capacity-") {
+            fdinfo
+                .capacity
+                .insert(engine.to_string(), value.parse().unwrap_or(1));
+        } else if let Some(engine) = key.strip_prefix("drm-engine-") {
+            fdinfo.times.insert(
+                engine.to_string(),
+                value
+                    .strip_suffix(" ns")
+                    .unwrap_or(value)
+                    .parse()
+                    .unwrap_or(0),
+            );
+        } else if let Some(engine) = key.strip_prefix("drm-cycles-") {
+            fdinfo
+                .cycles
+                .insert(engine.to_string(), value.parse().unwrap_or(0));
+        } else if let Some(engine) = key.strip_prefix("drm-total-cycles-") {
+            fdinfo
+                .total_cycles
+                .insert(engine.to_string(), value.parse().unwrap_or(0));
+        } else if let Some(engine) = key.strip_prefix("drm-maxfreq-") {
+            let (freq, unit) = value.split_once(' ').unwrap_or((value, "Hz"));
+            let multiplier = match unit {
+                "Hz" => 1,
+                "KHz" => 1000,
+                "MHz" => 1000 * 1000,
+                _ => continue,
+            };
+            fdinfo.maxfreq.insert(
+                engine.to_string(),
+                freq.parse::<u64>().unwrap_or(0) * multiplier,
+            );
+        } else if let Some(region) = key.strip_prefix("drm-shared-") {
+            if let Some(bytes) = parse_size(value) {
+                fdinfo.shared_mem.insert(region.to_string(), bytes);
+            }
+        } else if let Some(region) = key.strip_prefix("drm-resident-") {
+            if let Some(bytes) = parse_size(value) {
+                fdinfo.resident_mem.insert(region.to_string(), bytes);
+            }
+        } else if let Some(region) = key.strip_prefix("drm-memory-") {
+            // legacy amdgpu name for the resident memory, only used when drm-resident is missing
+            if let Some(bytes) = parse_size(value) {
+                fdinfo
+                    .resident_mem
+                    .entry(region.to_string())
+                    .or_insert(bytes);
+            }
+        }
+    }
+
+    fdinfo
+}
+
+/// Parses a memory size, which the kernel prints in KiB or MiB (bytes when it has no unit).
+fn parse_size(value: &str) -> Option<u64> {
+    let (bytes, unit) = value.split_once(' ').unwrap_or((value, ""));
+    let multiplier = match unit {
+        "" => 1,
+        "KiB" => 1024,
+        "MiB" => 1024 * 1024,
+        _ => return None,
+    };
+    Some(bytes.parse::<u64>().ok()? * multiplier)
+}
+
+fn diff(prev: &DrmFdinfo, cur: &DrmFdinfo) -> Option<GpuUsage> {
+    let mut result = GpuUsage::default();
+    for (region, &cur_resident) in cur.resident_mem.iter() {
+        // not every driver reports shared memory, in which case all of it is private
+        let cur_shared = cur.shared_mem.get(region).copied().unwrap_or(0);
+        if region.starts_with("vram") {
+            result.vram_usage += cur_resident.saturating_sub(cur_shared);
+        } else if region.contains("system")
+            || region.contains("cpu")
+            || region == "gtt"
+            || region == "memory"
+        {
+            result.system_usage += cur_resident.saturating_sub(cur_shared);
+        }
+    }
+    let elapsed_ns = cur.timestamp.duration_since(prev.timestamp).as_nanos() as u64;
+    if !cur.cycles.is_empty() {
+        for (engine, &cur_cycles) in cur.cycles.iter() {
+            let Some(&prev_cycles) = prev.cycles.get(engine) else {
+                continue;
+            };
+
+            let cycle_diff = cur_cycles.saturating_sub(prev_cycles);
+
+            // total cycles is the GPU timestamp the client's cycles are measured against (xe)
+            if let Some(&cur_total_cycles) = cur.total_cycles.get(engine)
+                && let Some(&prev_total_cycles) = prev.total_cycles.get(engine)
+            {
+                let total_cycle_diff = cur_total_cycles.saturating_sub(prev_total_cycles);
+                if total_cycle_diff > 0 {
+                    result
+                        .engines
+                        .insert(engine.clone(), percent(cycle_diff, total_cycle_diff));
+                }
+            } else if let Some(&max_freq) = cur.maxfreq.get(engine) {
+                // otherwise the engine could have run max_freq cycles every second
+                let max_cycles = (max_freq as u128 * elapsed_ns as u128 / 1_000_000_000) as u64;
+                if max_cycles > 0 {
+                    result
+                        .engines
+                        .insert(engine.clone(), percent(cycle_diff, max_cycles));
+                }
+            }
+        }
+    } else if !cur.times.is_empty() {
+        for (engine, &cur_time) in cur.times.iter() {
+            let Some(&prev_time) = prev.times.get(engine) else {
+                continue;
+            };
+            let time_diff = cur_time.saturating_sub(prev_time);
+            let capacity = cur.capacity.get(engine).copied().unwrap_or(1).max(1);
+            if elapsed_ns > 0 {
+                result
+                    .engines
+                    .insert(engine.clone(), percent(time_diff, elapsed_ns * capacity));
+            }
+        }
+    } else if result.vram_usage == 0 && result.system_usage == 0 {
+        return None;
+    }
+
+    Some(result)
+}
+
+/// Percentage of `part` in `whole`, clamped to 100 since the counters aren't sampled atomically.
+fn percent(part: u64, whole: u64) -> u32 {
+    (part as u128 * 100 / whole as u128).min(100) as u32
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn engine_time() {
+        let sample = |render_ns: u64, video_ns: u64| {
+            parse(&format!(
+                "pos:\t0\nflags:\t02100002\ndrm-driver:\ti915\ndrm-client-id:\t42\n\
+                 drm-pdev:\t0000:00:02.0\ndrm-engine-render:\t{render_ns} ns\n\
+                 drm-engine-video:\t{video_ns} ns\ndrm-engine-capacity-video:\t2\n"
+            ))
+        };
+        let prev = sample(1_000_000, 0);
+        let mut cur = sample(251_000_000, 500_000_000);
+        cur.timestamp = prev.timestamp + std::time::Duration::from_millis(500);
+
+        assert_eq!(cur.driver.as_deref(), Some("i915"));
+        assert_eq!(cur.client_id, Some(42));
+        assert_eq!(cur.pdev.as_deref(), Some("0000:00:02.0"));
+        assert!(!cur.times.contains_key("capacity-video"));
+
+        let usage = diff(&prev, &cur).unwrap();
+        assert_eq!(usage.engines["render"], 50);
+        // both video engines were busy for half the interval
+        assert_eq!(usage.engines["video"], 50);
+    }
+
+    #[test]
+    fn engine_cycles() {
+        let sample = |cycles: u64, total: u64| {
+            parse(&format!(
+                "drm-driver:\txe\ndrm-client-id:\t7\ndrm-pdev:\t0000:03:00.0\n\
+                 drm-cycles-rcs:\t{cycles}\ndrm-total-cycles-rcs:\t{total}\n\
+                 drm-resident-vram0:\t2048 KiB\ndrm-shared-vram0:\t1024 KiB\n"
+            ))
+        };
+        let prev = sample(100, 1000);
+        let cur = sample(400, 2000);
+
+        let usage = diff(&prev, &cur).unwrap();
+        assert_eq!(usage.engines["rcs"], 30);
+        assert_eq!(usage.vram_usage, 1024 * 1024);
+    }
+
+    #[test]
+    fn legacy_memory() {
+        let fdinfo = |vram: &str| {
+            parse(&format!(
+                "drm-driver:\tamdgpu\ndrm-client-id:\t3\ndrm-pdev:\t0000:0b:00.0\n\
+                 {vram}drm-memory-gtt:\t512 KiB\ndrm-engine-gfx:\t1000 ns\n"
+            ))
+        };
+        // older amdgpu kernels only report drm-memory-*, and VRAM is absent on APUs without carve-out
+        let usage = diff(&fdinfo(""), &fdinfo("")).unwrap();
+        assert_eq!(usage.vram_usage, 0);
+        assert_eq!(usage.system_usage, 512 * 1024);
+
+        let usage = diff(&fdinfo(""), &fdinfo("drm-memory-vram:\t4 MiB\n")).unwrap();
+        assert_eq!(usage.vram_usage, 4 * 1024 * 1024);
+
+        // drm-resident takes precedence over the legacy key regardless of order
+        let usage = diff(
+            &fdinfo(""),
+            &fdinfo("drm-resident-vram:\t8 MiB\ndrm-memory-vram:\t4 MiB\n"),
+        )
+        .unwrap();
+        assert_eq!(usage.vram_usage, 8 * 1024 * 1024);
+    }
+
+    #[test]
+    fn counter_reset() {
+        // counters start over when the driver is reloaded
+        let prev = parse("drm-driver:\tamdgpu\ndrm-engine-gfx:\t900000000 ns\n");
+        let mut cur = parse("drm-driver:\tamdgpu\ndrm-engine-gfx:\t1000 ns\n");
+        cur.timestamp = prev.timestamp + std::time::Duration::from_secs(1);
+        assert_eq!(diff(&prev, &cur).unwrap().engines["gfx"], 0);
+
+        let prev = parse("drm-cycles-rcs:\t500\ndrm-total-cycles-rcs:\t9000\n");
+        let cur = parse("drm-cycles-rcs:\t10\ndrm-total-cycles-rcs:\t100\n");
+        // no elapsed GPU time to measure against, so nothing is reported until the next sample
+        assert!(!diff(&prev, &cur).unwrap().engines.contains_key("rcs"));
+    }
+}
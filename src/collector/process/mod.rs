@@ -4,21 +4,33 @@
  * file, You can obtain one at https://mozilla.org/MPL/2.0/.
  */
 
-use std::collections::HashMap;
+mod cgroup;
+mod environ;
+mod fdinfo;
+mod security;
+mod smaps;
+mod sockets;
+mod users;
 
-use rustix::fd::AsFd;
-use rustix::fs::{Mode, OFlags};
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
 
-use super::helpers::*;
+use crate::collector::helpers::glob_matches;
 
 #[doc(inline)]
 pub use crate::metrics::process::*;
 
 pub struct Collector {
     cpu_counters: HashMap<PidId, CpuCounters>,
-    prev_gpu_fdinfo: HashMap<u32, DrmFdinfo>,
+    /// When the CPU counters were read
+    cpu_sampled_at: Option<Instant>,
+    gpu_clients: fdinfo::Clients,
     disk_counters: HashMap<PidId, DiskCounters>,
     net_counters: HashMap<PidId, HashMap<String, NetUsage>>,
+    socket_owners: sockets::Owners,
+    executables: HashMap<PidId, Executable>,
+    users: users::Users,
+    rollups: smaps::Rollups,
 }
 
 impl Default for Collector {
@@ -32,9 +44,14 @@ impl Collector {
         tracing::info!("creating collector");
         Self {
             cpu_counters: HashMap::new(),
-            prev_gpu_fdinfo: HashMap::new(),
+            cpu_sampled_at: None,
+            gpu_clients: fdinfo::Clients::default(),
             disk_counters: HashMap::new(),
             net_counters: HashMap::new(),
+            socket_owners: sockets::Owners::default(),
+            executables: HashMap::new(),
+            users: users::Users::default(),
+            rollups: smaps::Rollups::default(),
         }
     }
 }
@@ -53,16 +70,24 @@ impl super::Collector for Collector {
         let mut snapshot = Snapshot::default();
 
         let mut cpu_counters = HashMap::new();
-        let mut cur_gpu_fdinfo = HashMap::new();
+        let now = Instant::now();
+        let elapsed = self
+            .cpu_sampled_at
+            .map(|sampled_at| now.duration_since(sampled_at));
+        let cpus = online_cpus();
         let mut disk_counters = HashMap::new();
         let mut net_counters: HashMap<PidId, HashMap<String, NetUsage>> = HashMap::new();
+        let mut executables = HashMap::new();
+        if config.identity {
+            self.users.begin(config.user_refresh_interval);
+        }
+        let scan_sockets = config.sockets && self.socket_owners.begin();
 
         for proc in procfs::process::all_processes()?.flatten() {
             let Ok(stat) = proc.stat() else {
                 continue;
             };
-            // Skip kernel threads
-            if stat.flags & 0x00200000 != 0 {
+            if !is_selected(config, stat.flags, stat.state) {
                 continue;
             }
             let Ok(status) = proc.status() else {
@@ -85,11 +110,10 @@ impl super::Collector for Collector {
                 };
 
                 if let Some(prev) = self.cpu_counters.get_mut(&pid_id) {
-                    let util = ((cur.utime - prev.utime) + (cur.stime - prev.stime)) as f64
-                        / procfs::ticks_per_second() as f64
-                        * 100.0;
+                    let ticks = (cur.utime - prev.utime) + (cur.stime - prev.stime);
+                    let util = ticks as f64 / procfs::ticks_per_second() as f64 * 100.0;
                     let mut affinity = Vec::new();
-                    if let Some(allowed) = status.cpus_allowed_list {
+                    if let Some(allowed) = &status.cpus_allowed_list {
                         for range in allowed {
                             for i in range.0..=range.1 {
                                 affinity.push(i)
@@ -101,6 +125,11 @@ impl super::Collector for Collector {
                         threads: stat.num_threads as u32,
                         nice: stat.nice as i32,
                         affinity,
+                        user_seconds: cur.utime as f64 / procfs::ticks_per_second() as f64,
+                        system_seconds: cur.stime as f64 / procfs::ticks_per_second() as f64,
+                        usage_normalized: elapsed
+                            .map(|elapsed| normalized_usage(ticks, elapsed, cpus))
+                            .unwrap_or_default(),
                     });
                 }
                 cpu_counters.insert(pid_id, cur);
@@ -118,28 +147,31 @@ impl super::Collector for Collector {
                 }
             }
 
-            if config.gpu_usage {
-                if let Ok(fdinfo) = proc.fd() {
-                    for fd in fdinfo.flatten() {
-                        let pid_id = PidId {
-                            pid: proc.pid as u32,
-                            timestamp: stat.starttime,
-                        };
-
-                        if let Ok(cur) = parse_fdinfo(pid_id, fd.fd as u32)
-                            && cur.driver.is_some()
-                            && let Some(client_id) = cur.client_id
-                            && !cur_gpu_fdinfo.contains_key(&client_id)
-                        {
-                            cur_gpu_fdinfo
-                                .entry(client_id)
-                                .or_insert(cur)
-                                .pids
-                                .push(proc.pid as u32);
-                        }
+            // Count of the open fds, or the size of the fd table when they can't be read
+            let mut open_files: Option<(u32, bool)> = None;
+            if (config.gpu_usage || scan_sockets)
+                && let Ok(fds) = proc.fd()
+            {
+                let mut count = 0;
+                for fd in fds.flatten() {
+                    count += 1;
+                    if config.gpu_usage {
+                        self.gpu_clients.observe(proc.pid as u32, fd.fd as u32);
+                    }
+                    if scan_sockets && let procfs::process::FDTarget::Socket(inode) = fd.target {
+                        self.socket_owners.observe(proc.pid as u32, inode);
                     }
                 }
+                open_files = Some((count, false));
+            }
+            if config.fd_counts && open_files.is_none() {
+                open_files = Some(match count_fds(proc.pid) {
+                    Some(count) => (count, false),
+                    None => (status.fdsize, true),
+                });
             }
+            let (open_files, open_files_estimated) =
+                open_files.filter(|_| config.fd_counts).unwrap_or_default();
 
             if config.disk_usage {
                 let usage = usage.get_or_insert_default();
@@ -148,15 +180,11 @@ impl super::Collector for Collector {
                     let cur = DiskCounters {
                         read_bytes: io.read_bytes,
                         write_bytes: io.write_bytes,
+                        taken_at: now,
                     };
 
-                    if let Some(prev) = self.disk_counters.get_mut(&pid_id) {
-                        usage.disk = Some(DiskUsage {
-                            read_bytes: cur.read_bytes - prev.read_bytes,
-                            read_total: cur.read_bytes,
-                            write_bytes: cur.write_bytes - prev.write_bytes,
-                            write_total: cur.write_bytes,
-                        })
+                    if let Some(prev) = self.disk_counters.get(&pid_id) {
+                        usage.disk = Some(disk_usage(prev, &cur));
                     }
                     disk_counters.insert(pid_id, cur);
                 };
@@ -224,83 +252,120 @@ impl super::Collector for Collector {
                 }
             }
 
+            if config.memory_breakdown
+                && let Some(detail) = self.rollups.read(
+                    config,
+                    pid_id,
+                    &stat.comm,
+                    status.vmrss.unwrap_or_default() * 1024,
+                )
+            {
+                usage.get_or_insert_default().memory_detail = Some(detail);
+            }
+
+            let executable = config.identity.then(|| {
+                let executable =
+                    Executable::refresh(self.executables.remove(&pid_id), &proc, &stat.comm);
+                executables.insert(pid_id, executable.clone());
+                executable
+            });
+
+            let cgroup_path = config.cgroup.then(|| cgroup::read(proc.pid)).flatten();
+
             snapshot.processes.insert(
                 proc.pid as u32,
                 Process {
-                    identity: config.identity.then(|| Identity {
+                    identity: executable.map(|executable| Identity {
                         pid: proc.pid as u32,
                         ppid: stat.ppid as u32,
-                        uid: proc.uid().unwrap_or(0),
+                        uid: status.euid,
                         gid: status.egid,
                         session: stat.session,
-                        name: stat.comm.clone(),
-                        exe: proc
-                            .exe()
-                            .map(|e| e.to_string_lossy().into_owned())
-                            .unwrap_or_default(),
-                        cmdline: proc
-                            .cmdline()
-                            .map(|c| c.into_iter().collect::<Vec<_>>().join(" "))
-                            .unwrap_or_default(),
+                        name: executable.name,
+                        exe: executable.exe,
+                        cmdline: executable.cmdline,
+                        exe_deleted: executable.exe_deleted,
+                        user: self.users.name(status.euid),
                     }),
-                    status: config
-                        .status
-                        .then(|| {
-                            // test
-                            use procfs::process::ProcState;
-                            stat.state()
-                                .map(|s| match s {
-                                    ProcState::Running => Status::Running as i32,
-                                    ProcState::Sleeping => Status::Sleeping as i32,
-                                    ProcState::Waiting => Status::DiskSleep as i32,
-                                    ProcState::Stopped => Status::Stopped as i32,
-                                    ProcState::Tracing => Status::Tracing as i32,
-                                    ProcState::Zombie => Status::Zombie as i32,
-                                    ProcState::Idle => Status::Idle as i32,
-                                    ProcState::Wakekill => Status::WakeKill as i32,
-                                    ProcState::Waking => Status::Waking as i32,
-                                    ProcState::Parked => Status::Parked as i32,
-                                    ProcState::Dead => Status::Dead as i32,
-                                })
-                                .ok()
-                        })
-                        .flatten()
-                        .unwrap_or(-1),
+                    status: if config.status {
+                        status_of(stat.state)
+                    } else {
+                        Status::Unknown
+                    } as i32,
+                    security: config.security.then(|| security::read(proc.pid, &status)),
+                    state: if config.status {
+                        stat.state.to_string()
+                    } else {
+                        String::new()
+                    },
                     start_time: config
                         .start_time
                         .then(|| stat.starttime)
                         .unwrap_or_default(),
                     usage,
+                    threads: status.threads as u32,
+                    open_files,
+                    open_files_estimated,
+                    container_id: cgroup_path.as_deref().and_then(cgroup::container_id),
+                    cgroup_path,
+                    nice: stat.nice as i32,
+                    io_priority: if config.io_priority {
+                        io_priority(proc.pid, stat.nice as i32)
+                    } else {
+                        None
+                    },
+                    environment: if config.environment
+                        && environ::is_selected(config, proc.pid as u32, &stat.comm)
+                    {
+                        environ::read(config, proc.pid)
+                    } else {
+                        HashMap::new()
+                    },
                 },
             );
         }
 
-        // Iterate over fdinfos and calculate GPU usage, as well as oldest timestamp (the progenitor of the fd)
-        for (client_id, cur) in cur_gpu_fdinfo.iter() {
-            if let Some(prev) = self.prev_gpu_fdinfo.get(client_id)
-                && let Some(pdev) = cur.pdev.clone()
-            {
-                let Some(fd_usage) = diff_fdinfo(prev, cur) else {
-                    continue;
-                };
-                if let Some(oldest) = cur.pids.iter().min_by(|pid_a, pid_b| {
-                    snapshot.processes[pid_a]
-                        .start_time
-                        .cmp(&snapshot.processes[pid_b].start_time)
-                }) {
-                    let Some(proc) = snapshot.processes.get_mut(oldest) else {
-                        continue;
-                    };
-                    let usage = proc.usage.get_or_insert_default();
-                    merge_gpu_usage(usage.gpu.entry(pdev.clone()).or_default(), &fd_usage);
+        // Attribute the usage of every DRM client to the oldest process sharing it (the progenitor of the fd)
+        for client in self.gpu_clients.finish() {
+            let Some(oldest) = client
+                .pids
+                .iter()
+                .filter(|pid| snapshot.processes.contains_key(pid))
+                .min_by_key(|pid| snapshot.processes[pid].start_time)
+            else {
+                continue;
+            };
+            if let Some(proc) = snapshot.processes.get_mut(oldest) {
+                let usage = proc.usage.get_or_insert_default();
+                merge_gpu_usage(usage.gpu.entry(client.pdev).or_default(), &client.usage);
+            }
+        }
+
+        if config.sockets {
+            let owners = self
+                .socket_owners
+                .finish(|pid| snapshot.processes.contains_key(&pid));
+            for (pid, sockets) in owners {
+                if let Some(proc) = snapshot.processes.get_mut(&pid) {
+                    proc.usage.get_or_insert_default().sockets = Some(sockets);
                 }
             }
         }
 
+        limit(config, &mut snapshot.processes);
+
+        if config.fd_counts {
+            snapshot.file_handles = std::fs::read_to_string("/proc/sys/fs/file-nr")
+                .ok()
+                .and_then(|file_nr| parse_file_nr(&file_nr));
+        }
+
         self.cpu_counters = cpu_counters;
-        self.prev_gpu_fdinfo = cur_gpu_fdinfo;
+        self.cpu_sampled_at = config.cpu_usage.then_some(now);
         self.disk_counters = disk_counters;
         self.net_counters = net_counters;
+        self.executables = executables;
+        self.rollups.finish();
 
         Ok(snapshot)
     }
@@ -312,7 +377,12 @@ impl super::Resolver for Collector {
     fn resolve(&mut self, input: &Self::Input, output: &mut Self::Output) -> anyhow::Result<()> {
         for device in input.gpus.iter() {
             for gpu_process in device.processes.iter() {
-                if let Some(process) = output.processes.get_mut(&gpu_process.pid) {
+                if let Some(process) = output.processes.get_mut(&gpu_process.pid)
+                    // Both start times are only known when they were collected
+                    && (process.start_time == 0
+                        || gpu_process.start_time == 0
+                        || process.start_time == gpu_process.start_time)
+                {
                     let process_usage = process.usage.get_or_insert_default();
                     let process_gpu_usage =
                         process_usage.gpu.entry(device.pci_id.clone()).or_default();
@@ -346,6 +416,23 @@ impl super::Resolver for Collector {
     }
 }
 
+/// Number of online logical CPUs, the same ones /proc/stat lists.
+fn online_cpus() -> u32 {
+    // SAFETY: sysconf only takes an integer
+    let cpus = unsafe { nix::libc::sysconf(nix::libc::_SC_NPROCESSORS_ONLN) };
+    cpus.max(1) as u32
+}
+
+/// Converts the CPU ticks a process used over an interval to a share of every CPU's capacity, so a
+/// process keeping all CPUs busy is at 100.
+fn normalized_usage(ticks: u64, elapsed: Duration, cpus: u32) -> f64 {
+    let capacity = elapsed.as_secs_f64() * cpus as f64;
+    if capacity <= 0.0 {
+        return 0.0;
+    }
+    (ticks as f64 / procfs::ticks_per_second() as f64 / capacity * 100.0).clamp(0.0, 100.0)
+}
+
 struct CpuCounters {
     utime: u64,
     stime: u64,
@@ -354,6 +441,82 @@ struct CpuCounters {
 struct DiskCounters {
     read_bytes: u64,
     write_bytes: u64,
+    taken_at: Instant,
+}
+
+/// Computes the IO of a process between two samples. The counters are keyed by pid and start time,
+/// so a reused pid starts over rather than being diffed against the previous process.
+fn disk_usage(prev: &DiskCounters, cur: &DiskCounters) -> DiskUsage {
+    let read_bytes = cur.read_bytes.saturating_sub(prev.read_bytes);
+    let write_bytes = cur.write_bytes.saturating_sub(prev.write_bytes);
+    let elapsed = cur.taken_at.duration_since(prev.taken_at).as_secs_f64();
+    let per_second = |bytes: u64| {
+        if elapsed > 0.0 {
+            bytes as f64 / elapsed
+        } else {
+            0.0
+        }
+    };
+    DiskUsage {
+        read_bytes,
+        read_total: cur.read_bytes,
+        write_bytes,
+        write_total: cur.write_bytes,
+        read_bytes_per_second: per_second(read_bytes),
+        write_bytes_per_second: per_second(write_bytes),
+    }
+}
+
+/// What a process runs. The command line is read once per process rather than every sample, it
+/// only changes on exec, which renames the process or points the exe link somewhere else, so
+/// either means it has to be read again.
+#[derive(Debug, Clone, PartialEq)]
+struct Executable {
+    name: String,
+    exe: String,
+    exe_deleted: bool,
+    cmdline: String,
+}
+
+impl Executable {
+    /// Reuses the cached command line while the process runs the same binary. The exe link is
+    /// read every sample, it's a single readlink and the binary can be deleted while it runs.
+    fn refresh(cached: Option<Self>, proc: &procfs::process::Process, name: &str) -> Self {
+        let (exe, exe_deleted) = read_exe(proc);
+        match cached {
+            Some(cached) if cached.is_current(name, &exe) => Self {
+                exe_deleted,
+                ..cached
+            },
+            _ => Self {
+                name: name.to_string(),
+                exe,
+                exe_deleted,
+                cmdline: proc
+                    .cmdline()
+                    .map(|c| c.into_iter().collect::<Vec<_>>().join(" "))
+                    .unwrap_or_default(),
+            },
+        }
+    }
+
+    /// Whether the process still runs what was cached, deleting the binary leaves its path as is.
+    fn is_current(&self, name: &str, exe: &str) -> bool {
+        self.name == name && self.exe == exe
+    }
+}
+
+/// Resolves the exe link of a process, the kernel appends " (deleted)" once the file is unlinked.
+/// Empty when the link can't be read (other users' processes when not running as root).
+fn read_exe(proc: &procfs::process::Process) -> (String, bool) {
+    let exe = proc
+        .exe()
+        .map(|e| e.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    match exe.strip_suffix(" (deleted)") {
+        Some(path) => (path.to_string(), true),
+        None => (exe, false),
+    }
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
@@ -362,217 +525,149 @@ struct PidId {
     timestamp: u64,
 }
 
-#[derive(Debug, Clone)]
-struct DrmFdinfo {
-    timestamp: std::time::Instant,
-    driver: Option<String>,
-    client_id: Option<u32>,
-    pdev: Option<String>,
-    times: HashMap<String, u64>,
-    cycles: HashMap<String, u64>,
-    total_cycles: HashMap<String, u64>,
-    maxfreq: HashMap<String, u64>,
-    shared_mem: HashMap<String, u64>,
-    resident_mem: HashMap<String, u64>,
-
-    pids: Vec<u32>,
+/// Counts the entries of /proc/<pid>/fd without resolving the links, None when the directory
+/// can't be read (other users' processes when not running as root, or the process exited).
+fn count_fds(pid: i32) -> Option<u32> {
+    std::fs::read_dir(format!("/proc/{pid}/fd"))
+        .ok()
+        .map(|fds| fds.count() as u32)
 }
 
-impl Default for DrmFdinfo {
-    fn default() -> Self {
-        Self {
-            timestamp: std::time::Instant::now(),
-            driver: None,
-            client_id: None,
-            pdev: None,
-            times: HashMap::new(),
-            cycles: HashMap::new(),
-            total_cycles: HashMap::new(),
-            maxfreq: HashMap::new(),
-            shared_mem: HashMap::new(),
-            resident_mem: HashMap::new(),
-            pids: Vec::new(),
-        }
-    }
+/// Whether a process is in a list of pids and glob patterns of process names.
+fn is_listed(list: &[String], pid: u32, name: &str) -> bool {
+    list.iter().any(|entry| {
+        entry.parse::<u32>().map_or_else(
+            |_| glob_matches(entry, name),
+            |listed_pid| listed_pid == pid,
+        )
+    })
 }
 
-fn parse_fdinfo(proc: PidId, fd: u32) -> anyhow::Result<DrmFdinfo> {
-    // open the fdinfo file. we can safely assume that the pid is not reused because the collect function still has an open pidfd.
-    let path = format!("/proc/{}/fdinfo/{}", proc.pid, fd);
-    let file = rustix::fs::open(path, OFlags::RDONLY | OFlags::CLOEXEC, Mode::empty())?;
-
-    let contents = sysfs::read_string(file.as_fd())
-        .ok_or_else(|| anyhow::anyhow!("failed to read fdinfo file"))?;
-
-    let mut fdinfo = DrmFdinfo::default();
-    fdinfo.pids.push(proc.pid);
-
-    // parse the fdinfo file
-    for line in contents.lines() {
-        if let Some((key, value)) = line.split_once(':') {
-            if key == "drm-driver" {
-                fdinfo.driver = Some(value.trim().to_string());
-            } else if key == "drm-client-id" {
-                fdinfo.client_id = value.trim().parse().ok();
-            } else if key == "drm-pdev" {
-                fdinfo.pdev = Some(value.trim().to_string());
-            } else if key.starts_with("drm-engine-") {
-                if let Some(engine) = key.strip_prefix("drm-engine-") {
-                    fdinfo.times.insert(
-                        engine.to_string(),
-                        value
-                            .trim()
-                            .strip_suffix(" ms")
-                            .and_then(|s| s.parse().ok())
-                            .unwrap_or(0),
-                    );
-                }
-            } else if key.starts_with("drm-cycles-") {
-                if let Some(engine) = key.strip_prefix("drm-cycles-") {
-                    fdinfo
-                        .cycles
-                        .insert(engine.to_string(), value.trim().parse().unwrap_or(0));
-                }
-            } else if key.starts_with("drm-total-cycles-") {
-                if let Some(engine) = key.strip_prefix("drm-total-cycles-") {
-                    fdinfo
-                        .total_cycles
-                        .insert(engine.to_string(), value.trim().parse().unwrap_or(0));
-                }
-            } else if key.starts_with("drm-maxfreq-") {
-                if let Some(engine) = key.strip_prefix("drm-maxfreq-") {
-                    let (freq, unit) = value.trim().split_once(' ').unwrap_or((value.trim(), "Hz"));
-                    match unit {
-                        "Hz" => {
-                            fdinfo
-                                .maxfreq
-                                .insert(engine.to_string(), freq.parse().unwrap_or(0));
-                        }
-                        "KHz" => {
-                            fdinfo
-                                .maxfreq
-                                .insert(engine.to_string(), freq.parse().unwrap_or(0) * 1000);
-                        }
-                        "MHz" => {
-                            fdinfo.maxfreq.insert(
-                                engine.to_string(),
-                                freq.parse().unwrap_or(0) * 1000 * 1000,
-                            );
-                        }
-                        _ => {}
-                    }
-                }
-            } else if key.starts_with("drm-shared-") {
-                if let Some(engine) = key.strip_prefix("drm-shared-") {
-                    let (bytes, unit) = value
-                        .trim()
-                        .split_once(' ')
-                        .unwrap_or((value.trim(), "KiB"));
-                    match unit {
-                        "KiB" => {
-                            fdinfo
-                                .shared_mem
-                                .insert(engine.to_string(), bytes.parse().unwrap_or(0) * 1024);
-                        }
-                        "MiB" => {
-                            fdinfo.shared_mem.insert(
-                                engine.to_string(),
-                                bytes.parse().unwrap_or(0) * 1024 * 1024,
-                            );
-                        }
-                        _ => {}
-                    }
-                }
-            } else if key.starts_with("drm-resident-") {
-                if let Some(engine) = key.strip_prefix("drm-resident-") {
-                    let (bytes, unit) = value
-                        .trim()
-                        .split_once(' ')
-                        .unwrap_or((value.trim(), "KiB"));
-                    match unit {
-                        "KiB" => {
-                            fdinfo
-                                .resident_mem
-                                .insert(engine.to_string(), bytes.parse().unwrap_or(0) * 1024);
-                        }
-                        "MiB" => {
-                            fdinfo.resident_mem.insert(
-                                engine.to_string(),
-                                bytes.parse().unwrap_or(0) * 1024 * 1024,
-                            );
-                        }
-                        _ => {}
-                    }
-                }
-            }
-        }
-    }
+/// PF_KTHREAD in include/linux/sched.h, set for kernel threads
+const PF_KTHREAD: u32 = 0x00200000;
 
-    Ok(fdinfo)
+/// Whether a process is collected, going by the flags and state letter of /proc/<pid>/stat.
+fn is_selected(config: &Config, flags: u32, state: char) -> bool {
+    (config.include_kernel_threads || flags & PF_KTHREAD == 0)
+        && !(config.exclude_zombies && state == 'Z')
 }
 
-fn diff_fdinfo(prev: &DrmFdinfo, cur: &DrmFdinfo) -> Option<GpuUsage> {
-    let mut result = GpuUsage::default();
-    for (region, &cur_shared) in cur.shared_mem.iter() {
-        let Some(&cur_resident) = cur.resident_mem.get(region) else {
-            continue;
-        };
-        if region.starts_with("vram") {
-            result.vram_usage += cur_resident - cur_shared;
-        } else if region.contains("system")
-            || region.contains("cpu")
-            || region == "gtt"
-            || region == "memory"
-        {
-            result.system_usage += cur_resident - cur_shared;
-        }
+/// Maps the state letter of /proc/<pid>/stat to a status.
+fn status_of(state: char) -> Status {
+    use procfs::process::ProcState;
+    match ProcState::from_char(state) {
+        Some(ProcState::Running) => Status::Running,
+        Some(ProcState::Sleeping) => Status::Sleeping,
+        Some(ProcState::Waiting) => Status::DiskSleep,
+        Some(ProcState::Stopped) => Status::Stopped,
+        Some(ProcState::Tracing) => Status::Tracing,
+        Some(ProcState::Zombie) => Status::Zombie,
+        Some(ProcState::Idle) => Status::Idle,
+        Some(ProcState::Wakekill) => Status::WakeKill,
+        Some(ProcState::Waking) => Status::Waking,
+        Some(ProcState::Parked) => Status::Parked,
+        Some(ProcState::Dead) => Status::Dead,
+        None => Status::Unknown,
     }
-    if !cur.cycles.is_empty() {
-        for (engine, &cur_cycles) in cur.cycles.iter() {
-            let Some(&prev_cycles) = prev.cycles.get(engine) else {
-                continue;
-            };
+}
 
-            let cycle_diff = cur_cycles.saturating_sub(prev_cycles);
+/// Drops the processes under the thresholds, then keeps the top max_processes by the sort key.
+pub fn limit(config: &Config, processes: &mut HashMap<u32, Process>) {
+    let page_size = procfs::page_size();
+    let cpu = |process: &Process| {
+        process
+            .usage
+            .as_ref()
+            .and_then(|usage| usage.cpu.as_ref())
+            .map_or(0, |cpu| cpu.usage as u64)
+    };
+    let memory = |process: &Process| {
+        process
+            .usage
+            .as_ref()
+            .and_then(|usage| usage.memory.as_ref())
+            .map_or(0, |memory| memory.resident * page_size)
+    };
+    let disk = |process: &Process| {
+        process
+            .usage
+            .as_ref()
+            .and_then(|usage| usage.disk.as_ref())
+            .map_or(0, |disk| disk.read_bytes + disk.write_bytes)
+    };
+    processes.retain(|_, process| {
+        cpu(process) >= config.min_cpu_percent as u64 && memory(process) >= config.min_memory_bytes
+    });
 
-            // priority to use total cycles since that's more of a "utilization" metric
-            if let Some(&cur_total_cycles) = cur.total_cycles.get(engine)
-                && let Some(&prev_total_cycles) = prev.total_cycles.get(engine)
-                && cycle_diff > 0
-            {
-                let total_cycle_diff = cur_total_cycles.saturating_sub(prev_total_cycles);
-                if total_cycle_diff > 0 {
-                    result
-                        .engines
-                        .insert(engine.clone(), (total_cycle_diff * 100 / cycle_diff) as u32);
-                }
-            } else if let Some(&max_freq) = cur.maxfreq.get(engine) {
-                if max_freq > 0 {
-                    result
-                        .engines
-                        .insert(engine.clone(), (cycle_diff * 100 / max_freq) as u32);
-                }
-            }
-        }
-    } else if !cur.times.is_empty() {
-        for (engine, &cur_time) in cur.times.iter() {
-            let Some(&prev_time) = prev.times.get(engine) else {
-                continue;
-            };
-            let time_diff = cur_time.saturating_sub(prev_time);
-            let total_time_diff = cur.timestamp - prev.timestamp;
-            if total_time_diff.as_nanos() > 0 {
-                result.engines.insert(
-                    engine.clone(),
-                    (time_diff * 100 / total_time_diff.as_nanos() as u64) as u32,
-                );
-            }
-        }
-    } else if result.vram_usage == 0 && result.system_usage == 0 {
+    let max_processes = config.max_processes as usize;
+    if max_processes == 0 || processes.len() <= max_processes {
+        return;
+    }
+    let sort_by = config.sort_by();
+    let key = |process: &Process| match sort_by {
+        SortBy::Cpu => cpu(process),
+        SortBy::Memory => memory(process),
+        SortBy::Disk => disk(process),
+    };
+    let mut ranked: Vec<(u64, u32)> = processes
+        .iter()
+        .map(|(pid, process)| (key(process), *pid))
+        .collect();
+    // Highest first, the lower pid wins ties so the selection is stable across samples
+    ranked.sort_unstable_by(|a, b| b.0.cmp(&a.0).then(a.1.cmp(&b.1)));
+    for (_, pid) in &ranked[max_processes..] {
+        processes.remove(pid);
+    }
+}
+
+// IOPRIO_* in include/uapi/linux/ioprio.h
+const IOPRIO_WHO_PROCESS: i32 = 1;
+const IOPRIO_CLASS_SHIFT: i64 = 13;
+const IOPRIO_LEVEL_MASK: i64 = 0x7;
+
+/// Reads the IO scheduling class and level of a process with ioprio_get, there's no procfs file
+/// for it. None when the process exited or isn't visible.
+fn io_priority(pid: i32, nice: i32) -> Option<IoPriority> {
+    // SAFETY: ioprio_get only takes integers
+    let ioprio = unsafe {
+        nix::libc::syscall(
+            nix::libc::SYS_ioprio_get,
+            IOPRIO_WHO_PROCESS,
+            pid as nix::libc::c_int,
+        )
+    };
+    if ioprio < 0 {
         return None;
     }
+    let level = (ioprio & IOPRIO_LEVEL_MASK) as u32;
+    let (class, level) = match ioprio >> IOPRIO_CLASS_SHIFT {
+        // Without a class of its own the process gets best-effort at a level following its nice value
+        0 => ("none", ((nice + 20) / 5).clamp(0, 7) as u32),
+        1 => ("realtime", level),
+        2 => ("best-effort", level),
+        3 => ("idle", 0),
+        _ => return None,
+    };
+    Some(IoPriority {
+        class: class.to_string(),
+        level,
+    })
+}
 
-    Some(result)
+/// Parses /proc/sys/fs/file-nr, "<allocated> <free> <maximum>". Since Linux 2.6 freed handles are
+/// released right away, so the free count is 0 and allocated is the number in use.
+fn parse_file_nr(file_nr: &str) -> Option<FileHandles> {
+    let fields: Vec<u64> = file_nr
+        .split_whitespace()
+        .map(|field| field.parse().ok())
+        .collect::<Option<_>>()?;
+    match fields[..] {
+        [allocated, free, maximum] => Some(FileHandles {
+            allocated: allocated.saturating_sub(free),
+            maximum,
+        }),
+        _ => None,
+    }
 }
 
 fn merge_gpu_usage(accumulator: &mut GpuUsage, usage: &GpuUsage) {
@@ -588,6 +683,7 @@ mod tests {
     use super::*;
     use crate::collector::Collector;
     use crate::collector::Resolver;
+    use crate::collector::helpers::test::FakeSysfs;
 
     #[tracing_test::traced_test]
     #[test]
@@ -603,6 +699,26 @@ mod tests {
             gpu_usage: true,
             disk_usage: true,
             net_usage: true,
+            sockets: true,
+            fd_counts: true,
+            environment: true,
+            environment_processes: Vec::new(),
+            redact_environment: Vec::new(),
+            environment_max_bytes: 0,
+            cgroup: true,
+            max_processes: 0,
+            sort_by: SortBy::Cpu as i32,
+            min_cpu_percent: 0,
+            min_memory_bytes: 0,
+            user_refresh_interval: 0,
+            include_kernel_threads: false,
+            exclude_zombies: false,
+            memory_breakdown: false,
+            memory_breakdown_min_rss_bytes: 0,
+            memory_breakdown_processes: Vec::new(),
+            security: false,
+            separate_gpu_processes: false,
+            io_priority: false,
         });
         let _ = collector.collect(&config)?;
         std::thread::sleep(std::time::Duration::from_secs(1));
@@ -613,6 +729,401 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn threads() -> anyhow::Result<()> {
+        let mut collector = super::Collector::new();
+        let config = crate::metrics::Config {
+            process: Some(Config::default()),
+            ..Default::default()
+        };
+
+        let (done, wait) = std::sync::mpsc::channel::<()>();
+        let wait = std::sync::Arc::new(std::sync::Mutex::new(wait));
+        let workers: Vec<_> = (0..4)
+            .map(|_| {
+                let wait = wait.clone();
+                std::thread::spawn(move || {
+                    let _ = wait.lock().map(|wait| wait.recv());
+                })
+            })
+            .collect();
+        let snapshot = collector.collect(&config);
+        drop(done);
+        for worker in workers {
+            let _ = worker.join();
+        }
+
+        let snapshot = snapshot?;
+        // The test harness runs other tests on threads of its own
+        assert!(snapshot.processes[&std::process::id()].threads >= 5);
+        Ok(())
+    }
+
+    #[test]
+    fn open_files() -> anyhow::Result<()> {
+        let mut collector = super::Collector::new();
+        let config = crate::metrics::Config {
+            process: Some(Config {
+                fd_counts: true,
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+        let files = (0..8)
+            .map(|_| std::fs::File::open("/proc/self/stat"))
+            .collect::<Result<Vec<_>, _>>()?;
+        let snapshot = collector.collect(&config)?;
+        drop(files);
+
+        let process = &snapshot.processes[&std::process::id()];
+        assert!(process.open_files >= 8);
+        assert!(!process.open_files_estimated);
+        assert!(
+            snapshot
+                .file_handles
+                .is_some_and(|handles| handles.maximum > 0)
+        );
+
+        assert_eq!(
+            parse_file_nr("10336\t0\t9223372036854775807\n"),
+            Some(FileHandles {
+                allocated: 10336,
+                maximum: 9223372036854775807,
+            })
+        );
+        assert_eq!(parse_file_nr("10336 0"), None);
+        Ok(())
+    }
+
+    #[test]
+    fn priority() -> anyhow::Result<()> {
+        let mut child = std::process::Command::new("sleep").arg("10").spawn()?;
+        let pid = child.id() as i32;
+        let priorities = (|| -> anyhow::Result<_> {
+            rustix::process::setpriority_process(rustix::process::Pid::from_raw(pid), 10)?;
+            // SAFETY: ioprio_set only takes integers, the idle class
+            let set = unsafe {
+                nix::libc::syscall(
+                    nix::libc::SYS_ioprio_set,
+                    IOPRIO_WHO_PROCESS,
+                    pid as nix::libc::c_int,
+                    3 << IOPRIO_CLASS_SHIFT,
+                )
+            };
+            anyhow::ensure!(set == 0, "ioprio_set failed");
+
+            let mut collector = super::Collector::new();
+            let config = crate::metrics::Config {
+                process: Some(Config {
+                    io_priority: true,
+                    ..Default::default()
+                }),
+                ..Default::default()
+            };
+            let snapshot = collector.collect(&config)?;
+            let process = snapshot.processes[&(pid as u32)].clone();
+            Ok((process.nice, process.io_priority))
+        })();
+        child.kill()?;
+        child.wait()?;
+
+        let (nice, child_io_priority) = priorities?;
+        assert_eq!(nice, 10);
+        assert_eq!(
+            child_io_priority,
+            Some(IoPriority {
+                class: "idle".to_string(),
+                level: 0,
+            })
+        );
+        let own = io_priority(std::process::id() as i32, 0).unwrap();
+        if own.class == "none" {
+            assert_eq!(own.level, 4);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn gpu_merge() -> anyhow::Result<()> {
+        let gpu = crate::metrics::gpu::Snapshot {
+            gpus: vec![crate::metrics::gpu::Gpu {
+                pci_id: "0000:03:00.0".to_string(),
+                processes: vec![crate::metrics::gpu::Process {
+                    pid: 1200,
+                    vram_usage: 256 << 20,
+                    start_time: 5000,
+                    ..Default::default()
+                }],
+                ..Default::default()
+            }],
+        };
+        let merged = |start_time: u64| -> anyhow::Result<_> {
+            let mut snapshot = Snapshot {
+                processes: [(
+                    1200,
+                    Process {
+                        start_time,
+                        ..Default::default()
+                    },
+                )]
+                .into(),
+                ..Default::default()
+            };
+            super::Collector::new().resolve(&gpu, &mut snapshot)?;
+            Ok(snapshot.processes[&1200]
+                .usage
+                .as_ref()
+                .and_then(|usage| usage.gpu.get("0000:03:00.0"))
+                .map(|usage| usage.vram_usage))
+        };
+        assert_eq!(merged(5000)?, Some(256 << 20));
+        // Start time not collected
+        assert_eq!(merged(0)?, Some(256 << 20));
+        // The pid was reused after the GPU saw it
+        assert_eq!(merged(7000)?, None);
+        Ok(())
+    }
+
+    #[test]
+    fn disk_rates() {
+        let start = Instant::now();
+        let prev = DiskCounters {
+            read_bytes: 1 << 20,
+            write_bytes: 4096,
+            taken_at: start,
+        };
+        let cur = DiskCounters {
+            read_bytes: 5 << 20,
+            write_bytes: 4096,
+            taken_at: start + Duration::from_secs(2),
+        };
+        assert_eq!(
+            disk_usage(&prev, &cur),
+            DiskUsage {
+                read_bytes: 4 << 20,
+                read_total: 5 << 20,
+                write_bytes: 0,
+                write_total: 4096,
+                read_bytes_per_second: (2 << 20) as f64,
+                write_bytes_per_second: 0.0,
+            }
+        );
+        // Two samples at the same instant have no rate
+        assert_eq!(disk_usage(&prev, &prev).read_bytes_per_second, 0.0);
+    }
+
+    #[test]
+    fn cpu_normalization() -> anyhow::Result<()> {
+        let mut cpu_collector = crate::collector::cpu::Collector::new();
+        let config = crate::metrics::Config {
+            cpu: Some(crate::metrics::cpu::Config::default()),
+            ..Default::default()
+        };
+        let cpus = online_cpus();
+        // The logical CPUs are listed from the second sample on
+        let _ = cpu_collector.collect(&config)?;
+        assert_eq!(cpu_collector.collect(&config)?.logical.len(), cpus as usize);
+
+        let ticks = procfs::ticks_per_second();
+        let second = Duration::from_secs(1);
+        // One CPU kept busy for the whole interval
+        assert!((normalized_usage(ticks, second, cpus) - 100.0 / cpus as f64).abs() < 1e-9);
+        assert_eq!(normalized_usage(ticks * cpus as u64, second, cpus), 100.0);
+        // Counters are in whole ticks, so rounding can't push it over 100
+        assert_eq!(
+            normalized_usage(ticks * cpus as u64 + 1, second, cpus),
+            100.0
+        );
+        assert_eq!(normalized_usage(ticks, Duration::ZERO, cpus), 0.0);
+        Ok(())
+    }
+
+    #[test]
+    fn deleted_exe() -> anyhow::Result<()> {
+        let root = FakeSysfs::new("sleep");
+        let path = root.path("sleep");
+        std::fs::copy("/bin/sleep", &path)?;
+        let mut child = std::process::Command::new(&path).arg("10").spawn()?;
+        let proc = procfs::process::Process::new(child.id() as i32)?;
+        let before = read_exe(&proc);
+        root.remove("sleep");
+        let after = read_exe(&proc);
+        child.kill()?;
+        child.wait()?;
+
+        let path = path.to_string_lossy().into_owned();
+        assert_eq!(before, (path.clone(), false));
+        assert_eq!(after, (path, true));
+        Ok(())
+    }
+
+    #[test]
+    fn executable_cache() -> anyhow::Result<()> {
+        let root = FakeSysfs::new("exec");
+        let (before, after) = (root.path("before/sh"), root.path("after/sh"));
+        for path in [&before, &after] {
+            root.dir(path.parent().unwrap());
+            std::fs::copy("/bin/sh", path)?;
+        }
+        // Execs a binary of the same name once it reads a line
+        let mut child = std::process::Command::new(&before)
+            .arg("-c")
+            .arg(format!(
+                "read line; exec {} -c 'read line; true'",
+                after.display()
+            ))
+            .stdin(std::process::Stdio::piped())
+            .spawn()?;
+        let refreshed = (|| -> anyhow::Result<_> {
+            let proc = procfs::process::Process::new(child.id() as i32)?;
+            let first = Executable::refresh(None, &proc, "sh");
+            let cached = Executable::refresh(Some(first.clone()), &proc, "sh");
+
+            std::io::Write::write_all(child.stdin.as_mut().unwrap(), b"\n")?;
+            let deadline = Instant::now() + Duration::from_secs(5);
+            while proc.exe()? != after && Instant::now() < deadline {
+                std::thread::sleep(Duration::from_millis(10));
+            }
+            let exec = Executable::refresh(Some(cached.clone()), &proc, "sh");
+            Ok((first, cached, exec))
+        })();
+        child.kill()?;
+        child.wait()?;
+
+        let (first, cached, exec) = refreshed?;
+        assert_eq!(first.exe, before.to_string_lossy());
+        assert!(first.cmdline.starts_with(&*before.to_string_lossy()));
+        assert_eq!(cached, first);
+        // Same name, but the new exe link means the command line is read again
+        assert_eq!(exec.exe, after.to_string_lossy());
+        assert!(exec.cmdline.starts_with(&*after.to_string_lossy()));
+
+        let executable = Executable {
+            name: "sh".to_string(),
+            exe: "/usr/bin/sh".to_string(),
+            exe_deleted: false,
+            cmdline: String::new(),
+        };
+        assert!(executable.is_current("sh", "/usr/bin/sh"));
+        assert!(!executable.is_current("sh", "/tmp/sh"));
+        assert!(!executable.is_current("bash", "/usr/bin/sh"));
+        Ok(())
+    }
+
+    #[test]
+    fn selection() {
+        // (pid, flags, state) of a small process table
+        let table = [
+            (1, 0x00400100, 'S'),
+            (2, PF_KTHREAD | 0x40, 'S'),
+            (3, PF_KTHREAD | 0x04208060, 'I'),
+            (812, 0x00400140, 'R'),
+            (913, 0x0040010c, 'Z'),
+        ];
+        let selected = |config: Config| {
+            table
+                .iter()
+                .filter(|(_, flags, state)| is_selected(&config, *flags, *state))
+                .map(|(pid, _, _)| *pid)
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(selected(Config::default()), [1, 812, 913]);
+        assert_eq!(
+            selected(Config {
+                include_kernel_threads: true,
+                exclude_zombies: true,
+                ..Default::default()
+            }),
+            [1, 2, 3, 812]
+        );
+    }
+
+    #[test]
+    fn states() {
+        assert_eq!(status_of('R'), Status::Running);
+        assert_eq!(status_of('D'), Status::DiskSleep);
+        assert_eq!(status_of('t'), Status::Tracing);
+        assert_eq!(status_of('X'), Status::Dead);
+        assert_eq!(status_of('?'), Status::Unknown);
+        // RUNNING keeps the zero value of the wire format, so an unset status decodes as running
+        // and only the empty state shows it wasn't collected
+        let process = Process::default();
+        assert_eq!(process.status(), Status::Running);
+        assert!(process.state.is_empty());
+    }
+
+    #[test]
+    fn limits() {
+        let page_size = procfs::page_size();
+        let process = |cpu: u32, resident_bytes: u64, disk_bytes: u64| Process {
+            usage: Some(Usage {
+                cpu: Some(CpuUsage {
+                    usage: cpu,
+                    ..Default::default()
+                }),
+                memory: Some(MemoryUsage {
+                    resident: resident_bytes / page_size,
+                    ..Default::default()
+                }),
+                disk: Some(DiskUsage {
+                    read_bytes: disk_bytes,
+                    ..Default::default()
+                }),
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+        let processes: HashMap<u32, Process> = HashMap::from([
+            (1, process(0, 4 << 20, 0)),
+            (10, process(50, 1 << 20, 100)),
+            (11, process(50, 64 << 20, 0)),
+            (12, process(5, 512 << 20, 4096)),
+            // No usage collected yet
+            (13, Process::default()),
+        ]);
+        let limited = |config: Config| {
+            let mut processes = processes.clone();
+            limit(&config, &mut processes);
+            let mut pids: Vec<_> = processes.into_keys().collect();
+            pids.sort();
+            pids
+        };
+
+        assert_eq!(limited(Config::default()), [1, 10, 11, 12, 13]);
+        // Ties go to the lower pid
+        assert_eq!(
+            limited(Config {
+                max_processes: 2,
+                ..Default::default()
+            }),
+            [10, 11]
+        );
+        assert_eq!(
+            limited(Config {
+                max_processes: 2,
+                sort_by: SortBy::Memory as i32,
+                ..Default::default()
+            }),
+            [11, 12]
+        );
+        assert_eq!(
+            limited(Config {
+                max_processes: 1,
+                sort_by: SortBy::Disk as i32,
+                ..Default::default()
+            }),
+            [12]
+        );
+        assert_eq!(
+            limited(Config {
+                min_cpu_percent: 5,
+                min_memory_bytes: 2 << 20,
+                ..Default::default()
+            }),
+            [11, 12]
+        );
+    }
+
     #[tracing_test::traced_test]
     #[test]
     fn proc_resolve() -> anyhow::Result<()> {
@@ -627,6 +1138,10 @@ mod tests {
             power: false,
             thermals: false,
             processes: true,
+            fans: false,
+            pcie: false,
+            collect_other: false,
+            ecc: false,
         });
         config.process = Some(crate::metrics::process::Config {
             identity: true,
@@ -637,6 +1152,26 @@ mod tests {
             gpu_usage: true,
             disk_usage: false,
             net_usage: false,
+            sockets: false,
+            fd_counts: false,
+            environment: false,
+            environment_processes: Vec::new(),
+            redact_environment: Vec::new(),
+            environment_max_bytes: 0,
+            cgroup: false,
+            max_processes: 0,
+            sort_by: SortBy::Cpu as i32,
+            min_cpu_percent: 0,
+            min_memory_bytes: 0,
+            user_refresh_interval: 0,
+            include_kernel_threads: false,
+            exclude_zombies: false,
+            memory_breakdown: false,
+            memory_breakdown_min_rss_bytes: 0,
+            memory_breakdown_processes: Vec::new(),
+            security: false,
+            separate_gpu_processes: false,
+            io_priority: false,
         });
         let _ = proc_collector.collect(&config)?;
         let _ = gpu_collector.collect(&config)?;
@@ -0,0 +1,119 @@
+/*
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/.
+ */
+
+//! Security context of processes: their credentials, effective capabilities and LSM label.
+
+use super::Security;
+
+/// Capability names by bit, CAP_* in include/uapi/linux/capability.h
+const CAPABILITIES: &[&str] = &[
+    "cap_chown",
+    "cap_dac_override",
+    "cap_dac_read_search",
+    "cap_fowner",
+    "cap_fsetid",
+    "cap_kill",
+    "cap_setgid",
+    "cap_setuid",
+    "cap_setpcap",
+    "cap_linux_immutable",
+    "cap_net_bind_service",
+    "cap_net_broadcast",
+    "cap_net_admin",
+    "cap_net_raw",
+    "cap_ipc_lock",
+    "cap_ipc_owner",
+    "cap_sys_module",
+    "cap_sys_rawio",
+    "cap_sys_chroot",
+    "cap_sys_ptrace",
+    "cap_sys_pacct",
+    "cap_sys_admin",
+    "cap_sys_boot",
+    "cap_sys_nice",
+    "cap_sys_resource",
+    "cap_sys_time",
+    "cap_sys_tty_config",
+    "cap_mknod",
+    "cap_lease",
+    "cap_audit_write",
+    "cap_audit_control",
+    "cap_setfcap",
+    "cap_mac_override",
+    "cap_mac_admin",
+    "cap_syslog",
+    "cap_wake_alarm",
+    "cap_block_suspend",
+    "cap_audit_read",
+    "cap_perfmon",
+    "cap_bpf",
+    "cap_checkpoint_restore",
+];
+
+/// Reads the security context of a process from its already parsed status.
+pub fn read(pid: i32, status: &procfs::process::Status) -> Security {
+    Security {
+        real_uid: status.ruid,
+        effective_uid: status.euid,
+        saved_uid: status.suid,
+        real_gid: status.rgid,
+        effective_gid: status.egid,
+        saved_gid: status.sgid,
+        capabilities: capability_names(status.capeff),
+        label: std::fs::read(format!("/proc/{pid}/attr/current"))
+            .ok()
+            .and_then(|label| parse_label(&label)),
+    }
+}
+
+/// Decodes a capability set, bits newer than the table are named by number like capsh does.
+fn capability_names(mask: u64) -> Vec<String> {
+    (0..64)
+        .filter(|bit| mask & (1 << bit) != 0)
+        .map(|bit| match CAPABILITIES.get(bit) {
+            Some(name) => name.to_string(),
+            None => format!("cap_{bit}"),
+        })
+        .collect()
+}
+
+/// Cleans up the label of the active LSM. SELinux terminates it with a NUL, AppArmor with a
+/// newline and reports the mode after the profile ("firefox (enforce)").
+fn parse_label(label: &[u8]) -> Option<String> {
+    let label = String::from_utf8_lossy(label);
+    let label = label.trim_end_matches(['\0', '\n']).trim();
+    (!label.is_empty()).then(|| label.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn capabilities() {
+        assert!(capability_names(0).is_empty());
+        // A typical web server: bind low ports, drop privileges
+        assert_eq!(
+            capability_names(0x4c0),
+            ["cap_setgid", "cap_setuid", "cap_net_bind_service"]
+        );
+        let all = capability_names(0x000001ffffffffff);
+        assert_eq!(all.len(), 41);
+        assert_eq!(all[21], "cap_sys_admin");
+        assert_eq!(all[40], "cap_checkpoint_restore");
+        assert_eq!(capability_names(1 << 41), ["cap_41"]);
+
+        assert_eq!(
+            parse_label(b"system_u:system_r:httpd_t:s0\0").as_deref(),
+            Some("system_u:system_r:httpd_t:s0")
+        );
+        assert_eq!(
+            parse_label(b"firefox (enforce)\n").as_deref(),
+            Some("firefox (enforce)")
+        );
+        assert_eq!(parse_label(b""), None);
+    }
+}
@@ -0,0 +1,141 @@
+/*
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/.
+ */
+
+//! Memory breakdown of processes from /proc/<pid>/smaps_rollup.
+//! The resident size counts shared pages fully in every process mapping them, the proportional set
+//! size (PSS) splits them between those processes instead. Reading the rollup walks every page table
+//! of the process, so it's only done for the processes the config selects.
+
+use std::collections::HashSet;
+
+use super::{Config, MemoryDetail, PidId};
+
+/// Processes without a memory breakdown, remembered so they aren't read again
+#[derive(Default)]
+pub struct Rollups {
+    empty: HashSet<PidId>,
+    next_empty: HashSet<PidId>,
+}
+
+impl Rollups {
+    /// Reads the breakdown of a process the config selects, either by its resident size or by
+    /// being listed. None for processes without memory of their own (kernel threads) and
+    /// unreadable rollups (other users' processes when not running as root).
+    pub fn read(
+        &mut self,
+        config: &Config,
+        pid_id: PidId,
+        name: &str,
+        rss_bytes: u64,
+    ) -> Option<MemoryDetail> {
+        if self.empty.contains(&pid_id) {
+            self.next_empty.insert(pid_id);
+            return None;
+        }
+        let min_rss_bytes = config.memory_breakdown_min_rss_bytes;
+        let listed = &config.memory_breakdown_processes;
+        let selected = (min_rss_bytes == 0 && listed.is_empty())
+            || (min_rss_bytes > 0 && rss_bytes >= min_rss_bytes)
+            || super::is_listed(listed, pid_id.pid, name);
+        if !selected {
+            return None;
+        }
+        let detail = std::fs::read_to_string(format!("/proc/{}/smaps_rollup", pid_id.pid))
+            .ok()
+            .and_then(|rollup| parse_rollup(&rollup));
+        if detail.is_none() {
+            self.next_empty.insert(pid_id);
+        }
+        detail
+    }
+
+    /// Ends a collection, forgetting the processes that weren't seen in it.
+    pub fn finish(&mut self) {
+        self.empty = std::mem::take(&mut self.next_empty);
+    }
+}
+
+/// Sums up the rollup, "Name:    1234 kB" lines after a header line. Empty for processes without
+/// an address space.
+fn parse_rollup(rollup: &str) -> Option<MemoryDetail> {
+    let field = |name: &str| {
+        rollup.lines().find_map(|line| {
+            let value = line.strip_prefix(name)?.strip_prefix(':')?;
+            value.trim().strip_suffix("kB")?.trim().parse::<u64>().ok()
+        })
+    };
+    let bytes = |name: &str| field(name).unwrap_or_default() * 1024;
+    Some(MemoryDetail {
+        pss_bytes: field("Pss")? * 1024,
+        rss_bytes: bytes("Rss"),
+        shared_bytes: bytes("Shared_Clean") + bytes("Shared_Dirty"),
+        private_bytes: bytes("Private_Clean") + bytes("Private_Dirty"),
+        swap_bytes: bytes("Swap"),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rollup() {
+        let rollup =
+            "55d1c7a4d000-7ffc3c1f8000 ---p 00000000 00:00 0                          [rollup]
+Rss:                4216 kB
+Pss:                1130 kB
+Pss_Dirty:           624 kB
+Pss_Anon:            596 kB
+Pss_File:            534 kB
+Pss_Shmem:             0 kB
+Shared_Clean:       3056 kB
+Shared_Dirty:         32 kB
+Private_Clean:       536 kB
+Private_Dirty:       592 kB
+Referenced:         4216 kB
+Anonymous:           596 kB
+Swap:                128 kB
+SwapPss:             128 kB
+Locked:                0 kB
+";
+        assert_eq!(
+            parse_rollup(rollup),
+            Some(MemoryDetail {
+                pss_bytes: 1130 * 1024,
+                rss_bytes: 4216 * 1024,
+                shared_bytes: 3088 * 1024,
+                private_bytes: 1128 * 1024,
+                swap_bytes: 128 * 1024,
+            })
+        );
+        // Kernel threads
+        assert_eq!(parse_rollup(""), None);
+
+        let mut rollups = Rollups::default();
+        let config = Config {
+            memory_breakdown_min_rss_bytes: 64 << 20,
+            memory_breakdown_processes: vec!["postgres*".to_string()],
+            ..Default::default()
+        };
+        let own = PidId {
+            pid: std::process::id(),
+            timestamp: 0,
+        };
+        assert!(rollups.read(&config, own, "cargo", 1 << 20).is_none());
+        assert!(rollups.read(&config, own, "cargo", 128 << 20).is_some());
+        assert!(rollups.read(&config, own, "postgres", 0).is_some());
+        // A pid without a rollup is only tried once
+        let gone = PidId {
+            pid: u32::MAX,
+            timestamp: 0,
+        };
+        assert!(rollups.read(&config, gone, "gone", 128 << 20).is_none());
+        rollups.finish();
+        assert!(rollups.empty.contains(&gone));
+        rollups.finish();
+        assert!(rollups.empty.is_empty());
+    }
+}
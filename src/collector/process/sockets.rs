@@ -0,0 +1,137 @@
+/*
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/.
+ */
+
+//! Attribution of the system's sockets to the processes holding them.
+//! Finding the owner of a socket means reading the fd links of every process, so the owners are
+//! cached and the fds are only walked again once a socket shows up that the cache doesn't know.
+//! The mtime of /proc/<pid>/fd doesn't change when fds are opened, so it can't narrow the walk down.
+
+use std::collections::{HashMap, HashSet};
+
+use super::SocketUsage;
+use crate::collector::net::sockets::{self, Protocol, Socket};
+
+#[derive(Default)]
+pub struct Owners {
+    /// Socket inodes to the pid holding them
+    owners: HashMap<u64, u32>,
+    /// Sockets no readable fd links to (e.g. held by other users' processes when not running as root),
+    /// remembered so they don't force a walk on every sample
+    unowned: HashSet<u64>,
+    sockets: Vec<Socket>,
+    scanning: bool,
+}
+
+impl Owners {
+    /// Reads the socket tables, returning whether the fds have to be walked to find new owners.
+    pub fn begin(&mut self) -> bool {
+        self.sockets = sockets::read_tables();
+        self.scanning = self.sockets.iter().any(|socket| {
+            socket.inode != 0
+                && !self.owners.contains_key(&socket.inode)
+                && !self.unowned.contains(&socket.inode)
+        });
+        if self.scanning {
+            self.owners.clear();
+        }
+        self.scanning
+    }
+
+    /// Records a socket fd of a process. Sockets shared across a fork (e.g. the listening socket of a
+    /// pre-forking server) go to the first process found, which is the lowest pid.
+    pub fn observe(&mut self, pid: u32, inode: u64) {
+        self.owners.entry(inode).or_insert(pid);
+    }
+
+    /// Sums up the sockets of each process. Owners that exited are forgotten, so the next sample looks
+    /// for the processes their sockets were handed to.
+    pub fn finish(&mut self, alive: impl Fn(u32) -> bool) -> HashMap<u32, SocketUsage> {
+        self.owners.retain(|_, pid| alive(*pid));
+        let inodes: HashSet<u64> = self.sockets.iter().map(|socket| socket.inode).collect();
+        if self.scanning {
+            self.unowned = inodes
+                .into_iter()
+                .filter(|inode| *inode != 0 && !self.owners.contains_key(inode))
+                .collect();
+        } else {
+            self.unowned.retain(|inode| inodes.contains(inode));
+        }
+
+        let mut usage: HashMap<u32, SocketUsage> = HashMap::new();
+        for socket in &self.sockets {
+            let Some(&pid) = self.owners.get(&socket.inode) else {
+                continue;
+            };
+            let usage = usage.entry(pid).or_default();
+            match (socket.protocol, socket.state) {
+                (_, sockets::TCP_ESTABLISHED) => usage.open_connections += 1,
+                // Unconnected UDP sockets bound to a port are the equivalent of listening
+                (Protocol::Tcp, sockets::TCP_LISTEN) | (Protocol::Udp, sockets::TCP_CLOSE)
+                    if socket.local_port != 0 =>
+                {
+                    usage.listening_ports.push(socket.local_port as u32)
+                }
+                _ => {}
+            }
+        }
+        for usage in usage.values_mut() {
+            // IPv4 and IPv6 sockets commonly listen on the same port
+            usage.listening_ports.sort_unstable();
+            usage.listening_ports.dedup();
+        }
+        usage
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn attribution() {
+        let socket = |protocol, state, local_port, inode| Socket {
+            protocol,
+            state,
+            local_port,
+            inode,
+        };
+        let mut owners = Owners {
+            sockets: vec![
+                socket(Protocol::Tcp, sockets::TCP_LISTEN, 80, 10),
+                socket(Protocol::Tcp, sockets::TCP_LISTEN, 80, 11),
+                socket(Protocol::Tcp, sockets::TCP_ESTABLISHED, 80, 12),
+                socket(Protocol::Udp, sockets::TCP_CLOSE, 53, 13),
+                socket(Protocol::Tcp, sockets::TCP_ESTABLISHED, 22, 14),
+                // TIME_WAIT, no longer owned by anyone
+                socket(Protocol::Tcp, 0x06, 80, 0),
+            ],
+            scanning: true,
+            ..Default::default()
+        };
+        owners.observe(100, 10);
+        owners.observe(100, 11);
+        owners.observe(100, 12);
+        owners.observe(100, 13);
+        // A forked worker sharing the listening socket
+        owners.observe(101, 10);
+        owners.observe(102, 14);
+
+        let usage = owners.finish(|_| true);
+        assert_eq!(usage.len(), 2);
+        assert_eq!(usage[&100].open_connections, 1);
+        assert_eq!(usage[&100].listening_ports, vec![53, 80]);
+        assert_eq!(usage[&102].open_connections, 1);
+        assert!(owners.unowned.is_empty());
+
+        // The next sample is served from the cache, the socket of the exited process becomes
+        // unknown so the following sample walks the fds for its new owner
+        owners.scanning = false;
+        let usage = owners.finish(|pid| pid != 102);
+        assert_eq!(usage.len(), 1);
+        assert!(!owners.owners.contains_key(&14));
+        assert!(!owners.unowned.contains(&14));
+    }
+}
@@ -0,0 +1,141 @@
+/*
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/.
+ */
+
+//! User names of the uids processes run as, from /etc/passwd.
+//! Users from other NSS sources (LDAP, sssd, systemd's dynamic users) aren't in the file and come
+//! out without a name.
+
+use std::collections::{HashMap, HashSet};
+use std::path::PathBuf;
+
+/// Reload interval when the config leaves it at 0
+const DEFAULT_REFRESH_INTERVAL: u32 = 60;
+
+/// User names by uid, reloaded every few collections or when a uid shows up that isn't known.
+pub struct Users {
+    path: PathBuf,
+    names: HashMap<u32, String>,
+    /// Uids that weren't in the file at the last reload, so they don't force a reload each time
+    missing: HashSet<u32>,
+    /// Collections since the last reload
+    age: u32,
+    reloads: u32,
+}
+
+impl Default for Users {
+    fn default() -> Self {
+        Self::new("/etc/passwd".into())
+    }
+}
+
+impl Users {
+    fn new(path: PathBuf) -> Self {
+        Self {
+            path,
+            names: HashMap::new(),
+            missing: HashSet::new(),
+            // Load on the first collection
+            age: u32::MAX,
+            reloads: 0,
+        }
+    }
+
+    /// Starts a collection, reloading the file once it's older than the interval.
+    pub fn begin(&mut self, refresh_interval: u32) {
+        let interval = match refresh_interval {
+            0 => DEFAULT_REFRESH_INTERVAL,
+            interval => interval,
+        };
+        self.age = self.age.saturating_add(1);
+        if self.age >= interval {
+            self.reload();
+        }
+    }
+
+    /// Looks a uid up, empty when the file doesn't list it.
+    pub fn name(&mut self, uid: u32) -> String {
+        if !self.names.contains_key(&uid) && !self.missing.contains(&uid) {
+            // A user added since the last reload
+            self.reload();
+        }
+        match self.names.get(&uid) {
+            Some(name) => name.clone(),
+            None => {
+                self.missing.insert(uid);
+                String::new()
+            }
+        }
+    }
+
+    fn reload(&mut self) {
+        self.names = std::fs::read_to_string(&self.path)
+            .inspect_err(|e| tracing::debug!("unable to read {}: {}", self.path.display(), e))
+            .map(|passwd| parse_passwd(&passwd))
+            .unwrap_or_default();
+        self.missing.clear();
+        self.age = 0;
+        self.reloads += 1;
+    }
+}
+
+/// Parses the "name:password:uid:gid:gecos:home:shell" lines of a passwd file, the first entry of
+/// a uid wins like it does for getpwuid.
+fn parse_passwd(passwd: &str) -> HashMap<u32, String> {
+    let mut names = HashMap::new();
+    for line in passwd.lines() {
+        let mut fields = line.split(':');
+        let (Some(name), Some(uid)) = (fields.next(), fields.nth(1)) else {
+            continue;
+        };
+        if let Ok(uid) = uid.parse() {
+            names.entry(uid).or_insert_with(|| name.to_string());
+        }
+    }
+    names
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::collector::helpers::test::FakeSysfs;
+
+    #[test]
+    fn user_names() {
+        let passwd = FakeSysfs::new("passwd");
+        passwd.write(
+            "passwd",
+            "root:x:0:0:root:/root:/bin/bash\n# comment\nuser:x:1000:1000::/home/user:/bin/sh\n\
+             toor:x:0:0::/root:/bin/sh\n",
+        );
+        let mut users = Users::new(passwd.path("passwd"));
+        users.begin(3);
+        let (root, user) = (users.name(0), users.name(1000));
+
+        // A user added later is picked up as soon as a process runs as it
+        passwd.write(
+            "passwd",
+            "root:x:0:0::/root:/bin/bash\nnew:x:1001:1001::/:/bin/sh\n",
+        );
+        users.begin(3);
+        let new = users.name(1001);
+        let reloads = users.reloads;
+        // Unlisted uids reload once, then wait for the interval
+        let unlisted = [users.name(2000), users.name(2000)];
+        let reloads_unlisted = users.reloads;
+        for _ in 0..3 {
+            users.begin(3);
+        }
+        let reloads_interval = users.reloads;
+
+        assert_eq!(root, "root");
+        assert_eq!(user, "user");
+        assert_eq!(new, "new");
+        assert_eq!(reloads, 2);
+        assert_eq!(unlisted, ["", ""]);
+        assert_eq!(reloads_unlisted, 3);
+        assert_eq!(reloads_interval, 4);
+    }
+}
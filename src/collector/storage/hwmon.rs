@@ -0,0 +1,146 @@
+/*
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/.
+ */
+//! Disk temperatures from the drivetemp (SATA) and nvme hwmon drivers, which anyone can read.
+//! The hwmon devices hang off the SCSI device or NVMe controller rather than the disk, so they
+//! are matched to disks once and only looked up again when a disk appears.
+
+use std::collections::{HashMap, HashSet};
+
+use rustix::fd::{AsFd, BorrowedFd, OwnedFd};
+use rustix::fs::{Mode, OFlags};
+
+use crate::collector::helpers::*;
+
+/// Temperature sensors of the disks
+#[derive(Default)]
+pub struct Sensors {
+    hwmons: Vec<OwnedFd>,
+    /// Disk names to their hwmon device, the namespaces of an NVMe controller share one
+    disks: HashMap<String, usize>,
+    /// Disks the last lookup knew about, with or without a sensor
+    scanned: HashSet<String>,
+}
+
+impl Sensors {
+    /// Reads the temperature of a disk, unset for disks without a sensor.
+    pub fn temperature(&mut self, device_id: &str) -> Option<f32> {
+        if !self.scanned.contains(device_id) {
+            self.scan();
+            self.scanned.insert(device_id.to_string());
+        }
+        let hwmon = self.hwmons.get(*self.disks.get(device_id)?)?;
+        sysfs::readat_hwmon_temp(hwmon.as_fd(), "temp1_input")
+    }
+
+    /// Forgets the disks that are gone.
+    pub fn retain(&mut self, present: impl Fn(&str) -> bool) {
+        self.disks.retain(|device_id, _| present(device_id));
+        self.scanned.retain(|device_id| present(device_id));
+    }
+
+    fn scan(&mut self) {
+        self.hwmons.clear();
+        self.disks.clear();
+        for hwmon in ["drivetemp", "nvme"]
+            .into_iter()
+            .flat_map(sysfs::find_class_hwmon)
+        {
+            for disk in disks(hwmon.as_fd()) {
+                self.disks.insert(disk.clone(), self.hwmons.len());
+                self.scanned.insert(disk);
+            }
+            self.hwmons.push(hwmon);
+        }
+    }
+}
+
+/// Finds the disks of the device a hwmon device belongs to: the block devices of a SCSI device, or
+/// the namespaces of an NVMe controller.
+fn disks(hwmon: BorrowedFd) -> Vec<String> {
+    let Ok(device) = rustix::fs::openat(
+        hwmon,
+        "device",
+        OFlags::RDONLY | OFlags::CLOEXEC | OFlags::DIRECTORY,
+        Mode::empty(),
+    ) else {
+        return Vec::new();
+    };
+    let list = |fd: BorrowedFd| -> Vec<String> {
+        rustix::fs::Dir::read_from(fd)
+            .into_iter()
+            .flatten()
+            .flatten()
+            .map(|entry| entry.file_name().to_string_lossy().into_owned())
+            .filter(|name| name != "." && name != "..")
+            .collect()
+    };
+    let mut disks = match rustix::fs::openat(
+        device.as_fd(),
+        "block",
+        OFlags::RDONLY | OFlags::CLOEXEC | OFlags::DIRECTORY,
+        Mode::empty(),
+    ) {
+        Ok(block) => list(block.as_fd()),
+        Err(_) => list(device.as_fd())
+            .iter()
+            .filter_map(|name| namespace(name))
+            .collect(),
+    };
+    disks.sort();
+    disks.dedup();
+    disks
+}
+
+/// Maps a child of an NVMe controller to the block device of its namespace. Namespaces are
+/// "nvme<controller>n<namespace>", or with native multipathing hidden per-path devices
+/// "nvme<subsystem>c<controller>n<namespace>" behind "nvme<subsystem>n<namespace>".
+fn namespace(name: &str) -> Option<String> {
+    let rest = name.strip_prefix("nvme")?;
+    let (instance, rest) = rest.split_at(rest.find(|c: char| !c.is_ascii_digit())?);
+    let rest = match rest.strip_prefix('c') {
+        Some(path) => path.trim_start_matches(|c: char| c.is_ascii_digit()),
+        None => rest,
+    };
+    let namespace = rest.strip_prefix('n')?;
+    (!instance.is_empty() && !namespace.is_empty() && namespace.bytes().all(|c| c.is_ascii_digit()))
+        .then(|| format!("nvme{instance}n{namespace}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::collector::helpers::test::FakeSysfs;
+
+    #[test]
+    fn sensor_disks() {
+        let root = FakeSysfs::new("disk-hwmon");
+        let hwmon = |name: &str, device: &str, children: &[&str]| {
+            for child in children {
+                root.dir(format!("{device}/{child}"));
+            }
+            root.symlink(root.path(device), format!("{name}/device"));
+            root.open_dir(name)
+        };
+        let hwmons = [
+            hwmon("hwmon1", "ata1/0:0:0:0", &["block/sda", "scsi_disk"]),
+            hwmon("hwmon2", "nvme0", &["nvme0n1", "nvme0n2", "ng0n1", "power"]),
+            // A controller behind a multipath subsystem
+            hwmon("hwmon3", "nvme1", &["nvme1c1n1", "hwmon3"]),
+        ];
+        let disks = hwmons.map(|hwmon| disks(hwmon.as_fd()));
+
+        assert_eq!(
+            disks,
+            [
+                vec!["sda".to_string()],
+                vec!["nvme0n1".to_string(), "nvme0n2".to_string()],
+                vec!["nvme1n1".to_string()],
+            ]
+        );
+        assert_eq!(namespace("nvme0"), None);
+        assert_eq!(namespace("nvme2c5n3").as_deref(), Some("nvme2n3"));
+    }
+}
@@ -0,0 +1,128 @@
+/*
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/.
+ */
+//! md RAID array status from the md directory of the array's sysfs device, which carries the same
+//! data as /proc/mdstat without having to parse it.
+
+use rustix::fd::{AsFd, BorrowedFd};
+use rustix::fs::{Mode, OFlags};
+
+use super::{RaidInfo, RaidMember};
+use crate::collector::helpers::*;
+
+/// Reads the state of an array, `None` for md devices that aren't set up yet.
+pub fn read(array: BorrowedFd) -> Option<RaidInfo> {
+    let md = rustix::fs::openat(
+        array,
+        "md",
+        OFlags::RDONLY | OFlags::DIRECTORY | OFlags::CLOEXEC,
+        Mode::empty(),
+    )
+    .ok()?;
+    let level = sysfs::readat_string(md.as_fd(), "level").filter(|level| !level.is_empty())?;
+
+    // Members are the dev-<name> subdirectories
+    let mut members: Vec<_> = rustix::fs::Dir::read_from(md.as_fd())
+        .ok()?
+        .flatten()
+        .filter_map(|entry| {
+            let name = entry.file_name().to_string_lossy().into_owned();
+            let device_id = name.strip_prefix("dev-")?.to_string();
+            let state =
+                sysfs::readat_string(md.as_fd(), &format!("{name}/state")).unwrap_or_default();
+            Some(RaidMember {
+                device_id,
+                state: member_state(&state).to_string(),
+            })
+        })
+        .collect();
+    members.sort_by(|a, b| a.device_id.cmp(&b.device_id));
+
+    // Arrays without redundancy (raid0, linear) have no degraded count
+    let missing_devices = sysfs::readat_u32(md.as_fd(), "degraded").unwrap_or_default();
+    let sync_action = sysfs::readat_string(md.as_fd(), "sync_action").unwrap_or_default();
+    Some(RaidInfo {
+        level,
+        array_state: sysfs::readat_string(md.as_fd(), "array_state").unwrap_or_default(),
+        raid_disks: sysfs::readat_u32(md.as_fd(), "raid_disks").unwrap_or_default(),
+        missing_devices,
+        degraded: missing_devices != 0 || members.iter().any(|member| member.state == "faulty"),
+        sync_progress_percent: (sync_action != "idle")
+            .then(|| sysfs::readat_string(md.as_fd(), "sync_completed"))
+            .flatten()
+            .and_then(|completed| sync_progress(&completed)),
+        sync_action,
+        members,
+    })
+}
+
+/// Boils the comma separated flags of a member down to its role in the array.
+fn member_state(flags: &str) -> &'static str {
+    let flags: Vec<_> = flags.split(',').collect();
+    if flags.contains(&"faulty") {
+        "faulty"
+    } else if flags.contains(&"spare") {
+        // A spare being rebuilt onto is in the array but not in sync yet
+        if flags.contains(&"in_sync") {
+            "active"
+        } else {
+            "spare"
+        }
+    } else if flags.contains(&"in_sync") {
+        "active"
+    } else {
+        "unknown"
+    }
+}
+
+/// Parses sync_completed, "<done> / <total>" in sectors or "none" while nothing is running.
+fn sync_progress(completed: &str) -> Option<f64> {
+    let (done, total) = completed.split_once('/')?;
+    let (done, total) = (
+        done.trim().parse::<u64>().ok()?,
+        total.trim().parse::<u64>().ok()?,
+    );
+    (total != 0).then(|| done as f64 / total as f64 * 100.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::collector::helpers::test::FakeSysfs;
+
+    #[test]
+    fn degraded_array() {
+        let root = FakeSysfs::new("md");
+        let file = |path: &str, contents: &str| root.write(path, contents);
+        file("md0/md/level", "raid1\n");
+        file("md0/md/array_state", "clean\n");
+        file("md0/md/raid_disks", "2\n");
+        file("md0/md/degraded", "1\n");
+        file("md0/md/sync_action", "recover\n");
+        file("md0/md/sync_completed", "250 / 1000\n");
+        file("md0/md/dev-sda1/state", "in_sync\n");
+        file("md0/md/dev-sdb1/state", "faulty\n");
+        file("md0/md/dev-sdc1/state", "spare\n");
+        file("md1/md/level", "\n");
+        let [md0, inactive] = ["md0", "md1"].map(|name| read(root.open_dir(name).as_fd()));
+        let md0 = md0.unwrap();
+        assert_eq!(md0.level, "raid1");
+        assert!(md0.degraded);
+        assert_eq!(md0.missing_devices, 1);
+        assert_eq!(md0.sync_action, "recover");
+        assert_eq!(md0.sync_progress_percent, Some(25.0));
+        let states: Vec<_> = md0
+            .members
+            .iter()
+            .map(|member| (member.device_id.as_str(), member.state.as_str()))
+            .collect();
+        assert_eq!(
+            states,
+            [("sda1", "active"), ("sdb1", "faulty"), ("sdc1", "spare")]
+        );
+        assert_eq!(inactive, None);
+        assert_eq!(sync_progress("none"), None);
+    }
+}
@@ -6,7 +6,7 @@
 //! Storage device collector
 
 use std::collections::HashMap;
-use std::os::fd::AsRawFd;
+use std::os::fd::{AsRawFd, BorrowedFd, OwnedFd};
 
 use rustix::fd::AsFd;
 use rustix::fs::{AtFlags, Mode, OFlags};
@@ -16,8 +16,21 @@ pub use crate::metrics::storage::*;
 
 use super::helpers::*;
 
+mod hwmon;
+mod md;
+mod mounts;
+mod nvme;
+mod smart;
+
 pub struct Collector {
-    previous_samples: HashMap<String, (u64, u64)>,
+    /// IO counters of the previous sample, keyed by the "major:minor" device number
+    previous_samples: HashMap<String, Sampler<DiskStats>>,
+    /// Identity and queue settings of each device with the sequence number (or device number) they
+    /// were read for, a different number means another disk took the name
+    identities: HashMap<String, (String, Identity, Queue)>,
+    smart: smart::Cache,
+    nvme: nvme::Reader,
+    sensors: hwmon::Sensors,
 }
 
 impl Default for Collector {
@@ -34,11 +47,18 @@ impl super::Collector for Collector {
     }
 
     fn collect(&mut self, config: &crate::metrics::Config) -> anyhow::Result<Self::Output> {
-        let Some(config) = config.storage else {
+        let Some(config) = config.storage.as_ref() else {
             return Ok(Snapshot::default());
         };
 
         let mut devices = Vec::new();
+        let now = std::time::Instant::now();
+        let mount_points = mounts::mount_points();
+        let mounted_at = |fd: BorrowedFd| {
+            sysfs::readat_string(fd, "dev")
+                .and_then(|dev| mount_points.get(&dev).cloned())
+                .unwrap_or_default()
+        };
         for entry in std::fs::read_dir("/sys/block")? {
             let Ok(entry) = entry else {
                 continue;
@@ -51,30 +71,57 @@ impl super::Collector for Collector {
             ) else {
                 continue;
             };
-            // Filter out virtual devices
-            if !rustix::fs::statat(dir_fd.as_fd(), "device", AtFlags::empty()).is_ok() {
+            // Filter out virtual devices, except for the ones stacked on other block devices
+            let slaves = list_dir(dir_fd.as_fd(), "slaves");
+            if !rustix::fs::statat(dir_fd.as_fd(), "device", AtFlags::empty()).is_ok()
+                && slaves.is_empty()
+            {
                 continue;
             }
 
             let device_id = entry.file_name().to_string_lossy().to_string();
 
-            // Now we can read the data
-            let Some(name) = sysfs::readat_string(dir_fd.as_fd(), "device/model") else {
-                continue;
+            // Every attached disk gets a new sequence number (Linux 5.15 and later)
+            let sequence = sysfs::readat_string(dir_fd.as_fd(), "diskseq")
+                .or_else(|| sysfs::readat_string(dir_fd.as_fd(), "dev"))
+                .unwrap_or_default();
+            let (identity, queue) = match self.identities.get(&device_id) {
+                Some((read_for, identity, queue)) if *read_for == sequence => {
+                    (identity.clone(), queue.clone())
+                }
+                _ => {
+                    let identity = Identity::read(dir_fd.as_fd());
+                    let queue = Queue::read(dir_fd.as_fd());
+                    self.identities.insert(
+                        device_id.clone(),
+                        (sequence, identity.clone(), queue.clone()),
+                    );
+                    (identity, queue)
+                }
             };
 
+            let path = std::fs::read_link(format!("/proc/self/fd/{}", dir_fd.as_raw_fd()))
+                .map(|path| path.to_string_lossy().to_string())
+                .unwrap_or_default();
+            let bus = bus(&path);
+            let removable = sysfs::readat_u32(dir_fd.as_fd(), "removable") == Some(1);
+            // Sticks and cards, and USB disks that don't set the removable flag
+            if config.exclude_removable && (removable || bus == "usb" || bus == "mmc") {
+                continue;
+            }
+
             // Calculate type
-            let ty = if device_id.starts_with("mmcblk") {
+            let ty = if device_id.starts_with("dm-") {
+                DeviceType::DeviceMapper
+            } else if device_id.starts_with("md") {
+                DeviceType::Raid
+            } else if device_id.starts_with("mmcblk") {
                 DeviceType::SdCard
             } else if device_id.starts_with("sr") {
                 DeviceType::Optical
             } else if device_id.starts_with("nvme") {
                 DeviceType::Nvme
-            } else if let Ok(link) =
-                std::fs::read_link(format!("/proc/self/fd/{}", dir_fd.as_raw_fd()))
-                    .map(|path| path.to_string_lossy().to_string())
-                && link.contains("/usb")
-            {
+            } else if bus == "usb" {
                 DeviceType::Usb
             } else if let Some(rotational) = sysfs::readat_u32(dir_fd.as_fd(), "queue/rotational") {
                 if rotational == 1 {
@@ -92,53 +139,22 @@ impl super::Collector for Collector {
 
             let usage = config
                 .usage
-                .then(|| {
-                    let Some(stat) = sysfs::readat_string(dir_fd.as_fd(), "stat") else {
-                        return None;
-                    };
-
-                    let split: Vec<_> = stat.split_ascii_whitespace().collect();
-
-                    let Some((total_read, total_write)) = split
-                        .get(2)
-                        .and_then(|s| s.parse::<u64>().ok().map(|r| r * 512))
-                        .zip(
-                            split
-                                .get(6)
-                                .and_then(|s| s.parse::<u64>().ok().map(|w| w * 512)),
-                        )
-                    else {
-                        return None;
-                    };
-
-                    let Some(key) = sysfs::readat_string(dir_fd.as_fd(), "dev") else {
-                        return None;
-                    };
-                    let Some(&(prev_read, prev_write)) = self.previous_samples.get(&key) else {
-                        self.previous_samples
-                            .insert(key.clone(), (total_read, total_write));
-                        return Some(DiskUsage {
-                            read: 0,
-                            write: 0,
-                            total_read,
-                            total_write,
-                        });
-                    };
-
-                    let read = total_read.saturating_sub(prev_read);
-                    let write = total_write.saturating_sub(prev_write);
-
-                    self.previous_samples
-                        .insert(key.clone(), (total_read, total_write));
-
-                    Some(DiskUsage {
-                        read,
-                        write,
-                        total_read,
-                        total_write,
-                    })
-                })
+                .then(|| self.read_usage(dir_fd.as_fd(), now))
                 .flatten();
+            let partitions = read_partitions(dir_fd.as_fd())
+                .into_iter()
+                .map(|(device_id, partition)| Partition {
+                    capacity: sysfs::readat_u64(partition.as_fd(), "size").unwrap_or_default()
+                        * 512,
+                    usage: config
+                        .usage
+                        .then(|| self.read_usage(partition.as_fd(), now))
+                        .flatten(),
+                    device_id,
+                    holders: list_dir(partition.as_fd(), "holders"),
+                    mount_points: mounted_at(partition.as_fd()),
+                })
+                .collect();
 
             let writable = if let Some(ro) = sysfs::readat_u32(dir_fd.as_fd(), "ro") {
                 ro == 0
@@ -146,13 +162,50 @@ impl super::Collector for Collector {
                 false
             };
 
-            let removable = if let Some(removable) = sysfs::readat_u32(dir_fd.as_fd(), "removable")
-            {
-                removable == 1
+            let smart = config
+                .smart
+                .then(|| {
+                    self.smart.get(
+                        &device_id,
+                        config.smart_interval_secs,
+                        config.smart_subprocess,
+                        now,
+                    )
+                })
+                .flatten();
+
+            let temperature = (config.temperature || config.nvme)
+                .then(|| self.sensors.temperature(&device_id))
+                .flatten();
+            let nvme = (config.nvme && ty == DeviceType::Nvme as i32)
+                .then(|| {
+                    self.nvme.read(
+                        &device_id,
+                        temperature,
+                        smart::interval(config.smart_interval_secs),
+                        now,
+                    )
+                })
+                .flatten();
+
+            // The uuid starts with the kind of mapping, e.g. "CRYPT-LUKS2-..." or "LVM-..."
+            let (name, mapper_type) = if ty == DeviceType::DeviceMapper as i32 {
+                (
+                    sysfs::readat_string(dir_fd.as_fd(), "dm/name").unwrap_or_default(),
+                    sysfs::readat_string(dir_fd.as_fd(), "dm/uuid")
+                        .and_then(|uuid| uuid.split('-').next().map(String::from))
+                        .unwrap_or_default(),
+                )
             } else {
-                false
+                (identity.model, String::new())
             };
 
+            // Reads of a device that was unplugged in the meantime fail, skip it rather than
+            // reporting what was read before it went away
+            if rustix::fs::statat(dir_fd.as_fd(), "dev", AtFlags::empty()).is_err() {
+                continue;
+            }
+
             devices.push(Device {
                 name,
                 ty,
@@ -161,10 +214,52 @@ impl super::Collector for Collector {
                 device_id,
                 writable,
                 removable,
+                partitions,
+                smart,
+                nvme,
+                serial: identity.serial,
+                firmware_revision: identity.firmware_revision,
+                logical_block_size: identity.logical_block_size,
+                physical_block_size: identity.physical_block_size,
+                rotational: identity.rotational,
+                io_scheduler: queue.scheduler,
+                nr_requests: queue.nr_requests,
+                read_ahead_kb: queue.read_ahead_kb,
+                discard: queue.discard,
+                parent_device: String::new(),
+                slaves,
+                holders: list_dir(dir_fd.as_fd(), "holders"),
+                mount_points: mounted_at(dir_fd.as_fd()),
+                mapper_type,
+                raid: (ty == DeviceType::Raid as i32)
+                    .then(|| md::read(dir_fd.as_fd()))
+                    .flatten(),
+                bus: bus.to_string(),
+                temperature_celsius: temperature.filter(|_| config.temperature),
             });
         }
 
-        Ok(Snapshot { devices })
+        // Forget detached devices, a device attached later may reuse their numbers
+        self.previous_samples
+            .retain(|_, sampler| sampler.taken_at() == Some(now));
+        let present = |device_id: &str| devices.iter().any(|device| device.device_id == device_id);
+        self.identities.retain(|device_id, _| present(device_id));
+        self.smart.retain(present);
+        self.nvme.retain(present);
+        self.sensors.retain(present);
+
+        Ok(Snapshot {
+            devices: if config.flatten {
+                flatten(devices)
+            } else {
+                devices
+            },
+            filesystems: if config.filesystems {
+                mounts::read(config)
+            } else {
+                Vec::new()
+            },
+        })
     }
 }
 
@@ -172,21 +267,538 @@ impl Collector {
     pub fn new() -> Self {
         Self {
             previous_samples: HashMap::new(),
+            identities: HashMap::new(),
+            smart: smart::Cache::default(),
+            nvme: nvme::Reader::default(),
+            sensors: hwmon::Sensors::default(),
+        }
+    }
+
+    /// Reads the IO counters of a disk or partition and turns them into rates over the time since
+    /// the previous sample.
+    fn read_usage(&mut self, fd: BorrowedFd, now: std::time::Instant) -> Option<DiskUsage> {
+        let stats = DiskStats::parse(&sysfs::readat_string(fd, "stat")?)?;
+        let key = sysfs::readat_string(fd, "dev")?;
+        let delta = self
+            .previous_samples
+            .entry(key)
+            .or_default()
+            .push_at(stats.clone(), now);
+        let mut usage = DiskUsage {
+            total_read: stats.read_sectors * SECTOR_SIZE,
+            total_write: stats.write_sectors * SECTOR_SIZE,
+            in_flight: stats.in_flight,
+            io_time_ms_total: stats.io_ticks,
+            weighted_io_time_ms_total: stats.time_in_queue,
+            ..Default::default()
+        };
+        // Counters that went backwards belong to a re-attached device, which starts over like a new one
+        if let Some(sampler::Delta {
+            change: Some(change),
+            interval,
+        }) = delta
+        {
+            let secs = interval.as_secs_f64();
+            let per_second = |change: u64| {
+                if secs == 0.0 {
+                    0
+                } else {
+                    (change as f64 / secs) as u64
+                }
+            };
+            usage.read = change.read_sectors * SECTOR_SIZE;
+            usage.write = change.write_sectors * SECTOR_SIZE;
+            usage.read_bytes_per_second = per_second(change.read_sectors * SECTOR_SIZE);
+            usage.write_bytes_per_second = per_second(change.write_sectors * SECTOR_SIZE);
+            usage.read_iops = per_second(change.read_ios);
+            usage.write_iops = per_second(change.write_ios);
+            usage.utilization_percent = if secs == 0.0 {
+                0.0
+            } else {
+                (change.io_ticks as f64 / (secs * 1000.0) * 100.0).min(100.0)
+            };
+            // Left unset without completed requests, an idle disk has no latency rather than a zero one
+            let latency = |ticks: u64, ios: u64| (ios != 0).then(|| ticks as f64 / ios as f64);
+            usage.read_latency_ms = latency(change.read_ticks, change.read_ios);
+            usage.write_latency_ms = latency(change.write_ticks, change.write_ios);
+            usage.avg_queue_depth = (secs != 0.0 && change.read_ios + change.write_ios != 0)
+                .then(|| change.time_in_queue as f64 / (secs * 1000.0));
+        }
+        Some(usage)
+    }
+}
+
+/// What identifies a physical drive, which doesn't change while it is attached
+#[derive(Debug, Clone, Default, PartialEq)]
+struct Identity {
+    model: String,
+    serial: String,
+    firmware_revision: String,
+    logical_block_size: u32,
+    physical_block_size: u32,
+    rotational: bool,
+}
+
+impl Identity {
+    /// Reads the identity of a disk, the attributes are named differently by each subsystem.
+    fn read(fd: BorrowedFd) -> Self {
+        let first = |paths: &[&str]| {
+            paths
+                .iter()
+                .find_map(|path| sysfs::readat_string(fd, path).filter(|value| !value.is_empty()))
+                .unwrap_or_default()
+        };
+        let serial = match first(&["device/serial", "serial"]) {
+            // SCSI and SATA disks only have it in the unit serial number VPD page
+            serial if serial.is_empty() => rustix::fs::openat(
+                fd,
+                "device/vpd_pg80",
+                OFlags::RDONLY | OFlags::CLOEXEC,
+                Mode::empty(),
+            )
+            .ok()
+            .and_then(|page| sysfs::read_bin(page.as_fd()))
+            .and_then(|page| parse_unit_serial(&page))
+            .unwrap_or_default(),
+            serial => serial,
+        };
+        Self {
+            // mmc cards have a name rather than a model
+            model: first(&["device/model", "device/name"]),
+            serial,
+            firmware_revision: first(&["device/firmware_rev", "device/rev", "device/fwrev"]),
+            logical_block_size: sysfs::readat_u32(fd, "queue/logical_block_size")
+                .unwrap_or_default(),
+            physical_block_size: sysfs::readat_u32(fd, "queue/physical_block_size")
+                .unwrap_or_default(),
+            rotational: sysfs::readat_u32(fd, "queue/rotational") == Some(1),
+        }
+    }
+}
+
+/// Request queue settings of a block device, the knobs that matter when tuning its IO
+#[derive(Debug, Clone, Default, PartialEq)]
+struct Queue {
+    /// Active IO scheduler, empty for devices without a request queue
+    scheduler: String,
+    nr_requests: u32,
+    read_ahead_kb: u32,
+    discard: bool,
+}
+
+impl Queue {
+    fn read(fd: BorrowedFd) -> Self {
+        Self {
+            scheduler: sysfs::readat_string(fd, "queue/scheduler")
+                .map(|schedulers| active_scheduler(&schedulers))
+                .unwrap_or_default(),
+            nr_requests: sysfs::readat_u32(fd, "queue/nr_requests").unwrap_or_default(),
+            read_ahead_kb: sysfs::readat_u32(fd, "queue/read_ahead_kb").unwrap_or_default(),
+            discard: sysfs::readat_u64(fd, "queue/discard_granularity").is_some_and(|g| g > 0),
+        }
+    }
+}
+
+/// Picks the active scheduler out of queue/scheduler, which lists the available ones with the
+/// active one in brackets ("mq-deadline kyber [bfq] none"), or just "none" when there's no choice.
+fn active_scheduler(schedulers: &str) -> String {
+    let listed: Vec<_> = schedulers.split_whitespace().collect();
+    match listed.iter().find_map(|scheduler| {
+        scheduler
+            .strip_prefix('[')
+            .and_then(|scheduler| scheduler.strip_suffix(']'))
+    }) {
+        Some(active) => active.to_string(),
+        None if listed.len() == 1 => listed[0].to_string(),
+        None => String::new(),
+    }
+}
+
+/// Reads the serial number out of a unit serial number VPD page (0x80), a 4-byte header with the
+/// length of the ASCII serial in its last byte.
+fn parse_unit_serial(page: &[u8]) -> Option<String> {
+    let length = *page.get(3)? as usize;
+    let serial = String::from_utf8_lossy(page.get(4..4 + length)?)
+        .trim()
+        .to_string();
+    (!serial.is_empty()).then_some(serial)
+}
+
+/// The stat files count in 512-byte sectors whatever the logical block size of the device
+const SECTOR_SIZE: u64 = 512;
+
+/// Counters of a block device's stat file (Documentation/block/stat.rst), the same as its line in
+/// /proc/diskstats without the device number and name
+#[derive(Debug, Clone, Default, PartialEq)]
+struct DiskStats {
+    read_ios: u64,
+    read_sectors: u64,
+    /// Milliseconds spent by completed reads
+    read_ticks: u64,
+    write_ios: u64,
+    write_sectors: u64,
+    write_ticks: u64,
+    /// Requests issued to the driver that haven't completed yet, not a counter
+    in_flight: u64,
+    /// Milliseconds during which at least one request was in flight
+    io_ticks: u64,
+    /// Milliseconds spent by all requests, weighted by the number in flight
+    time_in_queue: u64,
+}
+
+impl DiskStats {
+    fn parse(stat: &str) -> Option<Self> {
+        let fields: Vec<u64> = stat
+            .split_ascii_whitespace()
+            .map(|field| field.parse().ok())
+            .collect::<Option<_>>()?;
+        Some(Self {
+            read_ios: *fields.first()?,
+            read_sectors: *fields.get(2)?,
+            read_ticks: *fields.get(3)?,
+            write_ios: *fields.get(4)?,
+            write_sectors: *fields.get(6)?,
+            write_ticks: *fields.get(7)?,
+            in_flight: *fields.get(8)?,
+            io_ticks: *fields.get(9)?,
+            time_in_queue: *fields.get(10)?,
+        })
+    }
+}
+
+impl sampler::Differential for DiskStats {
+    /// `None` when a counter went backwards
+    type Delta = Option<DiskStats>;
+
+    fn delta(&self, previous: &Self) -> Self::Delta {
+        Some(DiskStats {
+            read_ios: self.read_ios.checked_sub(previous.read_ios)?,
+            read_sectors: self.read_sectors.checked_sub(previous.read_sectors)?,
+            read_ticks: self.read_ticks.checked_sub(previous.read_ticks)?,
+            write_ios: self.write_ios.checked_sub(previous.write_ios)?,
+            write_sectors: self.write_sectors.checked_sub(previous.write_sectors)?,
+            write_ticks: self.write_ticks.checked_sub(previous.write_ticks)?,
+            in_flight: self.in_flight,
+            io_ticks: self.io_ticks.checked_sub(previous.io_ticks)?,
+            time_in_queue: self.time_in_queue.checked_sub(previous.time_in_queue)?,
+        })
+    }
+}
+
+/// Finds the bus a disk is connected through from its sysfs device path, empty for virtual devices.
+fn bus(path: &str) -> &'static str {
+    // USB comes first, USB enclosures put an ATA or NVMe bridge behind a USB port
+    [
+        ("/usb", "usb"),
+        ("/nvme", "nvme"),
+        ("/ata", "sata"),
+        ("/mmc_host/", "mmc"),
+        ("/virtio", "virtio"),
+        ("/target", "scsi"),
+    ]
+    .into_iter()
+    .find(|(component, _)| path.contains(component))
+    .map(|(_, bus)| bus)
+    .unwrap_or_default()
+}
+
+/// Turns the device tree into its leaves, with partitions taking the place of their disk.
+fn flatten(devices: Vec<Device>) -> Vec<Device> {
+    let mut leaves = Vec::new();
+    for mut device in devices {
+        let partitions = std::mem::take(&mut device.partitions);
+        for partition in partitions
+            .iter()
+            .filter(|partition| partition.holders.is_empty())
+        {
+            leaves.push(Device {
+                device_id: partition.device_id.clone(),
+                capacity: partition.capacity,
+                usage: partition.usage,
+                holders: Vec::new(),
+                mount_points: partition.mount_points.clone(),
+                parent_device: device.device_id.clone(),
+                smart: None,
+                nvme: None,
+                ..device.clone()
+            });
+        }
+        if partitions.is_empty() && device.holders.is_empty() {
+            leaves.push(device);
         }
     }
+    leaves
+}
+
+/// Lists the entries of a sysfs directory of a block device, e.g. its holders.
+fn list_dir(fd: BorrowedFd, path: &str) -> Vec<String> {
+    let Ok(dir) = rustix::fs::openat(
+        fd,
+        path,
+        OFlags::RDONLY | OFlags::DIRECTORY | OFlags::CLOEXEC,
+        Mode::empty(),
+    ) else {
+        return Vec::new();
+    };
+    let Ok(entries) = rustix::fs::Dir::read_from(dir.as_fd()) else {
+        return Vec::new();
+    };
+    let mut names: Vec<_> = entries
+        .flatten()
+        .map(|entry| entry.file_name().to_string_lossy().into_owned())
+        .filter(|name| name != "." && name != "..")
+        .collect();
+    names.sort();
+    names
+}
+
+/// Opens the partitions of a disk, which are the subdirectories of its sysfs directory with a
+/// partition file. Their counters are already included in the disk's own.
+fn read_partitions(disk: BorrowedFd) -> Vec<(String, OwnedFd)> {
+    let Ok(dir) = rustix::fs::Dir::read_from(disk) else {
+        return Vec::new();
+    };
+    let mut partitions: Vec<_> = dir
+        .flatten()
+        .filter_map(|entry| {
+            let name = entry.file_name().to_string_lossy().into_owned();
+            if name == "." || name == ".." {
+                return None;
+            }
+            let fd = rustix::fs::openat(
+                disk,
+                name.as_str(),
+                OFlags::RDONLY | OFlags::DIRECTORY | OFlags::CLOEXEC,
+                Mode::empty(),
+            )
+            .ok()?;
+            rustix::fs::statat(fd.as_fd(), "partition", AtFlags::empty())
+                .is_ok()
+                .then_some((name, fd))
+        })
+        .collect();
+    partitions.sort_by(|(a, _), (b, _)| a.cmp(b));
+    partitions
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
     use crate::collector::Collector;
+    use crate::collector::helpers::test::FakeSysfs;
+
+    #[test]
+    fn identity() {
+        let root = FakeSysfs::new("identity");
+        let file = |path: &str, contents: &[u8]| root.write(path, contents);
+        file(
+            "nvme0n1/device/model",
+            b"Samsung SSD 980 PRO 1TB                 \n",
+        );
+        file("nvme0n1/device/serial", b"S5GXNF0R123456      \n");
+        file("nvme0n1/device/firmware_rev", b"5B2QGXA7\n");
+        file("nvme0n1/queue/logical_block_size", b"512\n");
+        file("nvme0n1/queue/physical_block_size", b"512\n");
+        file("nvme0n1/queue/rotational", b"0\n");
+        file("nvme0n1/queue/scheduler", b"[none] mq-deadline kyber\n");
+        file("nvme0n1/queue/nr_requests", b"1023\n");
+        file("nvme0n1/queue/read_ahead_kb", b"128\n");
+        file("nvme0n1/queue/discard_granularity", b"512\n");
+        file("sda/device/model", b"ST4000DM004-2CV1\n");
+        file("sda/device/rev", b"0001\n");
+        file("sda/device/vpd_pg80", b"\x00\x80\x00\x10        ZFN0ABCD");
+        file("sda/queue/logical_block_size", b"512\n");
+        file("sda/queue/physical_block_size", b"4096\n");
+        file("sda/queue/rotational", b"1\n");
+        file("sda/queue/scheduler", b"mq-deadline kyber [bfq] none\n");
+        file("sda/queue/discard_granularity", b"0\n");
+        let read = |name: &str| {
+            let fd = root.open_dir(name);
+            (Identity::read(fd.as_fd()), Queue::read(fd.as_fd()))
+        };
+        let [(nvme, nvme_queue), (sata, sata_queue)] = ["nvme0n1", "sda"].map(read);
+        assert_eq!(
+            nvme,
+            Identity {
+                model: "Samsung SSD 980 PRO 1TB".to_string(),
+                serial: "S5GXNF0R123456".to_string(),
+                firmware_revision: "5B2QGXA7".to_string(),
+                logical_block_size: 512,
+                physical_block_size: 512,
+                rotational: false,
+            }
+        );
+        assert_eq!(
+            nvme_queue,
+            Queue {
+                scheduler: "none".to_string(),
+                nr_requests: 1023,
+                read_ahead_kb: 128,
+                discard: true,
+            }
+        );
+        assert_eq!(sata_queue.scheduler, "bfq");
+        assert!(!sata_queue.discard);
+        assert_eq!(active_scheduler("none"), "none");
+        assert_eq!(active_scheduler(""), "");
+        assert_eq!(sata.serial, "ZFN0ABCD");
+        assert_eq!(sata.firmware_revision, "0001");
+        assert_eq!(sata.physical_block_size, 4096);
+        assert!(sata.rotational);
+    }
+
+    #[test]
+    fn buses() {
+        assert_eq!(
+            bus(
+                "/sys/devices/pci0000:00/0000:00:14.0/usb2/2-1/2-1:1.0/host6/target6:0:0/6:0:0:0/block/sdb"
+            ),
+            "usb"
+        );
+        assert_eq!(
+            bus("/sys/devices/pci0000:00/0000:00:1d.0/0000:3d:00.0/nvme/nvme0/nvme0n1"),
+            "nvme"
+        );
+        assert_eq!(
+            bus("/sys/devices/pci0000:00/0000:00:17.0/ata1/host0/target0:0:0/0:0:0:0/block/sda"),
+            "sata"
+        );
+        assert_eq!(
+            bus("/sys/devices/platform/fe320000.mmc/mmc_host/mmc1/mmc1:aaaa/block/mmcblk1"),
+            "mmc"
+        );
+        assert_eq!(
+            bus("/sys/devices/pci0000:00/0000:00:02.0/virtio1/block/vda"),
+            "virtio"
+        );
+        assert_eq!(
+            bus("/sys/devices/pci0000:00/0000:00:10.0/host2/target2:0:0/2:0:0:0/block/sdc"),
+            "scsi"
+        );
+        assert_eq!(bus("/sys/devices/virtual/block/dm-0"), "");
+    }
+
+    #[test]
+    fn flattened() {
+        let partition = |device_id: &str, holders: &[&str], mount_point: &str| Partition {
+            device_id: device_id.to_string(),
+            capacity: 1 << 30,
+            holders: holders.iter().map(|holder| holder.to_string()).collect(),
+            mount_points: vec![mount_point.to_string()],
+            ..Default::default()
+        };
+        let devices = vec![
+            Device {
+                device_id: "nvme0n1".to_string(),
+                ty: DeviceType::Nvme as i32,
+                partitions: vec![
+                    partition("nvme0n1p1", &[], "/boot/efi"),
+                    partition("nvme0n1p2", &["dm-0"], ""),
+                ],
+                ..Default::default()
+            },
+            // LUKS on the second partition, with LVM on top
+            Device {
+                device_id: "dm-0".to_string(),
+                ty: DeviceType::DeviceMapper as i32,
+                slaves: vec!["nvme0n1p2".to_string()],
+                holders: vec!["dm-1".to_string()],
+                mapper_type: "CRYPT".to_string(),
+                ..Default::default()
+            },
+            Device {
+                device_id: "dm-1".to_string(),
+                name: "vg-root".to_string(),
+                ty: DeviceType::DeviceMapper as i32,
+                slaves: vec!["dm-0".to_string()],
+                mount_points: vec!["/".to_string()],
+                mapper_type: "LVM".to_string(),
+                ..Default::default()
+            },
+            Device {
+                device_id: "sda".to_string(),
+                ..Default::default()
+            },
+        ];
+        let leaves: Vec<_> = flatten(devices)
+            .into_iter()
+            .map(|device| (device.device_id, device.parent_device, device.mount_points))
+            .collect();
+        assert_eq!(
+            leaves,
+            [
+                (
+                    "nvme0n1p1".to_string(),
+                    "nvme0n1".to_string(),
+                    vec!["/boot/efi".to_string()]
+                ),
+                ("dm-1".to_string(), String::new(), vec!["/".to_string()]),
+                ("sda".to_string(), String::new(), vec![]),
+            ]
+        );
+    }
+
+    #[test]
+    fn io_rates() {
+        let root = FakeSysfs::new("diskstats");
+        root.write("dev", "8:0\n");
+        let fd = root.open_dir("");
+        let mut collector = super::Collector::new();
+        let start = std::time::Instant::now();
+        let mut sample = |stat: &str, secs: u64| {
+            root.write("stat", stat);
+            collector.read_usage(fd.as_fd(), start + std::time::Duration::from_secs(secs))
+        };
+        let samples = [
+            sample("1000 0 8000 2000 500 0 4000 1000 0 100 200\n", 0),
+            sample("1400 0 12000 2600 700 0 8000 1800 3 600 1400\n", 2),
+            sample("1400 0 12000 2600 700 0 8000 1800 0 600 1400\n", 3),
+            // The device was detached and attached again under the same number
+            sample("10 0 80 0 5 0 40 0 0 1 2\n", 4),
+        ];
+
+        let [first, second, idle, reset] = samples.map(Option::unwrap);
+        assert_eq!(first.total_read, 8000 * 512);
+        assert_eq!(first.read_bytes_per_second, 0);
+        assert_eq!(second.read, 4000 * 512);
+        assert_eq!(second.read_bytes_per_second, 1_024_000);
+        assert_eq!(second.write_bytes_per_second, 1_024_000);
+        assert_eq!(second.read_iops, 200);
+        assert_eq!(second.write_iops, 100);
+        assert_eq!(second.in_flight, 3);
+        assert_eq!(second.utilization_percent, 25.0);
+        assert_eq!(second.weighted_io_time_ms_total, 1400);
+        assert_eq!(first.read_latency_ms, None);
+        assert_eq!(second.read_latency_ms, Some(1.5));
+        assert_eq!(second.write_latency_ms, Some(4.0));
+        assert_eq!(second.avg_queue_depth, Some(0.6));
+        assert_eq!(idle.read_latency_ms, None);
+        assert_eq!(idle.write_latency_ms, None);
+        assert_eq!(idle.avg_queue_depth, None);
+        assert_eq!(idle.utilization_percent, 0.0);
+        assert_eq!(reset.total_read, 80 * 512);
+        assert_eq!(reset.read, 0);
+        assert_eq!(reset.read_iops, 0);
+    }
 
     #[tracing_test::traced_test]
     #[test]
     fn storage() -> anyhow::Result<()> {
         let mut collector = super::Collector::new();
         let mut config = crate::metrics::Config::default();
-        config.storage = Some(Config { usage: true });
+        config.storage = Some(Config {
+            usage: true,
+            filesystems: true,
+            smart: true,
+            smart_interval_secs: 0,
+            smart_subprocess: false,
+            nvme: true,
+            include_fs_types: Vec::new(),
+            exclude_fs_types: Vec::new(),
+            flatten: false,
+            exclude_removable: false,
+            temperature: true,
+        });
 
         let _ = collector.collect(&config)?;
         for _ in 0..60 {
@@ -201,6 +813,16 @@ mod tests {
                     )
                 }
             }
+            for filesystem in snapshot.filesystems.iter() {
+                println!(
+                    "filesystem: {} on {} ({}) available: {}/{}",
+                    filesystem.mount_point,
+                    filesystem.device_id,
+                    filesystem.fs_type,
+                    filesystem.available_bytes,
+                    filesystem.total_bytes
+                )
+            }
             println!("")
         }
         Ok(())
@@ -0,0 +1,289 @@
+/*
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/.
+ */
+//! Mounted filesystems from the mount table of the mount namespace monitord runs in.
+
+use std::collections::HashMap;
+
+use super::{Config, Filesystem};
+use crate::collector::helpers::*;
+
+/// Filesystems without storage of their own, skipped unless the config includes them
+const PSEUDO_FILESYSTEMS: &[&str] = &[
+    "autofs",
+    "binfmt_misc",
+    "bpf",
+    "cgroup",
+    "cgroup2",
+    "configfs",
+    "debugfs",
+    "devpts",
+    "devtmpfs",
+    "efivarfs",
+    "fusectl",
+    "hugetlbfs",
+    "mqueue",
+    "nsfs",
+    "overlay",
+    "proc",
+    "pstore",
+    "ramfs",
+    "rpc_pipefs",
+    "securityfs",
+    "selinuxfs",
+    "sysfs",
+    "tmpfs",
+    "tracefs",
+];
+
+/// A single entry of /proc/self/mountinfo
+#[derive(Debug, Clone, PartialEq)]
+pub struct Mount {
+    /// Device number of the filesystem, major 0 for filesystems without a block device
+    pub major: u32,
+    pub minor: u32,
+    /// The directory of the filesystem that is mounted, "/" unless it's a bind mount of a subdirectory
+    pub root: String,
+    pub mount_point: String,
+    pub fs_type: String,
+    /// What was mounted, e.g. "/dev/nvme0n1p2", or whatever the filesystem puts there ("tmpfs", "proc")
+    pub source: String,
+}
+
+/// Reads the usage of every mounted filesystem the config selects, once per filesystem.
+pub fn read(config: &Config) -> Vec<Filesystem> {
+    let Some(mountinfo) = sysfs::read_string_path("/proc/self/mountinfo") else {
+        tracing::warn!("unable to read /proc/self/mountinfo");
+        return Vec::new();
+    };
+    let mounts: Vec<_> = parse_mountinfo(&mountinfo)
+        .into_iter()
+        .filter(|mount| is_selected(config, &mount.fs_type))
+        .collect();
+    deduplicate(mounts)
+        .into_iter()
+        .filter_map(|mount| {
+            let stat = rustix::fs::statvfs(mount.mount_point.as_str())
+                .inspect_err(|e| tracing::debug!("statvfs {} failed: {}", mount.mount_point, e))
+                .ok()?;
+            Some(Filesystem {
+                device_id: disk(&mount).unwrap_or_default(),
+                device_name: mount.source,
+                mount_point: mount.mount_point,
+                fs_type: mount.fs_type,
+                total_bytes: stat.f_blocks * stat.f_frsize,
+                available_bytes: stat.f_bavail * stat.f_frsize,
+                used_bytes: stat.f_blocks.saturating_sub(stat.f_bfree) * stat.f_frsize,
+                inodes_total: stat.f_files,
+                inodes_used: stat.f_files.saturating_sub(stat.f_ffree),
+                inodes_available: stat.f_favail,
+                inodes_free: stat.f_ffree,
+                inode_usage_percent: inode_usage(stat.f_files, stat.f_ffree),
+                read_only: stat.f_flag.contains(rustix::fs::StatVfsMountFlags::RDONLY),
+            })
+        })
+        .collect()
+}
+
+/// Percentage of the inodes in use, like `df -i`. Filesystems that allocate inodes on demand report
+/// a total of 0, so there's nothing to run out of.
+fn inode_usage(total: u64, free: u64) -> Option<f64> {
+    (total > 0).then(|| total.saturating_sub(free) as f64 / total as f64 * 100.0)
+}
+
+/// Maps device numbers to the places they are mounted at.
+pub fn mount_points() -> HashMap<String, Vec<String>> {
+    let mut mount_points: HashMap<String, Vec<String>> = HashMap::new();
+    for mount in
+        parse_mountinfo(&sysfs::read_string_path("/proc/self/mountinfo").unwrap_or_default())
+    {
+        mount_points
+            .entry(format!("{}:{}", mount.major, mount.minor))
+            .or_default()
+            .push(mount.mount_point);
+    }
+    mount_points
+}
+
+/// Pseudo filesystems are skipped unless included, and excluded types always are.
+fn is_selected(config: &Config, fs_type: &str) -> bool {
+    let listed = |types: &[String]| types.iter().any(|listed| listed == fs_type);
+    !listed(&config.exclude_fs_types)
+        && (!PSEUDO_FILESYSTEMS.contains(&fs_type) || listed(&config.include_fs_types))
+}
+
+/// Keeps one mount per filesystem, bind mounts share the device number of the filesystem they
+/// expose. The mount of the filesystem's root is preferred, then the first one mounted.
+fn deduplicate(mounts: Vec<Mount>) -> Vec<Mount> {
+    let mut unique: Vec<Mount> = Vec::new();
+    let mut seen: HashMap<(u32, u32), usize> = HashMap::new();
+    for mount in mounts {
+        match seen.get(&(mount.major, mount.minor)) {
+            Some(&index) => {
+                if unique[index].root != "/" && mount.root == "/" {
+                    unique[index] = mount;
+                }
+            }
+            None => {
+                seen.insert((mount.major, mount.minor), unique.len());
+                unique.push(mount);
+            }
+        }
+    }
+    unique
+}
+
+/// Finds the disk of a mount. Filesystems like btrfs report an anonymous device number for each
+/// subvolume, so those go through the device node they were mounted from.
+fn disk(mount: &Mount) -> Option<String> {
+    if mount.major != 0 {
+        return disk_of(mount.major, mount.minor);
+    }
+    if !mount.source.starts_with("/dev/") {
+        return None;
+    }
+    let stat = rustix::fs::stat(mount.source.as_str()).ok()?;
+    (rustix::fs::FileType::from_raw_mode(stat.st_mode) == rustix::fs::FileType::BlockDevice)
+        .then(|| {
+            disk_of(
+                rustix::fs::major(stat.st_rdev),
+                rustix::fs::minor(stat.st_rdev),
+            )
+        })
+        .flatten()
+}
+
+/// Parses the mount table. Lines look like
+/// "28 1 254:0 / / rw,relatime shared:1 - ext4 /dev/vda rw", with a variable number of optional
+/// fields before the "-" separator.
+pub fn parse_mountinfo(mountinfo: &str) -> Vec<Mount> {
+    mountinfo
+        .lines()
+        .filter_map(|line| {
+            let fields: Vec<_> = line.split_whitespace().collect();
+            let (major, minor) = fields.get(2)?.split_once(':')?;
+            let separator = fields.iter().skip(6).position(|field| *field == "-")? + 6;
+            Some(Mount {
+                major: major.parse().ok()?,
+                minor: minor.parse().ok()?,
+                root: unescape(fields.get(3)?),
+                mount_point: unescape(fields.get(4)?),
+                fs_type: unescape(fields.get(separator + 1)?),
+                source: unescape(fields.get(separator + 2)?),
+            })
+        })
+        .collect()
+}
+
+/// Undoes the octal escapes the kernel uses for spaces, tabs, newlines and backslashes in paths.
+fn unescape(field: &str) -> String {
+    let bytes = field.as_bytes();
+    let mut unescaped = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let octal = bytes
+            .get(i + 1..i + 4)
+            .and_then(|digits| std::str::from_utf8(digits).ok())
+            .and_then(|digits| u8::from_str_radix(digits, 8).ok());
+        match (bytes[i], octal) {
+            (b'\\', Some(byte)) => {
+                unescaped.push(byte);
+                i += 4;
+            }
+            (byte, _) => {
+                unescaped.push(byte);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&unescaped).into_owned()
+}
+
+/// Finds the kernel name of the disk a block device belongs to, so a filesystem on a partition maps
+/// to the device it is on.
+pub fn disk_of(major: u32, minor: u32) -> Option<String> {
+    let path = std::fs::canonicalize(format!("/sys/dev/block/{major}:{minor}")).ok()?;
+    // Partitions are subdirectories of their disk
+    let disk = if path.join("partition").exists() {
+        path.parent()?
+    } else {
+        &path
+    };
+    Some(disk.file_name()?.to_string_lossy().into_owned())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn mountinfo() {
+        let mountinfo = "23 28 0:22 / /proc rw,relatime - proc proc rw
+28 1 254:0 / / rw,relatime shared:1 - ext4 /dev/vda rw,discard
+29 28 259:2 / /mnt/my\\040disk ro,nosuid shared:5 master:1 - btrfs /dev/nvme0n1p2 ro
+";
+        let mounts = parse_mountinfo(mountinfo);
+        assert_eq!(mounts.len(), 3);
+        assert_eq!(mounts[0].major, 0);
+        assert_eq!(mounts[0].fs_type, "proc");
+        assert_eq!(
+            mounts[1],
+            Mount {
+                major: 254,
+                minor: 0,
+                root: "/".to_string(),
+                mount_point: "/".to_string(),
+                fs_type: "ext4".to_string(),
+                source: "/dev/vda".to_string(),
+            }
+        );
+        assert_eq!(mounts[2].mount_point, "/mnt/my disk");
+        assert_eq!(mounts[2].fs_type, "btrfs");
+        assert_eq!(mounts[2].source, "/dev/nvme0n1p2");
+        assert_eq!(unescape("a\\134b\\"), "a\\b\\");
+    }
+
+    #[test]
+    fn selection() {
+        let mountinfo = "28 1 254:0 / / rw,relatime - ext4 /dev/vda rw
+23 28 0:22 / /proc rw,relatime - proc proc rw
+26 25 0:24 / /dev/shm rw,relatime - tmpfs tmpfs rw
+40 28 0:45 / /var/lib/docker/overlay2/abc/merged rw - overlay overlay rw,lowerdir=/a
+41 28 254:0 /var/lib/kubelet /var/lib/kubelet rw,relatime - ext4 /dev/vda rw
+42 28 254:0 /var/lib/kubelet /mnt/kubelet rw,relatime - ext4 /dev/vda rw
+43 28 259:1 /data /srv/data rw,relatime - xfs /dev/nvme0n1p1 rw
+44 28 259:1 / /data rw,relatime - xfs /dev/nvme0n1p1 rw
+";
+        let mounts = parse_mountinfo(mountinfo);
+        let select = |config: &Config| {
+            deduplicate(
+                mounts
+                    .iter()
+                    .filter(|mount| is_selected(config, &mount.fs_type))
+                    .cloned()
+                    .collect(),
+            )
+            .into_iter()
+            .map(|mount| mount.mount_point)
+            .collect::<Vec<_>>()
+        };
+        // Bind mounts collapse into the mount of the filesystem's root
+        assert_eq!(select(&Config::default()), ["/", "/data"]);
+        let config = Config {
+            include_fs_types: vec!["tmpfs".to_string()],
+            exclude_fs_types: vec!["xfs".to_string(), "proc".to_string()],
+            ..Default::default()
+        };
+        assert_eq!(select(&config), ["/", "/dev/shm"]);
+    }
+
+    #[test]
+    fn inodes() {
+        assert_eq!(inode_usage(1000, 250), Some(75.0));
+        assert_eq!(inode_usage(1000, 1000), Some(0.0));
+        // btrfs
+        assert_eq!(inode_usage(0, 0), None);
+    }
+}
@@ -0,0 +1,242 @@
+/*
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/.
+ */
+//! NVMe wear and endurance from the SMART / health information log page, which takes an admin
+//! command and so CAP_SYS_ADMIN.
+
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+use rustix::fs::{Mode, OFlags};
+
+use super::NvmeHealth;
+use crate::collector::helpers::ioctl::*;
+use crate::{_ioc, _iowr};
+
+/// The admin command passthrough ioctl
+const NVME_IOCTL_ADMIN_CMD: u32 = _iowr!('N' as u32, 0x41, PassthruCmd);
+
+/// Get Log Page admin command
+const NVME_ADMIN_GET_LOG_PAGE: u8 = 0x02;
+
+/// SMART / Health Information log page
+const NVME_LOG_SMART: u32 = 0x02;
+
+/// The log page is controller-wide, asked for with the broadcast namespace
+const NVME_NSID_ALL: u32 = 0xffffffff;
+
+const LOG_SIZE: usize = 512;
+
+/// struct nvme_passthru_cmd (linux/include/uapi/linux/nvme_ioctl.h)
+#[repr(C)]
+#[derive(Default)]
+struct PassthruCmd {
+    opcode: u8,
+    flags: u8,
+    rsvd1: u16,
+    nsid: u32,
+    cdw2: u32,
+    cdw3: u32,
+    metadata: u64,
+    addr: u64,
+    metadata_len: u32,
+    data_len: u32,
+    cdw10: u32,
+    cdw11: u32,
+    cdw12: u32,
+    cdw13: u32,
+    cdw14: u32,
+    cdw15: u32,
+    timeout_ms: u32,
+    result: u32,
+}
+
+unsafe impl rustix::ioctl::Ioctl for PassthruCmd {
+    /// The NVMe status of the command, 0 on success
+    type Output = i32;
+    const IS_MUTATING: bool = true;
+
+    fn opcode(&self) -> rustix::ioctl::Opcode {
+        NVME_IOCTL_ADMIN_CMD
+    }
+
+    fn as_ptr(&mut self) -> *mut std::ffi::c_void {
+        self as *mut _ as *mut std::ffi::c_void
+    }
+
+    unsafe fn output_from_ptr(
+        output: rustix::ioctl::IoctlOutput,
+        _: *mut std::ffi::c_void,
+    ) -> rustix::io::Result<Self::Output> {
+        Ok(output)
+    }
+}
+
+/// The fields of the log page (NVM Express Base Specification, Figure "SMART / Health Information")
+#[derive(Debug, Default, PartialEq)]
+pub struct HealthLog {
+    /// Bit field of critical warnings, 0 while the drive is healthy
+    pub critical_warning: u8,
+    /// Composite temperature in Kelvin, 0 if not reported
+    pub temperature_kelvin: u16,
+    /// Remaining spare capacity in percent
+    pub available_spare: u8,
+    /// Spare capacity below which the drive raises a critical warning
+    pub available_spare_threshold: u8,
+    /// Vendor estimate of the life used in percent, can exceed 100
+    pub percentage_used: u8,
+    /// Thousands of 512-byte units read by the host
+    pub data_units_read: u64,
+    pub data_units_written: u64,
+    pub power_on_hours: u64,
+    /// Unrecovered data integrity errors
+    pub media_errors: u64,
+}
+
+impl HealthLog {
+    pub fn temperature_celsius(&self) -> Option<f64> {
+        (self.temperature_kelvin != 0).then_some(self.temperature_kelvin as f64 - 273.15)
+    }
+
+    /// Parses the raw log page, whose 128-bit counters are saturated to 64 bits
+    pub fn parse(log: &[u8; LOG_SIZE]) -> Self {
+        let u128_at = |offset: usize| {
+            let value = u128::from_le_bytes(log[offset..offset + 16].try_into().unwrap());
+            u64::try_from(value).unwrap_or(u64::MAX)
+        };
+        Self {
+            critical_warning: log[0],
+            temperature_kelvin: u16::from_le_bytes([log[1], log[2]]),
+            available_spare: log[3],
+            available_spare_threshold: log[4],
+            percentage_used: log[5],
+            data_units_read: u128_at(32),
+            data_units_written: u128_at(48),
+            power_on_hours: u128_at(128),
+            media_errors: u128_at(160),
+        }
+    }
+}
+
+/// Reads the health log through the block device of a namespace.
+pub fn health_log(device_id: &str) -> anyhow::Result<HealthLog> {
+    let device = rustix::fs::open(
+        format!("/dev/{device_id}"),
+        OFlags::RDONLY | OFlags::CLOEXEC | OFlags::NONBLOCK,
+        Mode::empty(),
+    )?;
+    let mut log = [0u8; LOG_SIZE];
+    let command = PassthruCmd {
+        opcode: NVME_ADMIN_GET_LOG_PAGE,
+        nsid: NVME_NSID_ALL,
+        addr: log.as_mut_ptr() as u64,
+        data_len: LOG_SIZE as u32,
+        // Number of dwords to read, zero based, and the log page
+        cdw10: ((LOG_SIZE as u32 / 4 - 1) << 16) | NVME_LOG_SMART,
+        ..Default::default()
+    };
+    let status = unsafe { rustix::ioctl::ioctl(&device, command) }?;
+    if status != 0 {
+        anyhow::bail!("get log page failed with NVMe status {:#x}", status);
+    }
+    Ok(HealthLog::parse(&log))
+}
+
+/// Bytes in one of the log page's data units
+const DATA_UNIT_BYTES: u64 = 512 * 1000;
+
+/// Reads the health of NVMe namespaces, keeping the log pages between refreshes.
+pub struct Reader {
+    /// Last log page of each namespace and when it was read
+    logs: HashMap<String, (Instant, Option<HealthLog>)>,
+    /// Admin commands are refused without CAP_SYS_ADMIN, which comes with root
+    privileged: bool,
+}
+
+impl Default for Reader {
+    fn default() -> Self {
+        Self {
+            logs: HashMap::new(),
+            privileged: rustix::process::geteuid().is_root(),
+        }
+    }
+}
+
+impl Reader {
+    /// Reads the health of a namespace, the log page is only asked for again once it is older than
+    /// the interval. The temperature from the nvme hwmon device is preferred over the log's.
+    pub fn read(
+        &mut self,
+        device_id: &str,
+        temperature: Option<f32>,
+        interval: Duration,
+        now: Instant,
+    ) -> Option<NvmeHealth> {
+        let log = if self.privileged {
+            let (read_at, log) = self
+                .logs
+                .entry(device_id.to_string())
+                .or_insert_with(|| (now, read_log(device_id)));
+            if now.duration_since(*read_at) >= interval {
+                *read_at = now;
+                *log = read_log(device_id);
+            }
+            log.as_ref()
+        } else {
+            None
+        };
+        if log.is_none() && temperature.is_none() {
+            return None;
+        }
+        Some(NvmeHealth {
+            temperature_celsius: temperature
+                .map(f64::from)
+                .or_else(|| log?.temperature_celsius()),
+            percentage_used: log.map(|log| log.percentage_used as u32),
+            available_spare_percent: log.map(|log| log.available_spare as u32),
+            available_spare_threshold_percent: log.map(|log| log.available_spare_threshold as u32),
+            data_read_bytes: log.map(|log| log.data_units_read.saturating_mul(DATA_UNIT_BYTES)),
+            data_written_bytes: log
+                .map(|log| log.data_units_written.saturating_mul(DATA_UNIT_BYTES)),
+        })
+    }
+
+    /// Forgets the namespaces that are gone.
+    pub fn retain(&mut self, present: impl Fn(&str) -> bool) {
+        self.logs.retain(|device_id, _| present(device_id));
+    }
+}
+
+fn read_log(device_id: &str) -> Option<HealthLog> {
+    health_log(device_id)
+        .inspect_err(|e| tracing::debug!("no NVMe health log for {}: {}", device_id, e))
+        .ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn health_log() {
+        let mut log = [0u8; 512];
+        log[1..3].copy_from_slice(&310u16.to_le_bytes());
+        log[3] = 100;
+        log[5] = 7;
+        log[32..48].copy_from_slice(&12_345_678u128.to_le_bytes());
+        log[48..64].copy_from_slice(&u128::MAX.to_le_bytes());
+        log[128..144].copy_from_slice(&4_321u128.to_le_bytes());
+        log[160..176].copy_from_slice(&2u128.to_le_bytes());
+        let log = HealthLog::parse(&log);
+        assert_eq!(log.critical_warning, 0);
+        assert_eq!(log.available_spare, 100);
+        assert_eq!(log.percentage_used, 7);
+        assert_eq!(log.data_units_read, 12_345_678);
+        assert_eq!(log.data_units_written, u64::MAX);
+        assert_eq!(log.power_on_hours, 4_321);
+        assert_eq!(log.media_errors, 2);
+        assert!((log.temperature_celsius().unwrap() - 36.85).abs() < 1e-9);
+    }
+}
@@ -0,0 +1,180 @@
+/*
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/.
+ */
+//! SMART health of storage devices.
+//!
+//! NVMe drives are asked for their health log directly, which needs CAP_SYS_ADMIN. Other drives
+//! need smartctl, which only runs when the config allows it.
+//! Reading SMART data is slow and can spin up a sleeping disk, so results are cached and refreshed
+//! on their own, much longer interval.
+
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+use super::{SmartInfo, nvme};
+
+/// Refresh interval when the config leaves it at 0
+const DEFAULT_INTERVAL: Duration = Duration::from_secs(60 * 60);
+
+/// The configured refresh interval of SMART data and NVMe health logs.
+pub fn interval(interval_secs: u32) -> Duration {
+    match interval_secs {
+        0 => DEFAULT_INTERVAL,
+        secs => Duration::from_secs(secs as u64),
+    }
+}
+
+/// Last SMART result of a device
+struct Entry {
+    /// When the device was last asked, whether or not it answered
+    checked_at: Instant,
+    /// The last answer and when it was read, kept while a sleeping disk is skipped
+    info: Option<(Instant, SmartInfo)>,
+}
+
+/// SMART results of every device, refreshed once they are older than the interval.
+#[derive(Default)]
+pub struct Cache {
+    entries: HashMap<String, Entry>,
+}
+
+impl Cache {
+    /// Gets the SMART info of a device, only touching the device when the cached result is older than the interval.
+    pub fn get(
+        &mut self,
+        device_id: &str,
+        interval_secs: u32,
+        subprocess: bool,
+        now: Instant,
+    ) -> Option<SmartInfo> {
+        let interval = interval(interval_secs);
+        let entry = self
+            .entries
+            .entry(device_id.to_string())
+            .or_insert_with(|| Entry {
+                checked_at: now,
+                info: read(device_id, subprocess).map(|info| (now, info)),
+            });
+        if now.duration_since(entry.checked_at) >= interval {
+            entry.checked_at = now;
+            if let Some(info) = read(device_id, subprocess) {
+                entry.info = Some((now, info));
+            }
+        }
+        let (read_at, info) = entry.info.as_ref()?;
+        Some(SmartInfo {
+            age_seconds: now.duration_since(*read_at).as_secs(),
+            ..*info
+        })
+    }
+
+    /// Forgets the devices that are gone.
+    pub fn retain(&mut self, present: impl Fn(&str) -> bool) {
+        self.entries.retain(|device_id, _| present(device_id));
+    }
+}
+
+/// Reads the SMART info of a device from whichever source is available.
+fn read(device_id: &str, subprocess: bool) -> Option<SmartInfo> {
+    let info = if device_id.starts_with("nvme") {
+        nvme::health_log(device_id)
+            .inspect_err(|e| tracing::debug!("no NVMe health log for {}: {}", device_id, e))
+            .ok()
+            .map(|log| SmartInfo {
+                healthy: Some(log.critical_warning == 0),
+                power_on_hours: Some(log.power_on_hours),
+                percentage_used: Some(log.percentage_used as u32),
+                media_errors: Some(log.media_errors),
+                temperature_celsius: log.temperature_celsius(),
+                ..Default::default()
+            })
+    } else {
+        None
+    };
+    match info {
+        Some(info) => Some(info),
+        None if subprocess => smartctl(device_id),
+        None => None,
+    }
+}
+
+/// Runs smartctl on a device. Sleeping disks are skipped rather than woken up.
+fn smartctl(device_id: &str) -> Option<SmartInfo> {
+    let output = std::process::Command::new("smartctl")
+        .args(["--json", "--all", "--nocheck=standby"])
+        .arg(format!("/dev/{device_id}"))
+        .output()
+        .inspect_err(|e| tracing::debug!("unable to run smartctl: {}", e))
+        .ok()?;
+    // The low bits of the exit status mean smartctl couldn't talk to the device (or the disk is
+    // in standby), the others report problems found in the SMART data
+    if output.status.code().is_none_or(|code| code & 0b11 != 0) {
+        return None;
+    }
+    parse_smartctl(&String::from_utf8_lossy(&output.stdout))
+}
+
+/// Picks the health summary out of smartctl's JSON output.
+fn parse_smartctl(json: &str) -> Option<SmartInfo> {
+    let json: serde_json::Value = serde_json::from_str(json).ok()?;
+    // ATA attributes by id, with the raw value being the count for the sector attributes
+    let attribute = |id: u64| {
+        json["ata_smart_attributes"]["table"]
+            .as_array()?
+            .iter()
+            .find(|attribute| attribute["id"].as_u64() == Some(id))?["raw"]["value"]
+            .as_u64()
+    };
+    let nvme = &json["nvme_smart_health_information_log"];
+    Some(SmartInfo {
+        healthy: json["smart_status"]["passed"].as_bool(),
+        power_on_hours: json["power_on_time"]["hours"].as_u64(),
+        reallocated_sectors: attribute(5),
+        pending_sectors: attribute(197),
+        percentage_used: nvme["percentage_used"].as_u64().map(|used| used as u32),
+        media_errors: nvme["media_errors"].as_u64(),
+        temperature_celsius: json["temperature"]["current"].as_f64(),
+        age_seconds: 0,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn smartctl_output() {
+        let ata = r#"{
+            "smart_status": {"passed": false},
+            "power_on_time": {"hours": 31024},
+            "temperature": {"current": 38},
+            "ata_smart_attributes": {"table": [
+                {"id": 5, "name": "Reallocated_Sector_Ct", "raw": {"value": 8, "string": "8"}},
+                {"id": 9, "name": "Power_On_Hours", "raw": {"value": 31024, "string": "31024"}},
+                {"id": 197, "name": "Current_Pending_Sector", "raw": {"value": 0, "string": "0"}}
+            ]}
+        }"#;
+        assert_eq!(
+            parse_smartctl(ata),
+            Some(SmartInfo {
+                healthy: Some(false),
+                power_on_hours: Some(31024),
+                reallocated_sectors: Some(8),
+                pending_sectors: Some(0),
+                temperature_celsius: Some(38.0),
+                ..Default::default()
+            })
+        );
+        let nvme = r#"{
+            "smart_status": {"passed": true},
+            "nvme_smart_health_information_log": {"percentage_used": 3, "media_errors": 0}
+        }"#;
+        let info = parse_smartctl(nvme).unwrap();
+        assert_eq!(info.percentage_used, Some(3));
+        assert_eq!(info.media_errors, Some(0));
+        assert_eq!(info.reallocated_sectors, None);
+        assert_eq!(parse_smartctl("not json"), None);
+    }
+}
@@ -0,0 +1,268 @@
+/*
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/.
+ */
+
+//! Selection of the processes a connection asked for with its process filters, shared by every
+//! transport so they report the same processes.
+//! Filters of the same kind widen the selection (two pid ranges select both), filters of
+//! different kinds narrow it (a user and a name select that user's processes with that name).
+
+use crate::metrics::process::{Config, Process, Snapshot};
+use crate::service::{PidRange, ProcessFilter, process_filter::Filter};
+
+/// The process filters of a connection, with the name patterns compiled once
+#[derive(Debug, Default)]
+pub struct ProcessFilters {
+    users: Vec<String>,
+    pids: Vec<PidRange>,
+    names: Vec<regex::Regex>,
+    statuses: Vec<i32>,
+}
+
+impl ProcessFilters {
+    /// Compiles the filters of a connection request, failing on an invalid name pattern.
+    pub fn new(filters: &[ProcessFilter]) -> anyhow::Result<Self> {
+        let mut compiled = Self::default();
+        for filter in filters.iter().filter_map(|filter| filter.filter.as_ref()) {
+            match filter {
+                Filter::ByUser(user) => compiled.users.push(user.clone()),
+                Filter::ByPid(range) => compiled.pids.push(*range),
+                Filter::ByNameRegex(pattern) => compiled.names.push(regex::Regex::new(pattern)?),
+                Filter::ByStatus(status) => compiled.statuses.push(*status as i32),
+            }
+        }
+        Ok(compiled)
+    }
+
+    /// Whether every process passes.
+    pub fn is_empty(&self) -> bool {
+        self.users.is_empty()
+            && self.pids.is_empty()
+            && self.names.is_empty()
+            && self.statuses.is_empty()
+    }
+
+    /// Whether a process passes, filters on identity fields need identity to be collected.
+    pub fn matches(&self, pid: u32, process: &Process) -> bool {
+        let identity = process.identity.as_ref();
+        let any = |empty: bool, matches: &dyn Fn() -> bool| empty || matches();
+        any(self.users.is_empty(), &|| {
+            identity.is_some_and(|identity| {
+                self.users
+                    .iter()
+                    .any(|user| *user == identity.user || *user == identity.uid.to_string())
+            })
+        }) && any(self.pids.is_empty(), &|| {
+            self.pids
+                .iter()
+                .any(|range| (range.lower_inclusive..range.higher_exclusive).contains(&pid))
+        }) && any(self.names.is_empty(), &|| {
+            identity.is_some_and(|identity| {
+                self.names
+                    .iter()
+                    .any(|pattern| pattern.is_match(&identity.name))
+            })
+        }) && any(self.statuses.is_empty(), &|| {
+            self.statuses.contains(&process.status)
+        })
+    }
+
+    /// Drops the processes that don't pass from a snapshot, then keeps the top `max_processes` of
+    /// the rest by `sort_by` of the process config.
+    pub fn apply(&self, config: &Config, snapshot: &mut Snapshot) {
+        snapshot
+            .processes
+            .retain(|&pid, process| self.matches(pid, process));
+        crate::collector::process::limit(config, &mut snapshot.processes);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::metrics::process::{CpuUsage, Identity, Status, Usage};
+
+    fn snapshot() -> Snapshot {
+        let process = |pid: u32, uid: u32, user: &str, name: &str, status: Status| {
+            (
+                pid,
+                Process {
+                    identity: Some(Identity {
+                        pid,
+                        uid,
+                        user: user.to_string(),
+                        name: name.to_string(),
+                        ..Default::default()
+                    }),
+                    status: status as i32,
+                    ..Default::default()
+                },
+            )
+        };
+        Snapshot {
+            processes: [
+                process(1, 0, "root", "systemd", Status::Sleeping),
+                process(812, 0, "root", "sshd", Status::Sleeping),
+                process(1500, 1000, "user", "bash", Status::Sleeping),
+                process(1600, 1000, "user", "cargo", Status::Running),
+                process(1700, 1000, "user", "rustc", Status::Running),
+                process(2000, 33, "", "nginx", Status::Zombie),
+                // Identity not collected
+                (
+                    3000,
+                    Process {
+                        status: Status::Running as i32,
+                        ..Default::default()
+                    },
+                ),
+            ]
+            .into(),
+            ..Default::default()
+        }
+    }
+
+    fn selected(filters: Vec<Filter>) -> Vec<u32> {
+        let filters: Vec<_> = filters
+            .into_iter()
+            .map(|filter| ProcessFilter {
+                filter: Some(filter),
+            })
+            .collect();
+        let mut snapshot = snapshot();
+        ProcessFilters::new(&filters)
+            .unwrap()
+            .apply(&Config::default(), &mut snapshot);
+        let mut pids: Vec<_> = snapshot.processes.into_keys().collect();
+        pids.sort();
+        pids
+    }
+
+    fn pids(lower_inclusive: u32, higher_exclusive: u32) -> Filter {
+        Filter::ByPid(PidRange {
+            lower_inclusive,
+            higher_exclusive,
+        })
+    }
+
+    #[test]
+    fn single_kinds() {
+        assert!(ProcessFilters::default().is_empty());
+        assert_eq!(selected(vec![]), [1, 812, 1500, 1600, 1700, 2000, 3000]);
+        assert_eq!(
+            selected(vec![Filter::ByUser("user".to_string())]),
+            [1500, 1600, 1700]
+        );
+        // By uid, for users without a name
+        assert_eq!(selected(vec![Filter::ByUser("33".to_string())]), [2000]);
+        assert_eq!(selected(vec![pids(1000, 2000)]), [1500, 1600, 1700]);
+        assert_eq!(selected(vec![pids(5, 5)]), [] as [u32; 0]);
+        assert_eq!(
+            selected(vec![Filter::ByNameRegex("^(ba)?sh|d$".to_string())]),
+            [1, 812, 1500]
+        );
+        assert_eq!(
+            selected(vec![Filter::ByStatus(Status::Running as u32)]),
+            [1600, 1700, 3000]
+        );
+    }
+
+    #[test]
+    fn same_kind_widens() {
+        assert_eq!(
+            selected(vec![
+                Filter::ByUser("root".to_string()),
+                Filter::ByUser("33".to_string()),
+            ]),
+            [1, 812, 2000]
+        );
+        assert_eq!(selected(vec![pids(0, 2), pids(1600, 1601)]), [1, 1600]);
+        assert_eq!(
+            selected(vec![
+                Filter::ByNameRegex("^cargo$".to_string()),
+                Filter::ByNameRegex("^rustc$".to_string()),
+            ]),
+            [1600, 1700]
+        );
+        assert_eq!(
+            selected(vec![
+                Filter::ByStatus(Status::Zombie as u32),
+                Filter::ByStatus(Status::Running as u32),
+            ]),
+            [1600, 1700, 2000, 3000]
+        );
+    }
+
+    #[test]
+    fn kinds_narrow() {
+        assert_eq!(
+            selected(vec![
+                Filter::ByUser("user".to_string()),
+                Filter::ByStatus(Status::Running as u32),
+            ]),
+            [1600, 1700]
+        );
+        assert_eq!(
+            selected(vec![
+                Filter::ByUser("user".to_string()),
+                Filter::ByNameRegex("^r".to_string()),
+                pids(0, 1701),
+            ]),
+            [1700]
+        );
+        assert_eq!(
+            selected(vec![
+                Filter::ByUser("root".to_string()),
+                Filter::ByUser("user".to_string()),
+                Filter::ByStatus(Status::Sleeping as u32),
+                pids(800, 1600),
+            ]),
+            [812, 1500]
+        );
+        // Processes without identity can't match identity filters
+        assert_eq!(
+            selected(vec![
+                Filter::ByStatus(Status::Running as u32),
+                Filter::ByNameRegex(".*".to_string()),
+            ]),
+            [1600, 1700]
+        );
+    }
+
+    #[test]
+    fn top_processes() {
+        let mut snapshot = snapshot();
+        // root's processes are the busiest, so a cap before the filters would leave none of user's
+        for (pid, usage) in [(1, 90), (812, 80), (1500, 5), (1600, 40), (1700, 60)] {
+            snapshot.processes.get_mut(&pid).unwrap().usage = Some(Usage {
+                cpu: Some(CpuUsage {
+                    usage,
+                    ..Default::default()
+                }),
+                ..Default::default()
+            });
+        }
+        let filters = [ProcessFilter {
+            filter: Some(Filter::ByUser("user".to_string())),
+        }];
+        let config = Config {
+            max_processes: 2,
+            ..Default::default()
+        };
+        let filters = ProcessFilters::new(&filters).unwrap();
+        assert!(!filters.is_empty());
+        filters.apply(&config, &mut snapshot);
+        let mut pids: Vec<_> = snapshot.processes.into_keys().collect();
+        pids.sort();
+        assert_eq!(pids, [1600, 1700]);
+    }
+
+    #[test]
+    fn invalid_pattern() {
+        let filters = [ProcessFilter {
+            filter: Some(Filter::ByNameRegex("(".to_string())),
+        }];
+        assert!(ProcessFilters::new(&filters).is_err());
+    }
+}
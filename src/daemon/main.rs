@@ -11,6 +11,7 @@ pub mod service {
     pub use v1::*;
 }
 
+mod filter;
 mod runtime;
 
 pub use monitord::collector;
@@ -25,9 +26,10 @@ pub async fn main() {
 
     let config = metrics::Config::default();
     // let config = config::read();
+    let request = service::ConnectionRequest::default();
 
     tokio::select! {
-        _ = runtime::runtime(snap_tx, stop_rx, config) => {}
+        _ = runtime::runtime(snap_tx, stop_rx, config, request) => {}
     }
 
     tracing::info!("initializing monitord");
@@ -47,8 +49,15 @@ mod tests {
                 topology: true,
                 hwid: true,
                 drivers: true,
+                fold_smt_siblings: false,
+                collect_freq_stats: false,
+                flags: true,
+                collect_cstates: false,
+            }),
+            memory: Some(metrics::memory::Config {
+                dimms: true,
+                dmi_table_fallback: true,
             }),
-            memory: Some(metrics::memory::Config { dimms: true }),
             gpu: Some(metrics::gpu::Config {
                 drivers: true,
                 engines: true,
@@ -57,12 +66,34 @@ mod tests {
                 power: true,
                 thermals: true,
                 processes: true,
+                fans: true,
+                pcie: true,
+                collect_other: false,
+                ecc: true,
             }),
             network: Some(metrics::network::Config {
                 addresses: true,
                 wifi_info: true,
+                sockets: true,
+                exclude_virtual: true,
+                routes: true,
+                gateway_state: true,
+                include_interfaces: Vec::new(),
+                exclude_interfaces: Vec::new(),
+            }),
+            storage: Some(metrics::storage::Config {
+                usage: true,
+                filesystems: true,
+                smart: false,
+                smart_interval_secs: 0,
+                smart_subprocess: false,
+                nvme: true,
+                include_fs_types: Vec::new(),
+                exclude_fs_types: Vec::new(),
+                flatten: false,
+                exclude_removable: false,
+                temperature: true,
             }),
-            storage: Some(metrics::storage::Config { usage: true }),
             process: Some(metrics::process::Config {
                 identity: true,
                 status: true,
@@ -72,15 +103,50 @@ mod tests {
                 gpu_usage: true,
                 disk_usage: true,
                 net_usage: true,
+                sockets: true,
+                fd_counts: true,
+                environment: false,
+                environment_processes: Vec::new(),
+                redact_environment: Vec::new(),
+                environment_max_bytes: 0,
+                cgroup: true,
+                max_processes: 0,
+                sort_by: metrics::process::SortBy::Cpu as i32,
+                min_cpu_percent: 0,
+                min_memory_bytes: 0,
+                user_refresh_interval: 0,
+                include_kernel_threads: false,
+                exclude_zombies: false,
+                memory_breakdown: false,
+                memory_breakdown_min_rss_bytes: 0,
+                memory_breakdown_processes: Vec::new(),
+                security: false,
+                separate_gpu_processes: false,
+                io_priority: true,
             }),
         };
 
+        // Only this test's process
+        let pid = std::process::id();
+        let request = service::ConnectionRequest {
+            process_filters: vec![service::ProcessFilter {
+                filter: Some(service::process_filter::Filter::ByPid(service::PidRange {
+                    lower_inclusive: pid,
+                    higher_exclusive: pid + 1,
+                })),
+            }],
+            ..Default::default()
+        };
+
         tokio::select! {
             // runtime
-            _ = runtime::runtime(snap_tx, stop_rx, config) => {}
+            _ = runtime::runtime(snap_tx, stop_rx, config, request) => {}
             // dummy server
             _ = async move {
                 while let Some(snap) = snap_rx.recv().await {
+                    if let Some(process) = &snap.process {
+                        assert!(process.processes.keys().all(|&listed| listed == pid));
+                    }
                     if let Ok(formatted) = format_snapshot(snap) {
                         tracing::info!("received snapshot: \n{}", formatted);
                     } else {
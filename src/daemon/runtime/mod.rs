@@ -10,6 +10,7 @@ pub async fn runtime(
     snap_tx: tokio::sync::mpsc::Sender<crate::metrics::Snapshot>,
     stop_rx: tokio::sync::oneshot::Receiver<()>,
     config: crate::metrics::Config,
+    request: crate::service::ConnectionRequest,
 ) -> anyhow::Result<()> {
     tokio::select! {
         _ = stop_rx => {
@@ -17,7 +18,7 @@ pub async fn runtime(
             Ok(())
         }
         res =
-            run_collectors(snap_tx, config)
+            run_collectors(snap_tx, config, request)
          => { res }
     }
 }
@@ -25,14 +26,28 @@ pub async fn runtime(
 async fn run_collectors(
     snap_tx: tokio::sync::mpsc::Sender<crate::metrics::Snapshot>,
     config: crate::metrics::Config,
+    request: crate::service::ConnectionRequest,
 ) -> anyhow::Result<()> {
     use crate::collector::*;
+    // Compiled once for the connection the snapshots are sent to
+    let filters = crate::filter::ProcessFilters::new(&request.process_filters)?;
     let mut cpu_collector = CollectorWrapper::new(cpu::Collector::new());
     let mut mem_collector = CollectorWrapper::new(mem::Collector::new());
     let mut gpu_collector = CollectorWrapper::new(gpu::Collector::new());
     let mut net_collector = CollectorWrapper::new(net::Collector::new());
     let mut stor_collector = CollectorWrapper::new(storage::Collector::new());
     let mut proc_collector = CollectorWrapper::new(process::Collector::new());
+    // Never changes while running, so it's only read once
+    let hardware = hardware::read();
+    // The collector caps the process list itself so the full list never leaves it. With filters
+    // the cap is deferred until after them, a cap first would keep the busiest processes overall
+    // and leave the connection with whichever of those happen to pass
+    let mut collect_config = config.clone();
+    if !filters.is_empty()
+        && let Some(process) = collect_config.process.as_mut()
+    {
+        process.max_processes = 0;
+    }
 
     // TODO: Daemon config interval
     let mut interval = tokio::time::interval(tokio::time::Duration::from_millis(200));
@@ -49,17 +64,21 @@ async fn run_collectors(
             storage_snapshot,
             mut process_snapshot,
         ) = tokio::join!(
-            async { cpu_collector.try_collect(&config) },
-            async { mem_collector.try_collect(&config) },
-            async { gpu_collector.try_collect(&config) },
-            async { net_collector.try_collect(&config) },
-            async { stor_collector.try_collect(&config) },
-            async { proc_collector.try_collect(&config) },
+            async { cpu_collector.try_collect(&collect_config) },
+            async { mem_collector.try_collect(&collect_config) },
+            async { gpu_collector.try_collect(&collect_config) },
+            async { net_collector.try_collect(&collect_config) },
+            async { stor_collector.try_collect(&collect_config) },
+            async { proc_collector.try_collect(&collect_config) },
         );
 
         // Resolve
         if let Some(proc) = process_snapshot.as_mut()
             && let Some(gpu) = gpu_snapshot.as_mut()
+            && !config
+                .process
+                .as_ref()
+                .is_some_and(|process| process.separate_gpu_processes)
         {
             proc_collector.collector.resolve(&gpu, proc)?;
         }
@@ -68,6 +87,11 @@ async fn run_collectors(
         {
             gpu_collector.collector.resolve(&proc, gpu)?;
         }
+        if let Some(proc) = process_snapshot.as_mut()
+            && let Some(process_config) = config.process.as_ref()
+        {
+            filters.apply(process_config, proc);
+        }
 
         let snapshot = crate::metrics::Snapshot {
             cpu: cpu_snapshot,
@@ -76,6 +100,7 @@ async fn run_collectors(
             network: network_snapshot,
             storage: storage_snapshot,
             process: process_snapshot,
+            hardware: hardware.clone(),
         };
 
         snap_tx.send(snapshot).await?;
@@ -10,6 +10,14 @@ pub mod v1 {
     }
     pub mod gpu {
         tonic::include_proto!("metrics.v1.gpu");
+
+        impl Power {
+            /// Returns the power draw as a percentage of the enforced limit, `None` when the limit is unknown.
+            pub fn utilization_percent(&self) -> Option<f32> {
+                (self.max_power_mw > 0)
+                    .then(|| self.current_power_mw as f32 / self.max_power_mw as f32 * 100.0)
+            }
+        }
     }
     pub mod memory {
         tonic::include_proto!("metrics.v1.memory");